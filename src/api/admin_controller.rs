@@ -0,0 +1,205 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    middleware,
+    extract::{State, Path, Query},
+    routing::{
+        Router,
+        get, post
+    }
+};
+use hyper::HeaderMap;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{models::{
+    monsters::Monster,
+    character::Character,
+    campaign::Campaign,
+    session::Session,
+    detailed_response::{
+        DetailedResponse,
+        UuidDetailedResponse,
+        AuditListDetailedResponse
+    },
+    state::YaddakState,
+    user::User,
+    errors::SResult,
+    audit::{AuditLog, AuditAction, AuditQuery},
+}, traits::{repo::Repo, audit::audit}, utilities::{headers::authenticate_admin, tls::require_client_cert}};
+
+#[utoipa::path(
+    post,
+    path = "/auth/admin/user/{id}/restore",
+    responses(
+        (status = 200, description = "Restored", body = UuidDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = UuidDetailedResponse),
+        (status = StatusCode::FORBIDDEN, body = UuidDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = UuidDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = UuidDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the soft deleted user")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn restore_user(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> SResult<Json<UuidDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate_admin(client.clone(), headers).await?;
+    User::restore(client.clone(), id).await?;
+    let after = User::get(client.clone(), id).await.ok();
+    audit(client.clone(), Some(actor), AuditAction::Restore, None, after.as_ref()).await;
+    Ok(Json(DetailedResponse::absorb_data(id)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/admin/monster/{id}/restore",
+    responses(
+        (status = 200, description = "Restored", body = UuidDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = UuidDetailedResponse),
+        (status = StatusCode::FORBIDDEN, body = UuidDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = UuidDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = UuidDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the soft deleted monster")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn restore_monster(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> SResult<Json<UuidDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate_admin(client.clone(), headers).await?;
+    Monster::restore(client.clone(), id).await?;
+    let after = Monster::get(client.clone(), id).await.ok();
+    audit(client.clone(), Some(actor), AuditAction::Restore, None, after.as_ref()).await;
+    Ok(Json(DetailedResponse::absorb_data(id)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/admin/character/{id}/restore",
+    responses(
+        (status = 200, description = "Restored", body = UuidDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = UuidDetailedResponse),
+        (status = StatusCode::FORBIDDEN, body = UuidDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = UuidDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = UuidDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the soft deleted character")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn restore_character(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> SResult<Json<UuidDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate_admin(client.clone(), headers).await?;
+    Character::restore(client.clone(), id).await?;
+    let after = Character::get(client.clone(), id).await.ok();
+    audit(client.clone(), Some(actor), AuditAction::Restore, None, after.as_ref()).await;
+    Ok(Json(DetailedResponse::absorb_data(id)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/admin/campaign/{id}/restore",
+    responses(
+        (status = 200, description = "Restored", body = UuidDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = UuidDetailedResponse),
+        (status = StatusCode::FORBIDDEN, body = UuidDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = UuidDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = UuidDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the soft deleted campaign")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn restore_campaign(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> SResult<Json<UuidDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate_admin(client.clone(), headers).await?;
+    Campaign::restore(client.clone(), id).await?;
+    let after = Campaign::get(client.clone(), id).await.ok();
+    audit(client.clone(), Some(actor), AuditAction::Restore, None, after.as_ref()).await;
+    Ok(Json(DetailedResponse::absorb_data(id)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/admin/session/{id}/restore",
+    responses(
+        (status = 200, description = "Restored", body = UuidDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = UuidDetailedResponse),
+        (status = StatusCode::FORBIDDEN, body = UuidDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = UuidDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = UuidDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the cancelled session")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn restore_session(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> SResult<Json<UuidDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate_admin(client.clone(), headers).await?;
+    Session::restore(client.clone(), id).await?;
+    let after = Session::get(client.clone(), id).await.ok();
+    audit(client.clone(), Some(actor), AuditAction::Restore, None, after.as_ref()).await;
+    Ok(Json(DetailedResponse::absorb_data(id)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/admin/audit",
+    responses(
+        (status = 200, description = "Matching audit entries, newest first", body = AuditListDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = AuditListDetailedResponse),
+        (status = StatusCode::FORBIDDEN, body = AuditListDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = AuditListDetailedResponse)
+    ),
+    params(AuditQuery)
+)]
+#[instrument(skip_all)]
+pub(super) async fn get_audit(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Query(filter): Query<AuditQuery>,
+) -> SResult<Json<AuditListDetailedResponse>> {
+    let client = &state.db;
+    authenticate_admin(client.clone(), headers).await?;
+    let entries = AuditLog::search(client.clone(), filter).await?;
+    Ok(Json(DetailedResponse::absorb_data(entries)))
+}
+
+pub fn admin_controller(state: Arc<YaddakState>) -> Router {
+    Router::new()
+        .route("/user/:id/restore", post(restore_user))
+        .route("/monster/:id/restore", post(restore_monster))
+        .route("/character/:id/restore", post(restore_character))
+        .route("/campaign/:id/restore", post(restore_campaign))
+        .route("/session/:id/restore", post(restore_session))
+        .route("/audit", get(get_audit))
+        .route_layer(middleware::from_fn(require_client_cert))
+        .with_state(state)
+}
@@ -0,0 +1,151 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    body::Bytes,
+    extract::{State, Path, DefaultBodyLimit, rejection::BytesRejection},
+    http::{header, StatusCode},
+    response::{IntoResponse, Response},
+    routing::{
+        Router,
+        get, post
+    }
+};
+use hyper::HeaderMap;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{models::{
+    asset::Asset,
+    detailed_response::{DetailedResponse, AssetDetailedResponse},
+    state::YaddakState,
+    errors::{SResult, YaddakError},
+    audit::AuditAction,
+}, config, traits::{audit::audit, object_store::ObjectStore}, utilities::headers::authenticate};
+
+/// Asset bytes never change once written, a new upload gets a new id.
+const CACHE_CONTROL: &str = "public, max-age=31536000, immutable";
+
+#[utoipa::path(
+    get,
+    path = "/asset/{id}",
+    responses(
+        (status = 200, description = "The original image", body = Vec<u8>,
+            content_type = ["image/png", "image/jpeg", "image/gif", "image/webp"]),
+        (status = StatusCode::NOT_MODIFIED, description = "`If-None-Match` matched the `ETag`"),
+        (status = StatusCode::NOT_FOUND, body = StringDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = StringDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the asset")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn get_asset(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> SResult<Response> {
+    serve(&state, &headers, id, false).await
+}
+
+#[utoipa::path(
+    get,
+    path = "/asset/{id}/thumbnail",
+    responses(
+        (status = 200, description = "A thumbnail no larger than `storage.thumbnail_size`", body = Vec<u8>,
+            content_type = ["image/png", "image/jpeg"]),
+        (status = StatusCode::NOT_MODIFIED, description = "`If-None-Match` matched the `ETag`"),
+        (status = StatusCode::NOT_FOUND, body = StringDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = StringDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the asset")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn get_thumbnail(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> SResult<Response> {
+    serve(&state, &headers, id, true).await
+}
+
+async fn serve(state: &YaddakState, headers: &HeaderMap, id: Uuid, thumbnail: bool) -> SResult<Response> {
+    let asset = Asset::get(state.db.clone(), id).await?;
+    let etag = asset.etag(thumbnail);
+    let matched = headers
+        .get(header::IF_NONE_MATCH)
+        .and_then(|value| value.to_str().ok())
+        .is_some_and(|value| value.split(',').any(|tag| tag.trim() == etag || tag.trim() == "*"));
+    if matched {
+        return Ok((
+            StatusCode::NOT_MODIFIED,
+            [(header::ETAG, etag), (header::CACHE_CONTROL, CACHE_CONTROL.to_string())],
+        ).into_response());
+    }
+
+    let (key, content_type) = if thumbnail {
+        (asset.thumbnail_key(), asset.thumbnail_type.clone())
+    } else {
+        (asset.key(), asset.content_type.clone())
+    };
+    let bytes = state.store.get(&key).await?
+        .ok_or_else(|| YaddakError::not_found(format!("asset {id} has no stored image")))?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, content_type),
+            (header::ETAG, etag),
+            (header::CACHE_CONTROL, CACHE_CONTROL.to_string()),
+        ],
+        bytes,
+    ).into_response())
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/asset",
+    request_body(content = Vec<u8>, description = "A PNG, JPEG, GIF or WebP image", content_type = "application/octet-stream"),
+    responses(
+        (status = 200, description = "Stored, serve it from `/asset/{id}`", body = AssetDetailedResponse),
+        (status = StatusCode::BAD_REQUEST, description = "Not an image we accept", body = AssetDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = AssetDetailedResponse),
+        (status = StatusCode::PAYLOAD_TOO_LARGE, body = AssetDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = AssetDetailedResponse)
+    ),
+)]
+#[instrument(skip_all)]
+pub(super) async fn upload(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    body: Result<Bytes, BytesRejection>,
+) -> SResult<Json<AssetDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    let asset = Asset::store(
+        client.clone(),
+        &state.store,
+        actor,
+        body?.to_vec(),
+        config::get().storage.thumbnail_size,
+        None,
+    ).await?;
+    audit(client.clone(), Some(actor), AuditAction::Create, None, Some(&asset)).await;
+    Ok(Json(DetailedResponse::absorb_data(asset)))
+}
+
+/// Public so `<img>` tags work without a token. Asset ids are random, so
+/// they are only found through the records that link them.
+pub fn asset_controller(state: Arc<YaddakState>) -> Router {
+    Router::new()
+        .route("/:id", get(get_asset))
+        .route("/:id/thumbnail", get(get_thumbnail))
+        .with_state(state)
+}
+
+pub fn asset_auth_controller(state: Arc<YaddakState>) -> Router {
+    Router::new()
+        .route("/", post(upload).layer(DefaultBodyLimit::max(config::get().storage.upload_limit)))
+        .with_state(state)
+}
@@ -0,0 +1,514 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{State, Path},
+    routing::{
+        Router,
+        get, post, put
+    }
+};
+use hyper::HeaderMap;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{models::{
+    campaign::{Campaign, CampaignDetail, CampaignEntry, CampaignLink, CampaignRequest, CampaignRole, CredentialRotation, RoleRequest},
+    character::{Character, CharacterSheet},
+    detailed_response::{
+        DetailedResponse,
+        CampaignDetailedResponse,
+        CampaignListDetailedResponse,
+        CharacterListDetailedResponse,
+        MonsterListDetailedResponse,
+        StringDetailedResponse,
+        UuidDetailedResponse
+    },
+    state::YaddakState,
+    errors::{SResult, YaddakError, YaddakErrorKind},
+    audit::AuditAction,
+}, traits::{repo::Repo, audit::audit}, utilities::{headers::authenticate, validated_json::ValidatedJson}};
+
+/// The campaign with its members, the invite code only for DMs.
+async fn detail(client: String, mut campaign: Campaign, role: CampaignRole) -> SResult<CampaignDetail> {
+    let members = Campaign::members(client, campaign.id).await?;
+    if role != CampaignRole::Dm {
+        campaign.invite_code = None;
+    }
+    Ok(CampaignDetail { campaign, role, members })
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/campaign",
+    responses(
+        (status = 200, description = "Campaigns the caller is in, without invite codes", body = CampaignListDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = CampaignListDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = CampaignListDetailedResponse)
+    ),
+)]
+#[instrument(skip_all)]
+pub(super) async fn get_all(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+) -> SResult<Json<CampaignListDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    let campaigns = Campaign::get_for_member(client.clone(), actor)
+        .await?
+        .into_iter()
+        .map(|campaign| Campaign { invite_code: None, ..campaign })
+        .collect();
+    Ok(Json(DetailedResponse::absorb_data(campaigns)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/campaign/{id}",
+    responses(
+        (status = 200, description = "Found", body = CampaignDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = CampaignDetailedResponse),
+        (status = StatusCode::NOT_FOUND, description = "No such campaign, or the caller is not in it", body = CampaignDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = CampaignDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn get_campaign(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> SResult<Json<CampaignDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    let role = Campaign::require_member(client.clone(), id, actor).await?;
+    let campaign = Campaign::get(client.clone(), id).await?;
+    Ok(Json(DetailedResponse::absorb_data(detail(client.clone(), campaign, role).await?)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/campaign",
+    request_body = CampaignRequest,
+    responses(
+        (status = 200, description = "Created with the caller as DM", body = CampaignDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = CampaignDetailedResponse),
+        (status = StatusCode::UNPROCESSABLE_ENTITY, description = "Invalid fields", body = CampaignDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = CampaignDetailedResponse)
+    ),
+)]
+#[instrument(skip_all)]
+pub(super) async fn create(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<CampaignRequest>,
+) -> SResult<Json<CampaignDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    let campaign = Campaign::create(client.clone(), payload, actor).await?;
+    audit(client.clone(), Some(actor), AuditAction::Create, None, Some(&campaign)).await;
+    Ok(Json(DetailedResponse::absorb_data(detail(client.clone(), campaign, CampaignRole::Dm).await?)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/auth/campaign/{id}",
+    request_body = CampaignRequest,
+    responses(
+        (status = 200, description = "Updated", body = CampaignDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = CampaignDetailedResponse),
+        (status = StatusCode::FORBIDDEN, description = "Only DMs can edit", body = CampaignDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = CampaignDetailedResponse),
+        (status = StatusCode::UNPROCESSABLE_ENTITY, description = "Invalid fields", body = CampaignDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = CampaignDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn update(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<CampaignRequest>,
+) -> SResult<Json<CampaignDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    Campaign::require_dm(client.clone(), id, actor).await?;
+    let before = Campaign::get(client.clone(), id).await?;
+    let campaign = Campaign {
+        name: payload.name,
+        description: payload.description,
+        leveling: payload.leveling,
+        ..before.clone()
+    };
+    Campaign::put(client.clone(), id, &campaign).await?;
+    audit(client.clone(), Some(actor), AuditAction::Update, Some(&before), Some(&campaign)).await;
+    Ok(Json(DetailedResponse::absorb_data(detail(client.clone(), campaign, CampaignRole::Dm).await?)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/auth/campaign/{id}",
+    responses(
+        (status = 200, description = "Deleted", body = UuidDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = UuidDetailedResponse),
+        (status = StatusCode::FORBIDDEN, description = "Only DMs can delete", body = UuidDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = UuidDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = UuidDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn remove(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> SResult<Json<UuidDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    Campaign::require_dm(client.clone(), id, actor).await?;
+    let before = Campaign::get(client.clone(), id).await?;
+    Campaign::delete(client.clone(), id).await?;
+    audit(client.clone(), Some(actor), AuditAction::Delete, Some(&before), None).await;
+    Ok(Json(DetailedResponse::absorb_data(id)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/campaign/{id}/invite",
+    responses(
+        (status = 200, description = "The new invite code, the old one stops working", body = StringDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = StringDetailedResponse),
+        (status = StatusCode::FORBIDDEN, body = StringDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = StringDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = StringDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn rotate_invite(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> SResult<Json<StringDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    Campaign::require_dm(client.clone(), id, actor).await?;
+    let code = Campaign::rotate_invite(client.clone(), id).await?;
+    let rotation = CredentialRotation { campaign_id: id, credential: "invite_code" };
+    audit(client.clone(), Some(actor), AuditAction::Update, None, Some(&rotation)).await;
+    Ok(Json(DetailedResponse::absorb_data(code)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/campaign/join/{code}",
+    responses(
+        (status = 200, description = "Joined as a player", body = CampaignDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = CampaignDetailedResponse),
+        (status = StatusCode::NOT_FOUND, description = "No campaign has this code", body = CampaignDetailedResponse),
+        (status = StatusCode::CONFLICT, description = "Already a member", body = CampaignDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = CampaignDetailedResponse)
+    ),
+    params(
+        ("code"=String, Path, description = "Invite code from a DM")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn join(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(code): Path<String>,
+) -> SResult<Json<CampaignDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    let campaign = Campaign::by_invite(client.clone(), &code).await?;
+    Campaign::add_member(client.clone(), campaign.id, actor, CampaignRole::Player)
+        .await
+        .map_err(|err| match err.kind {
+            YaddakErrorKind::Conflict =>
+                YaddakError::conflict("Already a member of this campaign".to_string()),
+            _ => err,
+        })?;
+    let member = Campaign::member(client.clone(), campaign.id, actor).await?;
+    audit(client.clone(), Some(actor), AuditAction::Create, None, Some(&member)).await;
+    Ok(Json(DetailedResponse::absorb_data(detail(client.clone(), campaign, CampaignRole::Player).await?)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/auth/campaign/{id}/member/{user_id}",
+    request_body = RoleRequest,
+    responses(
+        (status = 200, description = "Role changed", body = UuidDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = UuidDetailedResponse),
+        (status = StatusCode::FORBIDDEN, description = "Only DMs can change roles", body = UuidDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = UuidDetailedResponse),
+        (status = StatusCode::CONFLICT, description = "Would leave the campaign without a DM", body = UuidDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = UuidDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign"),
+        ("user_id"=Uuid, Path, description = "ID of the member")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn set_role(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path((id, user_id)): Path<(Uuid, Uuid)>,
+    ValidatedJson(payload): ValidatedJson<RoleRequest>,
+) -> SResult<Json<UuidDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    Campaign::require_dm(client.clone(), id, actor).await?;
+    let before = Campaign::member(client.clone(), id, user_id).await?;
+    Campaign::set_role(client.clone(), id, user_id, payload.role).await?;
+    let after = Campaign::member(client.clone(), id, user_id).await.ok();
+    audit(client.clone(), Some(actor), AuditAction::Update, Some(&before), after.as_ref()).await;
+    Ok(Json(DetailedResponse::absorb_data(user_id)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/auth/campaign/{id}/member/{user_id}",
+    responses(
+        (status = 200, description = "Removed, along with their characters", body = UuidDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = UuidDetailedResponse),
+        (status = StatusCode::FORBIDDEN, description = "Players can only remove themselves", body = UuidDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = UuidDetailedResponse),
+        (status = StatusCode::CONFLICT, description = "Would leave the campaign without a DM", body = UuidDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = UuidDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign"),
+        ("user_id"=Uuid, Path, description = "ID of the member")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn remove_member(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path((id, user_id)): Path<(Uuid, Uuid)>,
+) -> SResult<Json<UuidDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    if actor != user_id {
+        Campaign::require_dm(client.clone(), id, actor).await?;
+    }
+    let before = Campaign::member(client.clone(), id, user_id).await?;
+    Campaign::remove_member(client.clone(), id, user_id).await?;
+    audit(client.clone(), Some(actor), AuditAction::Delete, Some(&before), None).await;
+    Ok(Json(DetailedResponse::absorb_data(user_id)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/campaign/{id}/character",
+    responses(
+        (status = 200, description = "Characters in the campaign, with derived stats", body = CharacterListDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = CharacterListDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = CharacterListDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = CharacterListDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn get_characters(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> SResult<Json<CharacterListDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    Campaign::require_member(client.clone(), id, actor).await?;
+    let characters: Vec<CharacterSheet> = Campaign::characters(client.clone(), id)
+        .await?
+        .into_iter()
+        .map(Character::sheet)
+        .collect();
+    Ok(Json(DetailedResponse::absorb_data(characters)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/auth/campaign/{id}/character/{character_id}",
+    responses(
+        (status = 200, description = "The character is in the campaign", body = UuidDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = UuidDetailedResponse),
+        (status = StatusCode::FORBIDDEN, description = "Only the character's owner can bring it in", body = UuidDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = UuidDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = UuidDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign"),
+        ("character_id"=Uuid, Path, description = "ID of the character")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn add_character(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path((id, character_id)): Path<(Uuid, Uuid)>,
+) -> SResult<Json<UuidDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    Campaign::require_member(client.clone(), id, actor).await?;
+    let character = Character::get(client.clone(), character_id).await?;
+    if character.user_id != actor {
+        return Err(YaddakError::forbidden("Only the owner can add this character".to_string()));
+    }
+    if Campaign::attach(client.clone(), id, CampaignEntry::Character, character_id).await? {
+        let link = CampaignLink { campaign_id: id, entry: CampaignEntry::Character, entry_id: character_id };
+        audit(client.clone(), Some(actor), AuditAction::Create, None, Some(&link)).await;
+    }
+    Ok(Json(DetailedResponse::absorb_data(character_id)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/auth/campaign/{id}/character/{character_id}",
+    responses(
+        (status = 200, description = "The character left the campaign", body = UuidDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = UuidDetailedResponse),
+        (status = StatusCode::FORBIDDEN, description = "Only the owner or a DM can take it out", body = UuidDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = UuidDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = UuidDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign"),
+        ("character_id"=Uuid, Path, description = "ID of the character")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn remove_character(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path((id, character_id)): Path<(Uuid, Uuid)>,
+) -> SResult<Json<UuidDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    let role = Campaign::require_member(client.clone(), id, actor).await?;
+    if role != CampaignRole::Dm {
+        let character = Character::get(client.clone(), character_id).await?;
+        if character.user_id != actor {
+            return Err(YaddakError::forbidden("Only the owner or a DM can remove this character".to_string()));
+        }
+    }
+    Campaign::detach(client.clone(), id, CampaignEntry::Character, character_id).await?;
+    let link = CampaignLink { campaign_id: id, entry: CampaignEntry::Character, entry_id: character_id };
+    audit(client.clone(), Some(actor), AuditAction::Delete, Some(&link), None).await;
+    Ok(Json(DetailedResponse::absorb_data(character_id)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/campaign/{id}/monster",
+    responses(
+        (status = 200, description = "Homebrew monsters the DMs added to the campaign", body = MonsterListDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = MonsterListDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = MonsterListDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = MonsterListDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn get_monsters(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> SResult<Json<MonsterListDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    Campaign::require_member(client.clone(), id, actor).await?;
+    let monsters = Campaign::monsters(client.clone(), id).await?;
+    Ok(Json(DetailedResponse::absorb_data(monsters)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/auth/campaign/{id}/monster/{monster_id}",
+    responses(
+        (status = 200, description = "The monster is in the campaign", body = UuidDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = UuidDetailedResponse),
+        (status = StatusCode::FORBIDDEN, description = "Only DMs can add monsters", body = UuidDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = UuidDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = UuidDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign"),
+        ("monster_id"=Uuid, Path, description = "ID of the monster")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn add_monster(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path((id, monster_id)): Path<(Uuid, Uuid)>,
+) -> SResult<Json<UuidDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    Campaign::require_dm(client.clone(), id, actor).await?;
+    super::monster_controller::visible(client.clone(), actor, monster_id).await?;
+    if Campaign::attach(client.clone(), id, CampaignEntry::Monster, monster_id).await? {
+        let link = CampaignLink { campaign_id: id, entry: CampaignEntry::Monster, entry_id: monster_id };
+        audit(client.clone(), Some(actor), AuditAction::Create, None, Some(&link)).await;
+    }
+    Ok(Json(DetailedResponse::absorb_data(monster_id)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/auth/campaign/{id}/monster/{monster_id}",
+    responses(
+        (status = 200, description = "The monster left the campaign", body = UuidDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = UuidDetailedResponse),
+        (status = StatusCode::FORBIDDEN, description = "Only DMs can remove monsters", body = UuidDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = UuidDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = UuidDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign"),
+        ("monster_id"=Uuid, Path, description = "ID of the monster")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn remove_monster(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path((id, monster_id)): Path<(Uuid, Uuid)>,
+) -> SResult<Json<UuidDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    Campaign::require_dm(client.clone(), id, actor).await?;
+    Campaign::detach(client.clone(), id, CampaignEntry::Monster, monster_id).await?;
+    let link = CampaignLink { campaign_id: id, entry: CampaignEntry::Monster, entry_id: monster_id };
+    audit(client.clone(), Some(actor), AuditAction::Delete, Some(&link), None).await;
+    Ok(Json(DetailedResponse::absorb_data(monster_id)))
+}
+
+pub fn campaign_auth_controller(state: Arc<YaddakState>) -> Router {
+    Router::new()
+        .route("/", get(get_all).post(create))
+        .route("/join/:code", post(join))
+        .route("/:id", get(get_campaign).put(update).delete(remove))
+        .route("/:id/invite", post(rotate_invite))
+        .route("/:id/member/:user_id", put(set_role).delete(remove_member))
+        .route("/:id/character", get(get_characters))
+        .route("/:id/character/:character_id", put(add_character).delete(remove_character))
+        .route("/:id/monster", get(get_monsters))
+        .route("/:id/monster/:monster_id", put(add_monster).delete(remove_monster))
+        .with_state(state)
+}
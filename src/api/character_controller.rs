@@ -0,0 +1,230 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{State, Path},
+    routing::{
+        Router,
+        get
+    }
+};
+use hyper::HeaderMap;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{models::{
+    character::{Character, CharacterRequest, CharacterSheet, InventoryItem},
+    campaign::Campaign,
+    item::Item,
+    xp::XpAward,
+    detailed_response::{
+        DetailedResponse,
+        CharacterDetailedResponse,
+        CharacterListDetailedResponse,
+        UuidDetailedResponse,
+        XpAwardListDetailedResponse
+    },
+    state::YaddakState,
+    errors::{SResult, YaddakError},
+    audit::AuditAction,
+}, traits::{repo::Repo, audit::audit}, utilities::{headers::authenticate, validated_json::ValidatedJson}};
+
+/// Only the player edits a character, anyone else gets a 404 so ids
+/// cannot be probed.
+async fn owned(client: String, actor: Uuid, id: Uuid) -> SResult<Character> {
+    let character = Character::get(client, id).await?;
+    if character.user_id != actor {
+        return Err(YaddakError::not_found(format!("No character with id {id}")));
+    }
+    Ok(character)
+}
+
+/// The player, and anyone sharing a campaign with the character, can read it.
+async fn visible(client: String, actor: Uuid, id: Uuid) -> SResult<Character> {
+    let character = Character::get(client.clone(), id).await?;
+    if character.user_id != actor && !Campaign::can_view_character(client, actor, id).await? {
+        return Err(YaddakError::not_found(format!("No character with id {id}")));
+    }
+    Ok(character)
+}
+
+/// Checks the compendium items the inventory links to, and fills in the
+/// weight of entries that leave it out.
+async fn link_items(client: String, inventory: &mut [InventoryItem]) -> SResult<()> {
+    let ids = inventory.iter().filter_map(|entry| entry.item_id).collect();
+    let items = Item::linked(client, "item_id", ids).await?;
+    for entry in inventory.iter_mut() {
+        if let Some(item) = entry.item_id.and_then(|id| items.get(&id)) {
+            entry.weight = entry.weight.or(item.weight);
+        }
+    }
+    Ok(())
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/character/{id}",
+    responses(
+        (status = 200, description = "Found, with derived stats", body = CharacterDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = CharacterDetailedResponse),
+        (status = StatusCode::NOT_FOUND, description = "No such character, or the caller neither owns it nor shares a campaign with it", body = CharacterDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = CharacterDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the character")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn get_character(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>
+) -> SResult<Json<CharacterDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    let character = visible(client.clone(), actor, id).await?;
+    Ok(Json(DetailedResponse::absorb_data(character.sheet())))
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/character",
+    responses(
+        (status = 200, description = "The caller's characters", body = CharacterListDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = CharacterListDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = CharacterListDetailedResponse)
+    ),
+)]
+#[instrument(skip_all)]
+pub(super) async fn get_all(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+) -> SResult<Json<CharacterListDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    let characters: Vec<CharacterSheet> = Character::get_for_owner(client.clone(), actor)
+        .await?
+        .into_iter()
+        .map(Character::sheet)
+        .collect();
+    Ok(Json(DetailedResponse::absorb_data(characters)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/character",
+    request_body = CharacterRequest,
+    responses(
+        (status = 200, description = "Created", body = CharacterDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = CharacterDetailedResponse),
+        (status = StatusCode::UNPROCESSABLE_ENTITY, description = "Invalid fields, or inventory linking to no item", body = CharacterDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = CharacterDetailedResponse)
+    ),
+)]
+#[instrument(skip_all)]
+pub(super) async fn create(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    ValidatedJson(mut payload): ValidatedJson<CharacterRequest>,
+) -> SResult<Json<CharacterDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    link_items(client.clone(), &mut payload.inventory).await?;
+    let character = payload.into_character(Uuid::new_v4(), actor, None);
+    Character::post(client.clone(), &character).await?;
+    audit(client.clone(), Some(actor), AuditAction::Create, None, Some(&character)).await;
+    Ok(Json(DetailedResponse::absorb_data(character.sheet())))
+}
+
+#[utoipa::path(
+    put,
+    path = "/auth/character/{id}",
+    request_body = CharacterRequest,
+    responses(
+        (status = 200, description = "Updated", body = CharacterDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = CharacterDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = CharacterDetailedResponse),
+        (status = StatusCode::UNPROCESSABLE_ENTITY, description = "Invalid fields, or inventory linking to no item", body = CharacterDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = CharacterDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the character")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn update(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+    ValidatedJson(mut payload): ValidatedJson<CharacterRequest>,
+) -> SResult<Json<CharacterDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    let before = owned(client.clone(), actor, id).await?;
+    link_items(client.clone(), &mut payload.inventory).await?;
+    let character = payload.into_character(id, actor, Some(before.xp));
+    Character::put(client.clone(), id, &character).await?;
+    audit(client.clone(), Some(actor), AuditAction::Update, Some(&before), Some(&character)).await;
+    Ok(Json(DetailedResponse::absorb_data(character.sheet())))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/auth/character/{id}",
+    responses(
+        (status = 200, description = "Deleted", body = UuidDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = UuidDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = UuidDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = UuidDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the character")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn remove(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> SResult<Json<UuidDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    let before = owned(client.clone(), actor, id).await?;
+    Character::delete(client.clone(), id).await?;
+    audit(client.clone(), Some(actor), AuditAction::Delete, Some(&before), None).await;
+    Ok(Json(DetailedResponse::absorb_data(id)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/character/{id}/xp",
+    responses(
+        (status = 200, description = "Every XP award, newest first", body = XpAwardListDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = XpAwardListDetailedResponse),
+        (status = StatusCode::NOT_FOUND, description = "No such character, or the caller neither owns it nor shares a campaign with it", body = XpAwardListDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = XpAwardListDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the character")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn get_xp(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> SResult<Json<XpAwardListDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    visible(client.clone(), actor, id).await?;
+    let awards = XpAward::for_character(client.clone(), id).await?;
+    Ok(Json(DetailedResponse::absorb_data(awards)))
+}
+
+pub fn character_auth_controller(state: Arc<YaddakState>) -> Router {
+    Router::new()
+        .route("/", get(get_all).post(create))
+        .route("/:id", get(get_character).put(update).delete(remove))
+        .route("/:id/xp", get(get_xp))
+        .with_state(state)
+}
@@ -0,0 +1,104 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{State, Path, Query},
+    routing::{
+        Router,
+        get, post
+    }
+};
+use hyper::HeaderMap;
+use tracing::instrument;
+
+use crate::{models::{
+    condition::{Condition, ConditionEffects, EffectsRequest, ExhaustionQuery},
+    detailed_response::{
+        DetailedResponse,
+        ConditionReferenceDetailedResponse,
+        ConditionReferenceListDetailedResponse,
+        ConditionEffectsDetailedResponse
+    },
+    state::YaddakState,
+    errors::SResult,
+}, utilities::{headers::authenticate, validated_json::ValidatedJson}};
+
+#[utoipa::path(
+    get,
+    path = "/auth/condition",
+    responses(
+        (status = 200, description = "Every condition with its rules and effects", body = ConditionReferenceListDetailedResponse),
+        (status = StatusCode::BAD_REQUEST, description = "Exhaustion level out of range", body = ConditionReferenceListDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = ConditionReferenceListDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = ConditionReferenceListDetailedResponse)
+    ),
+    params(
+        ExhaustionQuery
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn get_all(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Query(query): Query<ExhaustionQuery>,
+) -> SResult<Json<ConditionReferenceListDetailedResponse>> {
+    authenticate(state.db.clone(), headers).await?;
+    let level = query.level()?;
+    let conditions = Condition::ALL.into_iter().map(|condition| condition.reference(level)).collect();
+    Ok(Json(DetailedResponse::absorb_data(conditions)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/condition/{condition}",
+    responses(
+        (status = 200, description = "Found", body = ConditionReferenceDetailedResponse),
+        (status = StatusCode::BAD_REQUEST, description = "Not a condition, or exhaustion level out of range", body = ConditionReferenceDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = ConditionReferenceDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = ConditionReferenceDetailedResponse)
+    ),
+    params(
+        ("condition"=Condition, Path, description = "Name of the condition, e.g. `prone`"),
+        ExhaustionQuery
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn get_condition(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(condition): Path<Condition>,
+    Query(query): Query<ExhaustionQuery>,
+) -> SResult<Json<ConditionReferenceDetailedResponse>> {
+    authenticate(state.db.clone(), headers).await?;
+    Ok(Json(DetailedResponse::absorb_data(condition.reference(query.level()?))))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/condition/effects",
+    request_body = EffectsRequest,
+    responses(
+        (status = 200, description = "What the conditions do together, including the conditions they bring with them", body = ConditionEffectsDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = ConditionEffectsDetailedResponse),
+        (status = StatusCode::UNPROCESSABLE_ENTITY, description = "Invalid fields", body = ConditionEffectsDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = ConditionEffectsDetailedResponse)
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn effects(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<EffectsRequest>,
+) -> SResult<Json<ConditionEffectsDetailedResponse>> {
+    authenticate(state.db.clone(), headers).await?;
+    let effects = ConditionEffects::combined(&payload.conditions, payload.exhaustion, &[]);
+    Ok(Json(DetailedResponse::absorb_data(effects)))
+}
+
+pub fn condition_auth_controller(state: Arc<YaddakState>) -> Router {
+    Router::new()
+        .route("/", get(get_all))
+        .route("/effects", post(effects))
+        .route("/:condition", get(get_condition))
+        .with_state(state)
+}
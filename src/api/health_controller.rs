@@ -0,0 +1,104 @@
+use std::{sync::Arc, time::Duration};
+
+use axum::{
+    Json,
+    extract::State,
+    routing::{Router, get},
+};
+use hyper::StatusCode;
+
+use crate::models::{
+    detailed_response::{
+        DetailedResponse,
+        StringDetailedResponse,
+        ReadinessDetailedResponse,
+        VersionDetailedResponse
+    },
+    health::{Readiness, VersionInfo},
+    monsters::Monster,
+    schema::{SchemaVersion, SCHEMA_VERSION},
+    state::YaddakState,
+};
+
+/// Readiness probes give up on the database after this long rather than
+/// hanging until the orchestrator's own timeout.
+const READY_TIMEOUT: Duration = Duration::from_secs(5);
+
+#[utoipa::path(
+    get,
+    path = "/healthz",
+    responses(
+        (status = 200, description = "The process is up", body = StringDetailedResponse)
+    )
+)]
+pub(super) async fn healthz() -> Json<StringDetailedResponse> {
+    Json(DetailedResponse::absorb_data("ok".to_string()))
+}
+
+#[utoipa::path(
+    get,
+    path = "/readyz",
+    responses(
+        (status = 200, description = "Ready to serve traffic", body = ReadinessDetailedResponse),
+        (status = StatusCode::SERVICE_UNAVAILABLE, description = "A check failed, see the body", body = ReadinessDetailedResponse)
+    )
+)]
+pub(super) async fn readyz(
+    State(state): State<Arc<YaddakState>>,
+) -> (StatusCode, Json<ReadinessDetailedResponse>) {
+    let client = &state.db;
+    let checks = async {
+        let schema_version = SchemaVersion::current(client.clone()).await;
+        let seeded = Monster::seeded(client.clone()).await;
+        (schema_version, seeded)
+    };
+
+    let mut readiness = Readiness {
+        expected_schema_version: SCHEMA_VERSION,
+        ..Default::default()
+    };
+    match tokio::time::timeout(READY_TIMEOUT, checks).await {
+        Ok((Ok(schema_version), Ok(seeded))) => {
+            readiness.database = true;
+            readiness.schema_version = schema_version;
+            readiness.seeded = seeded;
+        }
+        Ok((schema_version, seeded)) => {
+            if let Err(err) = schema_version.as_ref().and(seeded.as_ref()) {
+                tracing::warn!("readiness check failed: {err}");
+            }
+        }
+        Err(_) => tracing::warn!("readiness check timed out after {READY_TIMEOUT:?}"),
+    }
+
+    let status = if readiness.ready() {
+        StatusCode::OK
+    } else {
+        StatusCode::SERVICE_UNAVAILABLE
+    };
+    (status, Json(DetailedResponse::absorb_data(readiness)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/version",
+    responses(
+        (status = 200, description = "Build and schema versions", body = VersionDetailedResponse)
+    )
+)]
+pub(super) async fn version() -> Json<VersionDetailedResponse> {
+    Json(DetailedResponse::absorb_data(VersionInfo {
+        version: env!("CARGO_PKG_VERSION").to_string(),
+        git_hash: env!("GIT_HASH").to_string(),
+        schema_version: SCHEMA_VERSION,
+    }))
+}
+
+/// Probe routes. They take no auth and are merged outside the request timeout.
+pub fn health_controller(state: Arc<YaddakState>) -> Router {
+    Router::new()
+        .route("/healthz", get(healthz))
+        .route("/readyz", get(readyz))
+        .route("/version", get(version))
+        .with_state(state)
+}
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{State, Path, Query},
+    routing::{
+        Router,
+        get
+    }
+};
+use hyper::HeaderMap;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{models::{
+    item::{Item, ItemQuery},
+    detailed_response::{
+        DetailedResponse,
+        ItemDetailedResponse,
+        ItemListDetailedResponse
+    },
+    state::YaddakState,
+    errors::SResult,
+}, utilities::headers::authenticate};
+
+#[utoipa::path(
+    get,
+    path = "/auth/item",
+    responses(
+        (status = 200, description = "Matching items by category, then name", body = ItemListDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = ItemListDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = ItemListDetailedResponse)
+    ),
+    params(
+        ItemQuery
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn get_all(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Query(query): Query<ItemQuery>,
+) -> SResult<Json<ItemListDetailedResponse>> {
+    let client = &state.db;
+    authenticate(client.clone(), headers).await?;
+    let items = Item::search(client.clone(), query).await?;
+    Ok(Json(DetailedResponse::absorb_data(items)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/item/{id}",
+    responses(
+        (status = 200, description = "Found", body = ItemDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = ItemDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = ItemDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = ItemDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the item")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn get_item(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> SResult<Json<ItemDetailedResponse>> {
+    let client = &state.db;
+    authenticate(client.clone(), headers).await?;
+    let item = Item::get(client.clone(), id).await?;
+    Ok(Json(DetailedResponse::absorb_data(item)))
+}
+
+pub fn item_auth_controller(state: Arc<YaddakState>) -> Router {
+    Router::new()
+        .route("/", get(get_all))
+        .route("/:id", get(get_item))
+        .with_state(state)
+}
@@ -0,0 +1,51 @@
+use axum::{
+    extract::Request,
+    http::header,
+    middleware::{self, Next},
+    response::{IntoResponse, Response},
+    routing::{Router, get},
+};
+
+use crate::{config, models::errors::YaddakError, utilities::metrics::render};
+
+#[utoipa::path(
+    get,
+    path = "/metrics",
+    responses(
+        (status = 200, description = "Metrics in the Prometheus text format", body = String, content_type = "text/plain"),
+        (status = 401, description = "`metrics.token` is set and the request did not carry it")
+    )
+)]
+pub(super) async fn metrics() -> String {
+    render()
+}
+
+/// Lets the request through when no `metrics.token` is configured or the
+/// request carries it as a bearer token.
+async fn require_token(request: Request, next: Next) -> Response {
+    if let Some(token) = &config::get().metrics.token {
+        let sent = request
+            .headers()
+            .get(header::AUTHORIZATION)
+            .and_then(|value| value.to_str().ok())
+            .and_then(|value| value.strip_prefix("Bearer "))
+            .unwrap_or_default();
+        if !same_bytes(sent.as_bytes(), token.as_bytes()) {
+            return YaddakError::authorize_error("A valid metrics token is required".to_string()).into_response();
+        }
+    }
+    next.run(request).await
+}
+
+// compares every byte so the time taken does not leak a matching prefix
+fn same_bytes(a: &[u8], b: &[u8]) -> bool {
+    a.len() == b.len() && a.iter().zip(b).fold(0, |diff, (x, y)| diff | (x ^ y)) == 0
+}
+
+/// Scrape endpoint. It sits outside the request timeout and is served on
+/// the `metrics.listen` listener only, never next to the API.
+pub fn metrics_controller() -> Router {
+    Router::new()
+        .route("/metrics", get(metrics))
+        .layer(middleware::from_fn(require_token))
+}
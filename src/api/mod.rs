@@ -2,10 +2,38 @@ use utoipa::OpenApi;
 use utoipa_swagger_ui::SwaggerUi;
 
 pub mod user_controller;
+pub mod monster_controller;
+pub mod admin_controller;
+pub mod health_controller;
+pub mod metrics_controller;
+pub mod asset_controller;
+pub mod character_controller;
+pub mod campaign_controller;
+pub mod session_controller;
+pub mod xp_controller;
+pub mod spell_controller;
+pub mod item_controller;
+pub mod treasure_controller;
+pub mod condition_controller;
 
 use crate::models::user;
+use crate::models::monsters;
+use crate::models::monster_import;
+use crate::models::monster_export;
+use crate::models::asset;
+use crate::models::character;
+use crate::models::campaign;
+use crate::models::session;
+use crate::models::xp;
+use crate::models::spell;
+use crate::models::item;
+use crate::models::treasure;
+use crate::models::condition;
+use crate::models::rules;
+use crate::models::audit;
 use crate::models::detailed_response;
 use crate::models::errors;
+use crate::models::health;
 
 #[derive(OpenApi)]
 #[openapi(
@@ -16,17 +44,179 @@ use crate::models::errors;
        user_controller::get_user,
        user_controller::get_all,
        user_controller::register,
+       monster_controller::get_monster,
+       monster_controller::get_all,
+       monster_controller::create,
+       monster_controller::update,
+       monster_controller::remove,
+       monster_controller::import,
+       monster_controller::export_monster,
+       monster_controller::export_all,
+       monster_controller::upload_image,
+       monster_controller::get_spells,
+       monster_controller::apply_condition,
+       spell_controller::get_all,
+       spell_controller::get_spell,
+       item_controller::get_all,
+       item_controller::get_item,
+       treasure_controller::generate,
+       treasure_controller::attach,
+       condition_controller::get_all,
+       condition_controller::get_condition,
+       condition_controller::effects,
+       character_controller::get_character,
+       character_controller::get_all,
+       character_controller::create,
+       character_controller::update,
+       character_controller::remove,
+       character_controller::get_xp,
+       campaign_controller::get_all,
+       campaign_controller::get_campaign,
+       campaign_controller::create,
+       campaign_controller::update,
+       campaign_controller::remove,
+       campaign_controller::rotate_invite,
+       campaign_controller::join,
+       campaign_controller::set_role,
+       campaign_controller::remove_member,
+       campaign_controller::get_characters,
+       campaign_controller::add_character,
+       campaign_controller::remove_character,
+       campaign_controller::get_monsters,
+       campaign_controller::add_monster,
+       campaign_controller::remove_monster,
+       session_controller::get_sessions,
+       session_controller::get_session,
+       session_controller::create_session,
+       session_controller::update_session,
+       session_controller::remove_session,
+       session_controller::rsvp,
+       session_controller::record_log,
+       session_controller::calendar_url,
+       session_controller::rotate_calendar,
+       session_controller::get_calendar,
+       xp_controller::award,
+       xp_controller::milestone,
+       asset_controller::get_asset,
+       asset_controller::get_thumbnail,
+       asset_controller::upload,
+       admin_controller::restore_user,
+       admin_controller::restore_monster,
+       admin_controller::restore_character,
+       admin_controller::restore_campaign,
+       admin_controller::restore_session,
+       admin_controller::get_audit,
+       health_controller::healthz,
+       health_controller::readyz,
+       health_controller::version,
+       metrics_controller::metrics,
     ),
     components(
         schemas(
             user::User,
+            user::UserProfile,
+            user::UserRequest,
             user::CreateUserRequest,
             user::LoginUserRequest,
+            monsters::Monster,
+            monsters::MonsterRequest,
+            monster_import::ImportFormat,
+            monster_import::ImportReport,
+            monster_import::ImportRowError,
+            monster_export::ExportFormat,
+            rules::Ability,
+            rules::Skill,
+            character::CharacterClass,
+            character::ClassLevel,
+            character::Proficiencies,
+            character::InventoryItem,
+            character::CharacterRequest,
+            character::CharacterSheet,
+            character::DerivedStats,
+            character::Spellcasting,
+            campaign::Campaign,
+            campaign::CampaignRole,
+            campaign::Leveling,
+            campaign::CampaignMember,
+            campaign::CampaignDetail,
+            campaign::CampaignRequest,
+            campaign::RoleRequest,
+            session::Session,
+            session::SessionDetail,
+            session::SessionRequest,
+            session::SessionLog,
+            session::XpEntry,
+            session::LootEntry,
+            session::Rsvp,
+            session::RsvpRequest,
+            session::Attendee,
+            xp::XpAward,
+            xp::Defeated,
+            xp::AwardRequest,
+            xp::MilestoneRequest,
+            xp::AwardOutcome,
+            xp::AwardReport,
+            spell::Spell,
+            spell::SpellSchool,
+            spell::SpellLink,
+            item::Item,
+            item::ItemCategory,
+            item::WeaponStats,
+            item::WeaponProperty,
+            item::ArmorStats,
+            item::ArmorType,
+            item::MagicStats,
+            item::Rarity,
+            treasure::TreasureKind,
+            treasure::TreasureRequest,
+            treasure::Coins,
+            treasure::Valuable,
+            treasure::TreasureItem,
+            treasure::Treasure,
+            condition::Condition,
+            condition::Edge,
+            condition::ConditionEffects,
+            condition::ConditionReference,
+            condition::EffectsRequest,
+            condition::ApplyConditionRequest,
+            asset::Asset,
+            audit::AuditLog,
+            audit::AuditAction,
             errors::YaddakError,
             errors::YaddakErrorKind,
+            errors::FieldError,
+            health::Readiness,
+            health::VersionInfo,
             crate::api::detailed_response::UserDetailedResponse,
-            crate::api::detailed_response::UserListDetailedResponse,
+            crate::api::detailed_response::UserProfileDetailedResponse,
+            crate::api::detailed_response::UserProfileListDetailedResponse,
             crate::api::detailed_response::UuidDetailedResponse,
+            crate::api::detailed_response::MonsterDetailedResponse,
+            crate::api::detailed_response::MonsterListDetailedResponse,
+            crate::api::detailed_response::AuditListDetailedResponse,
+            crate::api::detailed_response::StringDetailedResponse,
+            crate::api::detailed_response::ReadinessDetailedResponse,
+            crate::api::detailed_response::VersionDetailedResponse,
+            crate::api::detailed_response::ImportReportDetailedResponse,
+            crate::api::detailed_response::AssetDetailedResponse,
+            crate::api::detailed_response::CharacterDetailedResponse,
+            crate::api::detailed_response::CharacterListDetailedResponse,
+            crate::api::detailed_response::CampaignDetailedResponse,
+            crate::api::detailed_response::CampaignListDetailedResponse,
+            crate::api::detailed_response::SessionDetailedResponse,
+            crate::api::detailed_response::SessionListDetailedResponse,
+            crate::api::detailed_response::AwardReportDetailedResponse,
+            crate::api::detailed_response::AwardOutcomeListDetailedResponse,
+            crate::api::detailed_response::XpAwardListDetailedResponse,
+            crate::api::detailed_response::SpellDetailedResponse,
+            crate::api::detailed_response::SpellListDetailedResponse,
+            crate::api::detailed_response::SpellLinkListDetailedResponse,
+            crate::api::detailed_response::ItemDetailedResponse,
+            crate::api::detailed_response::ItemListDetailedResponse,
+            crate::api::detailed_response::TreasureDetailedResponse,
+            crate::api::detailed_response::ConditionReferenceDetailedResponse,
+            crate::api::detailed_response::ConditionReferenceListDetailedResponse,
+            crate::api::detailed_response::ConditionEffectsDetailedResponse,
         )
     ),
     tags(
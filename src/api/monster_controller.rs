@@ -0,0 +1,400 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    body::Bytes,
+    extract::{State, Path, Query, DefaultBodyLimit, rejection::{BytesRejection, StringRejection}},
+    routing::{
+        Router,
+        get, post, put
+    }
+};
+use hyper::HeaderMap;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{models::{
+    monsters::{Monster, MonsterRequest},
+    campaign::Campaign,
+    monster_import::{self, ImportQuery},
+    monster_export::{self, Export, ExportQuery, BulkExportQuery},
+    asset::Asset,
+    spell::Spell,
+    condition::{self, ApplyConditionRequest, ConditionEffects},
+    detailed_response::{
+        DetailedResponse,
+        MonsterDetailedResponse,
+        MonsterListDetailedResponse,
+        UuidDetailedResponse,
+        ImportReportDetailedResponse,
+        SpellLinkListDetailedResponse,
+        ConditionEffectsDetailedResponse
+    },
+    state::YaddakState,
+    errors::{SResult, YaddakError, FieldError},
+    audit::AuditAction,
+}, config, traits::{repo::Repo, audit::audit}, utilities::{headers::authenticate, validated_json::ValidatedJson}};
+
+/// Bundled monsters are for everyone. Homebrew is read by its owner and
+/// by members of campaigns it is linked to, anyone else gets a 404.
+pub(super) async fn visible(client: String, actor: Uuid, id: Uuid) -> SResult<Monster> {
+    let monster = Monster::get(client.clone(), id).await?;
+    if !monster.bundled() && monster.user_id != actor && !Campaign::shared_monsters(client, actor).await?.contains(&id) {
+        return Err(YaddakError::not_found(format!("No monster with id {id}")));
+    }
+    Ok(monster)
+}
+
+/// Every monster `visible` would hand `actor`.
+async fn visible_all(client: String, actor: Uuid) -> SResult<Vec<Monster>> {
+    let shared = Campaign::shared_monsters(client.clone(), actor).await?;
+    Ok(Monster::get_all(client)
+        .await?
+        .into_iter()
+        .filter(|monster| monster.bundled() || monster.user_id == actor || shared.contains(&monster.id))
+        .collect())
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/monster/{id}",
+    responses(
+        (status = 200, description = "Found", body = MonsterDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = MonsterDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = MonsterDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = MonsterDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the monster")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn get_monster(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>
+) -> SResult<Json<MonsterDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    let monster = visible(client.clone(), actor, id).await?;
+    Ok(Json(DetailedResponse::absorb_data(monster)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/monster",
+    responses(
+        (status = 200, description = "Found", body = MonsterListDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = MonsterListDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = MonsterListDetailedResponse)
+    ),
+)]
+#[instrument(skip_all)]
+pub(super) async fn get_all(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+) -> SResult<Json<MonsterListDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    let monsters = visible_all(client.clone(), actor).await?;
+    Ok(Json(DetailedResponse::absorb_data(monsters)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/auth/monster/{id}",
+    responses(
+        (status = 200, description = "Deleted", body = UuidDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = UuidDetailedResponse),
+        (status = StatusCode::FORBIDDEN, description = "Monster belongs to another user", body = UuidDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = UuidDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = UuidDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the monster")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn remove(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> SResult<Json<UuidDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    let before = Monster::get(client.clone(), id).await?;
+    if before.user_id != actor {
+        return Err(YaddakError::forbidden("Only the owner can delete this monster".to_string()));
+    }
+    Monster::delete(client.clone(), id).await?;
+    audit(client.clone(), Some(actor), AuditAction::Delete, Some(&before), None).await;
+    Ok(Json(DetailedResponse::absorb_data(id)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/monster",
+    request_body = MonsterRequest,
+    responses(
+        (status = 200, description = "Created", body = MonsterDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = MonsterDetailedResponse),
+        (status = StatusCode::UNPROCESSABLE_ENTITY, description = "Invalid fields", body = MonsterDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = MonsterDetailedResponse)
+    ),
+)]
+#[instrument(skip_all)]
+pub(super) async fn create(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<MonsterRequest>,
+) -> SResult<Json<MonsterDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    let monster = payload.into_monster(Uuid::new_v4(), actor);
+    Monster::post(client.clone(), &monster).await?;
+    audit(client.clone(), Some(actor), AuditAction::Create, None, Some(&monster)).await;
+    Ok(Json(DetailedResponse::absorb_data(monster)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/auth/monster/{id}",
+    request_body = MonsterRequest,
+    responses(
+        (status = 200, description = "Updated", body = MonsterDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = MonsterDetailedResponse),
+        (status = StatusCode::FORBIDDEN, description = "Monster belongs to another user", body = MonsterDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = MonsterDetailedResponse),
+        (status = StatusCode::UNPROCESSABLE_ENTITY, description = "Invalid fields", body = MonsterDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = MonsterDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the monster")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn update(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<MonsterRequest>,
+) -> SResult<Json<MonsterDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    let before = Monster::get(client.clone(), id).await?;
+    if before.user_id != actor {
+        return Err(YaddakError::forbidden("Only the owner can edit this monster".to_string()));
+    }
+    let monster = payload.into_monster(id, actor);
+    Monster::put(client.clone(), id, &monster).await?;
+    audit(client.clone(), Some(actor), AuditAction::Update, Some(&before), Some(&monster)).await;
+    Ok(Json(DetailedResponse::absorb_data(monster)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/monster/import",
+    request_body(content = String, description = "The file to import, in the format named by `format`", content_type = "text/plain"),
+    responses(
+        (status = 200, description = "What was imported, or would be on a dry run", body = ImportReportDetailedResponse),
+        (status = StatusCode::BAD_REQUEST, description = "The file could not be parsed", body = ImportReportDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = ImportReportDetailedResponse),
+        (status = StatusCode::PAYLOAD_TOO_LARGE, body = ImportReportDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = ImportReportDetailedResponse)
+    ),
+    params(ImportQuery)
+)]
+#[instrument(skip_all)]
+pub(super) async fn import(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Query(query): Query<ImportQuery>,
+    body: Result<String, StringRejection>,
+) -> SResult<Json<ImportReportDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    let (report, created) = monster_import::import(client.clone(), actor, &body?, &query).await?;
+    for monster in &created {
+        audit(client.clone(), Some(actor), AuditAction::Create, None, Some(monster)).await;
+    }
+    Ok(Json(DetailedResponse::absorb_data(report)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/monster/{id}/export",
+    responses(
+        (status = 200, description = "The monster as a download in the requested format", body = String,
+            content_type = ["application/json", "text/csv", "text/markdown", "text/html"]),
+        (status = StatusCode::UNAUTHORIZED, body = StringDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = StringDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = StringDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the monster"),
+        ExportQuery
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn export_monster(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+    Query(query): Query<ExportQuery>,
+) -> SResult<Export> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    let monster = visible(client.clone(), actor, id).await?;
+    monster_export::export(std::slice::from_ref(&monster), query.format, &monster.name)
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/monster/export",
+    responses(
+        (status = 200, description = "Every matching monster as one download", body = String,
+            content_type = ["application/json", "text/csv", "text/markdown", "text/html"]),
+        (status = StatusCode::UNAUTHORIZED, body = StringDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = StringDetailedResponse)
+    ),
+    params(BulkExportQuery)
+)]
+#[instrument(skip_all)]
+pub(super) async fn export_all(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Query(query): Query<BulkExportQuery>,
+) -> SResult<Export> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    let monsters: Vec<Monster> = visible_all(client.clone(), actor)
+        .await?
+        .into_iter()
+        .filter(|monster| !query.mine || monster.user_id == actor)
+        .filter(|monster| query.source.as_ref().is_none_or(|source| monster.source.eq_ignore_ascii_case(source)))
+        .collect();
+    monster_export::export(&monsters, query.format, "monsters")
+}
+
+#[utoipa::path(
+    put,
+    path = "/auth/monster/{id}/image",
+    request_body(content = Vec<u8>, description = "A PNG, JPEG, GIF or WebP image", content_type = "application/octet-stream"),
+    responses(
+        (status = 200, description = "Stored, `img_url` now points at it", body = MonsterDetailedResponse),
+        (status = StatusCode::BAD_REQUEST, description = "Not an image we accept", body = MonsterDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = MonsterDetailedResponse),
+        (status = StatusCode::FORBIDDEN, description = "Monster belongs to another user", body = MonsterDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = MonsterDetailedResponse),
+        (status = StatusCode::PAYLOAD_TOO_LARGE, body = MonsterDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = MonsterDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the monster")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn upload_image(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+    body: Result<Bytes, BytesRejection>,
+) -> SResult<Json<MonsterDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    let before = Monster::get(client.clone(), id).await?;
+    if before.user_id != actor {
+        return Err(YaddakError::forbidden("Only the owner can change this monster's image".to_string()));
+    }
+    let asset = Asset::store(
+        client.clone(),
+        &state.store,
+        actor,
+        body?.to_vec(),
+        config::get().storage.thumbnail_size,
+        None,
+    ).await?;
+    audit(client.clone(), Some(actor), AuditAction::Create, None, Some(&asset)).await;
+
+    let monster = Monster { img_url: asset.url(), ..before.clone() };
+    Monster::put(client.clone(), id, &monster).await?;
+    audit(client.clone(), Some(actor), AuditAction::Update, Some(&before), Some(&monster)).await;
+    Ok(Json(DetailedResponse::absorb_data(monster)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/monster/{id}/spells",
+    responses(
+        (status = 200, description = "Spells the monster's spellcasting traits name, by level", body = SpellLinkListDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = SpellLinkListDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = SpellLinkListDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = SpellLinkListDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the monster")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn get_spells(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> SResult<Json<SpellLinkListDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    let monster = visible(client.clone(), actor, id).await?;
+    let spells = Spell::for_traits(client.clone(), monster.traits.as_deref().unwrap_or_default()).await?;
+    Ok(Json(DetailedResponse::absorb_data(spells)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/monster/{id}/condition",
+    request_body = ApplyConditionRequest,
+    responses(
+        (status = 200, description = "The monster can have the condition, with what it does to the monster", body = ConditionEffectsDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = ConditionEffectsDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = ConditionEffectsDetailedResponse),
+        (status = StatusCode::UNPROCESSABLE_ENTITY, description = "Invalid fields, or the monster is immune to the condition", body = ConditionEffectsDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = ConditionEffectsDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the monster")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn apply_condition(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<ApplyConditionRequest>,
+) -> SResult<Json<ConditionEffectsDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    let monster = visible(client.clone(), actor, id).await?;
+    let immune = condition::immunities(monster.condition_immunities.as_deref().unwrap_or_default());
+    if immune.contains(&payload.condition) {
+        return Err(YaddakError::validation(vec![FieldError {
+            field: "condition".to_string(),
+            message: format!("{} is immune to {}", monster.name, payload.condition.as_str()),
+        }]));
+    }
+    // conditions that come with this one, such as the prone of
+    // unconscious, are left out if the monster is immune to them
+    let effects = ConditionEffects::combined(&[payload.condition], payload.level, &immune);
+    Ok(Json(DetailedResponse::absorb_data(effects)))
+}
+
+pub fn monster_auth_controller(state: Arc<YaddakState>) -> Router {
+    Router::new()
+        .route("/", get(get_all).post(create))
+        .route("/import", post(import).layer(DefaultBodyLimit::max(config::get().http.import_body_limit)))
+        .route("/export", get(export_all))
+        .route("/:id", get(get_monster).put(update).delete(remove))
+        .route("/:id/export", get(export_monster))
+        .route("/:id/spells", get(get_spells))
+        .route("/:id/condition", post(apply_condition))
+        .route("/:id/image", put(upload_image).layer(DefaultBodyLimit::max(config::get().storage.upload_limit)))
+        .with_state(state)
+}
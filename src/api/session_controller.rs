@@ -0,0 +1,394 @@
+use std::{collections::HashSet, sync::Arc};
+
+use axum::{
+    Json,
+    extract::{State, Path, Query},
+    http::header,
+    response::{IntoResponse, Response},
+    routing::{
+        Router,
+        get, put
+    }
+};
+use hyper::HeaderMap;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{models::{
+    campaign::{Campaign, CredentialRotation},
+    item::Item,
+    session::{CalendarQuery, RsvpRecord, RsvpRequest, Session, SessionLog, SessionRequest},
+    detailed_response::{
+        DetailedResponse,
+        SessionDetailedResponse,
+        SessionListDetailedResponse,
+        StringDetailedResponse,
+        UuidDetailedResponse
+    },
+    state::YaddakState,
+    errors::{FieldError, SResult, YaddakError},
+    audit::AuditAction,
+}, traits::{repo::Repo, audit::audit}, utilities::{headers::authenticate, validated_json::ValidatedJson}};
+
+#[utoipa::path(
+    get,
+    path = "/auth/campaign/{id}/session",
+    responses(
+        (status = 200, description = "Sessions in start order", body = SessionListDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = SessionListDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = SessionListDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = SessionListDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn get_sessions(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> SResult<Json<SessionListDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    Campaign::require_member(client.clone(), id, actor).await?;
+    let sessions = Session::for_campaign(client.clone(), id).await?;
+    Ok(Json(DetailedResponse::absorb_data(sessions)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/campaign/{id}/session/{session_id}",
+    responses(
+        (status = 200, description = "Found, with every member's RSVP", body = SessionDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = SessionDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = SessionDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = SessionDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign"),
+        ("session_id"=Uuid, Path, description = "ID of the session")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn get_session(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path((id, session_id)): Path<(Uuid, Uuid)>,
+) -> SResult<Json<SessionDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    Campaign::require_member(client.clone(), id, actor).await?;
+    let session = Session::get_in(client.clone(), id, session_id).await?;
+    Ok(Json(DetailedResponse::absorb_data(Session::detail(client.clone(), session).await?)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/campaign/{id}/session",
+    request_body = SessionRequest,
+    responses(
+        (status = 200, description = "Scheduled", body = SessionDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = SessionDetailedResponse),
+        (status = StatusCode::FORBIDDEN, description = "Only DMs schedule sessions", body = SessionDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = SessionDetailedResponse),
+        (status = StatusCode::UNPROCESSABLE_ENTITY, description = "Invalid fields", body = SessionDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = SessionDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn create_session(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<SessionRequest>,
+) -> SResult<Json<SessionDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    Campaign::require_dm(client.clone(), id, actor).await?;
+    let session = payload.into_session(Uuid::new_v4(), id, actor, SessionLog::default());
+    Session::post(client.clone(), &session).await?;
+    audit(client.clone(), Some(actor), AuditAction::Create, None, Some(&session)).await;
+    Ok(Json(DetailedResponse::absorb_data(Session::detail(client.clone(), session).await?)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/auth/campaign/{id}/session/{session_id}",
+    request_body = SessionRequest,
+    responses(
+        (status = 200, description = "Rescheduled, RSVPs are kept", body = SessionDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = SessionDetailedResponse),
+        (status = StatusCode::FORBIDDEN, body = SessionDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = SessionDetailedResponse),
+        (status = StatusCode::UNPROCESSABLE_ENTITY, description = "Invalid fields", body = SessionDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = SessionDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign"),
+        ("session_id"=Uuid, Path, description = "ID of the session")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn update_session(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path((id, session_id)): Path<(Uuid, Uuid)>,
+    ValidatedJson(payload): ValidatedJson<SessionRequest>,
+) -> SResult<Json<SessionDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    Campaign::require_dm(client.clone(), id, actor).await?;
+    let before = Session::get_in(client.clone(), id, session_id).await?;
+    let session = payload.into_session(session_id, id, before.created_by, before.log.clone());
+    Session::put(client.clone(), session_id, &session).await?;
+    audit(client.clone(), Some(actor), AuditAction::Update, Some(&before), Some(&session)).await;
+    Ok(Json(DetailedResponse::absorb_data(Session::detail(client.clone(), session).await?)))
+}
+
+#[utoipa::path(
+    delete,
+    path = "/auth/campaign/{id}/session/{session_id}",
+    responses(
+        (status = 200, description = "Cancelled", body = UuidDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = UuidDetailedResponse),
+        (status = StatusCode::FORBIDDEN, body = UuidDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = UuidDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = UuidDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign"),
+        ("session_id"=Uuid, Path, description = "ID of the session")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn remove_session(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path((id, session_id)): Path<(Uuid, Uuid)>,
+) -> SResult<Json<UuidDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    Campaign::require_dm(client.clone(), id, actor).await?;
+    let before = Session::get_in(client.clone(), id, session_id).await?;
+    Session::delete(client.clone(), session_id).await?;
+    audit(client.clone(), Some(actor), AuditAction::Delete, Some(&before), None).await;
+    Ok(Json(DetailedResponse::absorb_data(session_id)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/auth/campaign/{id}/session/{session_id}/rsvp",
+    request_body = RsvpRequest,
+    responses(
+        (status = 200, description = "The caller's answer is recorded", body = SessionDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = SessionDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = SessionDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = SessionDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign"),
+        ("session_id"=Uuid, Path, description = "ID of the session")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn rsvp(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path((id, session_id)): Path<(Uuid, Uuid)>,
+    ValidatedJson(payload): ValidatedJson<RsvpRequest>,
+) -> SResult<Json<SessionDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    Campaign::require_member(client.clone(), id, actor).await?;
+    let session = Session::get_in(client.clone(), id, session_id).await?;
+    let before = Session::rsvps(client.clone(), session_id)
+        .await?
+        .remove(&actor)
+        .map(|(rsvp, _)| RsvpRecord { session_id, user_id: actor, rsvp });
+    Session::set_rsvp(client.clone(), session_id, actor, payload.rsvp).await?;
+    let after = RsvpRecord { session_id, user_id: actor, rsvp: payload.rsvp };
+    let action = if before.is_some() { AuditAction::Update } else { AuditAction::Create };
+    audit(client.clone(), Some(actor), action, before.as_ref(), Some(&after)).await;
+    Ok(Json(DetailedResponse::absorb_data(Session::detail(client.clone(), session).await?)))
+}
+
+#[utoipa::path(
+    put,
+    path = "/auth/campaign/{id}/session/{session_id}/log",
+    request_body = SessionLog,
+    responses(
+        (status = 200, description = "The log replaces any earlier one, except for the XP awards it keeps", body = SessionDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = SessionDetailedResponse),
+        (status = StatusCode::FORBIDDEN, description = "Only DMs write the log", body = SessionDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = SessionDetailedResponse),
+        (status = StatusCode::UNPROCESSABLE_ENTITY, description = "Invalid fields, a character not in the campaign, loot linking to no item, or XP that was not awarded", body = SessionDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = SessionDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign"),
+        ("session_id"=Uuid, Path, description = "ID of the session")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn record_log(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path((id, session_id)): Path<(Uuid, Uuid)>,
+    ValidatedJson(payload): ValidatedJson<SessionLog>,
+) -> SResult<Json<SessionDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    Campaign::require_dm(client.clone(), id, actor).await?;
+    let before = Session::get_in(client.clone(), id, session_id).await?;
+    // XP only reaches characters through awards, so the log's XP entries
+    // are theirs. Sending back what the log already holds is fine.
+    if !payload.xp.is_empty() && payload.xp != before.log.xp {
+        return Err(YaddakError::validation(vec![FieldError {
+            field: "xp".to_string(),
+            message: "XP is given through the session's XP awards, not the log".to_string(),
+        }]));
+    }
+
+    let party: HashSet<Uuid> = Campaign::characters(client.clone(), id)
+        .await?
+        .into_iter()
+        .map(|character| character.id)
+        .collect();
+    let strangers: Vec<FieldError> = payload
+        .character_ids()
+        .filter(|character_id| !party.contains(character_id))
+        .map(|character_id| FieldError {
+            field: "character_id".to_string(),
+            message: format!("character {character_id} is not in this campaign"),
+        })
+        .collect();
+    if !strangers.is_empty() {
+        return Err(YaddakError::validation(strangers));
+    }
+    Item::linked(client.clone(), "item_id", payload.item_ids().collect()).await?;
+
+    let log = SessionLog {
+        xp: before.log.xp.clone(),
+        ..payload
+    };
+    Session::set_log(client.clone(), session_id, &log).await?;
+    let session = Session::get(client.clone(), session_id).await?;
+    audit(client.clone(), Some(actor), AuditAction::Update, Some(&before), Some(&session)).await;
+    Ok(Json(DetailedResponse::absorb_data(Session::detail(client.clone(), session).await?)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/campaign/{id}/calendar",
+    responses(
+        (status = 200, description = "Path of the campaign's iCalendar feed, for calendar apps to subscribe to", body = StringDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = StringDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = StringDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = StringDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn calendar_url(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> SResult<Json<StringDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    Campaign::require_member(client.clone(), id, actor).await?;
+    let token = Campaign::calendar_token(client.clone(), id).await?;
+    Ok(Json(DetailedResponse::absorb_data(format!("/calendar/{id}?token={token}"))))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/campaign/{id}/calendar",
+    responses(
+        (status = 200, description = "Path of the feed with a new token, the old path stops working", body = StringDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = StringDetailedResponse),
+        (status = StatusCode::FORBIDDEN, body = StringDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = StringDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = StringDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn rotate_calendar(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> SResult<Json<StringDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    Campaign::require_dm(client.clone(), id, actor).await?;
+    let token = Campaign::rotate_calendar(client.clone(), id).await?;
+    let rotation = CredentialRotation { campaign_id: id, credential: "calendar_token" };
+    audit(client.clone(), Some(actor), AuditAction::Update, None, Some(&rotation)).await;
+    Ok(Json(DetailedResponse::absorb_data(format!("/calendar/{id}?token={token}"))))
+}
+
+#[utoipa::path(
+    get,
+    path = "/calendar/{id}",
+    responses(
+        (status = 200, description = "Every scheduled session", body = String, content_type = "text/calendar"),
+        (status = StatusCode::NOT_FOUND, description = "No such campaign, or a wrong token", body = StringDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = StringDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign"),
+        CalendarQuery
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn get_calendar(
+    State(state): State<Arc<YaddakState>>,
+    Path(id): Path<Uuid>,
+    Query(query): Query<CalendarQuery>,
+) -> SResult<Response> {
+    let client = &state.db;
+    let expected = Campaign::stored_calendar_token(client.clone(), id).await?.unwrap_or_default();
+    // compare every byte so the time taken says nothing about the token
+    let matches = !expected.is_empty()
+        && query.token.len() == expected.len()
+        && query.token.bytes().zip(expected.bytes()).fold(0, |acc, (a, b)| acc | (a ^ b)) == 0;
+    if !matches {
+        return Err(YaddakError::not_found(format!("No calendar for campaign {id}")));
+    }
+    let campaign = Campaign::get(client.clone(), id).await?;
+    let sessions = Session::for_campaign(client.clone(), id).await?;
+    Ok((
+        [
+            (header::CONTENT_TYPE, "text/calendar; charset=utf-8".to_string()),
+            (header::CACHE_CONTROL, "private, max-age=300".to_string()),
+        ],
+        Session::calendar(&campaign, &sessions),
+    ).into_response())
+}
+
+/// Merged into the campaign router, sessions live under their campaign.
+pub fn session_auth_controller(state: Arc<YaddakState>) -> Router {
+    Router::new()
+        .route("/:id/session", get(get_sessions).post(create_session))
+        .route("/:id/session/:session_id", get(get_session).put(update_session).delete(remove_session))
+        .route("/:id/session/:session_id/rsvp", put(rsvp))
+        .route("/:id/session/:session_id/log", put(record_log))
+        .route("/:id/calendar", get(calendar_url).post(rotate_calendar))
+        .with_state(state)
+}
+
+/// Public, the token in the query stands in for a login.
+pub fn calendar_controller(state: Arc<YaddakState>) -> Router {
+    Router::new()
+        .route("/:id", get(get_calendar))
+        .with_state(state)
+}
@@ -0,0 +1,80 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{State, Path, Query},
+    routing::{
+        Router,
+        get
+    }
+};
+use hyper::HeaderMap;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{models::{
+    spell::{Spell, SpellQuery},
+    detailed_response::{
+        DetailedResponse,
+        SpellDetailedResponse,
+        SpellListDetailedResponse
+    },
+    state::YaddakState,
+    errors::SResult,
+}, utilities::headers::authenticate};
+
+#[utoipa::path(
+    get,
+    path = "/auth/spell",
+    responses(
+        (status = 200, description = "Matching spells by level, then name", body = SpellListDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = SpellListDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = SpellListDetailedResponse)
+    ),
+    params(
+        SpellQuery
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn get_all(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Query(query): Query<SpellQuery>,
+) -> SResult<Json<SpellListDetailedResponse>> {
+    let client = &state.db;
+    authenticate(client.clone(), headers).await?;
+    let spells = Spell::search(client.clone(), query).await?;
+    Ok(Json(DetailedResponse::absorb_data(spells)))
+}
+
+#[utoipa::path(
+    get,
+    path = "/auth/spell/{id}",
+    responses(
+        (status = 200, description = "Found", body = SpellDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = SpellDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = SpellDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = SpellDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the spell")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn get_spell(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+) -> SResult<Json<SpellDetailedResponse>> {
+    let client = &state.db;
+    authenticate(client.clone(), headers).await?;
+    let spell = Spell::get(client.clone(), id).await?;
+    Ok(Json(DetailedResponse::absorb_data(spell)))
+}
+
+pub fn spell_auth_controller(state: Arc<YaddakState>) -> Router {
+    Router::new()
+        .route("/", get(get_all))
+        .route("/:id", get(get_spell))
+        .with_state(state)
+}
@@ -0,0 +1,135 @@
+use std::sync::Arc;
+
+use axum::{
+    Json,
+    extract::{State, Path},
+    routing::{
+        Router,
+        post
+    }
+};
+use hyper::HeaderMap;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{models::{
+    campaign::Campaign,
+    monsters::Monster,
+    session::{Session, SessionLog},
+    treasure::{Treasure, TreasureRequest},
+    detailed_response::{
+        DetailedResponse,
+        TreasureDetailedResponse
+    },
+    state::YaddakState,
+    errors::{SResult, YaddakError},
+    audit::AuditAction,
+}, traits::{repo::Repo, audit::audit}, utilities::{headers::authenticate, validated_json::ValidatedJson}};
+
+/// Loot a session log holds at most, as `SessionLog` validates it.
+const MAX_LOOT: usize = 500;
+
+/// Rolls the treasure `request` asks for, one rating per defeated monster
+/// or the rating it names.
+async fn roll(client: String, request: &TreasureRequest) -> SResult<Treasure> {
+    let mut challenges = Vec::new();
+    match request.challenge {
+        Some(challenge) => challenges.push(challenge),
+        None => {
+            for defeated in &request.defeated {
+                let monster = Monster::get(client.clone(), defeated.monster_id).await?;
+                challenges.extend((0..defeated.count).map(|_| monster.challenge));
+            }
+        }
+    }
+    Treasure::roll(client, request.kind, &challenges, request.seed).await
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/treasure",
+    request_body = TreasureRequest,
+    responses(
+        (status = 200, description = "Treasure rolled on the DMG tables, kept nowhere", body = TreasureDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = TreasureDetailedResponse),
+        (status = StatusCode::NOT_FOUND, description = "No such monster", body = TreasureDetailedResponse),
+        (status = StatusCode::UNPROCESSABLE_ENTITY, description = "Invalid fields", body = TreasureDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = TreasureDetailedResponse)
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn generate(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    ValidatedJson(payload): ValidatedJson<TreasureRequest>,
+) -> SResult<Json<TreasureDetailedResponse>> {
+    let client = &state.db;
+    authenticate(client.clone(), headers).await?;
+    let treasure = roll(client.clone(), &payload).await?;
+    Ok(Json(DetailedResponse::absorb_data(treasure)))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/campaign/{id}/session/{session_id}/treasure",
+    request_body = TreasureRequest,
+    responses(
+        (status = 200, description = "Treasure rolled and added to the session's loot for the DM to hand out", body = TreasureDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = TreasureDetailedResponse),
+        (status = StatusCode::FORBIDDEN, description = "Only DMs roll treasure into a session", body = TreasureDetailedResponse),
+        (status = StatusCode::NOT_FOUND, description = "No such campaign, session or monster", body = TreasureDetailedResponse),
+        (status = StatusCode::CONFLICT, description = "The session log has no room for more loot", body = TreasureDetailedResponse),
+        (status = StatusCode::UNPROCESSABLE_ENTITY, description = "Invalid fields", body = TreasureDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = TreasureDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign"),
+        ("session_id"=Uuid, Path, description = "ID of the session")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn attach(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path((id, session_id)): Path<(Uuid, Uuid)>,
+    ValidatedJson(payload): ValidatedJson<TreasureRequest>,
+) -> SResult<Json<TreasureDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    Campaign::require_dm(client.clone(), id, actor).await?;
+    let before = Session::get_in(client.clone(), id, session_id).await?;
+    let treasure = roll(client.clone(), &payload).await?;
+
+    let note = match &payload.encounter {
+        Some(encounter) => format!("{} treasure from {encounter}, seed {}", treasure.kind.as_str(), treasure.seed),
+        None => format!("{} treasure, seed {}", treasure.kind.as_str(), treasure.seed),
+    };
+    let loot = treasure.loot(&note);
+    if before.log.loot.len() + loot.len() > MAX_LOOT {
+        return Err(YaddakError::conflict(format!("Session {session_id} has no room for {} more loot entries", loot.len())));
+    }
+    let mut log = SessionLog {
+        loot: before.log.loot.iter().cloned().chain(loot).collect(),
+        ..before.log.clone()
+    };
+    if let Some(encounter) = payload.encounter.filter(|encounter| !log.encounters.contains(encounter)) {
+        log.encounters.push(encounter);
+    }
+    Session::set_log(client.clone(), session_id, &log).await?;
+    let session = Session::get(client.clone(), session_id).await?;
+    audit(client.clone(), Some(actor), AuditAction::Update, Some(&before), Some(&session)).await;
+    Ok(Json(DetailedResponse::absorb_data(treasure)))
+}
+
+pub fn treasure_auth_controller(state: Arc<YaddakState>) -> Router {
+    Router::new()
+        .route("/", post(generate))
+        .with_state(state)
+}
+
+/// Merged into the campaign router, treasure is attached to a session there.
+pub fn session_treasure_controller(state: Arc<YaddakState>) -> Router {
+    Router::new()
+        .route("/:id/session/:session_id/treasure", post(attach))
+        .with_state(state)
+}
@@ -8,20 +8,23 @@ use axum::{
         get, post
     }
 };
-use hyper::{StatusCode, HeaderMap};
+use hyper::HeaderMap;
+use tracing::instrument;
 use uuid::Uuid;
 
 use crate::{models::{
-    user::{User, CreateUserRequest, LoginUserRequest},
+    user::{User, UserProfile, UserRequest, CreateUserRequest, LoginUserRequest},
     detailed_response::{
         DetailedResponse,
         UserDetailedResponse,
-        UserListDetailedResponse,
+        UserProfileDetailedResponse,
+        UserProfileListDetailedResponse,
         UuidDetailedResponse
     },
     state::YaddakState,
-    errors::YaddakErrorKind
-}, traits::repo::Repo, utilities::headers::auth_handler};
+    errors::{SResult, YaddakError, YaddakErrorKind},
+    audit::AuditAction
+}, traits::{repo::Repo, audit::audit}, utilities::{headers::{authenticate, authenticate_self_or_admin}, validated_json::ValidatedJson}};
 
 
 #[utoipa::path(
@@ -30,35 +33,26 @@ use crate::{models::{
     request_body=CreateUserRequest,
     responses(
         (status = 200, description = "Created", body = UserDetailedResponse),
-        (status = StatusCode::BAD_REQUEST, body = UserDetailedResponse),
+        (status = StatusCode::CONFLICT, description = "Username or email already used", body = UserDetailedResponse),
+        (status = StatusCode::UNPROCESSABLE_ENTITY, description = "Invalid fields", body = UserDetailedResponse),
         (status = StatusCode::INTERNAL_SERVER_ERROR, body = UserDetailedResponse)
     )
 )]
+#[instrument(skip_all)]
 pub async fn register(
     State(state): State<Arc<YaddakState>>,
-    Json(payload): Json<CreateUserRequest>,
-) -> (StatusCode, Json<UserDetailedResponse>) {
+    ValidatedJson(payload): ValidatedJson<CreateUserRequest>,
+) -> SResult<Json<UserDetailedResponse>> {
     let client = &state.db;
     
-    match User::create(
+    let user = User::create(
         client.clone(),
         payload.user_name,
         payload.user_email,
         payload.user_pass,
-    ).await {
-        Ok(user) => {
-            (StatusCode::OK, Json(DetailedResponse::absorb_data(user)))
-        }
-        Err(e) => {
-            match e.kind {
-                YaddakErrorKind::AuthError => 
-                    (StatusCode::BAD_REQUEST, Json(DetailedResponse::absorb_error(e))),
-                _ => 
-                    (StatusCode::INTERNAL_SERVER_ERROR, Json(DetailedResponse::absorb_error(e)))
-
-            }
-        }
-    }
+    ).await?;
+    audit(client.clone(), Some(user.id), AuditAction::Create, None, Some(&user)).await;
+    Ok(Json(DetailedResponse::absorb_data(user)))
 }
 
 #[utoipa::path(
@@ -66,168 +60,147 @@ pub async fn register(
     path = "/user/login",
     request_body=LoginUserRequest,
     responses(
-        (status = 200, description = "Created", body = UserDetailedResponse),
-        (status = StatusCode::NOT_FOUND, body = UserDetailedResponse),
-        (status = StatusCode::UNAUTHORIZED, body = UserDetailedResponse)
+        (status = 200, description = "Signed in", body = UserDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = UserDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = UserDetailedResponse)
     )
 )]
+#[instrument(skip_all)]
 pub(super) async fn login(
     State(state): State<Arc<YaddakState>>,
-    Json(payload): Json<LoginUserRequest>
-) -> (StatusCode, Json<UserDetailedResponse>) {
+    ValidatedJson(payload): ValidatedJson<LoginUserRequest>
+) -> SResult<Json<UserDetailedResponse>> {
     let client = &state.db;
-    match User::get_user_by_name(
+    // an unknown name is reported the same way as a wrong password
+    let user = User::get_user_by_name(
         client.clone(),
         payload.user_name.clone()
-    ).await {
-        Ok(user) => {
-            match user.authenticate(
-                payload.user_name,
-                payload.user_pass
-            ) {
-                Ok(()) => {
-                    (StatusCode::OK, Json(DetailedResponse::absorb_data(user)))
-                },
-                Err(err) => {
-                    (StatusCode::UNAUTHORIZED, Json(DetailedResponse::absorb_error(err)))
-                }
-            }
-        },
-        Err(err) => {
-            (StatusCode::NOT_FOUND, Json(DetailedResponse::absorb_error(err)))
-        }
-    }
+    ).await
+        .map_err(|err| match err.kind {
+            YaddakErrorKind::NotFound => YaddakError::authorize_error("Could not Sign In".to_owned()),
+            _ => err,
+        })?;
+    user.authenticate(
+        payload.user_name,
+        payload.user_pass
+    )?;
+    Ok(Json(DetailedResponse::absorb_data(user)))
 }
 
 #[utoipa::path(
     get,
     path = "/auth/user/{id}",
     responses(
-        (status = 200, description = "Created", body = UserDetailedResponse),
-        (status = StatusCode::UNAUTHORIZED, body = UserDetailedResponse),
-        (status = StatusCode::FORBIDDEN, body = UserDetailedResponse),
-        (status = StatusCode::INTERNAL_SERVER_ERROR, body = UserDetailedResponse)
+        (status = 200, description = "Found", body = UserProfileDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = UserProfileDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = UserProfileDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = UserProfileDetailedResponse)
     ),
     params(
         ("id"=Uuid, Path, description = "ID of the user")
     )
 )]
+#[instrument(skip_all)]
 pub(super) async fn get_user(
     State(state): State<Arc<YaddakState>>,
     headers: HeaderMap,
     Path(id): Path<Uuid>
-) -> (StatusCode, Json<UserDetailedResponse>) {
+) -> SResult<Json<UserProfileDetailedResponse>> {
     let client = &state.db;
-    match auth_handler(headers) {
-        Ok(ah) => {
-           if let Err(e) = User::check_auth(client.clone(), ah).await {
-                return (StatusCode::UNAUTHORIZED, Json(DetailedResponse::absorb_error(e)));
-           }
-        },
-        Err(err) => return (StatusCode::FORBIDDEN, Json(DetailedResponse::absorb_error(err)))
-    }
-    match User::get(client.clone(), id).await {
-        Ok(user) => (StatusCode::OK, Json(DetailedResponse::absorb_data(user))),
-        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, Json(DetailedResponse::absorb_error(err)))
-    }    
+    authenticate(client.clone(), headers).await?;
+    let user = User::get(client.clone(), id).await?;
+    Ok(Json(DetailedResponse::absorb_data(UserProfile::from(user))))
 }
 
 #[utoipa::path(
     get,
     path = "/auth/user",
     responses(
-        (status = 200, description = "Created", body = UserDetailedResponse),
-        (status = StatusCode::BAD_REQUEST, body = UserDetailedResponse),
-        (status = StatusCode::INTERNAL_SERVER_ERROR, body = UserDetailedResponse)
+        (status = 200, description = "Found", body = UserProfileListDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = UserProfileListDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = UserProfileListDetailedResponse)
     ),
 )]
+#[instrument(skip_all)]
 pub(super) async fn get_all(
     State(state): State<Arc<YaddakState>>,
     headers: HeaderMap,
-) -> (StatusCode, Json<UserListDetailedResponse>) {
+) -> SResult<Json<UserProfileListDetailedResponse>> {
     let client = &state.db;
-    match auth_handler(headers) {
-        Ok(ah) => {
-           if let Err(e) = User::check_auth(client.clone(), ah).await {
-                return (StatusCode::UNAUTHORIZED, Json(DetailedResponse::absorb_error(e)));
-           }
-        },
-        Err(err) => return (StatusCode::FORBIDDEN, Json(DetailedResponse::absorb_error(err)))
-    }
-
-    match User::get_all(client.clone()).await {
-        Ok(user) => (StatusCode::OK, Json(DetailedResponse::absorb_data(user))),
-        Err(err) => (StatusCode::INTERNAL_SERVER_ERROR, Json(DetailedResponse::absorb_error(err)))
-    }    
+    authenticate(client.clone(), headers).await?;
+    let users = User::get_all(client.clone()).await?.into_iter().map(UserProfile::from).collect();
+    Ok(Json(DetailedResponse::absorb_data(users)))
 }
 
 #[utoipa::path(
     put,
     path = "/auth/user/{id}",
-    request_body = User,
+    request_body = UserRequest,
     responses(
-        (status = 200, description = "Created", body = UserDetailedResponse),
-        (status = StatusCode::UNAUTHORIZED, body = UserDetailedResponse),
-        (status = StatusCode::FORBIDDEN, body = UserDetailedResponse),
-        (status = StatusCode::INTERNAL_SERVER_ERROR, body = UserDetailedResponse)
+        (status = 200, description = "Updated", body = UserProfileDetailedResponse),
+        (status = StatusCode::UNPROCESSABLE_ENTITY, description = "Invalid fields", body = UserProfileDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = UserProfileDetailedResponse),
+        (status = StatusCode::FORBIDDEN, description = "Neither the user nor an admin", body = UserProfileDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = UserProfileDetailedResponse),
+        (status = StatusCode::CONFLICT, description = "Username or email already used", body = UserProfileDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = UserProfileDetailedResponse)
     ),
     params(
         ("id"=Uuid, Path, description = "ID of the user")
     )
 )]
+#[instrument(skip_all)]
 pub(super) async fn update(
     State(state): State<Arc<YaddakState>>,
     Path(id): Path<Uuid>,
     headers: HeaderMap,
-    Json(payload): Json<User>
-) -> (StatusCode, Json<UserDetailedResponse>) {
+    ValidatedJson(payload): ValidatedJson<UserRequest>
+) -> SResult<Json<UserProfileDetailedResponse>> {
     let client = &state.db;
-    match auth_handler(headers) {
-        Ok(ah) => {
-           if let Err(e) = User::check_auth(client.clone(), ah).await {
-                return (StatusCode::UNAUTHORIZED, Json(DetailedResponse::absorb_error(e)));
-           }
-        },
-        Err(err) => return (StatusCode::FORBIDDEN, Json(DetailedResponse::absorb_error(err)))
+    let actor = authenticate_self_or_admin(client.clone(), headers, id).await?;
+    let before = User::get(client.clone(), id).await?;
+    if payload.user_name != before.user_name {
+        User::user_name_not_used(client.clone(), payload.user_name.clone()).await?;
     }
-    match User::put(client.clone(), id, &payload).await {
-        Ok(()) => (StatusCode::OK, Json(DetailedResponse::absorb_data(payload))),
-        Err(err) => (StatusCode::NOT_MODIFIED, Json(DetailedResponse::absorb_error(err)))
+    if payload.user_email != before.user_email {
+        User::user_email_not_used(client.clone(), payload.user_email.clone()).await?;
     }
+    let after = User {
+        user_name: payload.user_name,
+        user_email: payload.user_email,
+        ..before.clone()
+    };
+    User::put(client.clone(), id, &after).await?;
+    audit(client.clone(), Some(actor), AuditAction::Update, Some(&before), Some(&after)).await;
+    Ok(Json(DetailedResponse::absorb_data(UserProfile::from(after))))
 }
 
 #[utoipa::path(
-    put,
+    delete,
     path = "/auth/user/{id}",
-    request_body = User,
     responses(
-        (status = 200, description = "Created", body = UserDetailedResponse),
-        (status = StatusCode::UNAUTHORIZED, body = UserDetailedResponse),
-        (status = StatusCode::FORBIDDEN, body = UserDetailedResponse),
-        (status = StatusCode::INTERNAL_SERVER_ERROR, body = UserDetailedResponse)
+        (status = 200, description = "Deleted", body = UuidDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = UuidDetailedResponse),
+        (status = StatusCode::FORBIDDEN, description = "Neither the user nor an admin", body = UuidDetailedResponse),
+        (status = StatusCode::NOT_FOUND, body = UuidDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = UuidDetailedResponse)
     ),
     params(
         ("id"=Uuid, Path, description = "ID of the user")
     )
 )]
+#[instrument(skip_all)]
 pub(super) async fn remove(
     State(state): State<Arc<YaddakState>>,
     headers: HeaderMap,
     Path(id): Path<Uuid>,
-) -> (StatusCode, Json<UuidDetailedResponse>) {
+) -> SResult<Json<UuidDetailedResponse>> {
     let client = &state.db;
-    match auth_handler(headers) {
-        Ok(ah) => {
-           if let Err(e) = User::check_auth(client.clone(), ah).await {
-                return (StatusCode::UNAUTHORIZED, Json(DetailedResponse::absorb_error(e)));
-           }
-        },
-        Err(err) => return (StatusCode::FORBIDDEN, Json(DetailedResponse::absorb_error(err)))
-    }
-    match User::delete(client.clone(), id).await {
-        Ok(()) => (StatusCode::OK, Json(DetailedResponse::absorb_data(id))),
-        Err(err) => (StatusCode::NOT_MODIFIED, Json(DetailedResponse::absorb_error(err)))
-    }
+    let actor = authenticate_self_or_admin(client.clone(), headers, id).await?;
+    let before = User::get(client.clone(), id).await?;
+    User::delete(client.clone(), id).await?;
+    audit(client.clone(), Some(actor), AuditAction::Delete, Some(&before), None).await;
+    Ok(Json(DetailedResponse::absorb_data(id)))
 }
 
 pub fn user_controller(state: Arc<YaddakState>) -> Router {
@@ -243,4 +216,3 @@ pub fn user_auth_controller(state: Arc<YaddakState>) -> Router {
         .route("/:id", get(get_user).put(update).delete(remove))
         .with_state(state)
 }
-
@@ -0,0 +1,244 @@
+use std::{collections::{HashMap, HashSet}, sync::Arc};
+
+use axum::{
+    Json,
+    extract::{State, Path},
+    routing::{
+        Router,
+        post
+    }
+};
+use chrono::Utc;
+use hyper::HeaderMap;
+use tracing::instrument;
+use uuid::Uuid;
+
+use crate::{models::{
+    campaign::{Campaign, Leveling},
+    character::Character,
+    monsters::{challenge_xp, Monster},
+    rules::{level_for_xp, xp_for_level},
+    session::{Session, SessionLog, XpEntry},
+    xp::{AwardOutcome, AwardReport, AwardRequest, MilestoneRequest, XpAward},
+    detailed_response::{
+        DetailedResponse,
+        AwardReportDetailedResponse,
+        AwardOutcomeListDetailedResponse
+    },
+    state::YaddakState,
+    errors::{FieldError, SResult, YaddakError},
+    audit::AuditAction,
+}, traits::{repo::Repo, audit::audit}, utilities::{headers::authenticate, validated_json::ValidatedJson}};
+
+/// The campaign's characters named in `ids`, in that order. Anyone named
+/// twice or not in the campaign is a validation error.
+async fn party(client: String, id: Uuid, ids: &[Uuid]) -> SResult<Vec<Character>> {
+    let mut members: HashMap<Uuid, Character> = Campaign::characters(client, id)
+        .await?
+        .into_iter()
+        .map(|character| (character.id, character))
+        .collect();
+    let mut seen = HashSet::new();
+    let mut errors = Vec::new();
+    let mut characters = Vec::new();
+    for character_id in ids {
+        if !seen.insert(*character_id) {
+            errors.push(FieldError {
+                field: "characters".to_string(),
+                message: format!("character {character_id} is listed more than once"),
+            });
+        } else if let Some(character) = members.remove(character_id) {
+            characters.push(character);
+        } else {
+            errors.push(FieldError {
+                field: "characters".to_string(),
+                message: format!("character {character_id} is not in this campaign"),
+            });
+        }
+    }
+    if !errors.is_empty() {
+        return Err(YaddakError::validation(errors));
+    }
+    Ok(characters)
+}
+
+/// Adds `xp` to each character, records why, appends the awards to the
+/// log of `session` when there is one, and reports where they stand. The
+/// writes share a transaction so a failure leaves nothing half awarded.
+async fn grant(
+    client: String,
+    actor: Uuid,
+    template: &XpAward,
+    shares: Vec<(Character, i32)>,
+    session: Option<Session>,
+) -> SResult<Vec<AwardOutcome>> {
+    let awards: Vec<XpAward> = shares
+        .iter()
+        .map(|(character, xp)| XpAward {
+            id: Uuid::new_v4(),
+            character_id: character.id,
+            xp: *xp,
+            created_at: Utc::now(),
+            ..template.clone()
+        })
+        .collect();
+    let log = session.as_ref().map(|session| SessionLog {
+        xp: session.log.xp
+            .iter()
+            .cloned()
+            .chain(awards.iter().map(|award| XpEntry { character_id: award.character_id, xp: award.xp }))
+            .collect(),
+        ..session.log.clone()
+    });
+    let totals = XpAward::grant(
+        client.clone(),
+        &awards,
+        session.as_ref().zip(log.as_ref()).map(|(session, log)| (session.id, log)),
+    ).await?;
+
+    let mut outcomes = Vec::with_capacity(shares.len());
+    for ((before, xp), total_xp) in shares.into_iter().zip(totals) {
+        let after = Character { xp: total_xp, ..before.clone() };
+        audit(client.clone(), Some(actor), AuditAction::Update, Some(&before), Some(&after)).await;
+
+        let level = after.level();
+        let xp_level = level_for_xp(total_xp);
+        outcomes.push(AwardOutcome {
+            character_id: after.id,
+            name: after.name,
+            xp_awarded: xp,
+            total_xp,
+            level,
+            xp_level,
+            level_up: xp_level > level,
+        });
+    }
+    if let Some(before) = session {
+        let after = Session::get(client.clone(), before.id).await?;
+        audit(client, Some(actor), AuditAction::Update, Some(&before), Some(&after)).await;
+    }
+    Ok(outcomes)
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/campaign/{id}/session/{session_id}/award",
+    request_body = AwardRequest,
+    responses(
+        (status = 200, description = "XP of the defeated monsters, split evenly between the characters", body = AwardReportDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = AwardReportDetailedResponse),
+        (status = StatusCode::FORBIDDEN, description = "Only DMs award XP", body = AwardReportDetailedResponse),
+        (status = StatusCode::NOT_FOUND, description = "No such campaign, session or monster", body = AwardReportDetailedResponse),
+        (status = StatusCode::CONFLICT, description = "The campaign levels by milestone", body = AwardReportDetailedResponse),
+        (status = StatusCode::UNPROCESSABLE_ENTITY, description = "Invalid fields, or a character not in the campaign", body = AwardReportDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = AwardReportDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign"),
+        ("session_id"=Uuid, Path, description = "ID of the session")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn award(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path((id, session_id)): Path<(Uuid, Uuid)>,
+    ValidatedJson(payload): ValidatedJson<AwardRequest>,
+) -> SResult<Json<AwardReportDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    Campaign::require_dm(client.clone(), id, actor).await?;
+    let campaign = Campaign::get(client.clone(), id).await?;
+    if campaign.leveling == Leveling::Milestone {
+        return Err(YaddakError::conflict(format!("Campaign {id} levels by milestone, use its milestone route")));
+    }
+    let session = Session::get_in(client.clone(), id, session_id).await?;
+    let characters = party(client.clone(), id, &payload.characters).await?;
+
+    let mut total_xp = payload.bonus_xp;
+    for defeated in &payload.defeated {
+        let monster = Monster::get(client.clone(), defeated.monster_id).await?;
+        // every stored rating is on the table, validation and import see to it
+        let xp = challenge_xp(monster.challenge).unwrap_or(0) as i32;
+        total_xp += xp * defeated.count;
+    }
+    let per_character = total_xp / characters.len() as i32;
+
+    let template = XpAward {
+        campaign_id: id,
+        session_id: Some(session_id),
+        reason: payload.reason,
+        awarded_by: actor,
+        ..Default::default()
+    };
+    let shares = characters.into_iter().map(|character| (character, per_character)).collect();
+    let outcomes = grant(client.clone(), actor, &template, shares, Some(session)).await?;
+    Ok(Json(DetailedResponse::absorb_data(AwardReport { total_xp, per_character, characters: outcomes })))
+}
+
+#[utoipa::path(
+    post,
+    path = "/auth/campaign/{id}/milestone",
+    request_body = MilestoneRequest,
+    responses(
+        (status = 200, description = "Each character given the XP its next level needs, none past 20", body = AwardOutcomeListDetailedResponse),
+        (status = StatusCode::UNAUTHORIZED, body = AwardOutcomeListDetailedResponse),
+        (status = StatusCode::FORBIDDEN, description = "Only DMs declare milestones", body = AwardOutcomeListDetailedResponse),
+        (status = StatusCode::NOT_FOUND, description = "No such campaign or session", body = AwardOutcomeListDetailedResponse),
+        (status = StatusCode::CONFLICT, description = "The campaign levels by XP", body = AwardOutcomeListDetailedResponse),
+        (status = StatusCode::UNPROCESSABLE_ENTITY, description = "Invalid fields, or a character not in the campaign", body = AwardOutcomeListDetailedResponse),
+        (status = StatusCode::INTERNAL_SERVER_ERROR, body = AwardOutcomeListDetailedResponse)
+    ),
+    params(
+        ("id"=Uuid, Path, description = "ID of the campaign")
+    )
+)]
+#[instrument(skip_all)]
+pub(super) async fn milestone(
+    State(state): State<Arc<YaddakState>>,
+    headers: HeaderMap,
+    Path(id): Path<Uuid>,
+    ValidatedJson(payload): ValidatedJson<MilestoneRequest>,
+) -> SResult<Json<AwardOutcomeListDetailedResponse>> {
+    let client = &state.db;
+    let actor = authenticate(client.clone(), headers).await?;
+    Campaign::require_dm(client.clone(), id, actor).await?;
+    let campaign = Campaign::get(client.clone(), id).await?;
+    if campaign.leveling == Leveling::Xp {
+        return Err(YaddakError::conflict(format!("Campaign {id} levels by XP, award it per session")));
+    }
+    let session = match payload.session_id {
+        Some(session_id) => Some(Session::get_in(client.clone(), id, session_id).await?),
+        None => None,
+    };
+    let characters = party(client.clone(), id, &payload.characters).await?;
+
+    // a milestone is one level, XP is topped up to match so a switch back
+    // to XP leveling carries on from there
+    let shares = characters
+        .into_iter()
+        .map(|character| {
+            let level = character.level();
+            let xp = if level < 20 { (xp_for_level(level + 1) - character.xp).max(0) } else { 0 };
+            (character, xp)
+        })
+        .collect();
+    let template = XpAward {
+        campaign_id: id,
+        session_id: payload.session_id,
+        milestone: true,
+        reason: payload.reason,
+        awarded_by: actor,
+        ..Default::default()
+    };
+    let outcomes = grant(client.clone(), actor, &template, shares, session).await?;
+    Ok(Json(DetailedResponse::absorb_data(outcomes)))
+}
+
+/// Merged into the campaign router, awards are made within a campaign.
+pub fn xp_auth_controller(state: Arc<YaddakState>) -> Router {
+    Router::new()
+        .route("/:id/session/:session_id/award", post(award))
+        .route("/:id/milestone", post(milestone))
+        .with_state(state)
+}
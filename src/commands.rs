@@ -0,0 +1,232 @@
+use std::path::Path;
+
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    config::{AssetAction, Command, Config, MigrateAction, MonsterAction, UserAction},
+    models::{
+        asset::{self, MirrorReport},
+        audit::AuditAction,
+        dump::Dump,
+        errors::{SResult, YaddakError, YaddakErrorKind},
+        monster_import::{self, ImportQuery, ImportReport},
+        monsters::{Monster, WOTCUUID},
+        schema::{SchemaVersion, SCHEMA_VERSION},
+        user::{CreateUserRequest, User},
+    },
+    traits::{self, audit::audit, repo::init_pool},
+    utilities::{tls, storage::LocalStore},
+};
+
+/// Runs an operations command. Everything but `serve` and `dev-cert`
+/// ends up here; results go to stdout, logs to stderr.
+pub async fn run(command: &Command, config: &Config) -> SResult<()> {
+    if let Command::CheckConfig = command {
+        return check_config(config).await;
+    }
+
+    init_pool(&config.database).await?;
+    let con_str = config.database.url.clone();
+    match command {
+        Command::Migrate { action: MigrateAction::Up } => {
+            traits::migrate(con_str).await?;
+            println!("schema is at version {SCHEMA_VERSION}");
+        }
+        Command::Migrate { action: MigrateAction::Down { yes } } => {
+            if !yes {
+                return Err(YaddakError::bad_request(
+                    "migrate down drops every table and its data, pass --yes to confirm".to_string(),
+                ));
+            }
+            traits::rollback(con_str).await?;
+            println!("dropped all tables");
+        }
+        Command::Migrate { action: MigrateAction::Status } => {
+            match SchemaVersion::current(con_str.clone()).await? {
+                None => println!("schema: not migrated (expected version {SCHEMA_VERSION})"),
+                Some(version) => {
+                    let state = if version == SCHEMA_VERSION { "up to date" } else { "needs `migrate up`" };
+                    println!("schema: version {version}, {state} (expected version {SCHEMA_VERSION})");
+                    let seeded = Monster::seeded(con_str).await?;
+                    println!("seed data: {}", if seeded { "loaded" } else { "missing, run `seed`" });
+                }
+            }
+        }
+        Command::Seed { file, spells, items } => {
+            let report = traits::seed(con_str.clone(), file.as_deref()).await?;
+            print_import_report(&report);
+            let added = traits::seed_spells(con_str.clone(), spells.as_deref()).await?;
+            println!("spells: added {added}");
+            let added = traits::seed_items(con_str, items.as_deref()).await?;
+            println!("items: added {added}");
+        }
+        Command::User { action: UserAction::CreateAdmin { name, email, password } } => {
+            CreateUserRequest {
+                user_name: name.clone(),
+                user_email: email.clone(),
+                user_pass: password.clone(),
+            }.validate()?;
+            let user = User::create(con_str.clone(), name.clone(), email.clone(), password.clone()).await?;
+            User::set_admin(con_str.clone(), user.id, true).await?;
+            audit(con_str, None, AuditAction::Create, None, Some(&user)).await;
+            println!("created admin {} ({})", user.user_name, user.id);
+        }
+        Command::User { action: UserAction::ResetPassword { name, password } } => {
+            let before = User::get_user_by_name(con_str.clone(), name.clone()).await?;
+            CreateUserRequest {
+                user_name: before.user_name.clone(),
+                user_email: before.user_email.clone(),
+                user_pass: password.clone(),
+            }.validate()?;
+            let after = User::reset_password(con_str.clone(), name.clone(), password.clone()).await?;
+            audit(con_str, None, AuditAction::Update, Some(&before), Some(&after)).await;
+            println!("reset the password of {} ({})", after.user_name, after.id);
+        }
+        Command::Monster { action: MonsterAction::Import { file, format, source, owner, dry_run } } => {
+            let owner = match owner {
+                Some(name) => User::get_user_by_name(con_str.clone(), name.clone()).await?.id,
+                None => {
+                    let system_user = Uuid::parse_str(WOTCUUID).unwrap();
+                    User::ensure_system_user(con_str.clone(), system_user, "wotc").await?;
+                    system_user
+                }
+            };
+            let content = tokio::fs::read_to_string(file).await?;
+            let query = ImportQuery { format: *format, source: source.clone(), dry_run: *dry_run };
+            let (report, created) = monster_import::import(con_str.clone(), owner, &content, &query).await?;
+            for monster in &created {
+                audit(con_str.clone(), None, AuditAction::Create, None, Some(monster)).await;
+            }
+            print_import_report(&report);
+        }
+        Command::Asset { action: AssetAction::Mirror { dry_run } } => {
+            let store = LocalStore::new(&config.storage);
+            let report = asset::mirror(
+                con_str,
+                &store,
+                config.storage.upload_limit,
+                config.storage.thumbnail_size,
+                *dry_run,
+            ).await?;
+            print_mirror_report(&report);
+        }
+        Command::Export { output, partial } => {
+            let dump = Dump::export(con_str.clone()).await?;
+            let left_behind = dump.left_behind(con_str).await?;
+            if !left_behind.is_empty() {
+                let message = format!(
+                    "the export cannot carry {} campaigns, {} sessions, {} XP awards or the stored images of {} monsters and characters",
+                    left_behind.campaigns,
+                    left_behind.sessions,
+                    left_behind.xp_awards,
+                    left_behind.stored_images,
+                );
+                if !partial {
+                    return Err(YaddakError::bad_request(format!("{message}, pass --partial to export without them")));
+                }
+                eprintln!("warning: {message}");
+            }
+            let json = serde_json::to_string_pretty(&dump)?;
+            match output {
+                Some(path) => {
+                    tokio::fs::write(path, json).await?;
+                    eprintln!(
+                        "exported {} users and {} monsters to {}",
+                        dump.users.len(),
+                        dump.monsters.len(),
+                        path.display()
+                    );
+                }
+                None => println!("{json}"),
+            }
+        }
+        Command::Import { file } => {
+            let dump = read_dump(file).await?;
+            let summary = Dump::import(con_str, &dump).await?;
+            println!(
+                "users: {} imported, {} skipped\nmonsters: {} imported, {} skipped\ncharacters: {} imported, {} skipped",
+                summary.users_imported,
+                summary.users_skipped,
+                summary.monsters_imported,
+                summary.monsters_skipped,
+                summary.characters_imported,
+                summary.characters_skipped,
+            );
+        }
+        Command::Serve | Command::DevCert { .. } | Command::CheckConfig => {
+            unreachable!("handled in main")
+        }
+    }
+    Ok(())
+}
+
+/// `Config::load` already rejected anything invalid, so this only checks
+/// what needs IO: the certificate exists and parses and the database
+/// answers.
+async fn check_config(config: &Config) -> SResult<()> {
+    println!("{config:#?}");
+    if config.server.tls {
+        tls::load(&config.tls).await.map_err(|err| YaddakError::new(
+            YaddakErrorKind::EnvError,
+            format!(
+                "TLS certificate {} or key {} could not be loaded (run `yaddak-encounter-server dev-cert` for a local one): {}",
+                config.tls.cert_path.display(),
+                config.tls.key_path.display(),
+                err,
+            ),
+        ))?;
+        println!("TLS certificate: ok");
+    }
+    init_pool(&config.database).await?;
+    println!("database: ok");
+    Ok(())
+}
+
+fn print_import_report(report: &ImportReport) {
+    let verb = if report.dry_run { "would create" } else { "created" };
+    println!(
+        "{} records: {verb} {}, {} duplicates, {} errors",
+        report.total,
+        report.created.len(),
+        report.duplicates.len(),
+        report.errors.len(),
+    );
+    for error in &report.errors {
+        let name = error.name.as_deref().unwrap_or("unnamed");
+        for detail in &error.errors {
+            println!("  row {} ({name}): {}: {}", error.row, detail.field, detail.message);
+        }
+    }
+}
+
+fn print_mirror_report(report: &MirrorReport) {
+    if report.dry_run {
+        println!("{} remote images on {} monsters would be mirrored", report.urls, report.monsters);
+        return;
+    }
+    println!(
+        "{} remote images: {} downloaded, {} already stored, {} failed; {} monsters updated",
+        report.urls,
+        report.downloaded,
+        report.reused,
+        report.failures.len(),
+        report.monsters,
+    );
+    for (url, err) in &report.failures {
+        println!("  {url}: {err}");
+    }
+}
+
+async fn read_dump(file: &Path) -> SResult<Dump> {
+    let content = tokio::fs::read_to_string(file).await?;
+    let dump: Dump = serde_json::from_str(&content)?;
+    if dump.schema_version > SCHEMA_VERSION {
+        return Err(YaddakError::bad_request(format!(
+            "{} was exported at schema version {}, this build only knows {SCHEMA_VERSION}",
+            file.display(),
+            dump.schema_version
+        )));
+    }
+    Ok(dump)
+}
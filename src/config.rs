@@ -0,0 +1,713 @@
+use std::{
+    net::{IpAddr, Ipv4Addr, SocketAddr},
+    path::{Path, PathBuf},
+    sync::OnceLock,
+};
+
+use clap::{Args, Parser, Subcommand, ValueEnum};
+use serde::Deserialize;
+
+use crate::models::{errors::{SResult, YaddakError, YaddakErrorKind}, monster_import::ImportFormat};
+
+static CONFIG: OnceLock<Config> = OnceLock::new();
+
+const DEFAULT_CONFIG_FILE: &str = "yaddak.toml";
+
+/// Settings are layered: built in defaults, then the TOML file, then
+/// environment variables, then command line flags.
+#[derive(Debug, Clone)]
+pub struct Config {
+    pub server: ServerConfig,
+    pub http: HttpConfig,
+    pub tls: TlsConfig,
+    pub database: DatabaseConfig,
+    pub log: LogConfig,
+    pub auth: AuthConfig,
+    pub storage: StorageConfig,
+    pub metrics: MetricsConfig,
+}
+
+#[derive(Debug, Clone)]
+pub struct ServerConfig {
+    pub bind_address: IpAddr,
+    pub http_port: u16,
+    pub https_port: u16,
+    /// Serve HTTPS on `https_port`. When off the API is served as plain
+    /// HTTP on `http_port`, for running behind a TLS terminating proxy.
+    pub tls: bool,
+    /// With TLS on, also listen on `http_port` and redirect to HTTPS.
+    pub redirect_http: bool,
+}
+
+#[derive(Debug, Clone)]
+pub struct HttpConfig {
+    /// Origins the browser frontend is served from. Empty disables CORS,
+    /// `*` allows any origin but cannot be combined with credentials.
+    pub cors_origins: Vec<String>,
+    pub cors_credentials: bool,
+    /// Send `Strict-Transport-Security`, only honoured when serving TLS.
+    pub hsts: bool,
+    /// Largest request body in bytes for the user and admin routes.
+    pub body_limit: usize,
+    /// Largest request body in bytes for the monster and character routes,
+    /// whose stat blocks and inventories carry long text.
+    pub monster_body_limit: usize,
+    /// Largest file in bytes accepted by the monster import endpoint.
+    pub import_body_limit: usize,
+}
+
+#[derive(Debug, Clone)]
+pub struct TlsConfig {
+    pub cert_path: PathBuf,
+    pub key_path: PathBuf,
+    /// CA bundle for client certificates. When set, admin routes only
+    /// answer connections that presented a certificate signed by it.
+    pub client_ca_path: Option<PathBuf>,
+    /// Reload the certificate every this many seconds, 0 for SIGHUP only.
+    pub reload_interval_secs: u64,
+}
+
+#[derive(Clone)]
+pub struct DatabaseConfig {
+    pub url: String,
+    pub max_connections: u32,
+    pub min_connections: u32,
+    pub retention_days: i64,
+}
+
+#[derive(Debug, Clone)]
+pub struct LogConfig {
+    pub filter: String,
+    pub format: LogFormat,
+    /// OTLP gRPC collector spans are exported to, needs the `otel` feature.
+    pub otlp_endpoint: Option<String>,
+}
+
+#[derive(Debug, Clone)]
+pub struct StorageConfig {
+    /// Directory uploaded and mirrored images are written to.
+    pub path: PathBuf,
+    /// Largest image in bytes accepted by an upload or a mirror download.
+    pub upload_limit: usize,
+    /// Longest edge in pixels of generated thumbnails.
+    pub thumbnail_size: u32,
+    /// Directory the bundled `monsters.json`, `spells.json` and `items.json`
+    /// seed files are read from.
+    pub data_dir: PathBuf,
+}
+
+/// The Prometheus scrape endpoint, served on its own plain HTTP listener
+/// so it is never reachable through the API's.
+#[derive(Clone)]
+pub struct MetricsConfig {
+    pub listen: SocketAddr,
+    /// Bearer token scrapers have to send, for when `listen` is reachable
+    /// from other hosts.
+    pub token: Option<String>,
+}
+
+#[derive(Debug, Clone, Copy, Default, PartialEq, Eq, Deserialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum LogFormat {
+    /// Human readable lines without timestamps, for a terminal
+    #[default]
+    Pretty,
+    /// One JSON object per event with a timestamp, for log shippers
+    Json,
+}
+
+/// Argon2 parameters. Stored hashes do not carry their parameters, so
+/// changing any of these (or the secret) invalidates existing passwords.
+#[derive(Clone)]
+pub struct AuthConfig {
+    pub secret: String,
+    pub argon2_mem_cost: u32,
+    pub argon2_time_cost: u32,
+    pub argon2_lanes: u32,
+}
+
+// the url may embed the database password
+impl std::fmt::Debug for DatabaseConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        let url = match url::Url::parse(&self.url) {
+            Ok(mut url) if url.password().is_some() => {
+                let _ = url.set_password(Some("***"));
+                url.to_string()
+            }
+            _ => self.url.clone(),
+        };
+        f.debug_struct("DatabaseConfig")
+            .field("url", &url)
+            .field("max_connections", &self.max_connections)
+            .field("min_connections", &self.min_connections)
+            .field("retention_days", &self.retention_days)
+            .finish()
+    }
+}
+
+impl std::fmt::Debug for AuthConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("AuthConfig")
+            .field("secret", &"[redacted]")
+            .field("argon2_mem_cost", &self.argon2_mem_cost)
+            .field("argon2_time_cost", &self.argon2_time_cost)
+            .field("argon2_lanes", &self.argon2_lanes)
+            .finish()
+    }
+}
+
+impl std::fmt::Debug for MetricsConfig {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("MetricsConfig")
+            .field("listen", &self.listen)
+            .field("token", &self.token.as_ref().map(|_| "[redacted]"))
+            .finish()
+    }
+}
+
+#[derive(Parser, Debug, Default)]
+#[command(version, about = "Yet Another Dungeons and Dragons All in one Kit")]
+pub struct Cli {
+    /// Path to a TOML config file, defaults to ./yaddak.toml when present
+    #[arg(long, short, env = "YADDAK_CONFIG")]
+    pub config: Option<PathBuf>,
+    #[command(flatten)]
+    pub overrides: Overrides,
+    #[command(subcommand)]
+    pub command: Option<Command>,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum Command {
+    /// Run the API server, the default when no command is given
+    Serve,
+    /// Manage the database schema
+    Migrate {
+        #[command(subcommand)]
+        action: MigrateAction,
+    },
+    /// Load SRD monsters, spells and items, skipping names that are already loaded
+    Seed {
+        /// File in the bundled data/monsters.json shape, defaults to the bundled one
+        #[arg(long)]
+        file: Option<PathBuf>,
+        /// File in the bundled data/spells.json shape, defaults to the bundled one
+        #[arg(long)]
+        spells: Option<PathBuf>,
+        /// File in the bundled data/items.json shape, defaults to the bundled one
+        #[arg(long)]
+        items: Option<PathBuf>,
+    },
+    /// Manage user accounts
+    User {
+        #[command(subcommand)]
+        action: UserAction,
+    },
+    /// Manage monsters
+    Monster {
+        #[command(subcommand)]
+        action: MonsterAction,
+    },
+    /// Manage stored images
+    Asset {
+        #[command(subcommand)]
+        action: AssetAction,
+    },
+    /// Write every active user and monster as JSON
+    Export {
+        /// Output file, stdout when omitted
+        #[arg(long, short)]
+        output: Option<PathBuf>,
+        /// Export even though campaigns, sessions, XP awards and stored
+        /// images cannot be carried
+        #[arg(long)]
+        partial: bool,
+    },
+    /// Load a file written by `export`, skipping records that already exist
+    Import {
+        file: PathBuf,
+    },
+    /// Validate the configuration and print it with secrets redacted
+    CheckConfig,
+    /// Write a self signed certificate and key for local development
+    DevCert {
+        /// Host names and addresses the certificate is valid for
+        #[arg(long = "host", default_values = ["localhost", "127.0.0.1"])]
+        hosts: Vec<String>,
+        /// Overwrite an existing certificate or key
+        #[arg(long)]
+        force: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum MigrateAction {
+    /// Create or update tables to the current schema version
+    Up,
+    /// Drop every table, data included
+    Down {
+        /// Confirm that all data should be dropped
+        #[arg(long)]
+        yes: bool,
+    },
+    /// Show the schema version the database is at
+    Status,
+}
+
+#[derive(Subcommand, Debug)]
+pub enum UserAction {
+    /// Create a user with admin rights
+    CreateAdmin {
+        #[arg(long)]
+        name: String,
+        #[arg(long)]
+        email: String,
+        #[arg(long, env = "YADDAK_USER_PASSWORD", hide_env_values = true)]
+        password: String,
+    },
+    /// Set a new password for an existing user
+    ResetPassword {
+        #[arg(long)]
+        name: String,
+        #[arg(long, env = "YADDAK_USER_PASSWORD", hide_env_values = true)]
+        password: String,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum MonsterAction {
+    /// Bulk import monsters from JSON or CSV, skipping duplicates
+    Import {
+        file: PathBuf,
+        #[arg(long, value_enum, default_value_t = ImportFormat::Yaddak)]
+        format: ImportFormat,
+        /// Source for records that do not name one
+        #[arg(long)]
+        source: Option<String>,
+        /// User name that will own the monsters, the SRD system user when omitted
+        #[arg(long)]
+        owner: Option<String>,
+        /// Validate and report without writing anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+#[derive(Subcommand, Debug)]
+pub enum AssetAction {
+    /// Download every remote monster `img_url` into local storage and point
+    /// the monster at the stored copy
+    Mirror {
+        /// List what would be downloaded without fetching anything
+        #[arg(long)]
+        dry_run: bool,
+    },
+}
+
+/// Every setting that can come from the environment or a flag.
+#[derive(Args, Debug, Default, Clone)]
+pub struct Overrides {
+    #[arg(long, env = "YADDAK_BIND_ADDRESS")]
+    pub bind_address: Option<IpAddr>,
+    #[arg(long, env = "YADDAK_HTTP_PORT")]
+    pub http_port: Option<u16>,
+    #[arg(long, env = "YADDAK_HTTPS_PORT")]
+    pub https_port: Option<u16>,
+    /// Set to false to serve plain HTTP when a proxy terminates TLS
+    #[arg(long, env = "YADDAK_TLS")]
+    pub tls: Option<bool>,
+    /// Set to false to skip the HTTP to HTTPS redirect listener
+    #[arg(long, env = "YADDAK_REDIRECT_HTTP")]
+    pub redirect_http: Option<bool>,
+    /// Comma separated origins allowed to call the API from a browser
+    #[arg(long, env = "YADDAK_CORS_ORIGINS", value_delimiter = ',')]
+    pub cors_origins: Option<Vec<String>>,
+    #[arg(long, env = "YADDAK_CORS_CREDENTIALS")]
+    pub cors_credentials: Option<bool>,
+    #[arg(long, env = "YADDAK_HSTS")]
+    pub hsts: Option<bool>,
+    #[arg(long, env = "YADDAK_BODY_LIMIT")]
+    pub body_limit: Option<usize>,
+    #[arg(long, env = "YADDAK_MONSTER_BODY_LIMIT")]
+    pub monster_body_limit: Option<usize>,
+    #[arg(long, env = "YADDAK_IMPORT_BODY_LIMIT")]
+    pub import_body_limit: Option<usize>,
+    #[arg(long, env = "YADDAK_TLS_CERT")]
+    pub tls_cert: Option<PathBuf>,
+    #[arg(long, env = "YADDAK_TLS_KEY")]
+    pub tls_key: Option<PathBuf>,
+    #[arg(long, env = "YADDAK_TLS_CLIENT_CA")]
+    pub tls_client_ca: Option<PathBuf>,
+    #[arg(long, env = "YADDAK_TLS_RELOAD_SECS")]
+    pub tls_reload_interval_secs: Option<u64>,
+    /// Full postgres:// URL, takes precedence over the DB_* parts
+    #[arg(long, env = "DATABASE_URL", hide_env_values = true)]
+    pub database_url: Option<String>,
+    #[arg(long, env = "DB_USER")]
+    pub db_user: Option<String>,
+    #[arg(long, env = "DB_PASSWORD", hide_env_values = true)]
+    pub db_password: Option<String>,
+    /// Database host and optional port
+    #[arg(long, env = "DB_URL")]
+    pub db_host: Option<String>,
+    #[arg(long, env = "DB_NAME")]
+    pub db_name: Option<String>,
+    #[arg(long, env = "YADDAK_DB_MAX_CONNECTIONS")]
+    pub db_max_connections: Option<u32>,
+    #[arg(long, env = "YADDAK_DB_MIN_CONNECTIONS")]
+    pub db_min_connections: Option<u32>,
+    #[arg(long, env = "YADDAK_RETENTION_DAYS")]
+    pub retention_days: Option<i64>,
+    #[arg(long, env = "YADDAK_LOG")]
+    pub log_filter: Option<String>,
+    #[arg(long, env = "YADDAK_LOG_FORMAT")]
+    pub log_format: Option<LogFormat>,
+    #[arg(long, env = "YADDAK_OTLP_ENDPOINT")]
+    pub otlp_endpoint: Option<String>,
+    #[arg(long, env = "YADDAK_SECRET", hide_env_values = true)]
+    pub secret: Option<String>,
+    #[arg(long, env = "YADDAK_ARGON2_MEM_COST")]
+    pub argon2_mem_cost: Option<u32>,
+    #[arg(long, env = "YADDAK_ARGON2_TIME_COST")]
+    pub argon2_time_cost: Option<u32>,
+    #[arg(long, env = "YADDAK_ARGON2_LANES")]
+    pub argon2_lanes: Option<u32>,
+    #[arg(long, env = "YADDAK_STORAGE_PATH")]
+    pub storage_path: Option<PathBuf>,
+    #[arg(long, env = "YADDAK_UPLOAD_LIMIT")]
+    pub upload_limit: Option<usize>,
+    #[arg(long, env = "YADDAK_THUMBNAIL_SIZE")]
+    pub thumbnail_size: Option<u32>,
+    #[arg(long, env = "YADDAK_DATA_DIR")]
+    pub data_dir: Option<PathBuf>,
+    #[arg(long, env = "YADDAK_METRICS_LISTEN")]
+    pub metrics_listen: Option<SocketAddr>,
+    #[arg(long, env = "YADDAK_METRICS_TOKEN", hide_env_values = true)]
+    pub metrics_token: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileConfig {
+    server: FileServer,
+    http: FileHttp,
+    tls: FileTls,
+    database: FileDatabase,
+    log: FileLog,
+    auth: FileAuth,
+    storage: FileStorage,
+    metrics: FileMetrics,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileServer {
+    bind_address: Option<IpAddr>,
+    http_port: Option<u16>,
+    https_port: Option<u16>,
+    tls: Option<bool>,
+    redirect_http: Option<bool>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileHttp {
+    cors_origins: Option<Vec<String>>,
+    cors_credentials: Option<bool>,
+    hsts: Option<bool>,
+    body_limit: Option<usize>,
+    monster_body_limit: Option<usize>,
+    import_body_limit: Option<usize>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileTls {
+    cert_path: Option<PathBuf>,
+    key_path: Option<PathBuf>,
+    client_ca_path: Option<PathBuf>,
+    reload_interval_secs: Option<u64>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileDatabase {
+    url: Option<String>,
+    user: Option<String>,
+    password: Option<String>,
+    host: Option<String>,
+    name: Option<String>,
+    max_connections: Option<u32>,
+    min_connections: Option<u32>,
+    retention_days: Option<i64>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileLog {
+    filter: Option<String>,
+    format: Option<LogFormat>,
+    otlp_endpoint: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileAuth {
+    secret: Option<String>,
+    argon2_mem_cost: Option<u32>,
+    argon2_time_cost: Option<u32>,
+    argon2_lanes: Option<u32>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileStorage {
+    path: Option<PathBuf>,
+    upload_limit: Option<usize>,
+    thumbnail_size: Option<u32>,
+    data_dir: Option<PathBuf>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default, deny_unknown_fields)]
+struct FileMetrics {
+    listen: Option<SocketAddr>,
+    token: Option<String>,
+}
+
+impl Config {
+    /// Reads the file named by `cli` and layers the overrides on top.
+    /// Every problem is collected so one run reports all of them.
+    pub fn load(cli: &Cli) -> SResult<Config> {
+        let file = read_file(cli.config.as_deref())?;
+        let o = &cli.overrides;
+        let mut problems: Vec<String> = Vec::new();
+
+        let database_url = match o.database_url.clone().or(file.database.url) {
+            Some(url) => url,
+            None => {
+                let mut part = |value: Option<String>, setting: &str, env: &str| {
+                    value.filter(|v| !v.is_empty()).unwrap_or_else(|| {
+                        problems.push(format!("database.{setting} is not set (env {env} or --db-{setting})"));
+                        String::new()
+                    })
+                };
+                let user = part(o.db_user.clone().or(file.database.user), "user", "DB_USER");
+                let password = part(o.db_password.clone().or(file.database.password), "password", "DB_PASSWORD");
+                let host = part(o.db_host.clone().or(file.database.host), "host", "DB_URL");
+                let name = part(o.db_name.clone().or(file.database.name), "name", "DB_NAME");
+                format!("postgres://{user}:{password}@{host}/{name}")
+            }
+        };
+
+        let secret = o.secret.clone()
+            .or(file.auth.secret)
+            .filter(|s| !s.is_empty())
+            .unwrap_or_else(|| {
+                problems.push("auth.secret is not set (env YADDAK_SECRET or --secret)".to_string());
+                String::new()
+            });
+
+        let tls = tls_paths(o, file.tls);
+
+        let server = ServerConfig {
+            bind_address: o.bind_address
+                .or(file.server.bind_address)
+                .unwrap_or(IpAddr::V4(Ipv4Addr::LOCALHOST)),
+            http_port: o.http_port.or(file.server.http_port).unwrap_or(8010),
+            https_port: o.https_port.or(file.server.https_port).unwrap_or(44310),
+            tls: o.tls.or(file.server.tls).unwrap_or(true),
+            redirect_http: o.redirect_http.or(file.server.redirect_http).unwrap_or(true),
+        };
+        // only `serve` reads the certificate; `check-config` loads it itself
+        let serving = matches!(cli.command, None | Some(Command::Serve));
+        if server.tls && serving {
+            for (setting, path) in [("tls.cert_path", &tls.cert_path), ("tls.key_path", &tls.key_path)] {
+                if !path.is_file() {
+                    problems.push(format!(
+                        "{setting} {} does not exist (run `yaddak-encounter-server dev-cert` for a local one)",
+                        path.display()
+                    ));
+                }
+            }
+            if let Some(ca) = tls.client_ca_path.as_ref().filter(|ca| !ca.is_file()) {
+                problems.push(format!("tls.client_ca_path {} does not exist", ca.display()));
+            }
+        } else if !server.tls && tls.client_ca_path.is_some() {
+            problems.push("tls.client_ca_path needs server.tls enabled".to_string());
+        }
+        if server.tls && server.redirect_http && server.http_port == server.https_port {
+            problems.push(format!("server.http_port and server.https_port are both {}", server.http_port));
+        }
+
+        let http = HttpConfig {
+            cors_origins: o.cors_origins.clone()
+                .or(file.http.cors_origins)
+                .unwrap_or_default()
+                .into_iter()
+                .map(|origin| origin.trim().trim_end_matches('/').to_string())
+                .filter(|origin| !origin.is_empty())
+                .collect(),
+            cors_credentials: o.cors_credentials.or(file.http.cors_credentials).unwrap_or(false),
+            hsts: o.hsts.or(file.http.hsts).unwrap_or(true),
+            body_limit: o.body_limit.or(file.http.body_limit).unwrap_or(16 * 1024),
+            monster_body_limit: o.monster_body_limit.or(file.http.monster_body_limit).unwrap_or(256 * 1024),
+            import_body_limit: o.import_body_limit.or(file.http.import_body_limit).unwrap_or(8 * 1024 * 1024),
+        };
+        for origin in &http.cors_origins {
+            if origin == "*" {
+                if http.cors_credentials {
+                    problems.push("http.cors_origins cannot be * with http.cors_credentials".to_string());
+                }
+                continue;
+            }
+            match url::Url::parse(origin) {
+                Ok(url) if url.has_host() && url.path() == "/" && url.query().is_none() => {},
+                _ => problems.push(format!("http.cors_origins {origin} is not an origin like https://example.com")),
+            }
+        }
+        if http.body_limit == 0 || http.monster_body_limit == 0 || http.import_body_limit == 0 {
+            problems.push("http.body_limit, http.monster_body_limit and http.import_body_limit must be at least 1".to_string());
+        }
+
+        let database = DatabaseConfig {
+            url: database_url,
+            max_connections: o.db_max_connections.or(file.database.max_connections).unwrap_or(10),
+            min_connections: o.db_min_connections.or(file.database.min_connections).unwrap_or(0),
+            retention_days: o.retention_days.or(file.database.retention_days).unwrap_or(30),
+        };
+        if database.max_connections == 0 {
+            problems.push("database.max_connections must be at least 1".to_string());
+        }
+        if database.min_connections > database.max_connections {
+            problems.push("database.min_connections is larger than database.max_connections".to_string());
+        }
+        if database.retention_days < 0 {
+            problems.push("database.retention_days cannot be negative".to_string());
+        }
+
+        let auth = AuthConfig {
+            secret,
+            argon2_mem_cost: o.argon2_mem_cost.or(file.auth.argon2_mem_cost).unwrap_or(65536),
+            argon2_time_cost: o.argon2_time_cost.or(file.auth.argon2_time_cost).unwrap_or(10),
+            argon2_lanes: o.argon2_lanes.or(file.auth.argon2_lanes).unwrap_or(4),
+        };
+        if auth.argon2_time_cost == 0 || auth.argon2_lanes == 0 {
+            problems.push("auth.argon2_time_cost and auth.argon2_lanes must be at least 1".to_string());
+        }
+        if auth.argon2_mem_cost < 8 * auth.argon2_lanes {
+            problems.push("auth.argon2_mem_cost must be at least 8 KiB per lane".to_string());
+        }
+
+        let log = LogConfig {
+            filter: o.log_filter.clone()
+                .or(file.log.filter)
+                .unwrap_or_else(|| "yaddak_encounter_server=debug,tower_http=debug,axum=trace".to_string()),
+            format: o.log_format.or(file.log.format).unwrap_or_default(),
+            otlp_endpoint: o.otlp_endpoint.clone().or(file.log.otlp_endpoint).filter(|e| !e.is_empty()),
+        };
+
+        let storage = StorageConfig {
+            path: o.storage_path.clone().or(file.storage.path).unwrap_or_else(|| PathBuf::from("assets")),
+            upload_limit: o.upload_limit.or(file.storage.upload_limit).unwrap_or(5 * 1024 * 1024),
+            thumbnail_size: o.thumbnail_size.or(file.storage.thumbnail_size).unwrap_or(256),
+            data_dir: o.data_dir.clone().or(file.storage.data_dir).unwrap_or_else(default_data_dir),
+        };
+        if storage.path.exists() && !storage.path.is_dir() {
+            problems.push(format!("storage.path {} is not a directory", storage.path.display()));
+        }
+        if storage.data_dir.exists() && !storage.data_dir.is_dir() {
+            problems.push(format!("storage.data_dir {} is not a directory", storage.data_dir.display()));
+        }
+        if storage.upload_limit == 0 {
+            problems.push("storage.upload_limit must be at least 1".to_string());
+        }
+        if !(16..=2048).contains(&storage.thumbnail_size) {
+            problems.push("storage.thumbnail_size must be between 16 and 2048".to_string());
+        }
+
+        let metrics = MetricsConfig {
+            listen: o.metrics_listen
+                .or(file.metrics.listen)
+                .unwrap_or(SocketAddr::new(IpAddr::V4(Ipv4Addr::LOCALHOST), 9464)),
+            token: o.metrics_token.clone().or(file.metrics.token).filter(|t| !t.is_empty()),
+        };
+        let api_ports = if server.tls && server.redirect_http {
+            vec![server.http_port, server.https_port]
+        } else if server.tls {
+            vec![server.https_port]
+        } else {
+            vec![server.http_port]
+        };
+        let shares_address = metrics.listen.ip() == server.bind_address
+            || metrics.listen.ip().is_unspecified()
+            || server.bind_address.is_unspecified();
+        if shares_address && api_ports.contains(&metrics.listen.port()) {
+            problems.push(format!("metrics.listen {} is already used by the API", metrics.listen));
+        }
+
+        if !problems.is_empty() {
+            return Err(YaddakError::new(
+                YaddakErrorKind::EnvError,
+                format!("invalid configuration:\n  - {}", problems.join("\n  - ")),
+            ));
+        }
+
+        Ok(Config { server, http, tls, database, log, auth, storage, metrics })
+    }
+}
+
+/// Only the TLS section, for commands that write certificates before a
+/// full config can be loaded.
+pub fn load_tls(cli: &Cli) -> SResult<TlsConfig> {
+    let file = read_file(cli.config.as_deref())?;
+    Ok(tls_paths(&cli.overrides, file.tls))
+}
+
+fn tls_paths(o: &Overrides, file: FileTls) -> TlsConfig {
+    TlsConfig {
+        cert_path: o.tls_cert.clone()
+            .or(file.cert_path)
+            .unwrap_or_else(|| PathBuf::from("yaddak_encounter_certificate.pem")),
+        key_path: o.tls_key.clone()
+            .or(file.key_path)
+            .unwrap_or_else(|| PathBuf::from("yaddak_encounter_private_key.pem")),
+        client_ca_path: o.tls_client_ca.clone().or(file.client_ca_path),
+        reload_interval_secs: o.tls_reload_interval_secs.or(file.reload_interval_secs).unwrap_or(0),
+    }
+}
+
+/// `data` next to the executable when it was installed with one, else
+/// `data` in the working directory.
+fn default_data_dir() -> PathBuf {
+    std::env::current_exe()
+        .ok()
+        .and_then(|exe| Some(exe.parent()?.join("data")))
+        .filter(|dir| dir.is_dir())
+        .unwrap_or_else(|| PathBuf::from("data"))
+}
+
+fn read_file(path: Option<&Path>) -> SResult<FileConfig> {
+    let path = match path {
+        Some(path) => path,
+        None if Path::new(DEFAULT_CONFIG_FILE).is_file() => Path::new(DEFAULT_CONFIG_FILE),
+        None => return Ok(FileConfig::default()),
+    };
+    let content = std::fs::read_to_string(path).map_err(|err| YaddakError::new(
+        YaddakErrorKind::EnvError,
+        format!("could not read config file {}: {err}", path.display()),
+    ))?;
+    toml::from_str(&content).map_err(|err| YaddakError::new(
+        YaddakErrorKind::EnvError,
+        format!("could not parse config file {}: {err}", path.display()),
+    ))
+}
+
+/// Makes `config` available to the rest of the server through `get`.
+pub fn init(config: Config) -> &'static Config {
+    CONFIG.get_or_init(|| config)
+}
+
+pub fn get() -> &'static Config {
+    CONFIG.get().expect("configuration is loaded before the server starts")
+}
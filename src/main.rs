@@ -1,27 +1,43 @@
+mod config;
+mod commands;
 mod models;
 mod api;
 mod traits;
 mod utilities;
 
 use std::future::Future;
-use std::path::PathBuf;
-use std::{time::Duration, sync::Arc, net::SocketAddr};
+use std::{time::Duration, sync::Arc, net::{IpAddr, SocketAddr}};
 use axum::extract::Host;
 use axum::handler::HandlerWithoutStateExt;
 use axum::response::Redirect;
 use hyper::{Uri, StatusCode};
 use models::state::YaddakState;
-use tokio::signal;
+use tokio::{signal, sync::watch};
 use dotenv::dotenv;
-use axum::{Router, BoxError};
-use axum_server::tls_rustls::RustlsConfig;
+use axum::{Router, BoxError, middleware, extract::DefaultBodyLimit};
+use clap::Parser;
 use tower_http::{trace::TraceLayer, timeout::TimeoutLayer};
 use tracing::debug;
 use tracing_subscriber::{layer::SubscriberExt, util::SubscriberInitExt};
 
 // yaddak
 use api::user_controller::{user_controller, user_auth_controller};
-use traits::migrate;
+use api::monster_controller::monster_auth_controller;
+use api::character_controller::character_auth_controller;
+use api::campaign_controller::campaign_auth_controller;
+use api::session_controller::{session_auth_controller, calendar_controller};
+use api::xp_controller::xp_auth_controller;
+use api::spell_controller::spell_auth_controller;
+use api::item_controller::item_auth_controller;
+use api::treasure_controller::{treasure_auth_controller, session_treasure_controller};
+use api::condition_controller::condition_auth_controller;
+use api::admin_controller::admin_controller;
+use api::health_controller::health_controller;
+use api::metrics_controller::metrics_controller;
+use api::asset_controller::{asset_controller, asset_auth_controller};
+use traits::{migrate, seed, seed_spells, seed_items, purge_job, repo::init_pool};
+use config::{Cli, Command, Config, LogFormat};
+use utilities::{tls, metrics, request_id, telemetry, security, storage::LocalStore};
 use crate::api::api_docs;
 
 #[derive(Clone, Copy)]
@@ -32,77 +48,225 @@ struct Ports {
 
 #[tokio::main]
 async fn main() {
+    dotenv().ok();
+    let cli = Cli::parse();
+    if let Some(Command::DevCert { hosts, force }) = &cli.command {
+        dev_cert(&cli, hosts.clone(), *force);
+        return;
+    }
+    let config = match Config::load(&cli) {
+        Ok(config) => config::init(config),
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    };
+    let otel = telemetry::layer(&config.log).unwrap_or_else(|err| {
+        eprintln!("{err}, spans will not be exported");
+        None
+    });
+
+    let registry = tracing_subscriber::registry()
+        .with(tracing_subscriber::EnvFilter::new(&config.log.filter))
+        .with(otel);
+    // logs go to stderr so command output on stdout stays clean
+    match config.log.format {
+        LogFormat::Pretty => registry
+            .with(tracing_subscriber::fmt::layer().without_time().with_writer(std::io::stderr))
+            .init(),
+        LogFormat::Json => registry
+            .with(tracing_subscriber::fmt::layer().json().with_current_span(true).with_span_list(false).with_writer(std::io::stderr))
+            .init(),
+    }
+
+    match &cli.command {
+        None | Some(Command::Serve) => serve(config).await,
+        Some(command) => {
+            let result = commands::run(command, config).await;
+            telemetry::shutdown();
+            if let Err(err) = result {
+                eprintln!("{err}");
+                std::process::exit(1);
+            }
+        }
+    }
+}
+
+async fn serve(config: &'static Config) {
     let ports = Ports {
-        http: 8010,
-        https: 44310,
+        http: config.server.http_port,
+        https: config.server.https_port,
     };
 
-    dotenv().ok();
-    tracing_subscriber::registry()
-        .with(
-            tracing_subscriber::EnvFilter::try_from_default_env().unwrap_or_else(|_| {
-                "yaddak_encounter_server=debug,tower_http=debug,axum=trace".into()
-            }),
-            )
-        .with(tracing_subscriber::fmt::layer().without_time())
-        .init();
-
-    let db_user = std::env::var("DB_USER").unwrap();
-    let db_password = std::env::var("DB_PASSWORD").unwrap();
-    let db_url = std::env::var("DB_URL").unwrap();
-    let db_name = std::env::var("DB_NAME").unwrap();
-
-    let con_str = format!(
-        "postgres://{}:{}@{}/{}",
-        db_user,
-        db_password,
-        db_url,
-        db_name
-    );
+    if let Err(err) = metrics::install() {
+        tracing::warn!("metrics are disabled: {err}");
+    }
+
+    let con_str = config.database.url.clone();
+    if let Err(err) = init_pool(&config.database).await {
+        tracing::error!("could not connect to the database: {err}");
+        std::process::exit(1);
+    }
 
     debug!("migrating");
-    let _ = migrate(con_str.clone()).await;
-    let state = Arc::new(YaddakState { db: con_str });
+    if let Err(err) = migrate(con_str.clone()).await {
+        tracing::warn!("migration failed: {err}");
+    }
+    match seed(con_str.clone(), None).await {
+        Ok(report) if report.created.is_empty() => {},
+        Ok(report) => tracing::info!("seeded {} monsters", report.created.len()),
+        Err(err) => tracing::warn!("seeding failed: {err}"),
+    }
+    match seed_spells(con_str.clone(), None).await {
+        Ok(0) => {},
+        Ok(added) => tracing::info!("seeded {added} spells"),
+        Err(err) => tracing::warn!("seeding spells failed: {err}"),
+    }
+    match seed_items(con_str.clone(), None).await {
+        Ok(0) => {},
+        Ok(added) => tracing::info!("seeded {added} items"),
+        Err(err) => tracing::warn!("seeding items failed: {err}"),
+    }
+    tokio::spawn(purge_job(
+        con_str.clone(),
+        LocalStore::new(&config.storage),
+        chrono::Duration::days(config.database.retention_days),
+        Duration::from_secs(60 * 60),
+    ));
+
+    let state = Arc::new(YaddakState { db: con_str, store: LocalStore::new(&config.storage) });
     let user_router = user_controller(state.clone());
     let user_auth_router = user_auth_controller(state.clone());
+    let monster_auth_router = monster_auth_controller(state.clone());
+    let character_auth_router = character_auth_controller(state.clone());
+    let spell_auth_router = spell_auth_controller(state.clone());
+    let item_auth_router = item_auth_controller(state.clone());
+    let treasure_auth_router = treasure_auth_controller(state.clone());
+    let condition_auth_router = condition_auth_controller(state.clone());
+    let campaign_auth_router = campaign_auth_controller(state.clone())
+        .merge(session_auth_controller(state.clone()))
+        .merge(xp_auth_controller(state.clone()))
+        .merge(session_treasure_controller(state.clone()));
+    let calendar_router = calendar_controller(state.clone());
+    let admin_router = admin_controller(state.clone());
+    let health_router = health_controller(state.clone());
+    let asset_router = asset_controller(state.clone());
+    let asset_auth_router = asset_auth_controller(state.clone());
 
     debug!("creating routes");
     let app = Router::new()
         .merge(api_docs())
-        .nest("/user", user_router)
-        .nest("/auth/user", user_auth_router)
+        .nest("/user", user_router.layer(DefaultBodyLimit::max(config.http.body_limit)))
+        .nest("/auth/user", user_auth_router.layer(DefaultBodyLimit::max(config.http.body_limit)))
+        .nest("/auth/monster", monster_auth_router.layer(DefaultBodyLimit::max(config.http.monster_body_limit)))
+        .nest("/auth/character", character_auth_router.layer(DefaultBodyLimit::max(config.http.monster_body_limit)))
+        .nest("/auth/spell", spell_auth_router)
+        .nest("/auth/item", item_auth_router)
+        .nest("/auth/condition", condition_auth_router.layer(DefaultBodyLimit::max(config.http.body_limit)))
+        .nest("/auth/treasure", treasure_auth_router.layer(DefaultBodyLimit::max(config.http.body_limit)))
+        .nest("/auth/campaign", campaign_auth_router.layer(DefaultBodyLimit::max(config.http.body_limit)))
+        .nest("/auth/admin", admin_router.layer(DefaultBodyLimit::max(config.http.body_limit)))
+        .nest("/asset", asset_router)
+        .nest("/calendar", calendar_router)
+        .nest("/auth/asset", asset_auth_router)
+        .route_layer(middleware::from_fn(metrics::track_requests))
+        .layer(TimeoutLayer::new(Duration::from_secs(15)))
+        // probes stay out of the timeout, readyz bounds its own checks
+        .merge(health_router)
         .layer((
-            TraceLayer::new_for_http(),
-            TimeoutLayer::new(Duration::from_secs(15)),
+            middleware::from_fn(request_id::propagate),
+            TraceLayer::new_for_http().make_span_with(request_id::make_span),
+            security::cors(&config.http),
+            middleware::from_fn(security::security_headers),
         ));
 
+    let handle  = axum_server::Handle::new();
+    let (shutdown_tx, shutdown_rx) = watch::channel(());
+    tokio::spawn(shutdown_signal(handle.clone(), shutdown_tx));
+    tokio::spawn(serve_metrics(config.metrics.listen, shutdown_received(shutdown_rx.clone())));
+
+    if !config.server.tls {
+        // TLS is terminated in front of us, serve the API as plain HTTP
+        let addr = SocketAddr::new(config.server.bind_address, ports.http);
+        let listener = match tokio::net::TcpListener::bind(addr).await {
+            Ok(listener) => listener,
+            Err(err) => {
+                tracing::error!("could not bind {addr}: {err}");
+                std::process::exit(1);
+            }
+        };
+        debug!("Listening:\t{addr}");
+        axum::serve(listener, app.into_make_service())
+            .with_graceful_shutdown(shutdown_received(shutdown_rx))
+            .await
+            .unwrap();
+        telemetry::shutdown();
+        return;
+    }
+
     // SSL 
     debug!("Creating SSL");
-    let config = RustlsConfig::from_pem_file(
-        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-            .join("yaddak_encounter_certificate.pem"),
-        PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-            .join("yaddak_encounter_private_key.pem")
-    )
-        .await
-        .unwrap();
+    let tls_config = match tls::load(&config.tls).await {
+        Ok(tls_config) => tls_config,
+        Err(err) => {
+            tracing::error!("could not load TLS certificate: {err}");
+            std::process::exit(1);
+        }
+    };
 
+    tokio::spawn(tls::reload_job(tls_config.clone(), config.tls.clone()));
 
-    let handle  = axum_server::Handle::new();
-    let shutdown_future = shutdown_signal(handle.clone());
+    if config.server.redirect_http {
+        tokio::spawn(redirect_http_to_https(
+            config.server.bind_address,
+            ports,
+            shutdown_received(shutdown_rx),
+        ));
+    }
 
     // Run the server with graceful shutdown
-    let addr = SocketAddr::from(([127,0,0,1], ports.https));
+    let addr = SocketAddr::new(config.server.bind_address, ports.https);
     debug!("Listening:\t{addr}");
 
-    axum_server::bind_rustls(addr, config)
+    axum_server::bind(addr)
+        .acceptor(tls::ClientCertAcceptor::new(tls_config))
         .handle(handle)
         .serve(app.into_make_service())
         .await
         .unwrap();
+    telemetry::shutdown();
+}
+
+fn dev_cert(cli: &Cli, hosts: Vec<String>, force: bool) {
+    let paths = match config::load_tls(cli) {
+        Ok(paths) => paths,
+        Err(err) => {
+            eprintln!("{err}");
+            std::process::exit(1);
+        }
+    };
+    if !force && (paths.cert_path.exists() || paths.key_path.exists()) {
+        eprintln!(
+            "{} or {} already exists, pass --force to overwrite",
+            paths.cert_path.display(),
+            paths.key_path.display()
+        );
+        std::process::exit(1);
+    }
+    if let Err(err) = tls::generate_dev_cert(&paths.cert_path, &paths.key_path, hosts) {
+        eprintln!("{err}");
+        std::process::exit(1);
+    }
+    println!("wrote {} and {}", paths.cert_path.display(), paths.key_path.display());
 }
 
-async fn shutdown_signal(handle: axum_server::Handle) {
+/// Resolves once `shutdown_signal` has fired.
+async fn shutdown_received(mut shutdown: watch::Receiver<()>) {
+    // the sender is dropped on shutdown, which surfaces as an error here
+    let _ = shutdown.changed().await;
+}
+
+async fn shutdown_signal(handle: axum_server::Handle, shutdown: watch::Sender<()>) {
     let ctrl_c = async {
         signal::ctrl_c()
             .await
@@ -128,9 +292,33 @@ async fn shutdown_signal(handle: axum_server::Handle) {
     tracing::info!("Received termination signal shutting down");
     handle.graceful_shutdown(Some(Duration::from_secs(10))); // 10 secs is how long docker will wait
                                                              // to force shutdown
+    drop(shutdown);
+}
+
+async fn serve_metrics<F>(addr: SocketAddr, signal: F)
+where
+    F: Future<Output = ()> + Send + 'static,
+{
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            tracing::error!("could not bind the metrics listener on {addr}: {err}");
+            return;
+        }
+    };
+    if !addr.ip().is_loopback() && config::get().metrics.token.is_none() {
+        tracing::warn!("metrics are served on {addr} without a metrics.token");
+    }
+    tracing::debug!("metrics listening on {addr}");
+    if let Err(err) = axum::serve(listener, metrics_controller().into_make_service())
+        .with_graceful_shutdown(signal)
+        .await
+    {
+        tracing::error!("metrics listener stopped: {err}");
+    }
 }
 
-async fn redirect_http_to_https<F>(ports: Ports, signal: F)
+async fn redirect_http_to_https<F>(bind_address: IpAddr, ports: Ports, signal: F)
 where
     F: Future<Output = ()> + Send + 'static,
 {
@@ -159,11 +347,19 @@ where
         }
     };
 
-    let addr = SocketAddr::from(([127, 0, 0, 1], ports.http));
-    let listener = tokio::net::TcpListener::bind(addr).await.unwrap();
+    let addr = SocketAddr::new(bind_address, ports.http);
+    let listener = match tokio::net::TcpListener::bind(addr).await {
+        Ok(listener) => listener,
+        Err(err) => {
+            tracing::error!("could not bind the HTTP redirect listener on {addr}: {err}");
+            return;
+        }
+    };
     tracing::debug!("listening on {addr}");
-    axum::serve(listener, redirect.into_make_service())
+    if let Err(err) = axum::serve(listener, redirect.into_make_service())
         .with_graceful_shutdown(signal)
         .await
-        .unwrap();
+    {
+        tracing::error!("HTTP redirect listener stopped: {err}");
+    }
 }
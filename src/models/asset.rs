@@ -0,0 +1,440 @@
+use std::{collections::BTreeMap, io::Cursor, time::Duration};
+
+use chrono::{DateTime, Utc};
+use image::{codecs::jpeg::JpegEncoder, ImageFormat, ImageReader, Limits};
+use sea_query::{Iden, Table, ColumnDef, PostgresQueryBuilder, Query, Expr, ForeignKey};
+use sea_query_binder::SqlxBinder;
+use serde::{Serialize, Deserialize};
+use sha2::{Digest, Sha256};
+use sqlx::{FromRow, query, query_as_with, query_with};
+use tokio::sync::Semaphore;
+use tracing::instrument;
+use utoipa::ToSchema;
+use uuid::Uuid;
+
+use crate::{
+    traits::{audit::{audit, Auditable}, object_store::ObjectStore, repo::{connect, Repo}},
+    utilities::metrics::QueryTimer,
+};
+
+use super::{
+    audit::AuditAction,
+    errors::{SResult, YaddakError, YaddakErrorKind},
+    monsters::Monster,
+};
+
+/// Images wider or taller than this are refused before they are decoded.
+const MAX_DIMENSION: u32 = 4096;
+
+/// Most a decoder may allocate for one image, a 4096 pixel square RGBA
+/// image. 16 bit images that size are refused.
+const MAX_DECODE_BYTES: u64 = 4096 * 4096 * 4;
+
+/// Images decoded at once. Each may hold `MAX_DECODE_BYTES` and a blocking
+/// thread, so uploads beyond this wait their turn.
+static DECODES: Semaphore = Semaphore::const_new(2);
+
+/// An uploaded or mirrored image. The bytes live in the object store under
+/// the asset id, the thumbnail under `thumbnail_key`.
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Default, FromRow,
+         ToSchema)]
+pub struct Asset {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    /// `image/png`, `image/jpeg`, `image/gif` or `image/webp`
+    pub content_type: String,
+    pub size: i64,
+    pub width: i32,
+    pub height: i32,
+    /// Hex SHA-256 of the original bytes, also sent as the `ETag`.
+    pub sha256: String,
+    /// `image/png` for images with transparency, `image/jpeg` otherwise.
+    pub thumbnail_type: String,
+    /// Where `asset mirror` downloaded the image from.
+    pub origin_url: Option<String>,
+    pub created_at: DateTime<Utc>,
+}
+
+/// What `mirror` did, or would do on a dry run.
+#[derive(Debug, Default)]
+pub struct MirrorReport {
+    pub dry_run: bool,
+    /// Distinct remote URLs found on monsters.
+    pub urls: usize,
+    /// URLs fetched and stored.
+    pub downloaded: usize,
+    /// URLs an earlier run had already stored.
+    pub reused: usize,
+    /// Monsters now pointing at a local copy.
+    pub monsters: usize,
+    /// URLs that could not be mirrored and why.
+    pub failures: Vec<(String, String)>,
+}
+
+/// The parts of an image worth keeping once it has been checked.
+struct Processed {
+    content_type: &'static str,
+    width: u32,
+    height: u32,
+    sha256: String,
+    thumbnail: Vec<u8>,
+    thumbnail_type: &'static str,
+}
+
+impl Asset {
+    pub async fn migrate(con_str: String) -> SResult<()> {
+        let mut client = connect(con_str).await?;
+        let sql = Table::create()
+            .table(AssetModel::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(AssetModel::Id)
+                    .uuid()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(AssetModel::UserId).uuid().not_null())
+            .col(ColumnDef::new(AssetModel::ContentType).string().not_null())
+            .col(ColumnDef::new(AssetModel::Size).big_integer().not_null())
+            .col(ColumnDef::new(AssetModel::Width).integer().not_null())
+            .col(ColumnDef::new(AssetModel::Height).integer().not_null())
+            .col(ColumnDef::new(AssetModel::Sha256).string().not_null())
+            .col(ColumnDef::new(AssetModel::ThumbnailType).string().not_null())
+            .col(ColumnDef::new(AssetModel::OriginUrl).string().null())
+            .col(ColumnDef::new(AssetModel::CreatedAt).timestamp_with_time_zone().not_null())
+            .foreign_key(ForeignKey::create()
+                           .name("FK_Asset_User")
+                           .from(AssetModel::Table, AssetModel::UserId)
+                           .to(super::user::UserModel::Table,
+                               super::user::UserModel::Id)
+                           .on_delete(sea_query::ForeignKeyAction::Cascade)
+                           .on_update(sea_query::ForeignKeyAction::Cascade)
+                        )
+            .build(PostgresQueryBuilder);
+
+        let _ = query(sql.as_str())
+            .execute(&mut *client)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(name = "asset.get", skip_all, fields(db.system = "postgresql"))]
+    pub async fn get(con_str: String, id: Uuid) -> SResult<Asset> {
+        let _timer = QueryTimer::new("asset", "get");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .columns(AssetModel::cols())
+            .from(AssetModel::Table)
+            .and_where(Expr::col(AssetModel::Id).eq(id))
+            .build_sqlx(PostgresQueryBuilder);
+
+        let asset: Asset = query_as_with(&sql, values)
+            .fetch_one(&mut *client)
+            .await?;
+        Ok(asset)
+    }
+
+    /// The asset an earlier mirror stored for `url`, if any.
+    #[instrument(name = "asset.by_origin", skip_all, fields(db.system = "postgresql"))]
+    pub async fn by_origin(con_str: String, url: &str) -> SResult<Option<Asset>> {
+        let _timer = QueryTimer::new("asset", "by_origin");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .columns(AssetModel::cols())
+            .from(AssetModel::Table)
+            .and_where(Expr::col(AssetModel::OriginUrl).eq(url))
+            .limit(1)
+            .build_sqlx(PostgresQueryBuilder);
+
+        let asset: Option<Asset> = query_as_with(&sql, values)
+            .fetch_optional(&mut *client)
+            .await?;
+        Ok(asset)
+    }
+
+    /// Assets of users soft deleted before `older_than`. Purging those
+    /// users drops the rows through `FK_Asset_User`, the files are left
+    /// for the caller to delete.
+    #[instrument(name = "asset.purgeable", skip_all, fields(db.system = "postgresql"))]
+    pub async fn purgeable(con_str: String, older_than: DateTime<Utc>) -> SResult<Vec<Asset>> {
+        let _timer = QueryTimer::new("asset", "purgeable");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .columns(AssetModel::cols())
+            .from(AssetModel::Table)
+            .and_where(
+                Expr::col(AssetModel::UserId).in_subquery(
+                    Query::select()
+                        .column(super::user::UserModel::Id)
+                        .from(super::user::UserModel::Table)
+                        .and_where(Expr::col(super::user::UserModel::DeletedAt).lt(older_than))
+                        .to_owned()
+                )
+            )
+            .build_sqlx(PostgresQueryBuilder);
+
+        let assets: Vec<Asset> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        Ok(assets)
+    }
+
+    /// Checks that `bytes` is an image we serve, writes it and a thumbnail
+    /// to `store` and records the asset for `owner`.
+    #[instrument(name = "asset.store", skip_all, fields(db.system = "postgresql"))]
+    pub async fn store<S: ObjectStore>(
+        con_str: String,
+        store: &S,
+        owner: Uuid,
+        bytes: Vec<u8>,
+        thumbnail_size: u32,
+        origin_url: Option<String>,
+    ) -> SResult<Asset> {
+        let permit = DECODES
+            .acquire()
+            .await
+            .map_err(|err| YaddakError::new(YaddakErrorKind::InternalError, err.to_string()))?;
+        // decoding and resizing are CPU bound, keep them off the runtime
+        let (bytes, processed) = tokio::task::spawn_blocking(move || {
+            let processed = process(&bytes, thumbnail_size);
+            (bytes, processed)
+        })
+        .await
+        .map_err(|err| YaddakError::new(YaddakErrorKind::InternalError, err.to_string()))?;
+        drop(permit);
+        let processed = processed?;
+
+        let asset = Asset {
+            id: Uuid::new_v4(),
+            user_id: owner,
+            content_type: processed.content_type.to_string(),
+            size: bytes.len() as i64,
+            width: processed.width as i32,
+            height: processed.height as i32,
+            sha256: processed.sha256,
+            thumbnail_type: processed.thumbnail_type.to_string(),
+            origin_url,
+            created_at: Utc::now(),
+        };
+        store.put(&asset.key(), &bytes).await?;
+        store.put(&asset.thumbnail_key(), &processed.thumbnail).await?;
+
+        let _timer = QueryTimer::new("asset", "post");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::insert()
+            .into_table(AssetModel::Table)
+            .columns(AssetModel::cols())
+            .values_panic([
+                asset.id.into(),
+                asset.user_id.into(),
+                asset.content_type.clone().into(),
+                asset.size.into(),
+                asset.width.into(),
+                asset.height.into(),
+                asset.sha256.clone().into(),
+                asset.thumbnail_type.clone().into(),
+                asset.origin_url.clone().into(),
+                asset.created_at.into(),
+            ])
+            .build_sqlx(PostgresQueryBuilder);
+
+        if let Err(err) = query_with(&sql, values).execute(&mut *client).await {
+            let _ = store.delete(&asset.key()).await;
+            let _ = store.delete(&asset.thumbnail_key()).await;
+            return Err(err.into());
+        }
+        Ok(asset)
+    }
+
+    pub fn key(&self) -> String {
+        self.id.to_string()
+    }
+
+    pub fn thumbnail_key(&self) -> String {
+        format!("{}.thumb", self.id)
+    }
+
+    /// Path the image is served from, what `img_url` is set to.
+    pub fn url(&self) -> String {
+        format!("/asset/{}", self.id)
+    }
+
+    /// Strong validator for the original or the thumbnail.
+    pub fn etag(&self, thumbnail: bool) -> String {
+        if thumbnail {
+            format!("\"{}-thumb\"", self.sha256)
+        } else {
+            format!("\"{}\"", self.sha256)
+        }
+    }
+}
+
+fn process(bytes: &[u8], thumbnail_size: u32) -> SResult<Processed> {
+    let unsupported = || YaddakError::bad_request("expected a PNG, JPEG, GIF or WebP image".to_string());
+    let format = image::guess_format(bytes).map_err(|_| unsupported())?;
+    let content_type = match format {
+        ImageFormat::Png => "image/png",
+        ImageFormat::Jpeg => "image/jpeg",
+        ImageFormat::Gif => "image/gif",
+        ImageFormat::WebP => "image/webp",
+        _ => return Err(unsupported()),
+    };
+
+    let mut limits = Limits::default();
+    limits.max_image_width = Some(MAX_DIMENSION);
+    limits.max_image_height = Some(MAX_DIMENSION);
+    limits.max_alloc = Some(MAX_DECODE_BYTES);
+    let mut reader = ImageReader::with_format(Cursor::new(bytes), format);
+    reader.limits(limits);
+    let image = reader
+        .decode()
+        .map_err(|err| YaddakError::bad_request(format!("could not read the image: {err}")))?;
+
+    let small = image.thumbnail(thumbnail_size, thumbnail_size);
+    let mut thumbnail = Vec::new();
+    let encode_error = |err: image::ImageError| YaddakError::new(YaddakErrorKind::InternalError, err.to_string());
+    let thumbnail_type = if small.color().has_alpha() {
+        small.write_to(&mut Cursor::new(&mut thumbnail), ImageFormat::Png).map_err(encode_error)?;
+        "image/png"
+    } else {
+        small.to_rgb8()
+            .write_with_encoder(JpegEncoder::new_with_quality(&mut thumbnail, 85))
+            .map_err(encode_error)?;
+        "image/jpeg"
+    };
+
+    Ok(Processed {
+        content_type,
+        width: image.width(),
+        height: image.height(),
+        sha256: format!("{:x}", Sha256::digest(bytes)),
+        thumbnail,
+        thumbnail_type,
+    })
+}
+
+/// Downloads every remote `img_url` on a monster into `store` and points
+/// the monsters at the local copy. URLs shared by several monsters are
+/// fetched once, and URLs an earlier run stored are not fetched again, so
+/// the command can be rerun after a failure.
+pub async fn mirror<S: ObjectStore>(
+    con_str: String,
+    store: &S,
+    upload_limit: usize,
+    thumbnail_size: u32,
+    dry_run: bool,
+) -> SResult<MirrorReport> {
+    let mut by_url: BTreeMap<String, Vec<Monster>> = BTreeMap::new();
+    for monster in Monster::get_all(con_str.clone()).await? {
+        if monster.img_url.starts_with("http://") || monster.img_url.starts_with("https://") {
+            by_url.entry(monster.img_url.clone()).or_default().push(monster);
+        }
+    }
+    let mut report = MirrorReport { dry_run, urls: by_url.len(), ..Default::default() };
+    if dry_run {
+        report.monsters = by_url.values().map(Vec::len).sum();
+        return Ok(report);
+    }
+
+    let client = reqwest::Client::builder()
+        .timeout(Duration::from_secs(30))
+        .user_agent(concat!("yaddak-encounter-server/", env!("CARGO_PKG_VERSION")))
+        .build()
+        .map_err(|err| YaddakError::new(YaddakErrorKind::InternalError, err.to_string()))?;
+
+    for (url, monsters) in by_url {
+        let asset = match Asset::by_origin(con_str.clone(), &url).await? {
+            Some(asset) => {
+                report.reused += 1;
+                asset
+            }
+            None => {
+                let owner = monsters[0].user_id;
+                let stored = match download(&client, &url, upload_limit).await {
+                    Ok(bytes) => Asset::store(con_str.clone(), store, owner, bytes, thumbnail_size, Some(url.clone()))
+                        .await
+                        .map_err(|err| err.to_string()),
+                    Err(err) => Err(err),
+                };
+                match stored {
+                    Ok(asset) => {
+                        audit(con_str.clone(), None, AuditAction::Create, None, Some(&asset)).await;
+                        report.downloaded += 1;
+                        asset
+                    }
+                    Err(err) => {
+                        tracing::warn!("could not mirror {url}: {err}");
+                        report.failures.push((url, err));
+                        continue;
+                    }
+                }
+            }
+        };
+        for before in monsters {
+            let after = Monster { img_url: asset.url(), ..before.clone() };
+            Monster::put(con_str.clone(), after.id, &after).await?;
+            audit(con_str.clone(), None, AuditAction::Update, Some(&before), Some(&after)).await;
+            report.monsters += 1;
+        }
+    }
+    Ok(report)
+}
+
+async fn download(client: &reqwest::Client, url: &str, limit: usize) -> Result<Vec<u8>, String> {
+    let mut response = client.get(url).send().await.map_err(|err| err.to_string())?;
+    if !response.status().is_success() {
+        return Err(format!("the server answered {}", response.status()));
+    }
+    if response.content_length().is_some_and(|length| length > limit as u64) {
+        return Err(format!("the image is larger than {limit} bytes"));
+    }
+    let mut bytes = Vec::new();
+    while let Some(chunk) = response.chunk().await.map_err(|err| err.to_string())? {
+        bytes.extend_from_slice(&chunk);
+        if bytes.len() > limit {
+            return Err(format!("the image is larger than {limit} bytes"));
+        }
+    }
+    Ok(bytes)
+}
+
+impl Auditable for Asset {
+    const ENTITY_TYPE: &'static str = "asset";
+
+    fn entity_id(&self) -> Uuid {
+        self.id
+    }
+}
+
+#[derive(Iden)]
+enum AssetModel {
+    Table,
+    Id,
+    UserId,
+    ContentType,
+    Size,
+    Width,
+    Height,
+    Sha256,
+    ThumbnailType,
+    OriginUrl,
+    CreatedAt,
+}
+
+impl AssetModel {
+    fn cols() -> Vec<Self> {
+        vec![
+            Self::Id,
+            Self::UserId,
+            Self::ContentType,
+            Self::Size,
+            Self::Width,
+            Self::Height,
+            Self::Sha256,
+            Self::ThumbnailType,
+            Self::OriginUrl,
+            Self::CreatedAt,
+        ]
+    }
+}
@@ -0,0 +1,214 @@
+use chrono::{DateTime, Utc};
+use sea_query::{Iden, Table, ColumnDef, PostgresQueryBuilder, Query, Expr, Order, Index};
+use sea_query_binder::SqlxBinder;
+use serde::{Serialize, Deserialize};
+use serde_json::{Map, Value};
+use sqlx::{FromRow, query, query_as_with, query_with};
+use utoipa::{ToSchema, IntoParams};
+use uuid::Uuid;
+
+use crate::traits::repo::connect;
+
+use super::errors::SResult;
+
+/// One data-changing operation. `changes` maps every field that differs
+/// between the before and after snapshots to `{ "before": .., "after": .. }`.
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Default, FromRow,
+         ToSchema)]
+pub struct AuditLog {
+    pub id: Uuid,
+    pub actor_id: Option<Uuid>,
+    pub action: String,
+    pub entity_type: String,
+    pub entity_id: Uuid,
+    #[schema(value_type = Object)]
+    pub changes: Value,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Copy, PartialEq, Eq,
+         ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum AuditAction {
+    Create,
+    Update,
+    Delete,
+    Restore,
+    /// Hard deleted by the purge job after the retention period.
+    Purge,
+}
+
+impl std::fmt::Display for AuditAction {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        match self {
+            AuditAction::Create => write!(f, "create"),
+            AuditAction::Update => write!(f, "update"),
+            AuditAction::Delete => write!(f, "delete"),
+            AuditAction::Restore => write!(f, "restore"),
+            AuditAction::Purge => write!(f, "purge"),
+        }
+    }
+}
+
+/// Filters for the admin audit query, every field is optional.
+#[derive(Deserialize, Debug, Clone, Default,
+         IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct AuditQuery {
+    pub actor_id: Option<Uuid>,
+    pub entity_type: Option<String>,
+    pub entity_id: Option<Uuid>,
+    pub from: Option<DateTime<Utc>>,
+    pub to: Option<DateTime<Utc>>,
+}
+
+impl AuditLog {
+    pub async fn record(
+        con_str: String,
+        actor_id: Option<Uuid>,
+        action: AuditAction,
+        entity_type: &str,
+        entity_id: Uuid,
+        changes: Value,
+    ) -> SResult<()> {
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::insert()
+            .into_table(AuditModel::Table)
+            .columns(AuditModel::cols())
+            .values_panic([
+                Uuid::new_v4().into(),
+                actor_id.into(),
+                action.to_string().into(),
+                entity_type.into(),
+                entity_id.into(),
+                changes.into(),
+                Utc::now().into(),
+            ])
+            .build_sqlx(PostgresQueryBuilder);
+
+        let _ = query_with(&sql, values)
+            .execute(&mut *client)
+            .await?;
+        Ok(())
+    }
+
+    pub async fn search(con_str: String, filter: AuditQuery) -> SResult<Vec<AuditLog>> {
+        let mut client = connect(con_str).await?;
+        let mut select = Query::select();
+        select
+            .columns(AuditModel::cols())
+            .from(AuditModel::Table)
+            .order_by(AuditModel::CreatedAt, Order::Desc);
+        if let Some(actor_id) = filter.actor_id {
+            select.and_where(Expr::col(AuditModel::ActorId).eq(actor_id));
+        }
+        if let Some(entity_type) = filter.entity_type {
+            select.and_where(Expr::col(AuditModel::EntityType).eq(entity_type));
+        }
+        if let Some(entity_id) = filter.entity_id {
+            select.and_where(Expr::col(AuditModel::EntityId).eq(entity_id));
+        }
+        if let Some(from) = filter.from {
+            select.and_where(Expr::col(AuditModel::CreatedAt).gte(from));
+        }
+        if let Some(to) = filter.to {
+            select.and_where(Expr::col(AuditModel::CreatedAt).lt(to));
+        }
+        let (sql, values) = select.build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<AuditLog> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        Ok(rows)
+    }
+
+    pub async fn migrate(con_str: String) -> SResult<()> {
+        let mut client = connect(con_str).await?;
+        let sql = Table::create()
+            .table(AuditModel::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(AuditModel::Id)
+                    .uuid()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(AuditModel::ActorId).uuid().null())
+            .col(ColumnDef::new(AuditModel::Action).string().not_null())
+            .col(ColumnDef::new(AuditModel::EntityType).string().not_null())
+            .col(ColumnDef::new(AuditModel::EntityId).uuid().not_null())
+            .col(ColumnDef::new(AuditModel::Changes).json_binary().not_null())
+            .col(ColumnDef::new(AuditModel::CreatedAt).timestamp_with_time_zone().not_null())
+            .build(PostgresQueryBuilder);
+
+        let _ = query(sql.as_str())
+            .execute(&mut *client)
+            .await?;
+
+        let sql = Index::create()
+            .if_not_exists()
+            .name("IDX_AuditLog_Entity")
+            .table(AuditModel::Table)
+            .col(AuditModel::EntityType)
+            .col(AuditModel::EntityId)
+            .build(PostgresQueryBuilder);
+
+        let _ = query(sql.as_str())
+            .execute(&mut *client)
+            .await?;
+        Ok(())
+    }
+}
+
+/// Field level difference between two JSON snapshots. Fields present on
+/// only one side are reported with `null` on the other.
+pub fn diff(before: Option<&Value>, after: Option<&Value>) -> Value {
+    let empty = Map::new();
+    let before = before.and_then(Value::as_object).unwrap_or(&empty);
+    let after = after.and_then(Value::as_object).unwrap_or(&empty);
+
+    let mut changes = Map::new();
+    for key in before.keys().chain(after.keys()) {
+        if changes.contains_key(key) {
+            continue;
+        }
+        let old = before.get(key).cloned().unwrap_or(Value::Null);
+        let new = after.get(key).cloned().unwrap_or(Value::Null);
+        if old != new {
+            let mut change = Map::new();
+            change.insert("before".to_string(), old);
+            change.insert("after".to_string(), new);
+            changes.insert(key.clone(), Value::Object(change));
+        }
+    }
+    Value::Object(changes)
+}
+
+#[derive(Iden)]
+enum AuditModel {
+    #[iden = "audit_log"]
+    Table,
+    Id,
+    ActorId,
+    Action,
+    EntityType,
+    EntityId,
+    Changes,
+    CreatedAt,
+}
+
+impl AuditModel {
+    pub fn cols() -> Vec<Self> {
+        vec![
+            Self::Id,
+            Self::ActorId,
+            Self::Action,
+            Self::EntityType,
+            Self::EntityId,
+            Self::Changes,
+            Self::CreatedAt,
+        ]
+    }
+}
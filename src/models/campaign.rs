@@ -0,0 +1,1094 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use sea_query::{Iden, Table, ColumnDef, PostgresQueryBuilder, Query, Expr, Func, ForeignKey, ForeignKeyAction, Index, LockType, SelectStatement, SimpleExpr};
+use sea_query_binder::{SqlxBinder, SqlxValues};
+use serde::{Serialize, Deserialize};
+use sqlx::{Connection, FromRow, PgConnection, query, query_as_with, query_with};
+use tracing::instrument;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{traits::{repo::{Repo, connect}, audit::Auditable}, utilities::metrics::QueryTimer};
+
+use super::{
+    character::{Character, CharacterModel},
+    errors::{SResult, YaddakError},
+    monsters::{Monster, MonsterModel},
+    user::UserModel,
+};
+
+/// DMs run the campaign, players join it with an invite code.
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Copy, Default, PartialEq, Eq,
+         ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CampaignRole {
+    Dm,
+    #[default]
+    Player,
+}
+
+impl CampaignRole {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            CampaignRole::Dm => "dm",
+            CampaignRole::Player => "player",
+        }
+    }
+}
+
+impl TryFrom<String> for CampaignRole {
+    type Error = YaddakError;
+
+    fn try_from(value: String) -> SResult<Self> {
+        match value.as_str() {
+            "dm" => Ok(CampaignRole::Dm),
+            "player" => Ok(CampaignRole::Player),
+            _ => Err(YaddakError::bad_request(format!("{value:?} is not a campaign role"))),
+        }
+    }
+}
+
+/// How characters in the campaign gain levels: by XP from defeated
+/// monsters, or when the DM declares a milestone.
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Copy, Default, PartialEq, Eq,
+         ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Leveling {
+    #[default]
+    Xp,
+    Milestone,
+}
+
+impl Leveling {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Leveling::Xp => "xp",
+            Leveling::Milestone => "milestone",
+        }
+    }
+}
+
+impl TryFrom<String> for Leveling {
+    type Error = YaddakError;
+
+    fn try_from(value: String) -> SResult<Self> {
+        match value.as_str() {
+            "xp" => Ok(Leveling::Xp),
+            "milestone" => Ok(Leveling::Milestone),
+            _ => Err(YaddakError::bad_request(format!("{value:?} is not a leveling mode"))),
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Default, FromRow,
+         ToSchema)]
+pub struct Campaign {
+    pub id: Uuid,
+    pub name: String,
+    pub description: Option<String>,
+    /// Only shown to DMs. Players join by posting it to
+    /// `/auth/campaign/join/{code}`.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub invite_code: Option<String>,
+    #[sqlx(try_from = "String")]
+    pub leveling: Leveling,
+    /// `None` once the creator's account is purged and no other DM was
+    /// left to take the campaign over.
+    pub created_by: Option<Uuid>,
+    pub created_at: DateTime<Utc>,
+}
+
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Default, FromRow,
+         ToSchema)]
+pub struct CampaignMember {
+    pub campaign_id: Uuid,
+    pub user_id: Uuid,
+    pub user_name: String,
+    #[sqlx(try_from = "String")]
+    pub role: CampaignRole,
+    pub joined_at: DateTime<Utc>,
+}
+
+/// A campaign as one of its members sees it.
+#[derive(Serialize, Debug, Clone, Default, ToSchema)]
+pub struct CampaignDetail {
+    #[serde(flatten)]
+    pub campaign: Campaign,
+    /// The caller's role.
+    pub role: CampaignRole,
+    pub members: Vec<CampaignMember>,
+}
+
+#[derive(Deserialize, Debug, Clone,
+         ToSchema, Validate)]
+pub struct CampaignRequest {
+    #[validate(length(min = 1, max = 128, message = "must be between 1 and 128 characters"))]
+    #[schema(min_length = 1, max_length = 128)]
+    pub name: String,
+    #[serde(default)]
+    #[validate(length(max = 4000, message = "must be at most 4000 characters"))]
+    #[schema(max_length = 4000)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub leveling: Leveling,
+}
+
+#[derive(Deserialize, Debug, Clone,
+         ToSchema, Validate)]
+pub struct RoleRequest {
+    pub role: CampaignRole,
+}
+
+/// 48 random bits from a v4 uuid, short enough to read out at the table.
+/// Joining still needs an account, so the code only has to be unguessable.
+fn new_invite_code() -> String {
+    Uuid::new_v4().simple().to_string()[..12].to_string()
+}
+
+/// 122 random bits from a v4 uuid. Calendar apps cannot send a bearer
+/// token, so this is the only credential the feed URL carries.
+fn new_calendar_token() -> String {
+    Uuid::new_v4().simple().to_string()
+}
+
+fn insert_campaign(model: &Campaign) -> (String, SqlxValues) {
+    Query::insert()
+        .into_table(CampaignModel::Table)
+        .columns(CampaignModel::cols())
+        .values_panic([
+            model.id.into(),
+            model.name.clone().into(),
+            model.description.clone().into(),
+            model.invite_code.clone().unwrap_or_else(new_invite_code).into(),
+            model.leveling.as_str().into(),
+            model.created_by.into(),
+            model.created_at.into(),
+        ])
+        .build_sqlx(PostgresQueryBuilder)
+}
+
+/// Hands campaigns created by one of the `purged` users to their longest
+/// standing DM who is not being purged as well. Campaigns with no such DM
+/// are left for `FK_Campaign_User` to null.
+pub(super) fn hand_over_campaigns(purged: SelectStatement) -> (String, SqlxValues) {
+    let next_dm = Query::select()
+        .column(CampaignMemberModel::UserId)
+        .from(CampaignMemberModel::Table)
+        .and_where(Expr::col((CampaignMemberModel::Table, CampaignMemberModel::CampaignId)).equals((CampaignModel::Table, CampaignModel::Id)))
+        .and_where(Expr::col(CampaignMemberModel::Role).eq(CampaignRole::Dm.as_str()))
+        .and_where(Expr::col(CampaignMemberModel::UserId).not_in_subquery(purged.clone()))
+        .order_by(CampaignMemberModel::JoinedAt, sea_query::Order::Asc)
+        .limit(1)
+        .to_owned();
+    Query::update()
+        .table(CampaignModel::Table)
+        .value(CampaignModel::CreatedBy, SimpleExpr::SubQuery(None, Box::new(next_dm.into_sub_query_statement())))
+        .and_where(Expr::col(CampaignModel::CreatedBy).in_subquery(purged))
+        .build_sqlx(PostgresQueryBuilder)
+}
+
+fn insert_member(id: Uuid, user_id: Uuid, role: CampaignRole) -> (String, SqlxValues) {
+    Query::insert()
+        .into_table(CampaignMemberModel::Table)
+        .columns([
+            CampaignMemberModel::CampaignId,
+            CampaignMemberModel::UserId,
+            CampaignMemberModel::Role,
+            CampaignMemberModel::JoinedAt,
+        ])
+        .values_panic([
+            id.into(),
+            user_id.into(),
+            role.as_str().into(),
+            Utc::now().into(),
+        ])
+        .build_sqlx(PostgresQueryBuilder)
+}
+
+impl Campaign {
+    /// Creates the campaign with `owner` as its first DM.
+    #[instrument(name = "campaign.create", skip_all, fields(db.system = "postgresql"))]
+    pub async fn create(con_str: String, request: CampaignRequest, owner: Uuid) -> SResult<Campaign> {
+        let campaign = Campaign {
+            id: Uuid::new_v4(),
+            name: request.name,
+            description: request.description,
+            invite_code: Some(new_invite_code()),
+            leveling: request.leveling,
+            created_by: Some(owner),
+            created_at: Utc::now(),
+        };
+        // one transaction, a campaign without its DM could not be reached
+        let _timer = QueryTimer::new("campaign", "create");
+        let mut client = connect(con_str).await?;
+        let mut tx = client.begin().await?;
+        for (sql, values) in [insert_campaign(&campaign), insert_member(campaign.id, owner, CampaignRole::Dm)] {
+            let _ = query_with(&sql, values)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(campaign)
+    }
+
+    /// The member's role, or `None` when they are not in the campaign or
+    /// it has been deleted.
+    #[instrument(name = "campaign.role_of", skip_all, fields(db.system = "postgresql"))]
+    pub async fn role_of(con_str: String, id: Uuid, user_id: Uuid) -> SResult<Option<CampaignRole>> {
+        let _timer = QueryTimer::new("campaign", "role_of");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .column((CampaignMemberModel::Table, CampaignMemberModel::Role))
+            .from(CampaignMemberModel::Table)
+            .inner_join(
+                CampaignModel::Table,
+                Expr::col((CampaignModel::Table, CampaignModel::Id))
+                    .equals((CampaignMemberModel::Table, CampaignMemberModel::CampaignId)),
+            )
+            .and_where(Expr::col((CampaignMemberModel::Table, CampaignMemberModel::CampaignId)).eq(id))
+            .and_where(Expr::col((CampaignMemberModel::Table, CampaignMemberModel::UserId)).eq(user_id))
+            .and_where(Expr::col((CampaignModel::Table, CampaignModel::DeletedAt)).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let row: Option<(String,)> = query_as_with(&sql, values)
+            .fetch_optional(&mut *client)
+            .await?;
+        row.map(|(role,)| CampaignRole::try_from(role)).transpose()
+    }
+
+    /// Non-members get a 404 so campaign ids cannot be probed.
+    pub async fn require_member(con_str: String, id: Uuid, user_id: Uuid) -> SResult<CampaignRole> {
+        Campaign::role_of(con_str, id, user_id)
+            .await?
+            .ok_or_else(|| YaddakError::not_found(format!("No campaign with id {id}")))
+    }
+
+    /// The check every DM only action goes through. Encounters will use it
+    /// too once they belong to a campaign.
+    pub async fn require_dm(con_str: String, id: Uuid, user_id: Uuid) -> SResult<()> {
+        match Campaign::require_member(con_str, id, user_id).await? {
+            CampaignRole::Dm => Ok(()),
+            CampaignRole::Player => Err(YaddakError::forbidden("Only a DM of this campaign can do that".to_string())),
+        }
+    }
+
+    /// Campaigns `user_id` is a member of, by name.
+    #[instrument(name = "campaign.get_for_member", skip_all, fields(db.system = "postgresql"))]
+    pub async fn get_for_member(con_str: String, user_id: Uuid) -> SResult<Vec<Campaign>> {
+        let _timer = QueryTimer::new("campaign", "get_for_member");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .columns(CampaignModel::cols().into_iter().map(|col| (CampaignModel::Table, col)))
+            .from(CampaignModel::Table)
+            .inner_join(
+                CampaignMemberModel::Table,
+                Expr::col((CampaignMemberModel::Table, CampaignMemberModel::CampaignId))
+                    .equals((CampaignModel::Table, CampaignModel::Id)),
+            )
+            .and_where(Expr::col((CampaignMemberModel::Table, CampaignMemberModel::UserId)).eq(user_id))
+            .and_where(Expr::col((CampaignModel::Table, CampaignModel::DeletedAt)).is_null())
+            .order_by((CampaignModel::Table, CampaignModel::Name), sea_query::Order::Asc)
+            .build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<Campaign> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        Ok(rows)
+    }
+
+    #[instrument(name = "campaign.by_invite", skip_all, fields(db.system = "postgresql"))]
+    pub async fn by_invite(con_str: String, code: &str) -> SResult<Campaign> {
+        let _timer = QueryTimer::new("campaign", "by_invite");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .columns(CampaignModel::cols())
+            .from(CampaignModel::Table)
+            .limit(1)
+            .and_where(Expr::col(CampaignModel::InviteCode).eq(code))
+            .and_where(Expr::col(CampaignModel::DeletedAt).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        query_as_with(&sql, values)
+            .fetch_optional(&mut *client)
+            .await?
+            .ok_or_else(|| YaddakError::not_found("No campaign with that invite code".to_string()))
+    }
+
+    /// Replaces the invite code, so links already handed out stop working.
+    #[instrument(name = "campaign.rotate_invite", skip_all, fields(db.system = "postgresql"))]
+    pub async fn rotate_invite(con_str: String, id: Uuid) -> SResult<String> {
+        let _timer = QueryTimer::new("campaign", "rotate_invite");
+        let mut client = connect(con_str).await?;
+        let code = new_invite_code();
+        let (sql, values) = Query::update()
+            .table(CampaignModel::Table)
+            .value(CampaignModel::InviteCode, code.clone())
+            .and_where(Expr::col(CampaignModel::Id).eq(id))
+            .and_where(Expr::col(CampaignModel::DeletedAt).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let _ = query_with(sql.as_str(), values)
+            .execute(&mut *client)
+            .await?;
+        Ok(code)
+    }
+
+    /// The token in the campaign's calendar feed URL, made the first time
+    /// it is asked for.
+    #[instrument(name = "campaign.calendar_token", skip_all, fields(db.system = "postgresql"))]
+    pub async fn calendar_token(con_str: String, id: Uuid) -> SResult<String> {
+        let _timer = QueryTimer::new("campaign", "calendar_token");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::update()
+            .table(CampaignModel::Table)
+            .value(
+                CampaignModel::CalendarToken,
+                Func::coalesce([Expr::col(CampaignModel::CalendarToken).into(), Expr::val(new_calendar_token()).into()]),
+            )
+            .and_where(Expr::col(CampaignModel::Id).eq(id))
+            .and_where(Expr::col(CampaignModel::DeletedAt).is_null())
+            .returning_col(CampaignModel::CalendarToken)
+            .build_sqlx(PostgresQueryBuilder);
+
+        let row: Option<(String,)> = query_as_with(&sql, values)
+            .fetch_optional(&mut *client)
+            .await?;
+        row.map(|(token,)| token)
+            .ok_or_else(|| YaddakError::not_found(format!("No campaign with id {id}")))
+    }
+
+    /// The calendar token as stored, without making one. `None` until a
+    /// member first asks for the feed URL.
+    #[instrument(name = "campaign.stored_calendar_token", skip_all, fields(db.system = "postgresql"))]
+    pub async fn stored_calendar_token(con_str: String, id: Uuid) -> SResult<Option<String>> {
+        let _timer = QueryTimer::new("campaign", "stored_calendar_token");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .column(CampaignModel::CalendarToken)
+            .from(CampaignModel::Table)
+            .and_where(Expr::col(CampaignModel::Id).eq(id))
+            .and_where(Expr::col(CampaignModel::DeletedAt).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let row: Option<(Option<String>,)> = query_as_with(&sql, values)
+            .fetch_optional(&mut *client)
+            .await?;
+        Ok(row.and_then(|(token,)| token))
+    }
+
+    /// Replaces the calendar token, so feed URLs already handed out stop
+    /// working.
+    #[instrument(name = "campaign.rotate_calendar", skip_all, fields(db.system = "postgresql"))]
+    pub async fn rotate_calendar(con_str: String, id: Uuid) -> SResult<String> {
+        let _timer = QueryTimer::new("campaign", "rotate_calendar");
+        let mut client = connect(con_str).await?;
+        let token = new_calendar_token();
+        let (sql, values) = Query::update()
+            .table(CampaignModel::Table)
+            .value(CampaignModel::CalendarToken, token.clone())
+            .and_where(Expr::col(CampaignModel::Id).eq(id))
+            .and_where(Expr::col(CampaignModel::DeletedAt).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let _ = query_with(sql.as_str(), values)
+            .execute(&mut *client)
+            .await?;
+        Ok(token)
+    }
+
+    #[instrument(name = "campaign.members", skip_all, fields(db.system = "postgresql"))]
+    pub async fn members(con_str: String, id: Uuid) -> SResult<Vec<CampaignMember>> {
+        let _timer = QueryTimer::new("campaign", "members");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .columns([
+                (CampaignMemberModel::Table, CampaignMemberModel::CampaignId),
+                (CampaignMemberModel::Table, CampaignMemberModel::UserId),
+                (CampaignMemberModel::Table, CampaignMemberModel::Role),
+                (CampaignMemberModel::Table, CampaignMemberModel::JoinedAt),
+            ])
+            .column((UserModel::Table, UserModel::UserName))
+            .from(CampaignMemberModel::Table)
+            .inner_join(
+                UserModel::Table,
+                Expr::col((UserModel::Table, UserModel::Id))
+                    .equals((CampaignMemberModel::Table, CampaignMemberModel::UserId)),
+            )
+            .and_where(Expr::col((CampaignMemberModel::Table, CampaignMemberModel::CampaignId)).eq(id))
+            .and_where(Expr::col((UserModel::Table, UserModel::DeletedAt)).is_null())
+            .order_by((CampaignMemberModel::Table, CampaignMemberModel::JoinedAt), sea_query::Order::Asc)
+            .build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<CampaignMember> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        Ok(rows)
+    }
+
+    pub async fn member(con_str: String, id: Uuid, user_id: Uuid) -> SResult<CampaignMember> {
+        Campaign::members(con_str, id)
+            .await?
+            .into_iter()
+            .find(|member| member.user_id == user_id)
+            .ok_or_else(|| YaddakError::not_found(format!("User {user_id} is not in this campaign")))
+    }
+
+    /// Already being a member is a conflict.
+    #[instrument(name = "campaign.add_member", skip_all, fields(db.system = "postgresql"))]
+    pub async fn add_member(con_str: String, id: Uuid, user_id: Uuid, role: CampaignRole) -> SResult<()> {
+        let _timer = QueryTimer::new("campaign", "add_member");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = insert_member(id, user_id, role);
+
+        let _ = query_with(&sql, values)
+            .execute(&mut *client)
+            .await?;
+        Ok(())
+    }
+
+    /// Refuses to leave the campaign without a DM.
+    #[instrument(name = "campaign.set_role", skip_all, fields(db.system = "postgresql"))]
+    pub async fn set_role(con_str: String, id: Uuid, user_id: Uuid, role: CampaignRole) -> SResult<()> {
+        let _timer = QueryTimer::new("campaign", "set_role");
+        let mut client = connect(con_str).await?;
+        let mut tx = client.begin().await?;
+        if role == CampaignRole::Player {
+            Campaign::keep_a_dm(&mut tx, id, user_id).await?;
+        }
+        let (sql, values) = Query::update()
+            .table(CampaignMemberModel::Table)
+            .value(CampaignMemberModel::Role, role.as_str())
+            .and_where(Expr::col(CampaignMemberModel::CampaignId).eq(id))
+            .and_where(Expr::col(CampaignMemberModel::UserId).eq(user_id))
+            .build_sqlx(PostgresQueryBuilder);
+
+        let result = query_with(sql.as_str(), values)
+            .execute(&mut *tx)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(YaddakError::not_found(format!("User {user_id} is not in this campaign")));
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Also takes the member's characters out of the campaign. Refuses to
+    /// remove the last DM.
+    #[instrument(name = "campaign.remove_member", skip_all, fields(db.system = "postgresql"))]
+    pub async fn remove_member(con_str: String, id: Uuid, user_id: Uuid) -> SResult<()> {
+        let _timer = QueryTimer::new("campaign", "remove_member");
+        let mut client = connect(con_str).await?;
+        let mut tx = client.begin().await?;
+        Campaign::keep_a_dm(&mut tx, id, user_id).await?;
+        let (sql, values) = Query::delete()
+            .from_table(CampaignCharacterModel::Table)
+            .and_where(Expr::col(CampaignCharacterModel::CampaignId).eq(id))
+            .and_where(
+                Expr::col(CampaignCharacterModel::CharacterId).in_subquery(
+                    Query::select()
+                        .column(CharacterModel::Id)
+                        .from(CharacterModel::Table)
+                        .and_where(Expr::col(CharacterModel::UserId).eq(user_id))
+                        .to_owned()
+                )
+            )
+            .build_sqlx(PostgresQueryBuilder);
+        let _ = query_with(sql.as_str(), values)
+            .execute(&mut *tx)
+            .await?;
+
+        let (sql, values) = Query::delete()
+            .from_table(CampaignMemberModel::Table)
+            .and_where(Expr::col(CampaignMemberModel::CampaignId).eq(id))
+            .and_where(Expr::col(CampaignMemberModel::UserId).eq(user_id))
+            .build_sqlx(PostgresQueryBuilder);
+        let result = query_with(sql.as_str(), values)
+            .execute(&mut *tx)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(YaddakError::not_found(format!("User {user_id} is not in this campaign")));
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    /// Errors when `user_id` is the campaign's only DM. Locks the DM rows
+    /// until `tx` ends, so two DMs stepping down at once cannot both pass.
+    async fn keep_a_dm(tx: &mut PgConnection, id: Uuid, user_id: Uuid) -> SResult<()> {
+        let (sql, values) = Query::select()
+            .column((CampaignMemberModel::Table, CampaignMemberModel::UserId))
+            .from(CampaignMemberModel::Table)
+            .inner_join(
+                UserModel::Table,
+                Expr::col((UserModel::Table, UserModel::Id))
+                    .equals((CampaignMemberModel::Table, CampaignMemberModel::UserId)),
+            )
+            .and_where(Expr::col((CampaignMemberModel::Table, CampaignMemberModel::CampaignId)).eq(id))
+            .and_where(Expr::col((CampaignMemberModel::Table, CampaignMemberModel::Role)).eq(CampaignRole::Dm.as_str()))
+            .and_where(Expr::col((UserModel::Table, UserModel::DeletedAt)).is_null())
+            .lock_with_tables(LockType::Update, [CampaignMemberModel::Table])
+            .build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<(Uuid,)> = query_as_with(&sql, values)
+            .fetch_all(&mut *tx)
+            .await?;
+        let dms: Vec<Uuid> = rows.into_iter().map(|(dm,)| dm).collect();
+        if dms == [user_id] {
+            return Err(YaddakError::conflict("A campaign needs at least one DM, promote another member first".to_string()));
+        }
+        Ok(())
+    }
+
+    #[instrument(name = "campaign.characters", skip_all, fields(db.system = "postgresql"))]
+    pub async fn characters(con_str: String, id: Uuid) -> SResult<Vec<Character>> {
+        let _timer = QueryTimer::new("campaign", "characters");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .columns(CharacterModel::cols().into_iter().map(|col| (CharacterModel::Table, col)))
+            .from(CharacterModel::Table)
+            .inner_join(
+                CampaignCharacterModel::Table,
+                Expr::col((CampaignCharacterModel::Table, CampaignCharacterModel::CharacterId))
+                    .equals((CharacterModel::Table, CharacterModel::Id)),
+            )
+            .and_where(Expr::col((CampaignCharacterModel::Table, CampaignCharacterModel::CampaignId)).eq(id))
+            .and_where(Expr::col((CharacterModel::Table, CharacterModel::DeletedAt)).is_null())
+            .order_by((CharacterModel::Table, CharacterModel::Name), sea_query::Order::Asc)
+            .build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<Character> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        Ok(rows)
+    }
+
+    /// Whether `viewer` shares a live campaign with the character.
+    #[instrument(name = "campaign.can_view_character", skip_all, fields(db.system = "postgresql"))]
+    pub async fn can_view_character(con_str: String, viewer: Uuid, character_id: Uuid) -> SResult<bool> {
+        let _timer = QueryTimer::new("campaign", "can_view_character");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .column((CampaignCharacterModel::Table, CampaignCharacterModel::CampaignId))
+            .from(CampaignCharacterModel::Table)
+            .inner_join(
+                CampaignMemberModel::Table,
+                Expr::col((CampaignMemberModel::Table, CampaignMemberModel::CampaignId))
+                    .equals((CampaignCharacterModel::Table, CampaignCharacterModel::CampaignId)),
+            )
+            .inner_join(
+                CampaignModel::Table,
+                Expr::col((CampaignModel::Table, CampaignModel::Id))
+                    .equals((CampaignCharacterModel::Table, CampaignCharacterModel::CampaignId)),
+            )
+            .limit(1)
+            .and_where(Expr::col((CampaignCharacterModel::Table, CampaignCharacterModel::CharacterId)).eq(character_id))
+            .and_where(Expr::col((CampaignMemberModel::Table, CampaignMemberModel::UserId)).eq(viewer))
+            .and_where(Expr::col((CampaignModel::Table, CampaignModel::DeletedAt)).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let row: Option<(Uuid,)> = query_as_with(&sql, values)
+            .fetch_optional(&mut *client)
+            .await?;
+        Ok(row.is_some())
+    }
+
+    /// Ids of the monsters linked to live campaigns `viewer` is in.
+    #[instrument(name = "campaign.shared_monsters", skip_all, fields(db.system = "postgresql"))]
+    pub async fn shared_monsters(con_str: String, viewer: Uuid) -> SResult<HashSet<Uuid>> {
+        let _timer = QueryTimer::new("campaign", "shared_monsters");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .distinct()
+            .column((CampaignMonsterModel::Table, CampaignMonsterModel::MonsterId))
+            .from(CampaignMonsterModel::Table)
+            .inner_join(
+                CampaignMemberModel::Table,
+                Expr::col((CampaignMemberModel::Table, CampaignMemberModel::CampaignId))
+                    .equals((CampaignMonsterModel::Table, CampaignMonsterModel::CampaignId)),
+            )
+            .inner_join(
+                CampaignModel::Table,
+                Expr::col((CampaignModel::Table, CampaignModel::Id))
+                    .equals((CampaignMonsterModel::Table, CampaignMonsterModel::CampaignId)),
+            )
+            .and_where(Expr::col((CampaignMemberModel::Table, CampaignMemberModel::UserId)).eq(viewer))
+            .and_where(Expr::col((CampaignModel::Table, CampaignModel::DeletedAt)).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<(Uuid,)> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        Ok(rows.into_iter().map(|(id,)| id).collect())
+    }
+
+    #[instrument(name = "campaign.monsters", skip_all, fields(db.system = "postgresql"))]
+    pub async fn monsters(con_str: String, id: Uuid) -> SResult<Vec<Monster>> {
+        let _timer = QueryTimer::new("campaign", "monsters");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .columns(MonsterModel::cols().into_iter().map(|col| (MonsterModel::Table, col)))
+            .from(MonsterModel::Table)
+            .inner_join(
+                CampaignMonsterModel::Table,
+                Expr::col((CampaignMonsterModel::Table, CampaignMonsterModel::MonsterId))
+                    .equals((MonsterModel::Table, MonsterModel::Id)),
+            )
+            .and_where(Expr::col((CampaignMonsterModel::Table, CampaignMonsterModel::CampaignId)).eq(id))
+            .and_where(Expr::col((MonsterModel::Table, MonsterModel::DeletedAt)).is_null())
+            .order_by((MonsterModel::Table, MonsterModel::Name), sea_query::Order::Asc)
+            .build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<Monster> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        Ok(rows)
+    }
+
+    /// Links a character or monster to the campaign. Linking twice is a
+    /// no-op, only a new link returns `true`.
+    #[instrument(name = "campaign.attach", skip_all, fields(db.system = "postgresql"))]
+    pub async fn attach(con_str: String, id: Uuid, entry: CampaignEntry, entry_id: Uuid) -> SResult<bool> {
+        let _timer = QueryTimer::new("campaign", "attach");
+        let mut client = connect(con_str).await?;
+        let (table, campaign_col, entry_col) = entry.idens();
+        let (sql, values) = Query::insert()
+            .into_table(table)
+            .columns([campaign_col, entry_col])
+            .values_panic([id.into(), entry_id.into()])
+            .on_conflict(sea_query::OnConflict::new().do_nothing().to_owned())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let result = query_with(&sql, values)
+            .execute(&mut *client)
+            .await?;
+        Ok(result.rows_affected() > 0)
+    }
+
+    #[instrument(name = "campaign.detach", skip_all, fields(db.system = "postgresql"))]
+    pub async fn detach(con_str: String, id: Uuid, entry: CampaignEntry, entry_id: Uuid) -> SResult<()> {
+        let _timer = QueryTimer::new("campaign", "detach");
+        let mut client = connect(con_str).await?;
+        let (table, campaign_col, entry_col) = entry.idens();
+        let (sql, values) = Query::delete()
+            .from_table(table)
+            .and_where(Expr::col(campaign_col).eq(id))
+            .and_where(Expr::col(entry_col).eq(entry_id))
+            .build_sqlx(PostgresQueryBuilder);
+
+        let result = query_with(sql.as_str(), values)
+            .execute(&mut *client)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(YaddakError::not_found(format!("{entry_id} is not in this campaign")));
+        }
+        Ok(())
+    }
+}
+
+/// What can be linked to a campaign.
+#[derive(Serialize, Debug, Clone, Copy)]
+#[serde(rename_all = "lowercase")]
+pub enum CampaignEntry {
+    Character,
+    Monster,
+}
+
+impl CampaignEntry {
+    fn idens(self) -> (sea_query::DynIden, sea_query::DynIden, sea_query::DynIden) {
+        match self {
+            CampaignEntry::Character => (
+                sea_query::SeaRc::new(CampaignCharacterModel::Table),
+                sea_query::SeaRc::new(CampaignCharacterModel::CampaignId),
+                sea_query::SeaRc::new(CampaignCharacterModel::CharacterId),
+            ),
+            CampaignEntry::Monster => (
+                sea_query::SeaRc::new(CampaignMonsterModel::Table),
+                sea_query::SeaRc::new(CampaignMonsterModel::CampaignId),
+                sea_query::SeaRc::new(CampaignMonsterModel::MonsterId),
+            ),
+        }
+    }
+}
+
+/// A character or monster linked to a campaign, for the audit log.
+#[derive(Serialize, Debug, Clone)]
+pub struct CampaignLink {
+    pub campaign_id: Uuid,
+    pub entry: CampaignEntry,
+    pub entry_id: Uuid,
+}
+
+/// Which of a campaign's credentials was rotated. The value itself never
+/// reaches the audit log.
+#[derive(Serialize, Debug, Clone)]
+pub struct CredentialRotation {
+    pub campaign_id: Uuid,
+    pub credential: &'static str,
+}
+
+#[derive(Iden)]
+pub(crate) enum CampaignModel {
+    Table,
+    Id,
+    Name,
+    Description,
+    InviteCode,
+    Leveling,
+    /// Kept out of `cols` so it never leaves the database with a campaign.
+    CalendarToken,
+    CreatedBy,
+    CreatedAt,
+    DeletedAt,
+}
+
+impl CampaignModel {
+    pub fn cols() -> Vec<Self> {
+        vec![
+            Self::Id,
+            Self::Name,
+            Self::Description,
+            Self::InviteCode,
+            Self::Leveling,
+            Self::CreatedBy,
+            Self::CreatedAt,
+        ]
+    }
+}
+
+#[derive(Iden)]
+enum CampaignMemberModel {
+    Table,
+    CampaignId,
+    UserId,
+    Role,
+    JoinedAt,
+}
+
+#[derive(Iden)]
+enum CampaignCharacterModel {
+    Table,
+    CampaignId,
+    CharacterId,
+}
+
+#[derive(Iden)]
+enum CampaignMonsterModel {
+    Table,
+    CampaignId,
+    MonsterId,
+}
+
+impl Repo<'_, Campaign> for Campaign {
+    /// Creates the campaign table and the member, character and monster
+    /// link tables hanging off it.
+    async fn migrate(con_str: String) -> SResult<()> {
+        let mut client = connect(con_str).await?;
+        let campaign = Table::create()
+            .table(CampaignModel::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(CampaignModel::Id)
+                    .uuid()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(CampaignModel::Name).string().not_null())
+            .col(ColumnDef::new(CampaignModel::Description).text().null())
+            .col(ColumnDef::new(CampaignModel::InviteCode).string().not_null().unique_key())
+            .col(ColumnDef::new(CampaignModel::Leveling).string().not_null().default(Leveling::Xp.as_str()))
+            .col(ColumnDef::new(CampaignModel::CalendarToken).string().null())
+            .col(ColumnDef::new(CampaignModel::CreatedBy).uuid().null())
+            .col(ColumnDef::new(CampaignModel::CreatedAt).timestamp_with_time_zone().not_null())
+            .col(ColumnDef::new(CampaignModel::DeletedAt).timestamp_with_time_zone().null())
+            .foreign_key(ForeignKey::create()
+                           .name("FK_Campaign_User")
+                           .from(CampaignModel::Table, CampaignModel::CreatedBy)
+                           .to(UserModel::Table, UserModel::Id)
+                           .on_delete(ForeignKeyAction::SetNull)
+                           .on_update(ForeignKeyAction::Cascade)
+                        )
+            .build(PostgresQueryBuilder);
+
+        let members = Table::create()
+            .table(CampaignMemberModel::Table)
+            .if_not_exists()
+            .col(ColumnDef::new(CampaignMemberModel::CampaignId).uuid().not_null())
+            .col(ColumnDef::new(CampaignMemberModel::UserId).uuid().not_null())
+            .col(ColumnDef::new(CampaignMemberModel::Role).string().not_null())
+            .col(ColumnDef::new(CampaignMemberModel::JoinedAt).timestamp_with_time_zone().not_null())
+            .primary_key(Index::create()
+                           .col(CampaignMemberModel::CampaignId)
+                           .col(CampaignMemberModel::UserId))
+            .foreign_key(ForeignKey::create()
+                           .name("FK_CampaignMember_Campaign")
+                           .from(CampaignMemberModel::Table, CampaignMemberModel::CampaignId)
+                           .to(CampaignModel::Table, CampaignModel::Id)
+                           .on_delete(ForeignKeyAction::Cascade)
+                        )
+            .foreign_key(ForeignKey::create()
+                           .name("FK_CampaignMember_User")
+                           .from(CampaignMemberModel::Table, CampaignMemberModel::UserId)
+                           .to(UserModel::Table, UserModel::Id)
+                           .on_delete(ForeignKeyAction::Cascade)
+                        )
+            .build(PostgresQueryBuilder);
+
+        let characters = Table::create()
+            .table(CampaignCharacterModel::Table)
+            .if_not_exists()
+            .col(ColumnDef::new(CampaignCharacterModel::CampaignId).uuid().not_null())
+            .col(ColumnDef::new(CampaignCharacterModel::CharacterId).uuid().not_null())
+            .primary_key(Index::create()
+                           .col(CampaignCharacterModel::CampaignId)
+                           .col(CampaignCharacterModel::CharacterId))
+            .foreign_key(ForeignKey::create()
+                           .name("FK_CampaignCharacter_Campaign")
+                           .from(CampaignCharacterModel::Table, CampaignCharacterModel::CampaignId)
+                           .to(CampaignModel::Table, CampaignModel::Id)
+                           .on_delete(ForeignKeyAction::Cascade)
+                        )
+            .foreign_key(ForeignKey::create()
+                           .name("FK_CampaignCharacter_Character")
+                           .from(CampaignCharacterModel::Table, CampaignCharacterModel::CharacterId)
+                           .to(CharacterModel::Table, CharacterModel::Id)
+                           .on_delete(ForeignKeyAction::Cascade)
+                        )
+            .build(PostgresQueryBuilder);
+
+        let monsters = Table::create()
+            .table(CampaignMonsterModel::Table)
+            .if_not_exists()
+            .col(ColumnDef::new(CampaignMonsterModel::CampaignId).uuid().not_null())
+            .col(ColumnDef::new(CampaignMonsterModel::MonsterId).uuid().not_null())
+            .primary_key(Index::create()
+                           .col(CampaignMonsterModel::CampaignId)
+                           .col(CampaignMonsterModel::MonsterId))
+            .foreign_key(ForeignKey::create()
+                           .name("FK_CampaignMonster_Campaign")
+                           .from(CampaignMonsterModel::Table, CampaignMonsterModel::CampaignId)
+                           .to(CampaignModel::Table, CampaignModel::Id)
+                           .on_delete(ForeignKeyAction::Cascade)
+                        )
+            .foreign_key(ForeignKey::create()
+                           .name("FK_CampaignMonster_Monster")
+                           .from(CampaignMonsterModel::Table, CampaignMonsterModel::MonsterId)
+                           .to(MonsterModel::Table, MonsterModel::Id)
+                           .on_delete(ForeignKeyAction::Cascade)
+                        )
+            .build(PostgresQueryBuilder);
+
+        // tables created before the leveling setting existed level by XP
+        let leveling = Table::alter()
+            .table(CampaignModel::Table)
+            .add_column_if_not_exists(ColumnDef::new(CampaignModel::Leveling).string().not_null().default(Leveling::Xp.as_str()))
+            .build(PostgresQueryBuilder);
+
+        // campaigns made before feeds had their own token get one on first use
+        let calendar = Table::alter()
+            .table(CampaignModel::Table)
+            .add_column_if_not_exists(ColumnDef::new(CampaignModel::CalendarToken).string().null())
+            .build(PostgresQueryBuilder);
+
+        // tables made before schema 11 deleted a shared campaign along
+        // with its creator, swap the cascade for SET NULL in one go
+        let nullable_creator = Table::alter()
+            .table(CampaignModel::Table)
+            .modify_column(ColumnDef::new(CampaignModel::CreatedBy).uuid().null())
+            .build(PostgresQueryBuilder);
+        let drop_creator_fk = ForeignKey::drop()
+            .name("FK_Campaign_User")
+            .table(CampaignModel::Table)
+            .build(PostgresQueryBuilder);
+        let creator_fk = ForeignKey::create()
+            .name("FK_Campaign_User")
+            .from(CampaignModel::Table, CampaignModel::CreatedBy)
+            .to(UserModel::Table, UserModel::Id)
+            .on_delete(ForeignKeyAction::SetNull)
+            .on_update(ForeignKeyAction::Cascade)
+            .build(PostgresQueryBuilder);
+
+        for sql in [campaign, members, characters, monsters, leveling, calendar] {
+            let _ = query(sql.as_str())
+                .execute(&mut *client)
+                .await?;
+        }
+        let mut tx = client.begin().await?;
+        for sql in [nullable_creator, drop_creator_fk, creator_fk] {
+            let _ = query(sql.as_str())
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
+        Ok(())
+    }
+
+    #[instrument(name = "campaign.get", skip_all, fields(db.system = "postgresql"))]
+    async fn get(con_str: String, id: Uuid) -> SResult<Campaign> {
+        let _timer = QueryTimer::new("campaign", "get");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .columns(CampaignModel::cols())
+            .from(CampaignModel::Table)
+            .limit(1)
+            .and_where(Expr::col(CampaignModel::Id).eq(id))
+            .and_where(Expr::col(CampaignModel::DeletedAt).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let row: Campaign = query_as_with(&sql, values)
+            .fetch_one(&mut *client)
+            .await?;
+        Ok(row)
+    }
+
+    #[instrument(name = "campaign.get_all", skip_all, fields(db.system = "postgresql"))]
+    async fn get_all(con_str: String) -> SResult<Vec<Campaign>> {
+        let _timer = QueryTimer::new("campaign", "get_all");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .columns(CampaignModel::cols())
+            .from(CampaignModel::Table)
+            .and_where(Expr::col(CampaignModel::DeletedAt).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<Campaign> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        Ok(rows)
+    }
+
+    #[instrument(name = "campaign.post", skip_all, fields(db.system = "postgresql"))]
+    async fn post(con_str: String, model: &Campaign) -> SResult<()> {
+        let _timer = QueryTimer::new("campaign", "post");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = insert_campaign(model);
+
+        let _ = query_with(&sql, values)
+            .execute(&mut *client)
+            .await?;
+        Ok(())
+    }
+
+    /// Changes the name, description and leveling, the invite code has
+    /// `rotate_invite`.
+    #[instrument(name = "campaign.put", skip_all, fields(db.system = "postgresql"))]
+    async fn put(con_str: String, id: Uuid, model: &Campaign) -> SResult<()> {
+        let _timer = QueryTimer::new("campaign", "put");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::update()
+            .table(CampaignModel::Table)
+            .values([
+                (CampaignModel::Name, model.name.clone().into()),
+                (CampaignModel::Description, model.description.clone().into()),
+                (CampaignModel::Leveling, model.leveling.as_str().into()),
+            ])
+            .and_where(Expr::col(CampaignModel::Id).eq(id))
+            .and_where(Expr::col(CampaignModel::DeletedAt).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let _ = query_with(sql.as_str(), values)
+            .execute(&mut *client)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(name = "campaign.delete", skip_all, fields(db.system = "postgresql"))]
+    async fn delete(con_str: String, id: Uuid) -> SResult<()> {
+        let _timer = QueryTimer::new("campaign", "delete");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::update()
+            .table(CampaignModel::Table)
+            .value(CampaignModel::DeletedAt, Utc::now())
+            .and_where(Expr::col(CampaignModel::Id).eq(id))
+            .and_where(Expr::col(CampaignModel::DeletedAt).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let result = query_with(sql.as_str(), values)
+            .execute(&mut *client)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(YaddakError::not_found(format!("No campaign with id {id} to delete")));
+        }
+        Ok(())
+    }
+
+    #[instrument(name = "campaign.restore", skip_all, fields(db.system = "postgresql"))]
+    async fn restore(con_str: String, id: Uuid) -> SResult<()> {
+        let _timer = QueryTimer::new("campaign", "restore");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::update()
+            .table(CampaignModel::Table)
+            .value(CampaignModel::DeletedAt, Option::<DateTime<Utc>>::None)
+            .and_where(Expr::col(CampaignModel::Id).eq(id))
+            .and_where(Expr::col(CampaignModel::DeletedAt).is_not_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let result = query_with(sql.as_str(), values)
+            .execute(&mut *client)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(YaddakError::not_found(format!("No restorable campaign with id {id}")));
+        }
+        Ok(())
+    }
+
+    #[instrument(name = "campaign.purge", skip_all, fields(db.system = "postgresql"))]
+    async fn purge(con_str: String, older_than: DateTime<Utc>) -> SResult<Vec<Uuid>> {
+        let _timer = QueryTimer::new("campaign", "purge");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::delete()
+            .from_table(CampaignModel::Table)
+            .and_where(Expr::col(CampaignModel::DeletedAt).lt(older_than))
+            .returning_col(CampaignModel::Id)
+            .build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<(Uuid,)> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        Ok(rows.into_iter().map(|(id,)| id).collect())
+    }
+}
+
+impl Auditable for Campaign {
+    const ENTITY_TYPE: &'static str = "campaign";
+
+    fn entity_id(&self) -> Uuid {
+        self.id
+    }
+
+    /// Invite codes are credentials, keep them out of the log.
+    fn snapshot(&self) -> serde_json::Value {
+        serde_json::to_value(Campaign { invite_code: None, ..self.clone() }).unwrap_or_default()
+    }
+}
+
+/// Links are logged against the campaign id.
+impl Auditable for CampaignLink {
+    const ENTITY_TYPE: &'static str = "campaign_link";
+
+    fn entity_id(&self) -> Uuid {
+        self.campaign_id
+    }
+}
+
+impl Auditable for CredentialRotation {
+    const ENTITY_TYPE: &'static str = "campaign_credential";
+
+    fn entity_id(&self) -> Uuid {
+        self.campaign_id
+    }
+}
+
+/// Membership changes are logged against the campaign id.
+impl Auditable for CampaignMember {
+    const ENTITY_TYPE: &'static str = "campaign_member";
+
+    fn entity_id(&self) -> Uuid {
+        self.campaign_id
+    }
+}
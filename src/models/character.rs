@@ -0,0 +1,722 @@
+use std::collections::{BTreeMap, HashSet};
+
+use chrono::{DateTime, Utc};
+use sea_query::{Iden, Table, ColumnDef, PostgresQueryBuilder, Query, Expr, ForeignKey};
+use sea_query_binder::SqlxBinder;
+use serde::{Serialize, Deserialize};
+use sqlx::{FromRow, PgConnection, query, query_as_with, query_with};
+use tracing::instrument;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use crate::{traits::{repo::{Repo, connect}, audit::Auditable}, utilities::metrics::QueryTimer};
+
+use super::{
+    errors::{SResult, YaddakError},
+    monsters::validate_img_url,
+    rules::{ability_modifier, level_for_xp, proficiency_bonus, xp_for_level, Ability, Skill},
+};
+
+/// The SRD classes.
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Copy, PartialEq, Eq, Hash,
+         ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum CharacterClass {
+    Barbarian,
+    Bard,
+    Cleric,
+    Druid,
+    Fighter,
+    Monk,
+    Paladin,
+    Ranger,
+    Rogue,
+    Sorcerer,
+    Warlock,
+    Wizard,
+}
+
+impl CharacterClass {
+    /// The ability the class casts with, if it casts at all. Fighters and
+    /// rogues only cast as Eldritch Knights and Arcane Tricksters.
+    pub fn spellcasting_ability(self, subclass: Option<&str>) -> Option<Ability> {
+        let subclass = subclass.map(str::to_lowercase);
+        match self {
+            CharacterClass::Wizard => Some(Ability::Int),
+            CharacterClass::Cleric | CharacterClass::Druid | CharacterClass::Ranger => Some(Ability::Wis),
+            CharacterClass::Bard | CharacterClass::Paladin
+            | CharacterClass::Sorcerer | CharacterClass::Warlock => Some(Ability::Cha),
+            CharacterClass::Fighter if subclass.as_deref() == Some("eldritch knight") => Some(Ability::Int),
+            CharacterClass::Rogue if subclass.as_deref() == Some("arcane trickster") => Some(Ability::Int),
+            CharacterClass::Barbarian | CharacterClass::Fighter
+            | CharacterClass::Monk | CharacterClass::Rogue => None,
+        }
+    }
+}
+
+/// Levels taken in one class. A multiclassed character has one per class.
+#[derive(Serialize, Deserialize, Debug,
+         Clone, PartialEq,
+         ToSchema, Validate)]
+pub struct ClassLevel {
+    pub class: CharacterClass,
+    #[validate(range(min = 1, max = 20, message = "must be between 1 and 20"))]
+    #[schema(minimum = 1, maximum = 20)]
+    pub level: i16,
+    #[serde(default)]
+    #[validate(length(max = 64, message = "must be at most 64 characters"))]
+    pub subclass: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Default, PartialEq,
+         ToSchema, Validate)]
+#[serde(default)]
+pub struct Proficiencies {
+    pub saving_throws: Vec<Ability>,
+    pub skills: Vec<Skill>,
+    /// Skills whose proficiency bonus is doubled, each must also be in `skills`.
+    pub expertise: Vec<Skill>,
+    /// Armor, weapons, tools and languages, as free text.
+    #[validate(length(max = 100, message = "must have at most 100 entries"))]
+    pub other: Vec<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug,
+         Clone, PartialEq,
+         ToSchema, Validate)]
+pub struct InventoryItem {
+    #[validate(length(min = 1, max = 128, message = "must be between 1 and 128 characters"))]
+    #[schema(min_length = 1, max_length = 128)]
+    pub name: String,
+    /// The compendium item this is, if any. Its weight is used when
+    /// `weight` is left out.
+    #[serde(default)]
+    pub item_id: Option<Uuid>,
+    #[serde(default = "one")]
+    #[validate(range(min = 0, message = "cannot be negative"))]
+    #[schema(minimum = 0, default = 1)]
+    pub quantity: i32,
+    /// Pounds per item.
+    #[serde(default)]
+    pub weight: Option<f32>,
+    #[serde(default)]
+    pub equipped: bool,
+    #[serde(default)]
+    pub notes: Option<String>,
+}
+
+fn one() -> i32 {
+    1
+}
+
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Default, FromRow,
+         ToSchema)]
+pub struct Character {
+    pub id: Uuid,
+    pub user_id: Uuid,
+    pub name: String,
+    pub race: String,
+    #[sqlx(json)]
+    pub classes: Vec<ClassLevel>,
+    pub str: i16,
+    pub dex: i16,
+    pub con: i16,
+    pub int: i16,
+    pub wis: i16,
+    pub cha: i16,
+    #[sqlx(json)]
+    pub proficiencies: Proficiencies,
+    pub max_hp: i32,
+    pub current_hp: i32,
+    pub temp_hp: i32,
+    pub armor_class: i16,
+    /// Walking speed in feet.
+    pub speed: i16,
+    #[sqlx(json)]
+    pub inventory: Vec<InventoryItem>,
+    pub img_url: Option<String>,
+    /// Total XP, raised by awards rather than by editing the sheet.
+    pub xp: i32,
+}
+
+/// Values that follow from the sheet and are never stored.
+#[derive(Serialize, Debug, Clone, Default, ToSchema)]
+pub struct DerivedStats {
+    /// Sum of every class level.
+    pub level: i16,
+    pub proficiency_bonus: i16,
+    pub modifiers: BTreeMap<Ability, i16>,
+    pub saving_throws: BTreeMap<Ability, i16>,
+    pub skills: BTreeMap<Skill, i16>,
+    pub initiative: i16,
+    pub passive_perception: i16,
+    /// The level `xp` reaches on the 5e table.
+    pub xp_level: i16,
+    /// XP still needed for the level after `xp_level`, `None` at 20.
+    pub xp_to_next_level: Option<i32>,
+    /// `xp` reaches a higher level than the class levels add up to.
+    pub level_up: bool,
+    /// One entry per spellcasting class.
+    pub spellcasting: Vec<Spellcasting>,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct Spellcasting {
+    pub class: CharacterClass,
+    pub ability: Ability,
+    pub save_dc: i16,
+    pub attack_bonus: i16,
+}
+
+/// A character with its derived stats, what the API returns.
+#[derive(Serialize, Debug, Clone, Default, ToSchema)]
+pub struct CharacterSheet {
+    #[serde(flatten)]
+    pub character: Character,
+    pub derived: DerivedStats,
+}
+
+impl Character {
+    pub fn score(&self, ability: Ability) -> i16 {
+        match ability {
+            Ability::Str => self.str,
+            Ability::Dex => self.dex,
+            Ability::Con => self.con,
+            Ability::Int => self.int,
+            Ability::Wis => self.wis,
+            Ability::Cha => self.cha,
+        }
+    }
+
+    pub fn level(&self) -> i16 {
+        self.classes.iter().map(|class| class.level).sum()
+    }
+
+    pub fn derived(&self) -> DerivedStats {
+        let level = self.level();
+        let proficiency = proficiency_bonus(level);
+        let modifier = |ability: Ability| ability_modifier(self.score(ability));
+        let proficiencies = &self.proficiencies;
+        let xp_level = level_for_xp(self.xp);
+
+        let saving_throws = Ability::ALL
+            .into_iter()
+            .map(|ability| {
+                let proficient = proficiencies.saving_throws.contains(&ability);
+                (ability, modifier(ability) + if proficient { proficiency } else { 0 })
+            })
+            .collect();
+        let skills: BTreeMap<Skill, i16> = Skill::ALL
+            .into_iter()
+            .map(|skill| {
+                let multiplier = if proficiencies.expertise.contains(&skill) {
+                    2
+                } else if proficiencies.skills.contains(&skill) {
+                    1
+                } else {
+                    0
+                };
+                (skill, modifier(skill.ability()) + multiplier * proficiency)
+            })
+            .collect();
+        let spellcasting = self.classes
+            .iter()
+            .filter_map(|class| {
+                let ability = class.class.spellcasting_ability(class.subclass.as_deref())?;
+                let attack_bonus = proficiency + modifier(ability);
+                Some(Spellcasting { class: class.class, ability, save_dc: 8 + attack_bonus, attack_bonus })
+            })
+            .collect();
+
+        DerivedStats {
+            level,
+            proficiency_bonus: proficiency,
+            modifiers: Ability::ALL.into_iter().map(|ability| (ability, modifier(ability))).collect(),
+            saving_throws,
+            passive_perception: 10 + skills[&Skill::Perception],
+            skills,
+            initiative: modifier(Ability::Dex),
+            xp_level,
+            xp_to_next_level: (xp_level < 20).then(|| xp_for_level(xp_level + 1) - self.xp),
+            level_up: xp_level > level,
+            spellcasting,
+        }
+    }
+
+    pub fn sheet(self) -> CharacterSheet {
+        let derived = self.derived();
+        CharacterSheet { character: self, derived }
+    }
+
+    #[instrument(name = "character.get_for_owner", skip_all, fields(db.system = "postgresql"))]
+    pub async fn get_for_owner(con_str: String, owner: Uuid) -> SResult<Vec<Character>> {
+        let _timer = QueryTimer::new("character", "get_for_owner");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .columns(CharacterModel::cols())
+            .from(CharacterModel::Table)
+            .and_where(Expr::col(CharacterModel::UserId).eq(owner))
+            .and_where(Expr::col(CharacterModel::DeletedAt).is_null())
+            .order_by(CharacterModel::Name, sea_query::Order::Asc)
+            .build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<Character> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        Ok(rows)
+    }
+}
+
+impl Character {
+    /// Adds `xp` to the character's total within `tx` and returns the new
+    /// total. `XpAward::grant` is the way in, it records the award too.
+    pub(super) async fn add_xp(tx: &mut PgConnection, id: Uuid, xp: i32) -> SResult<i32> {
+        let (sql, values) = Query::update()
+            .table(CharacterModel::Table)
+            .value(CharacterModel::Xp, Expr::col(CharacterModel::Xp).add(xp))
+            .and_where(Expr::col(CharacterModel::Id).eq(id))
+            .and_where(Expr::col(CharacterModel::DeletedAt).is_null())
+            .returning_col(CharacterModel::Xp)
+            .build_sqlx(PostgresQueryBuilder);
+
+        let (total,): (i32,) = query_as_with(&sql, values)
+            .fetch_one(&mut *tx)
+            .await?;
+        Ok(total)
+    }
+}
+
+/// Body for creating or updating a character.
+#[derive(Deserialize, Debug, Clone,
+         ToSchema, Validate)]
+#[validate(schema(function = "validate_hit_points", skip_on_field_errors = false))]
+pub struct CharacterRequest {
+    #[validate(length(min = 1, max = 128, message = "must be between 1 and 128 characters"))]
+    #[schema(min_length = 1, max_length = 128)]
+    pub name: String,
+    #[validate(length(min = 1, max = 64, message = "must be between 1 and 64 characters"))]
+    #[schema(min_length = 1, max_length = 64)]
+    pub race: String,
+    /// At least one class, each class at most once, 20 levels in total.
+    #[validate(nested, custom(function = "validate_classes"))]
+    pub classes: Vec<ClassLevel>,
+    #[validate(range(min = 1, max = 30, message = "must be between 1 and 30"))]
+    #[schema(minimum = 1, maximum = 30)]
+    pub str: i16,
+    #[validate(range(min = 1, max = 30, message = "must be between 1 and 30"))]
+    #[schema(minimum = 1, maximum = 30)]
+    pub dex: i16,
+    #[validate(range(min = 1, max = 30, message = "must be between 1 and 30"))]
+    #[schema(minimum = 1, maximum = 30)]
+    pub con: i16,
+    #[validate(range(min = 1, max = 30, message = "must be between 1 and 30"))]
+    #[schema(minimum = 1, maximum = 30)]
+    pub int: i16,
+    #[validate(range(min = 1, max = 30, message = "must be between 1 and 30"))]
+    #[schema(minimum = 1, maximum = 30)]
+    pub wis: i16,
+    #[validate(range(min = 1, max = 30, message = "must be between 1 and 30"))]
+    #[schema(minimum = 1, maximum = 30)]
+    pub cha: i16,
+    #[serde(default)]
+    #[validate(nested, custom(function = "validate_expertise"))]
+    pub proficiencies: Proficiencies,
+    #[validate(range(min = 1, max = 9999, message = "must be between 1 and 9999"))]
+    #[schema(minimum = 1, maximum = 9999)]
+    pub max_hp: i32,
+    /// Defaults to `max_hp`.
+    pub current_hp: Option<i32>,
+    #[serde(default)]
+    #[validate(range(min = 0, max = 9999, message = "must be between 0 and 9999"))]
+    #[schema(minimum = 0, maximum = 9999)]
+    pub temp_hp: i32,
+    #[validate(range(min = 0, max = 50, message = "must be between 0 and 50"))]
+    #[schema(minimum = 0, maximum = 50)]
+    pub armor_class: i16,
+    #[serde(default = "default_speed")]
+    #[validate(range(min = 0, max = 500, message = "must be between 0 and 500"))]
+    #[schema(minimum = 0, maximum = 500, default = 30)]
+    pub speed: i16,
+    #[serde(default)]
+    #[validate(nested, length(max = 500, message = "must have at most 500 items"))]
+    pub inventory: Vec<InventoryItem>,
+    /// An absolute URL, or the `/asset/{id}` path of an uploaded image.
+    #[serde(default)]
+    #[validate(custom(function = "validate_img_url"))]
+    pub img_url: Option<String>,
+}
+
+fn default_speed() -> i16 {
+    30
+}
+
+fn validate_classes(classes: &[ClassLevel]) -> Result<(), ValidationError> {
+    let fail = |message: &'static str| {
+        let mut err = ValidationError::new("classes");
+        err.message = Some(message.into());
+        Err(err)
+    };
+    if classes.is_empty() {
+        return fail("must have at least one class");
+    }
+    let mut seen = HashSet::new();
+    if !classes.iter().all(|class| seen.insert(class.class)) {
+        return fail("must list each class once");
+    }
+    if classes.iter().map(|class| class.level).sum::<i16>() > 20 {
+        return fail("must add up to at most 20 levels");
+    }
+    Ok(())
+}
+
+fn validate_expertise(proficiencies: &Proficiencies) -> Result<(), ValidationError> {
+    if proficiencies.expertise.iter().all(|skill| proficiencies.skills.contains(skill)) {
+        Ok(())
+    } else {
+        let mut err = ValidationError::new("expertise");
+        err.message = Some("expertise needs proficiency in the same skill".into());
+        Err(err)
+    }
+}
+
+fn validate_hit_points(request: &CharacterRequest) -> Result<(), ValidationError> {
+    match request.current_hp {
+        Some(current) if current < 0 || current > request.max_hp => {
+            let mut err = ValidationError::new("current_hp");
+            err.message = Some("current_hp must be between 0 and max_hp".into());
+            Err(err)
+        }
+        _ => Ok(()),
+    }
+}
+
+impl CharacterRequest {
+    /// `xp` is `None` for a new character, which starts with the XP its
+    /// level needs.
+    pub fn into_character(self, id: Uuid, user_id: Uuid, xp: Option<i32>) -> Character {
+        let level = self.classes.iter().map(|class| class.level).sum();
+        Character {
+            id,
+            user_id,
+            name: self.name,
+            race: self.race,
+            classes: self.classes,
+            str: self.str,
+            dex: self.dex,
+            con: self.con,
+            int: self.int,
+            wis: self.wis,
+            cha: self.cha,
+            proficiencies: self.proficiencies,
+            current_hp: self.current_hp.unwrap_or(self.max_hp),
+            max_hp: self.max_hp,
+            temp_hp: self.temp_hp,
+            armor_class: self.armor_class,
+            speed: self.speed,
+            inventory: self.inventory,
+            img_url: self.img_url,
+            xp: xp.unwrap_or_else(|| xp_for_level(level)),
+        }
+    }
+}
+
+#[derive(Iden)]
+pub(crate) enum CharacterModel {
+    Table,
+    Id,
+    UserId,
+    Name,
+    Race,
+    Classes,
+    Str,
+    Dex,
+    Con,
+    Int,
+    Wis,
+    Cha,
+    Proficiencies,
+    MaxHp,
+    CurrentHp,
+    TempHp,
+    ArmorClass,
+    Speed,
+    Inventory,
+    ImgUrl,
+    Xp,
+    DeletedAt,
+}
+
+impl CharacterModel {
+    pub fn cols() -> Vec<Self> {
+        vec![
+            Self::Id,
+            Self::UserId,
+            Self::Name,
+            Self::Race,
+            Self::Classes,
+            Self::Str,
+            Self::Dex,
+            Self::Con,
+            Self::Int,
+            Self::Wis,
+            Self::Cha,
+            Self::Proficiencies,
+            Self::MaxHp,
+            Self::CurrentHp,
+            Self::TempHp,
+            Self::ArmorClass,
+            Self::Speed,
+            Self::Inventory,
+            Self::ImgUrl,
+            Self::Xp,
+        ]
+    }
+}
+
+fn json<T: Serialize>(value: &T) -> SResult<sea_query::SimpleExpr> {
+    Ok(sea_query::Value::from(serde_json::to_value(value)?).into())
+}
+
+impl Repo<'_, Character> for Character {
+    async fn migrate(con_str: String) -> SResult<()> {
+        let mut client = connect(con_str).await?;
+        let sql = Table::create()
+            .table(CharacterModel::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(CharacterModel::Id)
+                    .uuid()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(CharacterModel::UserId).uuid().not_null())
+            .col(ColumnDef::new(CharacterModel::Name).string().not_null())
+            .col(ColumnDef::new(CharacterModel::Race).string().not_null())
+            .col(ColumnDef::new(CharacterModel::Classes).json_binary().not_null())
+            .col(ColumnDef::new(CharacterModel::Str).small_integer().not_null())
+            .col(ColumnDef::new(CharacterModel::Dex).small_integer().not_null())
+            .col(ColumnDef::new(CharacterModel::Con).small_integer().not_null())
+            .col(ColumnDef::new(CharacterModel::Int).small_integer().not_null())
+            .col(ColumnDef::new(CharacterModel::Wis).small_integer().not_null())
+            .col(ColumnDef::new(CharacterModel::Cha).small_integer().not_null())
+            .col(ColumnDef::new(CharacterModel::Proficiencies).json_binary().not_null())
+            .col(ColumnDef::new(CharacterModel::MaxHp).integer().not_null())
+            .col(ColumnDef::new(CharacterModel::CurrentHp).integer().not_null())
+            .col(ColumnDef::new(CharacterModel::TempHp).integer().not_null())
+            .col(ColumnDef::new(CharacterModel::ArmorClass).small_integer().not_null())
+            .col(ColumnDef::new(CharacterModel::Speed).small_integer().not_null())
+            .col(ColumnDef::new(CharacterModel::Inventory).json_binary().not_null())
+            .col(ColumnDef::new(CharacterModel::ImgUrl).string().null())
+            .col(ColumnDef::new(CharacterModel::Xp).integer().not_null().default(0))
+            .col(ColumnDef::new(CharacterModel::DeletedAt).timestamp_with_time_zone().null())
+            .foreign_key(ForeignKey::create()
+                           .name("FK_Character_User")
+                           .from(CharacterModel::Table, CharacterModel::UserId)
+                           .to(super::user::UserModel::Table,
+                               super::user::UserModel::Id)
+                           .on_delete(sea_query::ForeignKeyAction::Cascade)
+                           .on_update(sea_query::ForeignKeyAction::Cascade)
+                        )
+            .build(PostgresQueryBuilder);
+
+        let _ = query(sql.as_str())
+            .execute(&mut *client)
+            .await?;
+
+        // tables created before XP was tracked need the new column
+        let sql = Table::alter()
+            .table(CharacterModel::Table)
+            .add_column_if_not_exists(ColumnDef::new(CharacterModel::Xp).integer().not_null().default(0))
+            .build(PostgresQueryBuilder);
+
+        let _ = query(sql.as_str())
+            .execute(&mut *client)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(name = "character.get", skip_all, fields(db.system = "postgresql"))]
+    async fn get(con_str: String, id: Uuid) -> SResult<Character> {
+        let _timer = QueryTimer::new("character", "get");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .columns(CharacterModel::cols())
+            .from(CharacterModel::Table)
+            .limit(1)
+            .and_where(Expr::col(CharacterModel::Id).eq(id))
+            .and_where(Expr::col(CharacterModel::DeletedAt).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let row: Character = query_as_with(&sql, values)
+            .fetch_one(&mut *client)
+            .await?;
+        Ok(row)
+    }
+
+    #[instrument(name = "character.get_all", skip_all, fields(db.system = "postgresql"))]
+    async fn get_all(con_str: String) -> SResult<Vec<Character>> {
+        let _timer = QueryTimer::new("character", "get_all");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .columns(CharacterModel::cols())
+            .from(CharacterModel::Table)
+            .and_where(Expr::col(CharacterModel::DeletedAt).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<Character> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        Ok(rows)
+    }
+
+    #[instrument(name = "character.post", skip_all, fields(db.system = "postgresql"))]
+    async fn post(con_str: String, model: &Character) -> SResult<()> {
+        let _timer = QueryTimer::new("character", "post");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::insert()
+            .into_table(CharacterModel::Table)
+            .columns(CharacterModel::cols())
+            .values_panic([
+                model.id.into(),
+                model.user_id.into(),
+                model.name.clone().into(),
+                model.race.clone().into(),
+                json(&model.classes)?,
+                model.str.into(),
+                model.dex.into(),
+                model.con.into(),
+                model.int.into(),
+                model.wis.into(),
+                model.cha.into(),
+                json(&model.proficiencies)?,
+                model.max_hp.into(),
+                model.current_hp.into(),
+                model.temp_hp.into(),
+                model.armor_class.into(),
+                model.speed.into(),
+                json(&model.inventory)?,
+                model.img_url.clone().into(),
+                model.xp.into(),
+            ])
+            .build_sqlx(PostgresQueryBuilder);
+
+        let _ = query_with(&sql, values)
+            .execute(&mut *client)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(name = "character.put", skip_all, fields(db.system = "postgresql"))]
+    async fn put(con_str: String, id: Uuid, model: &Character) -> SResult<()> {
+        let _timer = QueryTimer::new("character", "put");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::update()
+            .table(CharacterModel::Table)
+            .values([
+                (CharacterModel::Name, model.name.clone().into()),
+                (CharacterModel::Race, model.race.clone().into()),
+                (CharacterModel::Classes, json(&model.classes)?),
+                (CharacterModel::Str, model.str.into()),
+                (CharacterModel::Dex, model.dex.into()),
+                (CharacterModel::Con, model.con.into()),
+                (CharacterModel::Int, model.int.into()),
+                (CharacterModel::Wis, model.wis.into()),
+                (CharacterModel::Cha, model.cha.into()),
+                (CharacterModel::Proficiencies, json(&model.proficiencies)?),
+                (CharacterModel::MaxHp, model.max_hp.into()),
+                (CharacterModel::CurrentHp, model.current_hp.into()),
+                (CharacterModel::TempHp, model.temp_hp.into()),
+                (CharacterModel::ArmorClass, model.armor_class.into()),
+                (CharacterModel::Speed, model.speed.into()),
+                (CharacterModel::Inventory, json(&model.inventory)?),
+                (CharacterModel::ImgUrl, model.img_url.clone().into()),
+                (CharacterModel::Xp, model.xp.into()),
+            ])
+            .and_where(Expr::col(CharacterModel::Id).eq(id))
+            .and_where(Expr::col(CharacterModel::DeletedAt).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let _ = query_with(sql.as_str(), values)
+            .execute(&mut *client)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(name = "character.delete", skip_all, fields(db.system = "postgresql"))]
+    async fn delete(con_str: String, id: Uuid) -> SResult<()> {
+        let _timer = QueryTimer::new("character", "delete");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::update()
+            .table(CharacterModel::Table)
+            .value(CharacterModel::DeletedAt, Utc::now())
+            .and_where(Expr::col(CharacterModel::Id).eq(id))
+            .and_where(Expr::col(CharacterModel::DeletedAt).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let result = query_with(sql.as_str(), values)
+            .execute(&mut *client)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(YaddakError::not_found(format!("No character with id {id} to delete")));
+        }
+        Ok(())
+    }
+
+    #[instrument(name = "character.restore", skip_all, fields(db.system = "postgresql"))]
+    async fn restore(con_str: String, id: Uuid) -> SResult<()> {
+        let _timer = QueryTimer::new("character", "restore");
+        let mut client = connect(con_str).await?;
+        // a character cannot come back while its owner is still deleted
+        let (sql, values) = Query::update()
+            .table(CharacterModel::Table)
+            .value(CharacterModel::DeletedAt, Option::<DateTime<Utc>>::None)
+            .and_where(Expr::col(CharacterModel::Id).eq(id))
+            .and_where(Expr::col(CharacterModel::DeletedAt).is_not_null())
+            .and_where(
+                Expr::col(CharacterModel::UserId).in_subquery(
+                    Query::select()
+                        .column(super::user::UserModel::Id)
+                        .from(super::user::UserModel::Table)
+                        .and_where(Expr::col(super::user::UserModel::DeletedAt).is_null())
+                        .to_owned()
+                )
+            )
+            .build_sqlx(PostgresQueryBuilder);
+
+        let result = query_with(sql.as_str(), values)
+            .execute(&mut *client)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(YaddakError::not_found(format!("No restorable character with id {id}")));
+        }
+        Ok(())
+    }
+
+    #[instrument(name = "character.purge", skip_all, fields(db.system = "postgresql"))]
+    async fn purge(con_str: String, older_than: DateTime<Utc>) -> SResult<Vec<Uuid>> {
+        let _timer = QueryTimer::new("character", "purge");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::delete()
+            .from_table(CharacterModel::Table)
+            .and_where(Expr::col(CharacterModel::DeletedAt).lt(older_than))
+            .returning_col(CharacterModel::Id)
+            .build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<(Uuid,)> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        Ok(rows.into_iter().map(|(id,)| id).collect())
+    }
+}
+
+impl Auditable for Character {
+    const ENTITY_TYPE: &'static str = "character";
+
+    fn entity_id(&self) -> Uuid {
+        self.id
+    }
+}
@@ -0,0 +1,529 @@
+use serde::{Serialize, Deserialize};
+use utoipa::{IntoParams, ToSchema};
+use validator::Validate;
+
+use super::{
+    errors::{SResult, YaddakError},
+    rules::Ability,
+};
+
+/// The conditions of the 5e rules, as stat blocks list them in
+/// `condition_immunities`.
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Copy, Default, PartialEq, Eq, Hash, PartialOrd, Ord,
+         ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Condition {
+    #[default]
+    Blinded,
+    Charmed,
+    Deafened,
+    Exhaustion,
+    Frightened,
+    Grappled,
+    Incapacitated,
+    Invisible,
+    Paralyzed,
+    Petrified,
+    Poisoned,
+    Prone,
+    Restrained,
+    Stunned,
+    Unconscious,
+}
+
+impl Condition {
+    pub const ALL: [Condition; 15] = [
+        Condition::Blinded,
+        Condition::Charmed,
+        Condition::Deafened,
+        Condition::Exhaustion,
+        Condition::Frightened,
+        Condition::Grappled,
+        Condition::Incapacitated,
+        Condition::Invisible,
+        Condition::Paralyzed,
+        Condition::Petrified,
+        Condition::Poisoned,
+        Condition::Prone,
+        Condition::Restrained,
+        Condition::Stunned,
+        Condition::Unconscious,
+    ];
+
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Condition::Blinded => "blinded",
+            Condition::Charmed => "charmed",
+            Condition::Deafened => "deafened",
+            Condition::Exhaustion => "exhaustion",
+            Condition::Frightened => "frightened",
+            Condition::Grappled => "grappled",
+            Condition::Incapacitated => "incapacitated",
+            Condition::Invisible => "invisible",
+            Condition::Paralyzed => "paralyzed",
+            Condition::Petrified => "petrified",
+            Condition::Poisoned => "poisoned",
+            Condition::Prone => "prone",
+            Condition::Restrained => "restrained",
+            Condition::Stunned => "stunned",
+            Condition::Unconscious => "unconscious",
+        }
+    }
+
+    /// The rules as the SRD words them, one entry per bullet. Exhaustion
+    /// lists its levels.
+    pub fn rules(self) -> &'static [&'static str] {
+        match self {
+            Condition::Blinded => &[
+                "A blinded creature can't see and automatically fails any ability check that requires sight.",
+                "Attack rolls against the creature have advantage, and the creature's attack rolls have disadvantage.",
+            ],
+            Condition::Charmed => &[
+                "A charmed creature can't attack the charmer or target the charmer with harmful abilities or magical effects.",
+                "The charmer has advantage on any ability check to interact socially with the creature.",
+            ],
+            Condition::Deafened => &[
+                "A deafened creature can't hear and automatically fails any ability check that requires hearing.",
+            ],
+            Condition::Exhaustion => &[
+                "Level 1: Disadvantage on ability checks.",
+                "Level 2: Speed halved.",
+                "Level 3: Disadvantage on attack rolls and saving throws.",
+                "Level 4: Hit point maximum halved.",
+                "Level 5: Speed reduced to 0.",
+                "Level 6: Death.",
+                "A creature suffers the effect of its current level of exhaustion as well as all lower levels. Finishing a long rest reduces a creature's exhaustion level by 1, provided that the creature has also ingested some food and drink.",
+            ],
+            Condition::Frightened => &[
+                "A frightened creature has disadvantage on ability checks and attack rolls while the source of its fear is within line of sight.",
+                "The creature can't willingly move closer to the source of its fear.",
+            ],
+            Condition::Grappled => &[
+                "A grappled creature's speed becomes 0, and it can't benefit from any bonus to its speed.",
+                "The condition ends if the grappler is incapacitated.",
+                "The condition also ends if an effect removes the grappled creature from the reach of the grappler or grappling effect, such as when a creature is hurled away by the thunderwave spell.",
+            ],
+            Condition::Incapacitated => &[
+                "An incapacitated creature can't take actions or reactions.",
+            ],
+            Condition::Invisible => &[
+                "An invisible creature is impossible to see without the aid of magic or a special sense. For the purpose of hiding, the creature is heavily obscured. The creature's location can be detected by any noise it makes or any tracks it leaves.",
+                "Attack rolls against the creature have disadvantage, and the creature's attack rolls have advantage.",
+            ],
+            Condition::Paralyzed => &[
+                "A paralyzed creature is incapacitated and can't move or speak.",
+                "The creature automatically fails Strength and Dexterity saving throws.",
+                "Attack rolls against the creature have advantage.",
+                "Any attack that hits the creature is a critical hit if the attacker is within 5 feet of the creature.",
+            ],
+            Condition::Petrified => &[
+                "A petrified creature is transformed, along with any nonmagical object it is wearing or carrying, into a solid inanimate substance (usually stone). Its weight increases by a factor of ten, and it ceases aging.",
+                "The creature is incapacitated, can't move or speak, and is unaware of its surroundings.",
+                "Attack rolls against the creature have advantage.",
+                "The creature automatically fails Strength and Dexterity saving throws.",
+                "The creature has resistance to all damage.",
+                "The creature is immune to poison and disease, although a poison or disease already in its system is suspended, not neutralized.",
+            ],
+            Condition::Poisoned => &[
+                "A poisoned creature has disadvantage on attack rolls and ability checks.",
+            ],
+            Condition::Prone => &[
+                "A prone creature's only movement option is to crawl, unless it stands up and thereby ends the condition.",
+                "The creature has disadvantage on attack rolls.",
+                "An attack roll against the creature has advantage if the attacker is within 5 feet of the creature. Otherwise, the attack roll has disadvantage.",
+            ],
+            Condition::Restrained => &[
+                "A restrained creature's speed becomes 0, and it can't benefit from any bonus to its speed.",
+                "Attack rolls against the creature have advantage, and the creature's attack rolls have disadvantage.",
+                "The creature has disadvantage on Dexterity saving throws.",
+            ],
+            Condition::Stunned => &[
+                "A stunned creature is incapacitated, can't move, and can speak only falteringly.",
+                "The creature automatically fails Strength and Dexterity saving throws.",
+                "Attack rolls against the creature have advantage.",
+            ],
+            Condition::Unconscious => &[
+                "An unconscious creature is incapacitated, can't move or speak, and is unaware of its surroundings.",
+                "The creature drops whatever it's holding and falls prone.",
+                "The creature automatically fails Strength and Dexterity saving throws.",
+                "Attack rolls against the creature have advantage.",
+                "Any attack that hits the creature is a critical hit if the attacker is within 5 feet of the creature.",
+            ],
+        }
+    }
+
+    /// What the condition does on its own, `level` only counts for
+    /// exhaustion. Conditions it includes, such as the incapacitated of
+    /// paralyzed, are listed in `includes` and not folded in.
+    pub fn effects(self, level: u8) -> ConditionEffects {
+        use Edge::{Advantage, Disadvantage};
+        let helpless = ConditionEffects {
+            speed_zero: true,
+            attacked_in_melee: Some(Advantage),
+            attacked_at_range: Some(Advantage),
+            auto_fail_saves: vec![Ability::Str, Ability::Dex],
+            includes: vec![Condition::Incapacitated],
+            ..Default::default()
+        };
+        match self {
+            Condition::Blinded => ConditionEffects {
+                attack_rolls: Some(Disadvantage),
+                attacked_in_melee: Some(Advantage),
+                attacked_at_range: Some(Advantage),
+                fails_sight_checks: true,
+                ..Default::default()
+            },
+            Condition::Charmed => ConditionEffects {
+                cannot_target_source: true,
+                ..Default::default()
+            },
+            Condition::Deafened => ConditionEffects {
+                fails_hearing_checks: true,
+                ..Default::default()
+            },
+            Condition::Exhaustion => ConditionEffects {
+                ability_checks: Some(Disadvantage),
+                speed_halved: level >= 2,
+                attack_rolls: (level >= 3).then_some(Disadvantage),
+                saving_throws: (level >= 3).then_some(Disadvantage),
+                hit_point_maximum_halved: level >= 4,
+                speed_zero: level >= 5,
+                dead: level >= 6,
+                ..Default::default()
+            },
+            Condition::Frightened => ConditionEffects {
+                ability_checks: Some(Disadvantage),
+                attack_rolls: Some(Disadvantage),
+                cannot_approach_source: true,
+                ..Default::default()
+            },
+            Condition::Grappled => ConditionEffects {
+                speed_zero: true,
+                ..Default::default()
+            },
+            Condition::Incapacitated => ConditionEffects {
+                no_actions: true,
+                ..Default::default()
+            },
+            Condition::Invisible => ConditionEffects {
+                attack_rolls: Some(Advantage),
+                attacked_in_melee: Some(Disadvantage),
+                attacked_at_range: Some(Disadvantage),
+                ..Default::default()
+            },
+            Condition::Paralyzed => ConditionEffects {
+                melee_hits_critical: true,
+                ..helpless
+            },
+            Condition::Petrified => ConditionEffects {
+                resists_all_damage: true,
+                ..helpless
+            },
+            Condition::Poisoned => ConditionEffects {
+                attack_rolls: Some(Disadvantage),
+                ability_checks: Some(Disadvantage),
+                ..Default::default()
+            },
+            Condition::Prone => ConditionEffects {
+                attack_rolls: Some(Disadvantage),
+                attacked_in_melee: Some(Advantage),
+                attacked_at_range: Some(Disadvantage),
+                ..Default::default()
+            },
+            Condition::Restrained => ConditionEffects {
+                speed_zero: true,
+                attack_rolls: Some(Disadvantage),
+                attacked_in_melee: Some(Advantage),
+                attacked_at_range: Some(Advantage),
+                save_disadvantage: vec![Ability::Dex],
+                ..Default::default()
+            },
+            Condition::Stunned => helpless,
+            Condition::Unconscious => ConditionEffects {
+                melee_hits_critical: true,
+                includes: vec![Condition::Incapacitated, Condition::Prone],
+                ..helpless
+            },
+        }
+    }
+
+    pub fn reference(self, level: u8) -> ConditionReference {
+        ConditionReference {
+            condition: self,
+            level: (self == Condition::Exhaustion).then_some(level),
+            rules: self.rules().iter().map(|rule| rule.to_string()).collect(),
+            effects: self.effects(level),
+        }
+    }
+}
+
+impl TryFrom<String> for Condition {
+    type Error = YaddakError;
+
+    fn try_from(value: String) -> SResult<Self> {
+        serde_json::from_value(serde_json::Value::String(value.trim().to_lowercase()))
+            .map_err(|_| YaddakError::bad_request(format!("{value:?} is not a condition")))
+    }
+}
+
+/// The conditions a stat block's `condition_immunities` names, such as
+/// `Charmed, Exhaustion, Poisoned`. Each entry counts by its first word,
+/// so `charmed (while in the form of a cloud)` is still charmed. Anything
+/// else in it is left out, the text itself is never rejected.
+pub fn immunities(text: &str) -> Vec<Condition> {
+    let mut conditions: Vec<Condition> = text
+        .split([',', ';'])
+        .filter_map(|entry| entry.split(|c: char| !c.is_alphabetic()).find(|word| !word.is_empty()))
+        .filter_map(|name| Condition::try_from(name.to_string()).ok())
+        .collect();
+    conditions.sort();
+    conditions.dedup();
+    conditions
+}
+
+/// How a condition changes a d20 roll.
+#[derive(Serialize, Debug,
+         Clone, Copy, PartialEq, Eq,
+         ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Edge {
+    Advantage,
+    Disadvantage,
+    /// Advantage and disadvantage both apply, so the roll is made
+    /// normally however many of each there are.
+    Cancelled,
+}
+
+impl Edge {
+    fn and(edge: Option<Edge>, other: Option<Edge>) -> Option<Edge> {
+        match (edge, other) {
+            (None, other) => other,
+            (edge, None) => edge,
+            (Some(edge), Some(other)) if edge == other => Some(edge),
+            _ => Some(Edge::Cancelled),
+        }
+    }
+}
+
+/// What a condition does, for a tracker or sheet to apply without
+/// reading the rules text. `None` leaves a roll alone.
+#[derive(Serialize, Debug, Clone, Default, PartialEq, ToSchema)]
+pub struct ConditionEffects {
+    /// Can't take actions or reactions.
+    pub no_actions: bool,
+    pub speed_zero: bool,
+    pub speed_halved: bool,
+    /// The creature's own attack rolls.
+    pub attack_rolls: Option<Edge>,
+    /// Attack rolls against the creature from within 5 feet.
+    pub attacked_in_melee: Option<Edge>,
+    /// Attack rolls against the creature from further away.
+    pub attacked_at_range: Option<Edge>,
+    /// Hits from within 5 feet are critical hits.
+    pub melee_hits_critical: bool,
+    pub ability_checks: Option<Edge>,
+    /// Every saving throw.
+    pub saving_throws: Option<Edge>,
+    /// Saving throws of these abilities have disadvantage.
+    pub save_disadvantage: Vec<Ability>,
+    /// Saving throws of these abilities fail without a roll.
+    pub auto_fail_saves: Vec<Ability>,
+    pub fails_sight_checks: bool,
+    pub fails_hearing_checks: bool,
+    /// Can't attack or harm the source of the condition.
+    pub cannot_target_source: bool,
+    /// Can't willingly move closer to the source of the condition.
+    pub cannot_approach_source: bool,
+    pub resists_all_damage: bool,
+    pub hit_point_maximum_halved: bool,
+    pub dead: bool,
+    /// Conditions that come with this one.
+    pub includes: Vec<Condition>,
+}
+
+impl ConditionEffects {
+    /// Everything `conditions` do together, with the conditions they
+    /// include, leaving out any the creature is `immune` to. Exhaustion
+    /// counts at `exhaustion` levels.
+    pub fn combined(conditions: &[Condition], exhaustion: u8, immune: &[Condition]) -> ConditionEffects {
+        let mut active: Vec<Condition> = Vec::new();
+        let mut pending = conditions.to_vec();
+        while let Some(condition) = pending.pop() {
+            if !active.contains(&condition) && !immune.contains(&condition) {
+                active.push(condition);
+                pending.extend(condition.effects(exhaustion).includes);
+            }
+        }
+        active.sort();
+        active
+            .iter()
+            .map(|condition| condition.effects(exhaustion))
+            .fold(ConditionEffects::default(), |total, effects| total.and(effects))
+    }
+
+    fn and(self, other: ConditionEffects) -> ConditionEffects {
+        let union = |mut abilities: Vec<Ability>, more: Vec<Ability>| {
+            abilities.extend(more);
+            abilities.sort();
+            abilities.dedup();
+            abilities
+        };
+        let mut includes = self.includes;
+        includes.extend(other.includes);
+        includes.sort();
+        includes.dedup();
+        ConditionEffects {
+            no_actions: self.no_actions || other.no_actions,
+            speed_zero: self.speed_zero || other.speed_zero,
+            speed_halved: self.speed_halved || other.speed_halved,
+            attack_rolls: Edge::and(self.attack_rolls, other.attack_rolls),
+            attacked_in_melee: Edge::and(self.attacked_in_melee, other.attacked_in_melee),
+            attacked_at_range: Edge::and(self.attacked_at_range, other.attacked_at_range),
+            melee_hits_critical: self.melee_hits_critical || other.melee_hits_critical,
+            ability_checks: Edge::and(self.ability_checks, other.ability_checks),
+            saving_throws: Edge::and(self.saving_throws, other.saving_throws),
+            save_disadvantage: union(self.save_disadvantage, other.save_disadvantage),
+            auto_fail_saves: union(self.auto_fail_saves, other.auto_fail_saves),
+            fails_sight_checks: self.fails_sight_checks || other.fails_sight_checks,
+            fails_hearing_checks: self.fails_hearing_checks || other.fails_hearing_checks,
+            cannot_target_source: self.cannot_target_source || other.cannot_target_source,
+            cannot_approach_source: self.cannot_approach_source || other.cannot_approach_source,
+            resists_all_damage: self.resists_all_damage || other.resists_all_damage,
+            hit_point_maximum_halved: self.hit_point_maximum_halved || other.hit_point_maximum_halved,
+            dead: self.dead || other.dead,
+            includes,
+        }
+    }
+}
+
+/// A condition with its rules and effects.
+#[derive(Serialize, Debug, Clone, Default, ToSchema)]
+pub struct ConditionReference {
+    pub condition: Condition,
+    /// Only set for exhaustion.
+    pub level: Option<u8>,
+    pub rules: Vec<String>,
+    pub effects: ConditionEffects,
+}
+
+#[derive(Deserialize, Debug, Clone, Default,
+         IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExhaustionQuery {
+    /// Exhaustion level the effects are for, 1 to 6 and 1 when left out.
+    /// Other conditions ignore it.
+    pub level: Option<u8>,
+}
+
+impl ExhaustionQuery {
+    pub fn level(&self) -> SResult<u8> {
+        match self.level.unwrap_or(1) {
+            level @ 1..=6 => Ok(level),
+            level => Err(YaddakError::bad_request(format!("Exhaustion level {level} is not between 1 and 6"))),
+        }
+    }
+}
+
+/// Conditions on one creature at once.
+#[derive(Deserialize, Debug, Clone,
+         ToSchema, Validate)]
+pub struct EffectsRequest {
+    #[validate(length(max = 15, message = "must have at most 15 entries"))]
+    pub conditions: Vec<Condition>,
+    /// Exhaustion level, if `conditions` has exhaustion.
+    #[serde(default = "first_level")]
+    #[validate(range(min = 1, max = 6, message = "must be between 1 and 6"))]
+    #[schema(minimum = 1, maximum = 6, default = 1)]
+    pub exhaustion: u8,
+}
+
+/// A condition to put on a monster, refused if the monster is immune.
+#[derive(Deserialize, Debug, Clone,
+         ToSchema, Validate)]
+pub struct ApplyConditionRequest {
+    pub condition: Condition,
+    #[serde(default = "first_level")]
+    #[validate(range(min = 1, max = 6, message = "must be between 1 and 6"))]
+    #[schema(minimum = 1, maximum = 6, default = 1)]
+    pub level: u8,
+}
+
+fn first_level() -> u8 {
+    1
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn immunities_read_the_first_word_of_each_entry() {
+        assert_eq!(
+            immunities("Charmed, Exhaustion, Poisoned"),
+            [Condition::Charmed, Condition::Exhaustion, Condition::Poisoned]
+        );
+        assert_eq!(
+            immunities("charmed (while in the form of a mist); poisoned, poisoned"),
+            [Condition::Charmed, Condition::Poisoned]
+        );
+        assert_eq!(immunities("Prone, anything that would move it"), [Condition::Prone]);
+        assert!(immunities("").is_empty());
+    }
+
+    #[test]
+    fn included_conditions_are_folded_in() {
+        let effects = ConditionEffects::combined(&[Condition::Unconscious], 0, &[]);
+        assert_eq!(effects.includes, [Condition::Incapacitated, Condition::Prone]);
+        // incapacitated
+        assert!(effects.no_actions);
+        // prone
+        assert_eq!(effects.attack_rolls, Some(Edge::Disadvantage));
+        // helpless gives advantage at range, prone disadvantage
+        assert_eq!(effects.attacked_in_melee, Some(Edge::Advantage));
+        assert_eq!(effects.attacked_at_range, Some(Edge::Cancelled));
+        assert!(effects.melee_hits_critical);
+        assert_eq!(effects.auto_fail_saves, [Ability::Str, Ability::Dex]);
+    }
+
+    #[test]
+    fn immunity_drops_an_included_condition() {
+        let effects = ConditionEffects::combined(&[Condition::Unconscious], 0, &[Condition::Prone]);
+        assert!(effects.no_actions);
+        assert_eq!(effects.attack_rolls, None);
+        assert_eq!(effects.attacked_at_range, Some(Edge::Advantage));
+
+        let effects = ConditionEffects::combined(&[Condition::Paralyzed], 0, &[Condition::Incapacitated]);
+        assert!(!effects.no_actions);
+        assert!(effects.speed_zero);
+    }
+
+    #[test]
+    fn immunity_to_the_condition_itself_does_nothing() {
+        assert_eq!(
+            ConditionEffects::combined(&[Condition::Poisoned], 0, &[Condition::Poisoned]),
+            ConditionEffects::default()
+        );
+    }
+
+    #[test]
+    fn opposite_edges_cancel() {
+        let effects = ConditionEffects::combined(&[Condition::Blinded, Condition::Invisible], 0, &[]);
+        assert_eq!(effects.attack_rolls, Some(Edge::Cancelled));
+        assert_eq!(effects.attacked_in_melee, Some(Edge::Cancelled));
+        assert!(effects.fails_sight_checks);
+    }
+
+    #[test]
+    fn exhaustion_stacks_by_level() {
+        let tired = ConditionEffects::combined(&[Condition::Exhaustion], 1, &[]);
+        assert_eq!(tired.ability_checks, Some(Edge::Disadvantage));
+        assert!(!tired.speed_halved);
+
+        let spent = ConditionEffects::combined(&[Condition::Exhaustion], 5, &[]);
+        assert!(spent.speed_halved && spent.speed_zero && spent.hit_point_maximum_halved);
+        assert_eq!(spent.saving_throws, Some(Edge::Disadvantage));
+        assert!(!spent.dead);
+        assert!(ConditionEffects::combined(&[Condition::Exhaustion], 6, &[]).dead);
+    }
+}
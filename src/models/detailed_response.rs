@@ -2,19 +2,51 @@ use serde::Serialize;
 use utoipa::ToSchema;
 use uuid::Uuid;
 
-use super::{errors::YaddakError, user::User};
+use crate::utilities::request_id;
+
+use super::{errors::YaddakError, user::{User, UserProfile}, monsters::Monster, audit::AuditLog, health::{Readiness, VersionInfo}, monster_import::ImportReport, asset::Asset, character::CharacterSheet, campaign::{Campaign, CampaignDetail}, session::{Session, SessionDetail}, xp::{AwardOutcome, AwardReport, XpAward}, spell::{Spell, SpellLink}, item::Item, treasure::Treasure, condition::{ConditionEffects, ConditionReference}};
 
 #[derive(Serialize, ToSchema)]
 #[aliases(
     UserDetailedResponse = DetailedResponse<User>,
-    UserListDetailedResponse = DetailedResponse<Vec<User>>,
-    UuidDetailedResponse = DetailedResponse<Uuid>
+    UserProfileDetailedResponse = DetailedResponse<UserProfile>,
+    UserProfileListDetailedResponse = DetailedResponse<Vec<UserProfile>>,
+    UuidDetailedResponse = DetailedResponse<Uuid>,
+    MonsterDetailedResponse = DetailedResponse<Monster>,
+    MonsterListDetailedResponse = DetailedResponse<Vec<Monster>>,
+    AuditListDetailedResponse = DetailedResponse<Vec<AuditLog>>,
+    StringDetailedResponse = DetailedResponse<String>,
+    ReadinessDetailedResponse = DetailedResponse<Readiness>,
+    VersionDetailedResponse = DetailedResponse<VersionInfo>,
+    ImportReportDetailedResponse = DetailedResponse<ImportReport>,
+    AssetDetailedResponse = DetailedResponse<Asset>,
+    CharacterDetailedResponse = DetailedResponse<CharacterSheet>,
+    CharacterListDetailedResponse = DetailedResponse<Vec<CharacterSheet>>,
+    CampaignDetailedResponse = DetailedResponse<CampaignDetail>,
+    CampaignListDetailedResponse = DetailedResponse<Vec<Campaign>>,
+    SessionDetailedResponse = DetailedResponse<SessionDetail>,
+    SessionListDetailedResponse = DetailedResponse<Vec<Session>>,
+    AwardReportDetailedResponse = DetailedResponse<AwardReport>,
+    AwardOutcomeListDetailedResponse = DetailedResponse<Vec<AwardOutcome>>,
+    XpAwardListDetailedResponse = DetailedResponse<Vec<XpAward>>,
+    SpellDetailedResponse = DetailedResponse<Spell>,
+    SpellListDetailedResponse = DetailedResponse<Vec<Spell>>,
+    SpellLinkListDetailedResponse = DetailedResponse<Vec<SpellLink>>,
+    ItemDetailedResponse = DetailedResponse<Item>,
+    ItemListDetailedResponse = DetailedResponse<Vec<Item>>,
+    TreasureDetailedResponse = DetailedResponse<Treasure>,
+    ConditionReferenceDetailedResponse = DetailedResponse<ConditionReference>,
+    ConditionReferenceListDetailedResponse = DetailedResponse<Vec<ConditionReference>>,
+    ConditionEffectsDetailedResponse = DetailedResponse<ConditionEffects>
 )]
 #[serde(rename_all="camelCase")]
 pub struct DetailedResponse<T>
 where T: Serialize + Default {
     pub data: Option<T>,
-    pub error: Option<YaddakError>
+    pub error: Option<YaddakError>,
+    /// Echo of `X-Request-Id`, only sent with errors.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub request_id: Option<String>,
 }
 
 impl<T> DetailedResponse<T>
@@ -23,7 +55,8 @@ where T: Serialize + Default {
     -> Self {
         Self {
             data: Some(data),
-            error: None
+            error: None,
+            request_id: None,
         }
     }
     
@@ -31,7 +64,8 @@ where T: Serialize + Default {
     -> Self {
         Self {
             data: None,
-            error: Some(error)
+            error: Some(error),
+            request_id: request_id::current(),
         }
     }
 }
@@ -0,0 +1,158 @@
+use std::collections::HashSet;
+
+use chrono::{DateTime, Utc};
+use serde::{Serialize, Deserialize};
+
+use crate::traits::repo::Repo;
+
+use super::{
+    errors::{SResult, YaddakErrorKind},
+    monsters::Monster,
+    campaign::Campaign,
+    character::Character,
+    session::Session,
+    xp::XpAward,
+    schema::SCHEMA_VERSION,
+    user::User,
+};
+
+/// Every active user, monster and character, for `export` and `import`. Password
+/// hashes are only valid against the same `auth` settings they came from.
+/// Campaigns, sessions, XP awards and stored images are not carried, see
+/// `LeftBehind`.
+#[derive(Serialize, Deserialize, Debug)]
+pub struct Dump {
+    pub schema_version: i32,
+    pub exported_at: DateTime<Utc>,
+    pub users: Vec<DumpUser>,
+    pub monsters: Vec<Monster>,
+    /// Missing from dumps taken before schema version 4.
+    #[serde(default)]
+    pub characters: Vec<Character>,
+}
+
+#[derive(Serialize, Deserialize, Debug)]
+pub struct DumpUser {
+    #[serde(flatten)]
+    pub user: User,
+    #[serde(default)]
+    pub is_admin: bool,
+}
+
+/// What lives in the database but has no place in a `Dump` yet, so is
+/// lost on the way to another server.
+#[derive(Debug, Default)]
+pub struct LeftBehind {
+    pub campaigns: usize,
+    pub sessions: usize,
+    pub xp_awards: i64,
+    /// Monsters and characters whose image is a stored `/asset/{id}`,
+    /// which will not resolve after an import.
+    pub stored_images: usize,
+}
+
+impl LeftBehind {
+    pub fn is_empty(&self) -> bool {
+        self.campaigns == 0 && self.sessions == 0 && self.xp_awards == 0 && self.stored_images == 0
+    }
+}
+
+/// What `Dump::import` did with each record.
+#[derive(Debug, Default)]
+pub struct ImportSummary {
+    pub users_imported: u64,
+    pub users_skipped: u64,
+    pub monsters_imported: u64,
+    pub monsters_skipped: u64,
+    pub characters_imported: u64,
+    pub characters_skipped: u64,
+}
+
+impl Dump {
+    pub async fn export(con_str: String) -> SResult<Dump> {
+        let admins = User::admin_ids(con_str.clone()).await?;
+        let users = User::get_all(con_str.clone())
+            .await?
+            .into_iter()
+            .map(|user| DumpUser { is_admin: admins.contains(&user.id), user })
+            .collect();
+        let monsters = Monster::get_all(con_str.clone()).await?;
+        let characters = Character::get_all(con_str).await?;
+
+        Ok(Dump {
+            schema_version: SCHEMA_VERSION,
+            exported_at: Utc::now(),
+            users,
+            monsters,
+            characters,
+        })
+    }
+
+    /// Counts what `export` could not carry for this dump.
+    pub async fn left_behind(&self, con_str: String) -> SResult<LeftBehind> {
+        let stored = |img_url: &str| img_url.starts_with("/asset/");
+        Ok(LeftBehind {
+            campaigns: Campaign::get_all(con_str.clone()).await?.len(),
+            sessions: Session::get_all(con_str.clone()).await?.len(),
+            xp_awards: XpAward::count(con_str).await?,
+            stored_images: self.monsters.iter().filter(|monster| stored(&monster.img_url)).count()
+                + self.characters.iter().filter(|character| character.img_url.as_deref().is_some_and(stored)).count(),
+        })
+    }
+
+    /// Inserts users before the monsters and characters they own. Records
+    /// whose id, user name or email is already taken are skipped, so
+    /// importing twice is safe. A user skipped for a name or email another
+    /// account holds takes their monsters and characters with them.
+    pub async fn import(con_str: String, dump: &Dump) -> SResult<ImportSummary> {
+        let mut summary = ImportSummary::default();
+        let mut orphaned = HashSet::new();
+        for entry in &dump.users {
+            match import_user(con_str.clone(), entry).await {
+                Ok(()) => summary.users_imported += 1,
+                Err(err) if err.kind == YaddakErrorKind::Conflict => {
+                    summary.users_skipped += 1;
+                    if !User::exists(con_str.clone(), entry.user.id).await? {
+                        orphaned.insert(entry.user.id);
+                    }
+                }
+                Err(err) => return Err(err),
+            }
+        }
+        for monster in &dump.monsters {
+            if orphaned.contains(&monster.user_id) {
+                summary.monsters_skipped += 1;
+                continue;
+            }
+            match Monster::post(con_str.clone(), monster).await {
+                Ok(()) => summary.monsters_imported += 1,
+                Err(err) if err.kind == YaddakErrorKind::Conflict => summary.monsters_skipped += 1,
+                Err(err) => return Err(err),
+            }
+        }
+        for character in &dump.characters {
+            if orphaned.contains(&character.user_id) {
+                summary.characters_skipped += 1;
+                continue;
+            }
+            match Character::post(con_str.clone(), character).await {
+                Ok(()) => summary.characters_imported += 1,
+                Err(err) if err.kind == YaddakErrorKind::Conflict => summary.characters_skipped += 1,
+                Err(err) => return Err(err),
+            }
+        }
+        Ok(summary)
+    }
+}
+
+/// Nothing stops two rows sharing a user name or email, so they are
+/// checked here. Two accounts with one name would lock both out of login.
+async fn import_user(con_str: String, entry: &DumpUser) -> SResult<()> {
+    User::user_name_not_used(con_str.clone(), entry.user.user_name.clone()).await?;
+    User::user_email_not_used(con_str.clone(), entry.user.user_email.clone()).await?;
+    User::post(con_str.clone(), &entry.user).await?;
+    if entry.is_admin {
+        User::set_admin(con_str, entry.user.id, true).await?;
+    }
+    Ok(())
+}
@@ -1,112 +1,243 @@
 use std::env::VarError;
 
+use axum::{Json, extract::rejection::{BytesRejection, JsonRejection, StringRejection}, response::{IntoResponse, Response}};
+use hyper::StatusCode;
 use serde::Serialize ;
 use utoipa::ToSchema;
 
-#[derive(Serialize, Clone, ToSchema)]
+use super::detailed_response::DetailedResponse;
+
+#[derive(Serialize, Clone, Debug, ToSchema)]
 pub struct YaddakError {
     pub kind: YaddakErrorKind,
+    /// Stable code clients can match on, e.g. `E006`.
+    pub code: String,
     message: String,
+    #[serde(skip_serializing_if = "Vec::is_empty")]
+    pub details: Vec<FieldError>,
+}
+
+/// A single invalid field on a request body.
+#[derive(Serialize, Clone, Debug, ToSchema)]
+pub struct FieldError {
+    pub field: String,
+    pub message: String,
 }
 
-#[derive(Serialize, Clone, ToSchema)]
+#[derive(Serialize, Clone, Copy, Debug, PartialEq, Eq, ToSchema)]
+#[allow(clippy::enum_variant_names)]
 pub enum YaddakErrorKind {
     EnvError,
     InternalError,
     AuthError,
     DBError,
     IoError,
+    NotFound,
+    Conflict,
+    Validation,
+    Forbidden,
+    /// Not raised yet, reserved so throttling can slot into the mapping.
+    #[allow(dead_code)]
+    RateLimited,
+    BadRequest,
+    PayloadTooLarge,
+}
+
+impl YaddakErrorKind {
+    pub fn code(&self) -> &'static str {
+        match self {
+            YaddakErrorKind::EnvError => "E001",
+            YaddakErrorKind::InternalError => "E002",
+            YaddakErrorKind::AuthError => "E003",
+            YaddakErrorKind::DBError => "E004",
+            YaddakErrorKind::IoError => "E005",
+            YaddakErrorKind::NotFound => "E006",
+            YaddakErrorKind::Conflict => "E007",
+            YaddakErrorKind::Validation => "E008",
+            YaddakErrorKind::Forbidden => "E009",
+            YaddakErrorKind::RateLimited => "E010",
+            YaddakErrorKind::BadRequest => "E011",
+            YaddakErrorKind::PayloadTooLarge => "E012",
+        }
+    }
+
+    /// The one place error kinds are mapped onto HTTP.
+    pub fn status(&self) -> StatusCode {
+        match self {
+            YaddakErrorKind::EnvError
+            | YaddakErrorKind::InternalError
+            | YaddakErrorKind::DBError
+            | YaddakErrorKind::IoError => StatusCode::INTERNAL_SERVER_ERROR,
+            YaddakErrorKind::AuthError => StatusCode::UNAUTHORIZED,
+            YaddakErrorKind::NotFound => StatusCode::NOT_FOUND,
+            YaddakErrorKind::Conflict => StatusCode::CONFLICT,
+            YaddakErrorKind::Validation => StatusCode::UNPROCESSABLE_ENTITY,
+            YaddakErrorKind::Forbidden => StatusCode::FORBIDDEN,
+            YaddakErrorKind::RateLimited => StatusCode::TOO_MANY_REQUESTS,
+            YaddakErrorKind::BadRequest => StatusCode::BAD_REQUEST,
+            YaddakErrorKind::PayloadTooLarge => StatusCode::PAYLOAD_TOO_LARGE,
+        }
+    }
+
+    fn label(&self) -> &'static str {
+        match self {
+            YaddakErrorKind::EnvError => "Environment Variable Error",
+            YaddakErrorKind::InternalError => "Internal Error",
+            YaddakErrorKind::AuthError => "Authentication Error",
+            YaddakErrorKind::DBError => "Database Error",
+            YaddakErrorKind::IoError => "Io Error",
+            YaddakErrorKind::NotFound => "Not Found",
+            YaddakErrorKind::Conflict => "Conflict",
+            YaddakErrorKind::Validation => "Validation Error",
+            YaddakErrorKind::Forbidden => "Forbidden",
+            YaddakErrorKind::RateLimited => "Rate Limited",
+            YaddakErrorKind::BadRequest => "Bad Request",
+            YaddakErrorKind::PayloadTooLarge => "Payload Too Large",
+        }
+    }
 }
 
 
 impl std::fmt::Display for YaddakError {
     fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
-        match self.kind {
-            YaddakErrorKind::EnvError => {
-                write!(f, "[E001] Environment Variable Error: {}", self.message) 
-            },
-            YaddakErrorKind::InternalError => {
-                write!(f, "[E002] Internal Error: {}", self.message) 
-            },
-            YaddakErrorKind::AuthError => {
-                write!(f, "[E003] Authentication Error: {}", self.message) 
-            },
-            YaddakErrorKind::DBError => {
-                write!(f, "[E004] Database Error:  {}", self.message)
-            }
-            YaddakErrorKind::IoError => 
-                write!(f, "[E005] Io Error {}", self.message),
+        write!(f, "[{}] {}: {}", self.code, self.kind.label(), self.message)?;
+        for detail in &self.details {
+            write!(f, "\n\t{}: {}", detail.field, detail.message)?;
+        }
+        Ok(())
+    }
+}
+
+impl IntoResponse for YaddakError {
+    fn into_response(self) -> Response {
+        let status = self.kind.status();
+        if status.is_server_error() {
+            tracing::error!("{self}");
         }
+        (status, Json(DetailedResponse::<()>::absorb_error(self))).into_response()
     }
 }
 
 impl From<VarError> for YaddakError {
     fn from(value: VarError) -> Self {
-        return YaddakError {
-            kind: YaddakErrorKind::EnvError,
-            message: value.to_string()
-        }
+        YaddakError::new(YaddakErrorKind::EnvError, value.to_string())
     }
 }
 
 impl From<argon2::Error> for YaddakError {
     fn from(value: argon2::Error) -> Self {
-        return YaddakError {
-            kind: YaddakErrorKind::InternalError,
-            message: value.to_string()
-        }
+        YaddakError::new(YaddakErrorKind::InternalError, value.to_string())
     }
 }
 
 impl From<sqlx::Error> for YaddakError {
     fn from(value: sqlx::Error) -> Self {
-        return YaddakError {
-            kind: YaddakErrorKind::DBError,
-            message: value.to_string()
-        };
+        match &value {
+            sqlx::Error::RowNotFound =>
+                YaddakError::not_found("No matching record".to_string()),
+            // 23505 is unique_violation
+            sqlx::Error::Database(db) if db.code().as_deref() == Some("23505") =>
+                YaddakError::conflict(db.message().to_string()),
+            _ => YaddakError::new(YaddakErrorKind::DBError, value.to_string()),
+        }
+    }
+}
+
+impl From<JsonRejection> for YaddakError {
+    fn from(value: JsonRejection) -> Self {
+        match value {
+            // well formed JSON that does not fit the request type
+            JsonRejection::JsonDataError(err) => YaddakError::validation(vec![FieldError {
+                field: "body".to_string(),
+                message: err.body_text(),
+            }]),
+            // the body ran past the route's DefaultBodyLimit
+            _ if value.status() == StatusCode::PAYLOAD_TOO_LARGE =>
+                YaddakError::new(YaddakErrorKind::PayloadTooLarge, value.body_text()),
+            _ => YaddakError::bad_request(value.body_text()),
+        }
+    }
+}
+
+impl From<StringRejection> for YaddakError {
+    fn from(value: StringRejection) -> Self {
+        if value.status() == StatusCode::PAYLOAD_TOO_LARGE {
+            YaddakError::new(YaddakErrorKind::PayloadTooLarge, value.body_text())
+        } else {
+            YaddakError::bad_request(value.body_text())
+        }
+    }
+}
+
+impl From<BytesRejection> for YaddakError {
+    fn from(value: BytesRejection) -> Self {
+        if value.status() == StatusCode::PAYLOAD_TOO_LARGE {
+            YaddakError::new(YaddakErrorKind::PayloadTooLarge, value.body_text())
+        } else {
+            YaddakError::bad_request(value.body_text())
+        }
     }
 }
 
 impl From<sea_query::error::Error> for YaddakError {
     fn from(value: sea_query::error::Error) -> Self {
-        return YaddakError {
-            kind: YaddakErrorKind::DBError,
-            message: value.to_string()
-        };
+        YaddakError::new(YaddakErrorKind::DBError, value.to_string())
     }
 }
 
 impl From<std::io::Error> for YaddakError {
     fn from(value: std::io::Error) -> Self {
-        return YaddakError {
-            kind: YaddakErrorKind::IoError,
-            message: value.to_string()
-        };
+        YaddakError::new(YaddakErrorKind::IoError, value.to_string())
     }
 }
 
 impl From<serde_json::Error> for YaddakError {
     fn from(value: serde_json::Error) -> Self {
-        return YaddakError {
-            kind: YaddakErrorKind::IoError,
-            message: value.to_string()
-        };
+        YaddakError::new(YaddakErrorKind::IoError, value.to_string())
+    }
+}
+
+impl From<csv::Error> for YaddakError {
+    fn from(value: csv::Error) -> Self {
+        YaddakError::new(YaddakErrorKind::InternalError, value.to_string())
     }
 }
 
 impl YaddakError {
-    pub fn authorize_error(message: String)
+    pub fn new(kind: YaddakErrorKind, message: String)
     -> Self {
         Self {
-            kind: YaddakErrorKind::AuthError,
+            kind,
+            code: kind.code().to_string(),
             message,
+            details: Vec::new(),
         }
     }
-    pub fn database_error(message: String)
+    pub fn authorize_error(message: String)
+    -> Self {
+        Self::new(YaddakErrorKind::AuthError, message)
+    }
+    pub fn not_found(message: String)
+    -> Self {
+        Self::new(YaddakErrorKind::NotFound, message)
+    }
+    pub fn conflict(message: String)
+    -> Self {
+        Self::new(YaddakErrorKind::Conflict, message)
+    }
+    pub fn forbidden(message: String)
+    -> Self {
+        Self::new(YaddakErrorKind::Forbidden, message)
+    }
+    pub fn bad_request(message: String)
+    -> Self {
+        Self::new(YaddakErrorKind::BadRequest, message)
+    }
+    pub fn validation(details: Vec<FieldError>)
     -> Self {
         Self {
-            kind: YaddakErrorKind::DBError,
-            message,
+            details,
+            ..Self::new(YaddakErrorKind::Validation, "Request failed validation".to_string())
         }
     }
 }
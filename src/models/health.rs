@@ -0,0 +1,29 @@
+use serde::Serialize;
+use utoipa::ToSchema;
+
+/// What `/readyz` checked. The server is ready when the database answered,
+/// the schema is at `expected_schema_version` and the SRD monsters are loaded.
+#[derive(Serialize, Debug,
+         Clone, Default, ToSchema)]
+pub struct Readiness {
+    pub database: bool,
+    pub schema_version: Option<i32>,
+    pub expected_schema_version: i32,
+    pub seeded: bool,
+}
+
+impl Readiness {
+    pub fn ready(&self) -> bool {
+        self.database
+            && self.schema_version == Some(self.expected_schema_version)
+            && self.seeded
+    }
+}
+
+#[derive(Serialize, Debug,
+         Clone, Default, ToSchema)]
+pub struct VersionInfo {
+    pub version: String,
+    pub git_hash: String,
+    pub schema_version: i32,
+}
@@ -0,0 +1,445 @@
+use std::{collections::HashMap, path::{Path, PathBuf}};
+
+use sea_query::{Iden, Table, ColumnDef, PostgresQueryBuilder, Query, Expr, Index, OnConflict, Order, Cond, extension::postgres::PgExpr};
+use sea_query_binder::SqlxBinder;
+use serde::{Serialize, Deserialize};
+use sqlx::{FromRow, query, query_as_with, query_with};
+use tracing::instrument;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::{traits::repo::connect, utilities::metrics::QueryTimer};
+
+use super::{
+    errors::{FieldError, SResult, YaddakError},
+    monsters::SRD_SOURCE,
+    spell::escape_like,
+};
+
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Copy, Default, PartialEq, Eq,
+         ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum ItemCategory {
+    Weapon,
+    Armor,
+    #[default]
+    Gear,
+    MagicItem,
+}
+
+impl ItemCategory {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            ItemCategory::Weapon => "weapon",
+            ItemCategory::Armor => "armor",
+            ItemCategory::Gear => "gear",
+            ItemCategory::MagicItem => "magic_item",
+        }
+    }
+}
+
+impl TryFrom<String> for ItemCategory {
+    type Error = YaddakError;
+
+    fn try_from(value: String) -> SResult<Self> {
+        serde_json::from_value(serde_json::Value::String(value.clone()))
+            .map_err(|_| YaddakError::bad_request(format!("{value:?} is not an item category")))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Copy, PartialEq, Eq,
+         ToSchema)]
+#[serde(rename_all = "kebab-case")]
+pub enum WeaponProperty {
+    Ammunition,
+    Finesse,
+    Heavy,
+    Light,
+    Loading,
+    Reach,
+    Special,
+    Thrown,
+    TwoHanded,
+    Versatile,
+}
+
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Copy, PartialEq, Eq,
+         ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ArmorType {
+    Light,
+    Medium,
+    Heavy,
+    Shield,
+}
+
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Copy, PartialEq, Eq, Hash,
+         ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Rarity {
+    Common,
+    Uncommon,
+    Rare,
+    VeryRare,
+    Legendary,
+    Artifact,
+    /// Depends on the item's form, such as the level of a spell scroll.
+    Varies,
+}
+
+#[derive(Serialize, Deserialize, Debug,
+         Clone, PartialEq,
+         ToSchema)]
+pub struct WeaponStats {
+    /// Martial rather than simple.
+    pub martial: bool,
+    pub ranged: bool,
+    /// Dice rolled on a hit, e.g. `1d8`. `None` for weapons that deal no
+    /// damage, like a net.
+    pub damage: Option<String>,
+    pub damage_type: Option<String>,
+    #[serde(default)]
+    pub properties: Vec<WeaponProperty>,
+    /// Damage dice when wielded in two hands.
+    #[serde(default)]
+    pub versatile: Option<String>,
+    /// Normal and long range in feet, e.g. `80/320`.
+    #[serde(default)]
+    pub range: Option<String>,
+}
+
+/// AC is `base_ac`, plus the Dex modifier when `dex_bonus` is set, capped
+/// at `max_dex_bonus` if there is one. A shield's `base_ac` is added to
+/// whatever armor is worn.
+#[derive(Serialize, Deserialize, Debug,
+         Clone, PartialEq,
+         ToSchema)]
+pub struct ArmorStats {
+    pub armor_type: ArmorType,
+    pub base_ac: i16,
+    pub dex_bonus: bool,
+    #[serde(default)]
+    pub max_dex_bonus: Option<i16>,
+    /// Strength score needed to move at full speed in it.
+    #[serde(default)]
+    pub strength: Option<i16>,
+    pub stealth_disadvantage: bool,
+}
+
+#[derive(Serialize, Deserialize, Debug,
+         Clone, PartialEq,
+         ToSchema)]
+pub struct MagicStats {
+    /// As printed, e.g. `Wondrous item` or `Weapon (any sword)`.
+    pub item_type: String,
+    pub rarity: Rarity,
+    pub attunement: bool,
+    /// Who can attune to it, e.g. `by a spellcaster`, when not anyone.
+    #[serde(default)]
+    pub attunement_by: Option<String>,
+}
+
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Default, FromRow,
+         ToSchema)]
+pub struct Item {
+    pub id: Uuid,
+    pub name: String,
+    #[sqlx(try_from = "String")]
+    pub category: ItemCategory,
+    /// Price in copper pieces, magic items have none.
+    pub cost_cp: Option<i32>,
+    /// Pounds.
+    pub weight: Option<f32>,
+    pub description: Option<String>,
+    #[sqlx(json)]
+    pub weapon: Option<WeaponStats>,
+    #[sqlx(json)]
+    pub armor: Option<ArmorStats>,
+    #[sqlx(json)]
+    pub magic: Option<MagicStats>,
+    pub source: String,
+}
+
+/// An item in the bundled `data/items.json` shape, with the price written
+/// out as the rules print it, e.g. `1,500 gp`.
+#[derive(Deserialize, Debug, Clone)]
+pub struct ItemFromJson {
+    pub name: String,
+    pub category: ItemCategory,
+    #[serde(default)]
+    pub cost: Option<String>,
+    #[serde(default)]
+    pub weight: Option<f32>,
+    #[serde(default)]
+    pub description: Option<String>,
+    #[serde(default)]
+    pub weapon: Option<WeaponStats>,
+    #[serde(default)]
+    pub armor: Option<ArmorStats>,
+    #[serde(default)]
+    pub magic: Option<MagicStats>,
+}
+
+impl ItemFromJson {
+    pub fn into_item(self, id: Uuid, source: &str) -> SResult<Item> {
+        let cost_cp = self.cost.as_deref().map(copper).transpose()?;
+        Ok(Item {
+            id,
+            name: self.name,
+            category: self.category,
+            cost_cp,
+            weight: self.weight,
+            description: self.description,
+            weapon: self.weapon,
+            armor: self.armor,
+            magic: self.magic,
+            source: source.to_string(),
+        })
+    }
+}
+
+/// A price like `2 gp` or `1,500 gp` in copper pieces.
+fn copper(cost: &str) -> SResult<i32> {
+    let invalid = || YaddakError::bad_request(format!("{cost:?} is not a price"));
+    let (amount, coin) = cost.trim().split_once(' ').ok_or_else(invalid)?;
+    let amount: i32 = amount.replace(',', "").parse().map_err(|_| invalid())?;
+    let rate = match coin {
+        "cp" => 1,
+        "sp" => 10,
+        "ep" => 50,
+        "gp" => 100,
+        "pp" => 1000,
+        _ => return Err(invalid()),
+    };
+    Ok(amount * rate)
+}
+
+/// Search filters, every field is optional and they combine with AND.
+#[derive(Deserialize, Debug, Clone, Default,
+         IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ItemQuery {
+    pub category: Option<ItemCategory>,
+    /// Only magic items of this rarity.
+    pub rarity: Option<Rarity>,
+    /// Only magic items that do, or do not, need attunement.
+    pub attunement: Option<bool>,
+    /// Only weapons with this property.
+    pub property: Option<WeaponProperty>,
+    /// Only armor of this type.
+    pub armor_type: Option<ArmorType>,
+    /// Case insensitive text to find in the name or description.
+    pub q: Option<String>,
+}
+
+impl Item {
+    #[instrument(name = "item.get", skip_all, fields(db.system = "postgresql"))]
+    pub async fn get(con_str: String, id: Uuid) -> SResult<Item> {
+        let _timer = QueryTimer::new("item", "get");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .columns(ItemModel::cols())
+            .from(ItemModel::Table)
+            .and_where(Expr::col(ItemModel::Id).eq(id))
+            .limit(1)
+            .build_sqlx(PostgresQueryBuilder);
+
+        let row: Item = query_as_with(&sql, values)
+            .fetch_one(&mut *client)
+            .await?;
+        Ok(row)
+    }
+
+    /// Items matching `filter`, by category and then name.
+    #[instrument(name = "item.search", skip_all, fields(db.system = "postgresql"))]
+    pub async fn search(con_str: String, filter: ItemQuery) -> SResult<Vec<Item>> {
+        let _timer = QueryTimer::new("item", "search");
+        let mut client = connect(con_str).await?;
+        let mut select = Query::select();
+        select
+            .columns(ItemModel::cols())
+            .from(ItemModel::Table)
+            .order_by(ItemModel::Category, Order::Asc)
+            .order_by(ItemModel::Name, Order::Asc);
+        if let Some(category) = filter.category {
+            select.and_where(Expr::col(ItemModel::Category).eq(category.as_str()));
+        }
+        if let Some(rarity) = filter.rarity {
+            select.and_where(Expr::col(ItemModel::Magic).contains(serde_json::json!({ "rarity": rarity })));
+        }
+        if let Some(attunement) = filter.attunement {
+            select.and_where(Expr::col(ItemModel::Magic).contains(serde_json::json!({ "attunement": attunement })));
+        }
+        if let Some(property) = filter.property {
+            select.and_where(Expr::col(ItemModel::Weapon).contains(serde_json::json!({ "properties": [property] })));
+        }
+        if let Some(armor_type) = filter.armor_type {
+            select.and_where(Expr::col(ItemModel::Armor).contains(serde_json::json!({ "armor_type": armor_type })));
+        }
+        if let Some(text) = filter.q.filter(|text| !text.trim().is_empty()) {
+            let pattern = format!("%{}%", escape_like(text.trim()));
+            select.cond_where(
+                Cond::any()
+                    .add(Expr::col(ItemModel::Name).ilike(pattern.clone()))
+                    .add(Expr::col(ItemModel::Description).ilike(pattern))
+            );
+        }
+        let (sql, values) = select.build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<Item> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        Ok(rows)
+    }
+
+    /// The items `ids` link to. Ids with no item are validation errors
+    /// against `field`.
+    #[instrument(name = "item.linked", skip_all, fields(db.system = "postgresql"))]
+    pub async fn linked(con_str: String, field: &str, mut ids: Vec<Uuid>) -> SResult<HashMap<Uuid, Item>> {
+        ids.sort_unstable();
+        ids.dedup();
+        if ids.is_empty() {
+            return Ok(HashMap::new());
+        }
+        let _timer = QueryTimer::new("item", "linked");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .columns(ItemModel::cols())
+            .from(ItemModel::Table)
+            .and_where(Expr::col(ItemModel::Id).is_in(ids.clone()))
+            .build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<Item> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        let items: HashMap<Uuid, Item> = rows.into_iter().map(|item| (item.id, item)).collect();
+        let missing: Vec<FieldError> = ids
+            .into_iter()
+            .filter(|id| !items.contains_key(id))
+            .map(|id| FieldError {
+                field: field.to_string(),
+                message: format!("no item with id {id}"),
+            })
+            .collect();
+        if !missing.is_empty() {
+            return Err(YaddakError::validation(missing));
+        }
+        Ok(items)
+    }
+
+    /// Loads items from a file in the bundled `data/items.json` shape.
+    /// Items already loaded from the SRD are skipped, so seeding again
+    /// only adds what is new. Returns how many were added.
+    pub async fn seed(con_str: String, file_path: &Path) -> SResult<u64> {
+        let content = tokio::fs::read_to_string(file_path).await?;
+        let items: Vec<ItemFromJson> = serde_json::from_str(&content)?;
+        let mut client = connect(con_str).await?;
+        let mut added = 0;
+        for item in items {
+            let item = item.into_item(Uuid::new_v4(), SRD_SOURCE)?;
+            let (sql, values) = Query::insert()
+                .into_table(ItemModel::Table)
+                .columns(ItemModel::cols())
+                .values_panic([
+                    item.id.into(),
+                    item.name.into(),
+                    item.category.as_str().into(),
+                    item.cost_cp.into(),
+                    item.weight.into(),
+                    item.description.into(),
+                    sea_query::Value::from(serde_json::to_value(&item.weapon)?).into(),
+                    sea_query::Value::from(serde_json::to_value(&item.armor)?).into(),
+                    sea_query::Value::from(serde_json::to_value(&item.magic)?).into(),
+                    item.source.into(),
+                ])
+                .on_conflict(
+                    OnConflict::columns([ItemModel::Name, ItemModel::Source])
+                        .do_nothing()
+                        .to_owned()
+                )
+                .build_sqlx(PostgresQueryBuilder);
+
+            added += query_with(&sql, values)
+                .execute(&mut *client)
+                .await?
+                .rows_affected();
+        }
+        Ok(added)
+    }
+
+    pub fn bundled_seed() -> PathBuf {
+        crate::config::get().storage.data_dir.join("items.json")
+    }
+
+    pub async fn migrate(con_str: String) -> SResult<()> {
+        let mut client = connect(con_str).await?;
+        let sql = Table::create()
+            .table(ItemModel::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(ItemModel::Id)
+                    .uuid()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(ItemModel::Name).string().not_null())
+            .col(ColumnDef::new(ItemModel::Category).string().not_null())
+            .col(ColumnDef::new(ItemModel::CostCp).integer().null())
+            .col(ColumnDef::new(ItemModel::Weight).float().null())
+            .col(ColumnDef::new(ItemModel::Description).text().null())
+            // JSON null for an item without them, sqlx cannot decode SQL NULL
+            // into an optional json field
+            .col(ColumnDef::new(ItemModel::Weapon).json_binary().not_null())
+            .col(ColumnDef::new(ItemModel::Armor).json_binary().not_null())
+            .col(ColumnDef::new(ItemModel::Magic).json_binary().not_null())
+            .col(ColumnDef::new(ItemModel::Source).string().not_null())
+            .index(Index::create()
+                     .name("UQ_Item_Name_Source")
+                     .unique()
+                     .col(ItemModel::Name)
+                     .col(ItemModel::Source))
+            .build(PostgresQueryBuilder);
+
+        let _ = query(sql.as_str())
+            .execute(&mut *client)
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+enum ItemModel {
+    Table,
+    Id,
+    Name,
+    Category,
+    CostCp,
+    Weight,
+    Description,
+    Weapon,
+    Armor,
+    Magic,
+    Source,
+}
+
+impl ItemModel {
+    pub fn cols() -> Vec<Self> {
+        vec![
+            Self::Id,
+            Self::Name,
+            Self::Category,
+            Self::CostCp,
+            Self::Weight,
+            Self::Description,
+            Self::Weapon,
+            Self::Armor,
+            Self::Magic,
+            Self::Source,
+        ]
+    }
+}
@@ -3,3 +3,19 @@ pub mod errors;
 pub mod detailed_response;
 pub mod state;
 pub mod monsters;
+pub mod audit;
+pub mod schema;
+pub mod health;
+pub mod dump;
+pub mod monster_import;
+pub mod monster_export;
+pub mod asset;
+pub mod rules;
+pub mod character;
+pub mod campaign;
+pub mod session;
+pub mod xp;
+pub mod spell;
+pub mod item;
+pub mod treasure;
+pub mod condition;
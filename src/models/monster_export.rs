@@ -0,0 +1,403 @@
+use axum::{http::header, response::{IntoResponse, Response}};
+use serde::{Serialize, Deserialize};
+use utoipa::{IntoParams, ToSchema};
+
+use crate::utilities::html;
+
+use super::{
+    errors::{SResult, YaddakError, YaddakErrorKind},
+    monsters::{challenge_label, challenge_xp, Monster, MonsterFromJson},
+    rules::ability_modifier,
+};
+
+/// Formats `export` writes.
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Copy, Default, PartialEq, Eq,
+         ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum ExportFormat {
+    /// A JSON array the `yaddak` import format reads back
+    #[default]
+    Json,
+    /// The columns the CSV import reads, with a header row
+    Csv,
+    /// Stat blocks for pasting into notes
+    Markdown,
+    /// A standalone page of stat blocks
+    Html,
+}
+
+impl ExportFormat {
+    fn content_type(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "application/json",
+            ExportFormat::Csv => "text/csv; charset=utf-8",
+            ExportFormat::Markdown => "text/markdown; charset=utf-8",
+            ExportFormat::Html => "text/html; charset=utf-8",
+        }
+    }
+
+    fn extension(self) -> &'static str {
+        match self {
+            ExportFormat::Json => "json",
+            ExportFormat::Csv => "csv",
+            ExportFormat::Markdown => "md",
+            ExportFormat::Html => "html",
+        }
+    }
+}
+
+#[derive(Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+}
+
+#[derive(Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct BulkExportQuery {
+    #[serde(default)]
+    pub format: ExportFormat,
+    /// Only monsters from this source, e.g. `homebrew`
+    pub source: Option<String>,
+    /// Only monsters the caller owns
+    #[serde(default)]
+    pub mine: bool,
+}
+
+/// A rendered export, sent as a download named after what it holds.
+#[derive(Debug)]
+pub struct Export {
+    pub format: ExportFormat,
+    pub file_name: String,
+    pub body: String,
+}
+
+impl IntoResponse for Export {
+    fn into_response(self) -> Response {
+        let disposition = format!(
+            "attachment; filename=\"{}.{}\"",
+            self.file_name,
+            self.format.extension()
+        );
+        (
+            [
+                (header::CONTENT_TYPE, self.format.content_type().to_string()),
+                (header::CONTENT_DISPOSITION, disposition),
+            ],
+            self.body,
+        ).into_response()
+    }
+}
+
+/// Renders `monsters` in `format`. `name` becomes the file name once it is
+/// reduced to something safe for a header.
+pub fn export(monsters: &[Monster], format: ExportFormat, name: &str) -> SResult<Export> {
+    let body = match format {
+        ExportFormat::Json => to_json(monsters)?,
+        ExportFormat::Csv => to_csv(monsters)?,
+        ExportFormat::Markdown => monsters
+            .iter()
+            .map(to_markdown)
+            .collect::<Vec<_>>()
+            .join("\n---\n\n"),
+        ExportFormat::Html => to_html(monsters),
+    };
+    Ok(Export { format, file_name: file_name(name), body })
+}
+
+/// `Adult Red Dragon` becomes `adult-red-dragon`.
+fn file_name(name: &str) -> String {
+    let slug = name
+        .split(|c: char| !c.is_ascii_alphanumeric())
+        .filter(|part| !part.is_empty())
+        .map(str::to_ascii_lowercase)
+        .collect::<Vec<_>>()
+        .join("-");
+    if slug.is_empty() { "monster".to_string() } else { slug }
+}
+
+/// `10 (5,900 XP)`, or just the rating when it is off the table.
+fn challenge_text(challenge: f32) -> String {
+    match challenge_xp(challenge) {
+        Some(xp) => format!("{} ({} XP)", challenge_label(challenge), thousands(xp)),
+        None => challenge_label(challenge),
+    }
+}
+
+fn thousands(value: u32) -> String {
+    let digits = value.to_string();
+    let mut grouped = String::with_capacity(digits.len() + digits.len() / 3);
+    for (index, digit) in digits.chars().enumerate() {
+        if index > 0 && (digits.len() - index).is_multiple_of(3) {
+            grouped.push(',');
+        }
+        grouped.push(digit);
+    }
+    grouped
+}
+
+/// `21 (+5)`
+fn ability_text(score: i16) -> String {
+    format!("{score} ({:+})", ability_modifier(score))
+}
+
+fn abilities(monster: &Monster) -> [(&'static str, i16); 6] {
+    [
+        ("STR", monster.str),
+        ("DEX", monster.dex),
+        ("CON", monster.con),
+        ("INT", monster.int),
+        ("WIS", monster.wis),
+        ("CHA", monster.cha),
+    ]
+}
+
+/// The optional property lines, in stat block order, skipping empty ones.
+fn properties(monster: &Monster) -> Vec<(&'static str, String)> {
+    [
+        ("Saving Throws", Some(monster.saving_throws.clone())),
+        ("Skills", Some(monster.skills.clone())),
+        ("Damage Immunities", monster.damage_immunities.clone()),
+        ("Condition Immunities", monster.condition_immunities.clone()),
+        ("Senses", Some(monster.senses.clone())),
+        ("Languages", Some(monster.languages.clone())),
+    ]
+    .into_iter()
+    .filter_map(|(label, value)| value.filter(|value| !value.trim().is_empty()).map(|value| (label, value)))
+    .collect()
+}
+
+/// Trait, action and legendary action sections that have entries.
+fn sections(monster: &Monster) -> Vec<(Option<&'static str>, Vec<&str>)> {
+    [
+        (None, monster.traits.as_deref()),
+        (Some("Actions"), Some(monster.actions.as_str())),
+        (Some("Legendary Actions"), monster.legendary_actions.as_deref()),
+    ]
+    .into_iter()
+    .filter_map(|(title, text)| {
+        let entries: Vec<&str> = text?.lines().map(str::trim).filter(|line| !line.is_empty()).collect();
+        (!entries.is_empty()).then_some((title, entries))
+    })
+    .collect()
+}
+
+/// Splits `Multiattack. The aboleth makes...` into its name and text. Lines
+/// that open with a long sentence, like the legendary action preamble, have
+/// no name.
+fn entry(line: &str) -> (Option<&str>, &str) {
+    match line.split_once(". ") {
+        Some((name, text)) if name.split_whitespace().count() <= 6 => (Some(name), text),
+        _ => (None, line),
+    }
+}
+
+fn to_json(monsters: &[Monster]) -> SResult<String> {
+    let records: Vec<MonsterFromJson> = monsters
+        .iter()
+        .map(|monster| MonsterFromJson {
+            name: monster.name.clone(),
+            meta: monster.meta.clone(),
+            armor_class: monster.armor_class.clone(),
+            hit_points: monster.hit_points.clone(),
+            speed: monster.speed.clone(),
+            str: monster.str,
+            dex: monster.dex,
+            con: monster.con,
+            int: monster.int,
+            wis: monster.wis,
+            cha: monster.cha,
+            saving_throws: monster.saving_throws.clone(),
+            skills: monster.skills.clone(),
+            senses: monster.senses.clone(),
+            languages: monster.languages.clone(),
+            challenge: challenge_text(monster.challenge),
+            traits: monster.traits.clone(),
+            actions: monster.actions.clone(),
+            damage_immunities: monster.damage_immunities.clone(),
+            condition_immunities: monster.condition_immunities.clone(),
+            legendary_actions: monster.legendary_actions.clone(),
+            img_url: monster.img_url.clone(),
+            source: monster.source.clone(),
+        })
+        .collect();
+    Ok(serde_json::to_string_pretty(&records)?)
+}
+
+/// One row per monster under the header the CSV import expects.
+#[derive(Serialize)]
+struct CsvRow<'a> {
+    name: &'a str,
+    meta: &'a str,
+    armor_class: &'a str,
+    hit_points: &'a str,
+    speed: &'a str,
+    str: i16,
+    dex: i16,
+    con: i16,
+    int: i16,
+    wis: i16,
+    cha: i16,
+    saving_throws: &'a str,
+    skills: &'a str,
+    senses: &'a str,
+    languages: &'a str,
+    challenge: String,
+    traits: Option<&'a str>,
+    actions: &'a str,
+    damage_immunities: Option<&'a str>,
+    condition_immunities: Option<&'a str>,
+    legendary_actions: Option<&'a str>,
+    img_url: &'a str,
+    source: &'a str,
+}
+
+fn to_csv(monsters: &[Monster]) -> SResult<String> {
+    let mut writer = csv::Writer::from_writer(Vec::new());
+    for monster in monsters {
+        writer.serialize(CsvRow {
+            name: &monster.name,
+            meta: &monster.meta,
+            armor_class: &monster.armor_class,
+            hit_points: &monster.hit_points,
+            speed: &monster.speed,
+            str: monster.str,
+            dex: monster.dex,
+            con: monster.con,
+            int: monster.int,
+            wis: monster.wis,
+            cha: monster.cha,
+            saving_throws: &monster.saving_throws,
+            skills: &monster.skills,
+            senses: &monster.senses,
+            languages: &monster.languages,
+            challenge: challenge_label(monster.challenge),
+            traits: monster.traits.as_deref(),
+            actions: &monster.actions,
+            damage_immunities: monster.damage_immunities.as_deref(),
+            condition_immunities: monster.condition_immunities.as_deref(),
+            legendary_actions: monster.legendary_actions.as_deref(),
+            img_url: &monster.img_url,
+            source: &monster.source,
+        })?;
+    }
+    let bytes = writer
+        .into_inner()
+        .map_err(|err| YaddakError::new(YaddakErrorKind::InternalError, err.to_string()))?;
+    String::from_utf8(bytes).map_err(|err| YaddakError::new(YaddakErrorKind::InternalError, err.to_string()))
+}
+
+fn to_markdown(monster: &Monster) -> String {
+    let mut block = format!("## {}\n", monster.name);
+    if !monster.meta.is_empty() {
+        block.push_str(&format!("*{}*\n", monster.meta));
+    }
+    block.push_str(&format!(
+        "\n**Armor Class** {}  \n**Hit Points** {}  \n**Speed** {}\n\n",
+        monster.armor_class, monster.hit_points, monster.speed
+    ));
+
+    let abilities = abilities(monster);
+    let header: Vec<&str> = abilities.iter().map(|(ability, _)| *ability).collect();
+    let scores: Vec<String> = abilities.iter().map(|(_, score)| ability_text(*score)).collect();
+    block.push_str(&format!("| {} |\n", header.join(" | ")));
+    block.push_str(&format!("|{}\n", ":---:|".repeat(abilities.len())));
+    block.push_str(&format!("| {} |\n\n", scores.join(" | ")));
+
+    for (label, value) in properties(monster) {
+        block.push_str(&format!("**{label}** {value}  \n"));
+    }
+    block.push_str(&format!("**Challenge** {}\n", challenge_text(monster.challenge)));
+
+    for (title, entries) in sections(monster) {
+        match title {
+            Some(title) => block.push_str(&format!("\n### {title}\n")),
+            None => block.push('\n'),
+        }
+        for line in entries {
+            match entry(line) {
+                (Some(name), text) => block.push_str(&format!("\n***{name}.*** {text}\n")),
+                (None, text) => block.push_str(&format!("\n{text}\n")),
+            }
+        }
+    }
+    block
+}
+
+const HTML_STYLE: &str = "\
+body { font-family: Georgia, serif; background: #fdf1dc; margin: 2em; }
+.stat-block { max-width: 40em; margin: 0 auto 2em; padding: 1em 1.5em; background: #fff; border-top: 4px solid #e69a28; border-bottom: 4px solid #e69a28; }
+.stat-block h1 { color: #7a200d; margin: 0; font-variant: small-caps; }
+.stat-block h2 { color: #7a200d; border-bottom: 1px solid #7a200d; font-variant: small-caps; font-weight: normal; }
+.stat-block .meta { font-style: italic; margin: 0 0 0.5em; }
+.stat-block hr { border: 0; border-top: 2px solid #7a200d; }
+.stat-block p { margin: 0.3em 0; }
+.stat-block table { width: 100%; text-align: center; color: #7a200d; }
+";
+
+fn to_html(monsters: &[Monster]) -> String {
+    let title = match monsters {
+        [monster] => html::escape(&monster.name),
+        _ => "Monsters".to_string(),
+    };
+    let blocks: String = monsters.iter().map(html_block).collect();
+    format!(
+        "<!DOCTYPE html>\n<html lang=\"en\">\n<head>\n<meta charset=\"utf-8\">\n<title>{title}</title>\n\
+         <style>\n{HTML_STYLE}</style>\n</head>\n<body>\n{blocks}</body>\n</html>\n"
+    )
+}
+
+fn html_block(monster: &Monster) -> String {
+    let mut block = format!(
+        "<section class=\"stat-block\">\n<h1>{}</h1>\n<p class=\"meta\">{}</p>\n<hr>\n",
+        html::escape(&monster.name),
+        html::escape(&monster.meta)
+    );
+    for (label, value) in [
+        ("Armor Class", &monster.armor_class),
+        ("Hit Points", &monster.hit_points),
+        ("Speed", &monster.speed),
+    ] {
+        block.push_str(&format!("<p><strong>{label}</strong> {}</p>\n", html::escape(value)));
+    }
+
+    let abilities = abilities(monster);
+    block.push_str("<hr>\n<table>\n<tr>");
+    for (ability, _) in &abilities {
+        block.push_str(&format!("<th>{ability}</th>"));
+    }
+    block.push_str("</tr>\n<tr>");
+    for (_, score) in &abilities {
+        block.push_str(&format!("<td>{}</td>", ability_text(*score)));
+    }
+    block.push_str("</tr>\n</table>\n<hr>\n");
+
+    for (label, value) in properties(monster) {
+        block.push_str(&format!("<p><strong>{label}</strong> {}</p>\n", html::escape(&value)));
+    }
+    block.push_str(&format!(
+        "<p><strong>Challenge</strong> {}</p>\n",
+        challenge_text(monster.challenge)
+    ));
+
+    for (title, entries) in sections(monster) {
+        match title {
+            Some(title) => block.push_str(&format!("<h2>{title}</h2>\n")),
+            None => block.push_str("<hr>\n"),
+        }
+        for line in entries {
+            match entry(line) {
+                (Some(name), text) => block.push_str(&format!(
+                    "<p><strong><em>{}.</em></strong> {}</p>\n",
+                    html::escape(name),
+                    html::escape(text)
+                )),
+                (None, text) => block.push_str(&format!("<p>{}</p>\n", html::escape(text))),
+            }
+        }
+    }
+    block.push_str("</section>\n");
+    block
+}
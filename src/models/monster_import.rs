@@ -0,0 +1,716 @@
+use std::collections::HashSet;
+
+use clap::ValueEnum;
+use serde::{Serialize, Deserialize};
+use serde_json::{Map, Value};
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::utilities::html;
+
+use super::{
+    errors::{FieldError, SResult, YaddakError},
+    monsters::{parse_challenge, Monster, MonsterFromJson, MonsterRequest, HOMEBREW_SOURCE},
+};
+
+/// Formats `import` understands.
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Copy, Default, PartialEq, Eq,
+         ToSchema, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportFormat {
+    /// A JSON array in the bundled `data/monsters.json` shape, either as
+    /// scraped (`Armor Class`, HTML traits) or with snake_case keys as the
+    /// JSON export writes it
+    #[default]
+    Yaddak,
+    /// A JSON array of Open5e monsters, or an Open5e API page with `results`
+    Open5e,
+    /// A header row naming `MonsterRequest` fields, `source` optional
+    Csv,
+}
+
+#[derive(Deserialize, Debug, IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct ImportQuery {
+    #[serde(default)]
+    pub format: ImportFormat,
+    /// Source for records that do not name one, defaults to `homebrew`
+    pub source: Option<String>,
+    /// Validate and report without writing anything
+    #[serde(default)]
+    pub dry_run: bool,
+}
+
+/// One record that could not be imported.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct ImportRowError {
+    /// 1 based position of the record in the file, not counting a CSV header
+    pub row: usize,
+    pub name: Option<String>,
+    pub errors: Vec<FieldError>,
+}
+
+/// What an import did, or would do on a dry run.
+#[derive(Serialize, Debug, Clone, Default, ToSchema)]
+pub struct ImportReport {
+    pub format: ImportFormat,
+    pub dry_run: bool,
+    pub total: usize,
+    /// Names inserted, or that would be inserted on a dry run
+    pub created: Vec<String>,
+    /// Names skipped because the owner already has them from the same source
+    pub duplicates: Vec<String>,
+    pub errors: Vec<ImportRowError>,
+}
+
+/// Parses, validates and dedupes `content`, then inserts what is left for
+/// `owner` unless `dry_run` is set. Returns the report and the monsters
+/// that were inserted.
+pub async fn import(
+    con_str: String,
+    owner: Uuid,
+    content: &str,
+    query: &ImportQuery,
+) -> SResult<(ImportReport, Vec<Monster>)> {
+    let default_source = query.source.clone().unwrap_or_else(|| HOMEBREW_SOURCE.to_string());
+    let rows = parse(query.format, content, &default_source)?;
+
+    let mut seen = Monster::keys_for_owner(con_str.clone(), owner).await?;
+    let mut report = ImportReport {
+        format: query.format,
+        dry_run: query.dry_run,
+        total: rows.len(),
+        ..Default::default()
+    };
+    let mut to_insert = sort_rows(rows, &mut seen, owner, &mut report);
+
+    if !query.dry_run {
+        Monster::post_all(con_str, &to_insert).await?;
+    } else {
+        to_insert.clear();
+    }
+    Ok((report, to_insert))
+}
+
+/// Validates each row and drops those whose `dedupe_key` is already in
+/// `seen`, recording both in `report`. Returns the monsters to insert.
+fn sort_rows(
+    rows: Vec<Row>,
+    seen: &mut HashSet<(String, String)>,
+    owner: Uuid,
+    report: &mut ImportReport,
+) -> Vec<Monster> {
+    let mut to_insert = Vec::new();
+    for (index, row) in rows.into_iter().enumerate() {
+        let request = match row.and_then(validate) {
+            Ok(request) => request,
+            Err((name, errors)) => {
+                report.errors.push(ImportRowError { row: index + 1, name, errors });
+                continue;
+            }
+        };
+        if !seen.insert(dedupe_key(&request.name, &request.source)) {
+            report.duplicates.push(request.name);
+            continue;
+        }
+        report.created.push(request.name.clone());
+        to_insert.push(request.into_monster(Uuid::new_v4(), owner));
+    }
+    to_insert
+}
+
+/// Names compare case insensitively so `Goblin` and `goblin` collide.
+pub(crate) fn dedupe_key(name: &str, source: &str) -> (String, String) {
+    (name.trim().to_lowercase(), source.trim().to_lowercase())
+}
+
+type Row = Result<MonsterRequest, (Option<String>, Vec<FieldError>)>;
+
+fn validate(request: MonsterRequest) -> Row {
+    match request.validate() {
+        Ok(()) => Ok(request),
+        Err(errors) => Err((Some(request.name.clone()), YaddakError::from(errors).details)),
+    }
+}
+
+fn parse(format: ImportFormat, content: &str, default_source: &str) -> SResult<Vec<Row>> {
+    match format {
+        ImportFormat::Yaddak => Ok(json_records(content)?
+            .into_iter()
+            .map(|record| from_yaddak(record, default_source))
+            .collect()),
+        ImportFormat::Open5e => Ok(json_records(content)?
+            .into_iter()
+            .map(|record| from_open5e(record, default_source))
+            .collect()),
+        ImportFormat::Csv => from_csv(content, default_source),
+    }
+}
+
+/// Splits a JSON document into records so one bad record does not fail
+/// the rest.
+fn json_records(content: &str) -> SResult<Vec<Value>> {
+    let document: Value = serde_json::from_str(content)
+        .map_err(|err| YaddakError::bad_request(format!("the file is not valid JSON: {err}")))?;
+    match document {
+        Value::Array(records) => Ok(records),
+        Value::Object(mut page) => match page.remove("results") {
+            Some(Value::Array(records)) => Ok(records),
+            _ => Err(YaddakError::bad_request("expected a JSON array of monsters".to_string())),
+        },
+        _ => Err(YaddakError::bad_request("expected a JSON array of monsters".to_string())),
+    }
+}
+
+fn record_name(record: &Value) -> Option<String> {
+    record.get("name").and_then(Value::as_str).map(str::to_string)
+}
+
+fn unreadable(record: &Value, err: serde_json::Error) -> (Option<String>, Vec<FieldError>) {
+    (record_name(record), vec![FieldError { field: "record".to_string(), message: err.to_string() }])
+}
+
+fn challenge_or_error(challenge: &str, errors: &mut Vec<FieldError>) -> f32 {
+    parse_challenge(challenge).unwrap_or_else(|| {
+        errors.push(FieldError {
+            field: "challenge".to_string(),
+            message: format!("{challenge:?} is not a challenge rating"),
+        });
+        0.0
+    })
+}
+
+fn non_empty(value: String) -> Option<String> {
+    Some(value).filter(|value| !value.trim().is_empty())
+}
+
+/// Fields that hold stat block entries, scraped as `<p>` paragraphs.
+const ENTRY_FIELDS: [&str; 3] = ["traits", "actions", "legendary_actions"];
+
+/// Brings a scraped record into the `MonsterFromJson` shape: keys such as
+/// `Armor Class` or `STR` become `armor_class` and `str`, the `STR_mod`
+/// style modifiers are dropped since they follow from the scores, and entry
+/// fields go from HTML to text with one entry per line. Records that are
+/// already clean pass through unchanged.
+fn normalise(record: Value) -> Value {
+    let Value::Object(fields) = record else {
+        return record;
+    };
+    let fields = fields
+        .into_iter()
+        .filter_map(|(key, value)| {
+            let key = normalise_key(&key);
+            if key.ends_with("_mod") {
+                return None;
+            }
+            let value = match value {
+                Value::String(text) if ENTRY_FIELDS.contains(&key.as_str()) => Value::String(html::to_text(&text)),
+                Value::String(text) => Value::String(text.trim().to_string()),
+                value => value,
+            };
+            Some((key, value))
+        })
+        .collect();
+    Value::Object(fields)
+}
+
+/// `Legendary Actions` and `legendaryActions` both become `legendary_actions`.
+fn normalise_key(key: &str) -> String {
+    let mut normalised = String::with_capacity(key.len());
+    let mut previous: Option<char> = None;
+    for c in key.trim().chars() {
+        if c.is_whitespace() || c == '-' || c == '_' {
+            if !normalised.is_empty() && !normalised.ends_with('_') {
+                normalised.push('_');
+            }
+        } else if c.is_uppercase() {
+            if previous.is_some_and(char::is_lowercase) {
+                normalised.push('_');
+            }
+            normalised.extend(c.to_lowercase());
+        } else {
+            normalised.push(c);
+        }
+        previous = Some(c);
+    }
+    normalised
+}
+
+fn from_yaddak(record: Value, default_source: &str) -> Row {
+    let record = normalise(record);
+    let monster: MonsterFromJson = serde_json::from_value(record.clone())
+        .map_err(|err| unreadable(&record, err))?;
+    let mut errors = Vec::new();
+    let challenge = challenge_or_error(&monster.challenge, &mut errors);
+    if !errors.is_empty() {
+        return Err((Some(monster.name), errors));
+    }
+    Ok(MonsterRequest {
+        name: monster.name,
+        meta: monster.meta,
+        armor_class: monster.armor_class,
+        hit_points: monster.hit_points,
+        speed: monster.speed,
+        str: monster.str,
+        dex: monster.dex,
+        con: monster.con,
+        int: monster.int,
+        wis: monster.wis,
+        cha: monster.cha,
+        saving_throws: monster.saving_throws,
+        skills: monster.skills,
+        senses: monster.senses,
+        languages: monster.languages,
+        challenge,
+        traits: monster.traits.and_then(non_empty),
+        actions: monster.actions,
+        damage_immunities: monster.damage_immunities.and_then(non_empty),
+        condition_immunities: monster.condition_immunities.and_then(non_empty),
+        legendary_actions: monster.legendary_actions.and_then(non_empty),
+        img_url: non_empty(monster.img_url),
+        source: non_empty(monster.source).unwrap_or_else(|| default_source.to_string()),
+    })
+}
+
+/// The parts of an Open5e monster that map onto a stat block.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct Open5eMonster {
+    name: String,
+    size: String,
+    #[serde(rename = "type")]
+    kind: String,
+    subtype: String,
+    alignment: String,
+    armor_class: i32,
+    armor_desc: Option<String>,
+    hit_points: i32,
+    hit_dice: String,
+    speed: Map<String, Value>,
+    strength: i16,
+    dexterity: i16,
+    constitution: i16,
+    intelligence: i16,
+    wisdom: i16,
+    charisma: i16,
+    strength_save: Option<i32>,
+    dexterity_save: Option<i32>,
+    constitution_save: Option<i32>,
+    intelligence_save: Option<i32>,
+    wisdom_save: Option<i32>,
+    charisma_save: Option<i32>,
+    skills: Map<String, Value>,
+    senses: String,
+    languages: String,
+    challenge_rating: String,
+    special_abilities: Option<Vec<Open5eAction>>,
+    actions: Option<Vec<Open5eAction>>,
+    legendary_actions: Option<Vec<Open5eAction>>,
+    damage_immunities: String,
+    condition_immunities: String,
+    img_main: Option<String>,
+    #[serde(rename = "document__slug")]
+    document_slug: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct Open5eAction {
+    name: String,
+    desc: String,
+}
+
+fn from_open5e(record: Value, default_source: &str) -> Row {
+    let monster: Open5eMonster = serde_json::from_value(record.clone())
+        .map_err(|err| unreadable(&record, err))?;
+    let mut errors = Vec::new();
+    let challenge = challenge_or_error(&monster.challenge_rating, &mut errors);
+    if !errors.is_empty() {
+        return Err((Some(monster.name), errors));
+    }
+
+    let mut meta = format!("{} {}", monster.size, monster.kind.to_lowercase());
+    if !monster.subtype.is_empty() {
+        meta.push_str(&format!(" ({})", monster.subtype));
+    }
+    if !monster.alignment.is_empty() {
+        meta.push_str(&format!(", {}", monster.alignment));
+    }
+    let armor_class = match monster.armor_desc.filter(|desc| !desc.is_empty()) {
+        Some(desc) => format!("{} ({desc})", monster.armor_class),
+        None => monster.armor_class.to_string(),
+    };
+    let hit_points = if monster.hit_dice.is_empty() {
+        monster.hit_points.to_string()
+    } else {
+        format!("{} ({})", monster.hit_points, monster.hit_dice)
+    };
+    let saving_throws = [
+        ("STR", monster.strength_save),
+        ("DEX", monster.dexterity_save),
+        ("CON", monster.constitution_save),
+        ("INT", monster.intelligence_save),
+        ("WIS", monster.wisdom_save),
+        ("CHA", monster.charisma_save),
+    ]
+    .into_iter()
+    .filter_map(|(ability, bonus)| bonus.map(|bonus| format!("{ability} {bonus:+}")))
+    .collect::<Vec<_>>()
+    .join(", ");
+    let skills = monster.skills
+        .iter()
+        .filter_map(|(skill, bonus)| bonus.as_i64().map(|bonus| format!("{} {bonus:+}", capitalize(skill))))
+        .collect::<Vec<_>>()
+        .join(", ");
+
+    Ok(MonsterRequest {
+        name: monster.name,
+        meta,
+        armor_class,
+        hit_points,
+        speed: open5e_speed(&monster.speed),
+        str: monster.strength,
+        dex: monster.dexterity,
+        con: monster.constitution,
+        int: monster.intelligence,
+        wis: monster.wisdom,
+        cha: monster.charisma,
+        saving_throws,
+        skills,
+        senses: monster.senses,
+        languages: monster.languages,
+        challenge,
+        traits: join_actions(monster.special_abilities),
+        actions: join_actions(monster.actions).unwrap_or_default(),
+        damage_immunities: non_empty(monster.damage_immunities),
+        condition_immunities: non_empty(monster.condition_immunities),
+        legendary_actions: join_actions(monster.legendary_actions),
+        img_url: monster.img_main.and_then(non_empty),
+        source: monster.document_slug
+            .and_then(non_empty)
+            .unwrap_or_else(|| default_source.to_string()),
+    })
+}
+
+/// `{"walk": 30, "fly": 60, "hover": true}` becomes `30 ft., fly 60 ft. (hover)`.
+fn open5e_speed(speed: &Map<String, Value>) -> String {
+    let mut parts: Vec<String> = speed.get("walk")
+        .and_then(Value::as_i64)
+        .map(|walk| format!("{walk} ft."))
+        .into_iter()
+        .collect();
+    for (mode, feet) in speed {
+        if let (false, Some(feet)) = (mode == "walk", feet.as_i64()) {
+            parts.push(format!("{mode} {feet} ft."));
+        }
+    }
+    let mut speed_text = parts.join(", ");
+    if speed.get("hover").and_then(Value::as_bool) == Some(true) {
+        speed_text.push_str(" (hover)");
+    }
+    speed_text
+}
+
+/// Stat blocks store actions as `Name. Description` entries, one per line,
+/// like the bundled file once it is cleaned.
+fn join_actions(actions: Option<Vec<Open5eAction>>) -> Option<String> {
+    let text = actions?
+        .into_iter()
+        .map(|action| format!("{}. {}", action.name, action.desc))
+        .collect::<Vec<_>>()
+        .join("\n");
+    non_empty(text)
+}
+
+fn capitalize(word: &str) -> String {
+    let mut chars = word.chars();
+    match chars.next() {
+        Some(first) => first.to_uppercase().chain(chars).collect(),
+        None => String::new(),
+    }
+}
+
+/// A CSV row before the numeric columns are parsed, so each bad cell can be
+/// reported against its column.
+#[derive(Deserialize, Debug, Default)]
+#[serde(default)]
+struct CsvMonster {
+    name: String,
+    meta: String,
+    armor_class: String,
+    hit_points: String,
+    speed: String,
+    str: String,
+    dex: String,
+    con: String,
+    int: String,
+    wis: String,
+    cha: String,
+    saving_throws: String,
+    skills: String,
+    senses: String,
+    languages: String,
+    challenge: String,
+    traits: String,
+    actions: String,
+    damage_immunities: String,
+    condition_immunities: String,
+    legendary_actions: String,
+    img_url: String,
+    source: String,
+}
+
+fn from_csv(content: &str, default_source: &str) -> SResult<Vec<Row>> {
+    let mut reader = csv::ReaderBuilder::new()
+        .trim(csv::Trim::All)
+        .flexible(true)
+        .from_reader(content.as_bytes());
+    let headers = reader.headers()
+        .map_err(|err| YaddakError::bad_request(format!("could not read the CSV header: {err}")))?;
+    if !headers.iter().any(|header| header == "name") {
+        return Err(YaddakError::bad_request("the CSV header has no name column".to_string()));
+    }
+
+    let rows = reader
+        .deserialize::<CsvMonster>()
+        .map(|row| match row {
+            Ok(row) => csv_row(row, default_source),
+            Err(err) => Err((None, vec![FieldError { field: "record".to_string(), message: err.to_string() }])),
+        })
+        .collect();
+    Ok(rows)
+}
+
+fn csv_row(row: CsvMonster, default_source: &str) -> Row {
+    let mut errors = Vec::new();
+    let mut ability = |field: &str, value: &str| -> i16 {
+        value.parse().unwrap_or_else(|_| {
+            errors.push(FieldError { field: field.to_string(), message: format!("{value:?} is not a number") });
+            0
+        })
+    };
+    let (str, dex, con) = (ability("str", &row.str), ability("dex", &row.dex), ability("con", &row.con));
+    let (int, wis, cha) = (ability("int", &row.int), ability("wis", &row.wis), ability("cha", &row.cha));
+    let challenge = challenge_or_error(&row.challenge, &mut errors);
+    if !errors.is_empty() {
+        return Err((non_empty(row.name), errors));
+    }
+
+    Ok(MonsterRequest {
+        name: row.name,
+        meta: row.meta,
+        armor_class: row.armor_class,
+        hit_points: row.hit_points,
+        speed: row.speed,
+        str,
+        dex,
+        con,
+        int,
+        wis,
+        cha,
+        saving_throws: row.saving_throws,
+        skills: row.skills,
+        senses: row.senses,
+        languages: row.languages,
+        challenge,
+        traits: non_empty(row.traits),
+        actions: row.actions,
+        damage_immunities: non_empty(row.damage_immunities),
+        condition_immunities: non_empty(row.condition_immunities),
+        legendary_actions: non_empty(row.legendary_actions),
+        img_url: non_empty(row.img_url),
+        source: non_empty(row.source).unwrap_or_else(|| default_source.to_string()),
+    })
+}
+
+#[cfg(test)]
+mod tests {
+    use serde_json::json;
+
+    use super::*;
+    use crate::models::monster_export::{self, ExportFormat};
+
+    fn kobold() -> Monster {
+        Monster {
+            id: Uuid::new_v4(),
+            name: "Winged Kobold".to_string(),
+            meta: "Small humanoid (kobold), lawful evil".to_string(),
+            armor_class: "13 (natural armor)".to_string(),
+            hit_points: "7 (3d6 - 3)".to_string(),
+            speed: "30 ft., fly 30 ft.".to_string(),
+            str: 7,
+            dex: 16,
+            con: 9,
+            int: 8,
+            wis: 7,
+            cha: 8,
+            saving_throws: "DEX +5".to_string(),
+            skills: "Stealth +5".to_string(),
+            senses: "darkvision 60 ft., passive Perception 8".to_string(),
+            languages: "Common, Draconic".to_string(),
+            challenge: 0.25,
+            traits: Some("Sunlight Sensitivity. Disadvantage in sunlight.\nPack Tactics. Advantage near allies.".to_string()),
+            actions: "Dagger. Melee Weapon Attack: +5 to hit, one target.".to_string(),
+            damage_immunities: None,
+            condition_immunities: Some("Charmed, Frightened".to_string()),
+            legendary_actions: None,
+            img_url: "https://example.com/kobold.png".to_string(),
+            user_id: Uuid::new_v4(),
+            source: "homebrew".to_string(),
+        }
+    }
+
+    /// Everything but the id and owner, which an import assigns.
+    fn stat_block(monster: &Monster) -> Value {
+        let mut value = serde_json::to_value(monster).unwrap();
+        let fields = value.as_object_mut().unwrap();
+        fields.remove("id");
+        fields.remove("user_id");
+        value
+    }
+
+    #[test]
+    fn keys_become_snake_case() {
+        assert_eq!(normalise_key("Armor Class"), "armor_class");
+        assert_eq!(normalise_key("legendaryActions"), "legendary_actions");
+        assert_eq!(normalise_key("Legendary Actions"), "legendary_actions");
+        assert_eq!(normalise_key(" Hit-Points "), "hit_points");
+        assert_eq!(normalise_key("STR"), "str");
+        assert_eq!(normalise_key("STR_mod"), "str_mod");
+        assert_eq!(normalise_key("saving__throws"), "saving_throws");
+    }
+
+    #[test]
+    fn open5e_speed_walk_first() {
+        let speed = json!({"walk": 30, "fly": 60, "hover": true});
+        assert_eq!(open5e_speed(speed.as_object().unwrap()), "30 ft., fly 60 ft. (hover)");
+        let speed = json!({"swim": 40});
+        assert_eq!(open5e_speed(speed.as_object().unwrap()), "swim 40 ft.");
+        assert_eq!(open5e_speed(&Map::new()), "");
+    }
+
+    #[test]
+    fn open5e_record_becomes_a_stat_block() {
+        let record = json!({
+            "name": "Sahuagin",
+            "size": "Medium",
+            "type": "Humanoid",
+            "subtype": "sahuagin",
+            "alignment": "lawful evil",
+            "armor_class": 12,
+            "armor_desc": "natural armor",
+            "hit_points": 22,
+            "hit_dice": "4d8+4",
+            "speed": {"walk": 30, "swim": 40},
+            "strength": 13, "dexterity": 11, "constitution": 12,
+            "intelligence": 12, "wisdom": 13, "charisma": 9,
+            "wisdom_save": 3,
+            "skills": {"perception": 5},
+            "senses": "darkvision 120 ft., passive Perception 15",
+            "languages": "Sahuagin",
+            "challenge_rating": "1/2",
+            "special_abilities": [{"name": "Blood Frenzy", "desc": "Advantage against wounded creatures."}],
+            "actions": [
+                {"name": "Multiattack", "desc": "Two attacks."},
+                {"name": "Bite", "desc": "+3 to hit."}
+            ],
+            "condition_immunities": "",
+            "document__slug": "wotc-srd"
+        });
+        let request = from_open5e(record, "homebrew").expect("record should map");
+        assert_eq!(request.meta, "Medium humanoid (sahuagin), lawful evil");
+        assert_eq!(request.armor_class, "12 (natural armor)");
+        assert_eq!(request.hit_points, "22 (4d8+4)");
+        assert_eq!(request.speed, "30 ft., swim 40 ft.");
+        assert_eq!(request.saving_throws, "WIS +3");
+        assert_eq!(request.skills, "Perception +5");
+        assert_eq!(request.challenge, 0.5);
+        assert_eq!(request.traits.as_deref(), Some("Blood Frenzy. Advantage against wounded creatures."));
+        assert_eq!(request.actions, "Multiattack. Two attacks.\nBite. +3 to hit.");
+        assert_eq!(request.condition_immunities, None);
+        assert_eq!(request.legendary_actions, None);
+        assert_eq!(request.source, "wotc-srd");
+    }
+
+    #[test]
+    fn open5e_bad_challenge_is_reported() {
+        let (name, errors) = from_open5e(json!({"name": "Odd", "challenge_rating": "lots"}), "homebrew").unwrap_err();
+        assert_eq!(name.as_deref(), Some("Odd"));
+        assert_eq!(errors[0].field, "challenge");
+    }
+
+    #[test]
+    fn csv_reports_each_bad_cell() {
+        let content = "name,str,dex,con,int,wis,cha,challenge,source\n\
+                       Goblin,8,14,10,10,8,8,1/4,\n\
+                       Broken,strong,14,10,10,8,8,lots,\n";
+        let mut rows = from_csv(content, "homebrew").unwrap().into_iter();
+        let goblin = rows.next().unwrap().expect("goblin should parse");
+        assert_eq!((goblin.name.as_str(), goblin.dex, goblin.challenge), ("Goblin", 14, 0.25));
+        assert_eq!(goblin.source, "homebrew");
+
+        let (name, errors) = rows.next().unwrap().unwrap_err();
+        assert_eq!(name.as_deref(), Some("Broken"));
+        let fields: Vec<&str> = errors.iter().map(|error| error.field.as_str()).collect();
+        assert_eq!(fields, ["str", "challenge"]);
+        assert!(rows.next().is_none());
+    }
+
+    #[test]
+    fn csv_needs_a_name_column() {
+        assert!(from_csv("title,str\nGoblin,8\n", "homebrew").is_err());
+    }
+
+    #[test]
+    fn duplicates_are_skipped_case_insensitively() {
+        let rows = from_csv(
+            "name,armor_class,hit_points,str,dex,con,int,wis,cha,challenge,actions\n\
+             Goblin,15,7,8,14,10,10,8,8,1/4,Scimitar.\n\
+             goblin ,15,7,8,14,10,10,8,8,1/4,Scimitar.\n\
+             Orc,13,15,16,12,16,7,11,10,1/2,Greataxe.\n\
+             Wolf,13,11,12,15,12,3,12,6,1/4,Bite.\n",
+            "homebrew",
+        ).unwrap();
+        let mut seen = HashSet::from([dedupe_key("Orc", "homebrew")]);
+        let mut report = ImportReport::default();
+        let monsters = sort_rows(rows, &mut seen, Uuid::nil(), &mut report);
+
+        assert_eq!(report.created, ["Goblin", "Wolf"]);
+        assert_eq!(report.duplicates, ["goblin", "Orc"]);
+        assert!(report.errors.is_empty());
+        assert_eq!(monsters.len(), 2);
+        assert!(monsters.iter().all(|monster| monster.user_id == Uuid::nil()));
+    }
+
+    #[test]
+    fn invalid_rows_are_reported_by_position() {
+        let rows = from_csv("name,str,dex,con,int,wis,cha,challenge\nGiant,40,10,10,10,10,10,5\n", "homebrew").unwrap();
+        let mut report = ImportReport::default();
+        let monsters = sort_rows(rows, &mut HashSet::new(), Uuid::nil(), &mut report);
+        assert!(monsters.is_empty());
+        assert_eq!(report.errors[0].row, 1);
+        assert!(report.errors[0].errors.iter().any(|error| error.field == "str"));
+    }
+
+    #[test]
+    fn json_export_imports_back() {
+        let kobold = kobold();
+        let export = monster_export::export(std::slice::from_ref(&kobold), ExportFormat::Json, &kobold.name).unwrap();
+        let rows = parse(ImportFormat::Yaddak, &export.body, "elsewhere").unwrap();
+        let imported = sort_rows(rows, &mut HashSet::new(), kobold.user_id, &mut ImportReport::default());
+        assert_eq!(imported.len(), 1);
+        assert_eq!(stat_block(&imported[0]), stat_block(&kobold));
+    }
+
+    #[test]
+    fn csv_export_imports_back() {
+        let kobold = kobold();
+        let export = monster_export::export(std::slice::from_ref(&kobold), ExportFormat::Csv, &kobold.name).unwrap();
+        let rows = parse(ImportFormat::Csv, &export.body, "elsewhere").unwrap();
+        let imported = sort_rows(rows, &mut HashSet::new(), kobold.user_id, &mut ImportReport::default());
+        assert_eq!(imported.len(), 1);
+        assert_eq!(stat_block(&imported[0]), stat_block(&kobold));
+    }
+}
@@ -1,16 +1,22 @@
-use std::path::PathBuf;
+use std::{collections::HashSet, path::{Path, PathBuf}};
 
+use chrono::{DateTime, Utc};
 use sea_query::{Iden, Table, ColumnDef, PostgresQueryBuilder, Query, Expr, ForeignKey, Value};
-use sea_query_binder::SqlxBinder;
+use sea_query_binder::{SqlxBinder, SqlxValues};
 use serde::{Serialize, Deserialize};
-use sqlx::{FromRow, query, Statement, query_as_with, query_with};
-use tokio::{fs::File, io::AsyncReadExt};
+use sqlx::{Connection, FromRow, query, query_as_with, query_with};
+use tracing::instrument;
 use utoipa::ToSchema;
 use uuid::Uuid;
+use validator::{Validate, ValidationError};
 
-use crate::traits::repo::{Repo, connect};
+use crate::{traits::{repo::{Repo, connect}, audit::Auditable}, utilities::metrics::QueryTimer};
 
-use super::errors::SResult;
+use super::{
+    errors::{SResult, YaddakError},
+    monster_import::{self, dedupe_key, ImportFormat, ImportQuery, ImportReport},
+    user::User,
+};
 
 pub static WOTCUUID: &str = "ba4726f1-5df7-4798-a530-66ad845e1b05";
 
@@ -42,123 +48,373 @@ pub struct Monster {
     pub legendary_actions: Option<String>,
     pub img_url: String,
     pub user_id: Uuid,
+    /// Where the stat block comes from, e.g. `srd` or `homebrew`. Imports
+    /// treat the same name from the same source as a duplicate.
+    #[serde(default = "default_source")]
+    pub source: String,
 }
 
+pub const HOMEBREW_SOURCE: &str = "homebrew";
+pub const SRD_SOURCE: &str = "srd";
+
+fn default_source() -> String {
+    HOMEBREW_SOURCE.to_string()
+}
+
+/// A monster in the bundled `data/monsters.json` shape once its keys are
+/// normalised. Text fields the source leaves out or sets to null become
+/// empty strings, and ability scores may be quoted.
 #[derive(Serialize, Deserialize, Debug,
          Clone, Default)]
+#[serde(default)]
 pub struct MonsterFromJson {
     pub name: String,
     pub meta: String,
     pub armor_class: String, 
     pub hit_points: String,
+    #[serde(deserialize_with = "nullable_string")]
     pub speed: String,
+    #[serde(deserialize_with = "ability_score")]
     pub str: i16,
+    #[serde(deserialize_with = "ability_score")]
     pub dex: i16,
+    #[serde(deserialize_with = "ability_score")]
     pub con: i16,
+    #[serde(deserialize_with = "ability_score")]
     pub int: i16,
+    #[serde(deserialize_with = "ability_score")]
     pub wis: i16,
+    #[serde(deserialize_with = "ability_score")]
     pub cha: i16,
+    #[serde(deserialize_with = "nullable_string")]
     pub saving_throws: String,
+    #[serde(deserialize_with = "nullable_string")]
     pub skills: String,
     pub senses: String,
     pub languages: String,
     pub challenge: String,
     pub traits: Option<String>,
+    #[serde(deserialize_with = "nullable_string")]
     pub actions: String,
     pub damage_immunities: Option<String>,
     pub condition_immunities: Option<String>,
     pub legendary_actions: Option<String>,
-    pub img_url: String
+    pub img_url: String,
+    /// Left empty by the scraped SRD file, in which case the import's
+    /// default source applies.
+    pub source: String,
 }
 
-impl Monster {
-    pub async fn migrate_json(con_str: String) -> SResult<()> {
-        let file_path = PathBuf::from(env!("CARGO_MANIFEST_DIR"))
-            .join("monsters.json");
+fn nullable_string<'de, D>(deserializer: D) -> Result<String, D::Error>
+where D: serde::Deserializer<'de> {
+    Ok(Option::<String>::deserialize(deserializer)?.unwrap_or_default())
+}
+
+fn ability_score<'de, D>(deserializer: D) -> Result<i16, D::Error>
+where D: serde::Deserializer<'de> {
+    #[derive(Deserialize)]
+    #[serde(untagged)]
+    enum Score {
+        Number(i16),
+        Text(String),
+    }
+    match Score::deserialize(deserializer)? {
+        Score::Number(score) => Ok(score),
+        // scraped blocks may carry the modifier along, as in `21 (+5)`
+        Score::Text(score) => score
+            .split_whitespace()
+            .next()
+            .unwrap_or_default()
+            .parse()
+            .map_err(|_| serde::de::Error::custom(format!("ability score {score:?} is not a number"))),
+    }
+}
+
+/// Every challenge rating in the 5e table.
+pub const CHALLENGE_RATINGS: [f32; 34] = [
+    0.0, 0.125, 0.25, 0.5,
+    1.0, 2.0, 3.0, 4.0, 5.0, 6.0, 7.0, 8.0, 9.0, 10.0,
+    11.0, 12.0, 13.0, 14.0, 15.0, 16.0, 17.0, 18.0, 19.0, 20.0,
+    21.0, 22.0, 23.0, 24.0, 25.0, 26.0, 27.0, 28.0, 29.0, 30.0,
+];
+
+/// XP for defeating a monster of each rating in `CHALLENGE_RATINGS`.
+pub const CHALLENGE_XP: [u32; 34] = [
+    10, 25, 50, 100,
+    200, 450, 700, 1_100, 1_800, 2_300, 2_900, 3_900, 5_000, 5_900,
+    7_200, 8_400, 10_000, 11_500, 13_000, 15_000, 18_000, 20_000, 22_000, 25_000,
+    33_000, 41_000, 50_000, 62_000, 75_000, 90_000, 105_000, 120_000, 135_000, 155_000,
+];
+
+/// XP for a rating from the table, `None` for anything off it.
+pub fn challenge_xp(challenge: f32) -> Option<u32> {
+    CHALLENGE_RATINGS
+        .iter()
+        .position(|rating| *rating == challenge)
+        .map(|index| CHALLENGE_XP[index])
+}
+
+/// A rating as stat blocks print it, `1/4` rather than `0.25`.
+pub fn challenge_label(challenge: f32) -> String {
+    match challenge {
+        0.125 => "1/8".to_string(),
+        0.25 => "1/4".to_string(),
+        0.5 => "1/2".to_string(),
+        rating => rating.to_string(),
+    }
+}
+
+/// Body for creating or updating a homebrew monster.
+#[derive(Deserialize, Debug, Clone,
+         ToSchema, Validate)]
+pub struct MonsterRequest {
+    #[validate(length(min = 1, max = 128, message = "must be between 1 and 128 characters"))]
+    #[schema(min_length = 1, max_length = 128)]
+    pub name: String,
+    pub meta: String,
+    #[validate(length(min = 1, message = "is required"))]
+    #[schema(min_length = 1)]
+    pub armor_class: String,
+    #[validate(length(min = 1, message = "is required"))]
+    #[schema(min_length = 1)]
+    pub hit_points: String,
+    pub speed: String,
+    #[validate(range(min = 1, max = 30, message = "must be between 1 and 30"))]
+    #[schema(minimum = 1, maximum = 30)]
+    pub str: i16,
+    #[validate(range(min = 1, max = 30, message = "must be between 1 and 30"))]
+    #[schema(minimum = 1, maximum = 30)]
+    pub dex: i16,
+    #[validate(range(min = 1, max = 30, message = "must be between 1 and 30"))]
+    #[schema(minimum = 1, maximum = 30)]
+    pub con: i16,
+    #[validate(range(min = 1, max = 30, message = "must be between 1 and 30"))]
+    #[schema(minimum = 1, maximum = 30)]
+    pub int: i16,
+    #[validate(range(min = 1, max = 30, message = "must be between 1 and 30"))]
+    #[schema(minimum = 1, maximum = 30)]
+    pub wis: i16,
+    #[validate(range(min = 1, max = 30, message = "must be between 1 and 30"))]
+    #[schema(minimum = 1, maximum = 30)]
+    pub cha: i16,
+    #[serde(default)]
+    pub saving_throws: String,
+    #[serde(default)]
+    pub skills: String,
+    #[serde(default)]
+    pub senses: String,
+    #[serde(default)]
+    pub languages: String,
+    /// One of 0, 0.125, 0.25, 0.5 or a whole number from 1 to 30.
+    #[validate(custom(function = "validate_challenge"))]
+    #[schema(minimum = 0, maximum = 30)]
+    pub challenge: f32,
+    pub traits: Option<String>,
+    pub actions: String,
+    pub damage_immunities: Option<String>,
+    /// Comma separated condition names, e.g. `Charmed, Poisoned`. Entries
+    /// that start with a condition are enforced, other text is kept as is.
+    pub condition_immunities: Option<String>,
+    pub legendary_actions: Option<String>,
+    #[serde(default)]
+    /// An absolute URL, or the `/asset/{id}` path of an uploaded image.
+    #[validate(custom(function = "validate_img_url"))]
+    pub img_url: Option<String>,
+    #[serde(default = "default_source")]
+    #[validate(length(min = 1, max = 64, message = "must be between 1 and 64 characters"))]
+    #[schema(min_length = 1, max_length = 64, default = "homebrew")]
+    pub source: String,
+}
+
+fn validate_challenge(challenge: f32) -> Result<(), ValidationError> {
+    if CHALLENGE_RATINGS.contains(&challenge) {
+        Ok(())
+    } else {
+        let mut err = ValidationError::new("challenge_rating");
+        err.message = Some("must be a challenge rating from the table (0, 1/8, 1/4, 1/2, 1-30)".into());
+        Err(err)
+    }
+}
+
+pub(crate) fn validate_img_url(img_url: &str) -> Result<(), ValidationError> {
+    let local = img_url
+        .strip_prefix("/asset/")
+        .is_some_and(|id| Uuid::parse_str(id).is_ok());
+    if local || url::Url::parse(img_url).is_ok() {
+        Ok(())
+    } else {
+        let mut err = ValidationError::new("url");
+        err.message = Some("must be a valid URL or an /asset/{id} path".into());
+        Err(err)
+    }
+}
 
-        let mut file_content = String::new();
-        let mut file = File::open(file_path).await?;
-        file.read_to_string(&mut file_content).await?;
+impl MonsterRequest {
+    pub fn into_monster(self, id: Uuid, user_id: Uuid) -> Monster {
+        Monster {
+            id,
+            name: self.name,
+            meta: self.meta,
+            armor_class: self.armor_class,
+            hit_points: self.hit_points,
+            speed: self.speed,
+            str: self.str,
+            dex: self.dex,
+            con: self.con,
+            int: self.int,
+            wis: self.wis,
+            cha: self.cha,
+            saving_throws: self.saving_throws,
+            skills: self.skills,
+            senses: self.senses,
+            languages: self.languages,
+            challenge: self.challenge,
+            traits: self.traits,
+            actions: self.actions,
+            damage_immunities: self.damage_immunities,
+            condition_immunities: self.condition_immunities,
+            legendary_actions: self.legendary_actions,
+            img_url: self.img_url.unwrap_or_default(),
+            user_id,
+            source: self.source,
+        }
+    }
+}
 
-        let monsters_to_convert: Vec<MonsterFromJson> = serde_json::from_str(&file_content)?;
-        let monsters_to_post: Vec<Monster> = monsters_to_convert
-            .iter()
-            .map(|m| (*m).clone().into())
-            .collect();
+fn insert_monster(model: &Monster) -> (String, SqlxValues) {
+    Query::insert()
+        .into_table(MonsterModel::Table)
+        .columns(MonsterModel::cols())
+        .values_panic([
+            model.id.into(),
+            model.name.clone().into(),
+            model.meta.clone().into(),
+            model.armor_class.clone().into(),
+            model.hit_points.clone().into(),
+            model.speed.clone().into(),
+            model.str.into(),
+            model.dex.into(),
+            model.con.into(),
+            model.int.into(),
+            model.wis.into(),
+            model.cha.into(),
+            model.saving_throws.clone().into(),
+            model.skills.clone().into(),
+            model.senses.clone().into(),
+            model.languages.clone().into(),
+            model.challenge.into(),
+            model.traits.clone().into(),
+            model.actions.clone().into(),
+            model.damage_immunities.clone().into(),
+            model.condition_immunities.clone().into(),
+            model.legendary_actions.clone().into(),
+            model.img_url.clone().into(),
+            model.user_id.into(),
+            model.source.clone().into(),
+        ])
+        .build_sqlx(PostgresQueryBuilder)
+}
 
-        let mut client = connect(con_str.clone()).await?;
+impl Monster {
+    /// Whether the bundled SRD monsters have been loaded.
+    pub async fn seeded(con_str: String) -> SResult<bool> {
+        let mut client = connect(con_str).await?;
         let (sql, values) = Query::select()
             .from(MonsterModel::Table)
-            .columns(MonsterModel::cols()) 
-            .and_where(Expr::col(MonsterModel::Name).eq(String::from("Aboleth")))
+            .column(MonsterModel::Id)
+            .and_where(Expr::col(MonsterModel::UserId).eq(Uuid::parse_str(WOTCUUID).unwrap()))
+            .limit(1)
             .build_sqlx(PostgresQueryBuilder);
 
-        let rows: Vec<Monster> = query_as_with(&sql, values.clone())
+        let rows: Vec<_> = query_with(&sql, values)
             .fetch_all(&mut *client)
             .await?;
+        Ok(!rows.is_empty())
+    }
 
-        if rows.is_empty() {
-            for ele in monsters_to_post {
-                Self::post(con_str.clone(), &ele.clone()).await?;
-            }
-        }
+    /// Loads SRD monsters from a file in the bundled `data/monsters.json` shape,
+    /// owned by the system user. Monsters the system user already has are
+    /// skipped, so seeding again only adds what is new. Returns the report
+    /// of the underlying import.
+    pub async fn seed(con_str: String, file_path: &Path) -> SResult<ImportReport> {
+        let content = tokio::fs::read_to_string(file_path).await?;
+        let system_user = Uuid::parse_str(WOTCUUID).unwrap();
+        User::ensure_system_user(con_str.clone(), system_user, "wotc").await?;
+
+        let query = ImportQuery {
+            format: ImportFormat::Yaddak,
+            source: Some(SRD_SOURCE.to_string()),
+            dry_run: false,
+        };
+        let (report, _) = monster_import::import(con_str, system_user, &content, &query).await?;
+        Ok(report)
+    }
+
+    /// Seeded from the bundled SRD file rather than made by a user.
+    pub fn bundled(&self) -> bool {
+        self.user_id == Uuid::parse_str(WOTCUUID).unwrap()
+    }
 
+    /// Inserts `monsters` in one transaction, so a failure part way
+    /// leaves none of them behind.
+    #[instrument(name = "monster.post_all", skip_all, fields(db.system = "postgresql"))]
+    pub async fn post_all(con_str: String, monsters: &[Monster]) -> SResult<()> {
+        let _timer = QueryTimer::new("monster", "post_all");
+        let mut client = connect(con_str).await?;
+        let mut tx = client.begin().await?;
+        for monster in monsters {
+            let (sql, values) = insert_monster(monster);
+            let _ = query_with(&sql, values)
+                .execute(&mut *tx)
+                .await?;
+        }
+        tx.commit().await?;
         Ok(())
     }
+
+    /// Name and source of every monster `owner` has, as `dedupe_key`s.
+    pub async fn keys_for_owner(con_str: String, owner: Uuid) -> SResult<HashSet<(String, String)>> {
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .from(MonsterModel::Table)
+            .columns([MonsterModel::Name, MonsterModel::Source])
+            .and_where(Expr::col(MonsterModel::UserId).eq(owner))
+            .and_where(Expr::col(MonsterModel::DeletedAt).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<(String, String)> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        Ok(rows.iter().map(|(name, source)| dedupe_key(name, source)).collect())
+    }
+
+    /// The SRD stat blocks shipped in `storage.data_dir`, as scraped. The import
+    /// cleans them, so there is no separate cleaned copy to keep in sync.
+    pub fn bundled_seed() -> PathBuf {
+        crate::config::get().storage.data_dir.join("monsters.json")
+    }
 }
 
-impl From<MonsterFromJson> for Monster {
-    fn from(value: MonsterFromJson) -> Self {
-        Self {
-            id: Uuid::new_v4(),
-            name: value.name,
-            meta: value.meta,
-            armor_class: value.armor_class,
-            hit_points: value.hit_points,
-            speed: value.speed,
-            str: value.str,
-            dex: value.dex,
-            con: value.con,
-            int: value.int,
-            wis: value.wis,
-            cha: value.cha,
-            saving_throws: value.saving_throws,
-            skills: value.skills,
-            senses: value.senses,
-            languages: value.languages,
-            challenge: challenge_convert(value
-                                            .challenge
-                                            .rsplit(' ')
-                                            .next_back()
-                                            .unwrap_or("")
-                                        ),
-            traits: value.traits,
-            actions: value.actions,
-            damage_immunities: value.damage_immunities,
-            condition_immunities: value.condition_immunities,
-            legendary_actions: value.legendary_actions,
-            img_url: value.img_url,
-            user_id: Uuid::parse_str(WOTCUUID).unwrap()
-        }
+impl Auditable for Monster {
+    const ENTITY_TYPE: &'static str = "monster";
+
+    fn entity_id(&self) -> Uuid {
+        self.id
     }
 }
 
-fn challenge_convert(string: &str) -> f32 {
-    match string {
-        "1/8" => 0.125,
-        "1/4" => 0.25,
-        "1/2" => 0.5,
-        _ => {
-            match string.parse::<f32>() {
-                Ok(a) => a,
-                Err(e) => 0.0,
-            }
-        }
+/// Reads a challenge rating written as `1/4`, `0.25` or `10 (5,900 XP)`.
+pub(crate) fn parse_challenge(string: &str) -> Option<f32> {
+    match string.split_whitespace().next()? {
+        "1/8" => Some(0.125),
+        "1/4" => Some(0.25),
+        "1/2" => Some(0.5),
+        rating => rating.parse::<f32>().ok(),
     }
 }
 
 #[derive(Iden)]
-enum MonsterModel {
+pub(crate) enum MonsterModel {
     Table,
     Id,
     Name,
@@ -184,11 +440,13 @@ enum MonsterModel {
     LegendaryActions,
     ImgUrl,
     UserId,
+    DeletedAt,
+    Source,
 }
 
 impl MonsterModel {
     pub fn cols() -> Vec<Self> {
-        return vec![
+        vec![
             Self::Id,
             Self::Name,
             Self::Meta,
@@ -212,13 +470,14 @@ impl MonsterModel {
             Self::ConditionImmunities,
             Self::LegendaryActions,
             Self::ImgUrl,
-            Self::UserId
+            Self::UserId,
+            Self::Source,
         ]
     }
 }
 
 impl Repo<'_, Monster> for Monster {
-    async fn migrate(con_str: String) -> super::errors::SResult<()> {
+    async fn migrate(con_str: String) -> SResult<()> {
         let mut client = connect(con_str).await?;
 
 
@@ -236,12 +495,12 @@ impl Repo<'_, Monster> for Monster {
             .col(ColumnDef::new(MonsterModel::ArmorClass).string().not_null())
             .col(ColumnDef::new(MonsterModel::HitPoints).string().not_null())
             .col(ColumnDef::new(MonsterModel::Speed).string().not_null())
-            .col(ColumnDef::new(MonsterModel::Str).integer().not_null())
-            .col(ColumnDef::new(MonsterModel::Dex).integer().not_null())
-            .col(ColumnDef::new(MonsterModel::Con).integer().not_null())
-            .col(ColumnDef::new(MonsterModel::Int).integer().not_null())
-            .col(ColumnDef::new(MonsterModel::Wis).integer().not_null())
-            .col(ColumnDef::new(MonsterModel::Cha).integer().not_null())
+            .col(ColumnDef::new(MonsterModel::Str).small_integer().not_null())
+            .col(ColumnDef::new(MonsterModel::Dex).small_integer().not_null())
+            .col(ColumnDef::new(MonsterModel::Con).small_integer().not_null())
+            .col(ColumnDef::new(MonsterModel::Int).small_integer().not_null())
+            .col(ColumnDef::new(MonsterModel::Wis).small_integer().not_null())
+            .col(ColumnDef::new(MonsterModel::Cha).small_integer().not_null())
             .col(ColumnDef::new(MonsterModel::SavingThrows).string().not_null())
             .col(ColumnDef::new(MonsterModel::Skills).string().not_null())
             .col(ColumnDef::new(MonsterModel::Senses).string().not_null())
@@ -254,6 +513,8 @@ impl Repo<'_, Monster> for Monster {
             .col(ColumnDef::new(MonsterModel::DamageImmunities).string().default(Value::String(None)))
             .col(ColumnDef::new(MonsterModel::ImgUrl).string().not_null())
             .col(ColumnDef::new(MonsterModel::UserId).uuid().not_null())
+            .col(ColumnDef::new(MonsterModel::DeletedAt).timestamp_with_time_zone().null())
+            .col(ColumnDef::new(MonsterModel::Source).string().not_null().default(HOMEBREW_SOURCE))
             .foreign_key(ForeignKey::create()
                            .name("FK_User")
                            .from(MonsterModel::Table, MonsterModel::UserId)
@@ -268,70 +529,80 @@ impl Repo<'_, Monster> for Monster {
             .execute(&mut *client)
             .await;
 
+        // tables created before soft delete existed need the new column, and
+        // ability scores were created as integer which does not decode into i16
+        let sql = Table::alter()
+            .table(MonsterModel::Table)
+            .add_column_if_not_exists(ColumnDef::new(MonsterModel::DeletedAt).timestamp_with_time_zone().null())
+            .add_column_if_not_exists(ColumnDef::new(MonsterModel::Source).string().not_null().default(HOMEBREW_SOURCE))
+            .modify_column(ColumnDef::new(MonsterModel::Str).small_integer())
+            .modify_column(ColumnDef::new(MonsterModel::Dex).small_integer())
+            .modify_column(ColumnDef::new(MonsterModel::Con).small_integer())
+            .modify_column(ColumnDef::new(MonsterModel::Int).small_integer())
+            .modify_column(ColumnDef::new(MonsterModel::Wis).small_integer())
+            .modify_column(ColumnDef::new(MonsterModel::Cha).small_integer())
+            .build(PostgresQueryBuilder);
+
+        let _ = query(sql.as_str())
+            .execute(&mut *client)
+            .await?;
+
+        // rows seeded before `source` existed were defaulted to homebrew
+        let (sql, values) = Query::update()
+            .table(MonsterModel::Table)
+            .value(MonsterModel::Source, SRD_SOURCE)
+            .and_where(Expr::col(MonsterModel::UserId).eq(Uuid::parse_str(WOTCUUID).unwrap()))
+            .and_where(Expr::col(MonsterModel::Source).eq(HOMEBREW_SOURCE))
+            .build_sqlx(PostgresQueryBuilder);
+
+        let _ = query_with(&sql, values)
+            .execute(&mut *client)
+            .await?;
+
         Ok(())
     }
 
-    async fn get(con_str: String, id: Uuid) -> super::errors::SResult<Monster> {
+    #[instrument(name = "monster.get", skip_all, fields(db.system = "postgresql"))]
+    async fn get(con_str: String, id: Uuid) -> SResult<Monster> {
+        let _timer = QueryTimer::new("monster", "get");
         let mut client = connect(con_str).await?;
         let (sql, values) = Query::select()
             .columns(MonsterModel::cols()) 
             .from(MonsterModel::Table)
             .limit(1)
             .and_where(Expr::col(MonsterModel::Id).eq(id))
+            .and_where(Expr::col(MonsterModel::DeletedAt).is_null())
             .build_sqlx(PostgresQueryBuilder);
 
         let rows: Monster = query_as_with(&sql, values.clone())
             .fetch_one(&mut *client)
             .await?;
 
-        Ok(rows.into())
+        Ok(rows)
     }
 
-    async fn get_all(con_str: String ) -> super::errors::SResult<Vec<Monster>> {
+    #[instrument(name = "monster.get_all", skip_all, fields(db.system = "postgresql"))]
+    async fn get_all(con_str: String ) -> SResult<Vec<Monster>> {
+        let _timer = QueryTimer::new("monster", "get_all");
         let mut client = connect(con_str).await?;
         let (sql, values) = Query::select()
             .columns(MonsterModel::cols()) 
             .from(MonsterModel::Table)
+            .and_where(Expr::col(MonsterModel::DeletedAt).is_null())
             .build_sqlx(PostgresQueryBuilder);
 
         let rows: Vec<Monster> = query_as_with(&sql, values.clone())
             .fetch_all(&mut *client)
             .await?;
 
-        Ok(rows.into())
+        Ok(rows)
     }
 
-    async fn post(con_str: String, model: &Monster) ->  super::errors::SResult<()> {
+    #[instrument(name = "monster.post", skip_all, fields(db.system = "postgresql"))]
+    async fn post(con_str: String, model: &Monster) ->  SResult<()> {
+        let _timer = QueryTimer::new("monster", "post");
         let mut client = connect(con_str).await?;
-        let (sql, values) = Query::insert()
-            .into_table(MonsterModel::Table)
-            .columns(MonsterModel::cols())
-            .values_panic([
-                model.id.into(),
-                model.name.clone().into(),
-                model.meta.clone().into(),
-                model.armor_class.clone().into(),
-                model.hit_points.clone().into(),
-                model.speed.clone().into(),
-                model.str.to_string().into(),
-                model.dex.to_string().into(),
-                model.con.to_string().into(),
-                model.int.to_string().into(),
-                model.wis.to_string().into(),
-                model.cha.to_string().into(),
-                model.saving_throws.clone().into(),
-                model.skills.clone().into(),
-                model.senses.clone().into(),
-                model.languages.clone().into(),
-                model.challenge.to_string().into(),
-                model.traits.clone().into(),
-                model.actions.clone().into(),
-                model.damage_immunities.clone().into(),
-                model.condition_immunities.clone().into(),
-                model.legendary_actions.clone().into(),
-                model.img_url.clone().into()
-            ])
-            .build_sqlx(PostgresQueryBuilder);
+        let (sql, values) = insert_monster(model);
         let _ = query_with(&sql, values)
             .execute(&mut *client)
             .await?;
@@ -339,20 +610,111 @@ impl Repo<'_, Monster> for Monster {
         Ok(())
     }
 
-    async fn put(con_str: String, id: Uuid, model: &Monster) -> super::errors::SResult<()> {
-        todo!()
+    #[instrument(name = "monster.put", skip_all, fields(db.system = "postgresql"))]
+    async fn put(con_str: String, id: Uuid, model: &Monster) -> SResult<()> {
+        let _timer = QueryTimer::new("monster", "put");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::update()
+            .table(MonsterModel::Table)
+            .values([
+                (MonsterModel::Name, model.name.clone().into()),
+                (MonsterModel::Meta, model.meta.clone().into()),
+                (MonsterModel::ArmorClass, model.armor_class.clone().into()),
+                (MonsterModel::HitPoints, model.hit_points.clone().into()),
+                (MonsterModel::Speed, model.speed.clone().into()),
+                (MonsterModel::Str, model.str.into()),
+                (MonsterModel::Dex, model.dex.into()),
+                (MonsterModel::Con, model.con.into()),
+                (MonsterModel::Int, model.int.into()),
+                (MonsterModel::Wis, model.wis.into()),
+                (MonsterModel::Cha, model.cha.into()),
+                (MonsterModel::Source, model.source.clone().into()),
+                (MonsterModel::SavingThrows, model.saving_throws.clone().into()),
+                (MonsterModel::Skills, model.skills.clone().into()),
+                (MonsterModel::Senses, model.senses.clone().into()),
+                (MonsterModel::Languages, model.languages.clone().into()),
+                (MonsterModel::Challenge, model.challenge.into()),
+                (MonsterModel::Traits, model.traits.clone().into()),
+                (MonsterModel::Actions, model.actions.clone().into()),
+                (MonsterModel::DamageImmunities, model.damage_immunities.clone().into()),
+                (MonsterModel::ConditionImmunities, model.condition_immunities.clone().into()),
+                (MonsterModel::LegendaryActions, model.legendary_actions.clone().into()),
+                (MonsterModel::ImgUrl, model.img_url.clone().into()),
+            ])
+            .and_where(Expr::col(MonsterModel::Id).eq(id))
+            .and_where(Expr::col(MonsterModel::DeletedAt).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let _ = query_with(sql.as_str(), values)
+            .execute(&mut *client)
+            .await?;
+
+        Ok(())
     }
 
-    async fn delete(con_str: String, id: Uuid) -> super::errors::SResult<()> {
+    #[instrument(name = "monster.delete", skip_all, fields(db.system = "postgresql"))]
+    async fn delete(con_str: String, id: Uuid) -> SResult<()> {
+        let _timer = QueryTimer::new("monster", "delete");
         let mut client = connect(con_str).await?;
         let (sql, values) = Query::update()
             .table(MonsterModel::Table)
+            .value(MonsterModel::DeletedAt, Utc::now())
             .and_where(Expr::col(MonsterModel::Id).eq(id))
+            .and_where(Expr::col(MonsterModel::DeletedAt).is_null())
             .build_sqlx(PostgresQueryBuilder);
         
-        let _ = query_with(sql.as_str(), values)
+        let result = query_with(sql.as_str(), values)
             .execute(&mut *client)
             .await?;
+        if result.rows_affected() == 0 {
+            return Err(YaddakError::not_found(format!("No monster with id {id} to delete")));
+        }
         Ok(())
     }
+
+    #[instrument(name = "monster.restore", skip_all, fields(db.system = "postgresql"))]
+    async fn restore(con_str: String, id: Uuid) -> SResult<()> {
+        let _timer = QueryTimer::new("monster", "restore");
+        let mut client = connect(con_str).await?;
+        // a monster cannot come back while its owner is still deleted
+        let (sql, values) = Query::update()
+            .table(MonsterModel::Table)
+            .value(MonsterModel::DeletedAt, Option::<DateTime<Utc>>::None)
+            .and_where(Expr::col(MonsterModel::Id).eq(id))
+            .and_where(Expr::col(MonsterModel::DeletedAt).is_not_null())
+            .and_where(
+                Expr::col(MonsterModel::UserId).in_subquery(
+                    Query::select()
+                        .column(super::user::UserModel::Id)
+                        .from(super::user::UserModel::Table)
+                        .and_where(Expr::col(super::user::UserModel::DeletedAt).is_null())
+                        .to_owned()
+                )
+            )
+            .build_sqlx(PostgresQueryBuilder);
+
+        let result = query_with(sql.as_str(), values)
+            .execute(&mut *client)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(YaddakError::not_found(format!("No restorable monster with id {id}")));
+        }
+        Ok(())
+    }
+
+    #[instrument(name = "monster.purge", skip_all, fields(db.system = "postgresql"))]
+    async fn purge(con_str: String, older_than: DateTime<Utc>) -> SResult<Vec<Uuid>> {
+        let _timer = QueryTimer::new("monster", "purge");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::delete()
+            .from_table(MonsterModel::Table)
+            .and_where(Expr::col(MonsterModel::DeletedAt).lt(older_than))
+            .returning_col(MonsterModel::Id)
+            .build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<(Uuid,)> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        Ok(rows.into_iter().map(|(id,)| id).collect())
+    }
 }
@@ -0,0 +1,112 @@
+use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
+
+/// The six ability scores, serialized as their stat block abbreviations.
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord,
+         ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Ability {
+    Str,
+    Dex,
+    Con,
+    Int,
+    Wis,
+    Cha,
+}
+
+impl Ability {
+    pub const ALL: [Ability; 6] = [
+        Ability::Str,
+        Ability::Dex,
+        Ability::Con,
+        Ability::Int,
+        Ability::Wis,
+        Ability::Cha,
+    ];
+}
+
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Copy, PartialEq, Eq, Hash, PartialOrd, Ord,
+         ToSchema)]
+#[serde(rename_all = "snake_case")]
+pub enum Skill {
+    Acrobatics,
+    AnimalHandling,
+    Arcana,
+    Athletics,
+    Deception,
+    History,
+    Insight,
+    Intimidation,
+    Investigation,
+    Medicine,
+    Nature,
+    Perception,
+    Performance,
+    Persuasion,
+    Religion,
+    SleightOfHand,
+    Stealth,
+    Survival,
+}
+
+impl Skill {
+    pub const ALL: [Skill; 18] = [
+        Skill::Acrobatics,
+        Skill::AnimalHandling,
+        Skill::Arcana,
+        Skill::Athletics,
+        Skill::Deception,
+        Skill::History,
+        Skill::Insight,
+        Skill::Intimidation,
+        Skill::Investigation,
+        Skill::Medicine,
+        Skill::Nature,
+        Skill::Perception,
+        Skill::Performance,
+        Skill::Persuasion,
+        Skill::Religion,
+        Skill::SleightOfHand,
+        Skill::Stealth,
+        Skill::Survival,
+    ];
+
+    /// The ability a check with this skill adds.
+    pub fn ability(self) -> Ability {
+        match self {
+            Skill::Athletics => Ability::Str,
+            Skill::Acrobatics | Skill::SleightOfHand | Skill::Stealth => Ability::Dex,
+            Skill::Arcana | Skill::History | Skill::Investigation | Skill::Nature | Skill::Religion => Ability::Int,
+            Skill::AnimalHandling | Skill::Insight | Skill::Medicine | Skill::Perception | Skill::Survival => Ability::Wis,
+            Skill::Deception | Skill::Intimidation | Skill::Performance | Skill::Persuasion => Ability::Cha,
+        }
+    }
+}
+
+/// The modifier an ability score gives, rounding down: 9 is -1, 21 is +5.
+pub fn ability_modifier(score: i16) -> i16 {
+    (score - 10).div_euclid(2)
+}
+
+/// +2 at levels 1-4, rising by one every four levels to +6 at 17-20.
+pub fn proficiency_bonus(level: i16) -> i16 {
+    2 + (level.clamp(1, 20) - 1) / 4
+}
+
+/// XP needed to reach each level, level 1 first.
+pub const LEVEL_XP: [i32; 20] = [
+    0, 300, 900, 2_700, 6_500, 14_000, 23_000, 34_000, 48_000, 64_000,
+    85_000, 100_000, 120_000, 140_000, 165_000, 195_000, 225_000, 265_000, 305_000, 355_000,
+];
+
+/// The level an XP total reaches on the 5e table.
+pub fn level_for_xp(xp: i32) -> i16 {
+    LEVEL_XP.iter().take_while(|threshold| xp >= **threshold).count().max(1) as i16
+}
+
+/// XP needed to reach `level`, clamped to the table.
+pub fn xp_for_level(level: i16) -> i32 {
+    LEVEL_XP[(level.clamp(1, 20) - 1) as usize]
+}
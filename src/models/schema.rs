@@ -0,0 +1,83 @@
+use chrono::{DateTime, Utc};
+use sea_query::{Iden, Table, ColumnDef, PostgresQueryBuilder, Query, Expr, Func, OnConflict};
+use sea_query_binder::SqlxBinder;
+use serde::{Serialize, Deserialize};
+use sqlx::{FromRow, query, query_as_with, query_with};
+
+use crate::traits::repo::connect;
+
+use super::errors::SResult;
+
+/// Bump whenever `traits::migrate` changes the schema.
+pub const SCHEMA_VERSION: i32 = 11;
+
+/// One row per schema version `migrate` has brought the database to.
+#[derive(Serialize, Deserialize, Debug,
+         Clone, FromRow)]
+pub struct SchemaVersion {
+    pub version: i32,
+    pub applied_at: DateTime<Utc>,
+}
+
+impl SchemaVersion {
+    pub async fn migrate(con_str: String) -> SResult<()> {
+        let mut client = connect(con_str).await?;
+        let sql = Table::create()
+            .table(SchemaModel::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(SchemaModel::Version)
+                    .integer()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(SchemaModel::AppliedAt).timestamp_with_time_zone().not_null())
+            .build(PostgresQueryBuilder);
+
+        let _ = query(sql.as_str())
+            .execute(&mut *client)
+            .await?;
+        Ok(())
+    }
+
+    /// Marks `version` as applied, a no-op when it already is.
+    pub async fn record(con_str: String, version: i32) -> SResult<()> {
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::insert()
+            .into_table(SchemaModel::Table)
+            .columns([SchemaModel::Version, SchemaModel::AppliedAt])
+            .values_panic([version.into(), Utc::now().into()])
+            .on_conflict(OnConflict::column(SchemaModel::Version).do_nothing().to_owned())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let _ = query_with(&sql, values)
+            .execute(&mut *client)
+            .await?;
+        Ok(())
+    }
+
+    /// Highest applied version, `None` on a database `migrate` never ran on.
+    pub async fn current(con_str: String) -> SResult<Option<i32>> {
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .expr(Func::max(Expr::col(SchemaModel::Version)))
+            .from(SchemaModel::Table)
+            .build_sqlx(PostgresQueryBuilder);
+
+        match query_as_with::<_, (Option<i32>,), _>(&sql, values).fetch_one(&mut *client).await {
+            Ok((version,)) => Ok(version),
+            // 42P01 is undefined_table
+            Err(sqlx::Error::Database(db)) if db.code().as_deref() == Some("42P01") => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+}
+
+#[derive(Iden)]
+enum SchemaModel {
+    #[iden = "schema_version"]
+    Table,
+    Version,
+    AppliedAt,
+}
+
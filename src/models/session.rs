@@ -0,0 +1,651 @@
+use std::collections::HashMap;
+
+use chrono::{DateTime, Duration, FixedOffset, Utc};
+use sea_query::{Iden, Table, ColumnDef, PostgresQueryBuilder, Query, Expr, ForeignKey, ForeignKeyAction, Index, OnConflict};
+use sea_query_binder::SqlxBinder;
+use serde::{Serialize, Deserialize};
+use sqlx::{FromRow, PgConnection, query, query_as_with, query_with};
+use tracing::instrument;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{
+    traits::{repo::{Repo, connect}, audit::Auditable},
+    utilities::{ics, metrics::QueryTimer},
+};
+
+use super::{
+    campaign::{Campaign, CampaignModel, CampaignRole},
+    errors::{SResult, YaddakError},
+    user::UserModel,
+};
+
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Copy, PartialEq, Eq,
+         ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum Rsvp {
+    Yes,
+    No,
+    Maybe,
+}
+
+impl Rsvp {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            Rsvp::Yes => "yes",
+            Rsvp::No => "no",
+            Rsvp::Maybe => "maybe",
+        }
+    }
+}
+
+impl TryFrom<String> for Rsvp {
+    type Error = YaddakError;
+
+    fn try_from(value: String) -> SResult<Self> {
+        match value.as_str() {
+            "yes" => Ok(Rsvp::Yes),
+            "no" => Ok(Rsvp::No),
+            "maybe" => Ok(Rsvp::Maybe),
+            _ => Err(YaddakError::bad_request(format!("{value:?} is not an RSVP"))),
+        }
+    }
+}
+
+/// XP given to one character at the end of a session.
+#[derive(Serialize, Deserialize, Debug,
+         Clone, PartialEq,
+         ToSchema, Validate)]
+pub struct XpEntry {
+    pub character_id: Uuid,
+    #[validate(range(min = 0, max = 1000000, message = "must be between 0 and 1000000"))]
+    #[schema(minimum = 0, maximum = 1000000)]
+    pub xp: i32,
+}
+
+/// Treasure handed out, to a character or left with the party.
+#[derive(Serialize, Deserialize, Debug,
+         Clone, PartialEq,
+         ToSchema, Validate)]
+pub struct LootEntry {
+    #[validate(length(min = 1, max = 128, message = "must be between 1 and 128 characters"))]
+    #[schema(min_length = 1, max_length = 128)]
+    pub name: String,
+    /// The compendium item this is, if any.
+    #[serde(default)]
+    pub item_id: Option<Uuid>,
+    #[serde(default = "one")]
+    #[validate(range(min = 1, message = "must be at least 1"))]
+    #[schema(minimum = 1, default = 1)]
+    pub quantity: i32,
+    /// `None` while the party has not decided who carries it.
+    #[serde(default)]
+    pub character_id: Option<Uuid>,
+    #[serde(default)]
+    #[validate(length(max = 1000, message = "must be at most 1000 characters"))]
+    pub notes: Option<String>,
+}
+
+fn one() -> i32 {
+    1
+}
+
+/// What happened at the table, written by a DM after the session.
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Default, PartialEq,
+         ToSchema, Validate)]
+#[serde(default)]
+pub struct SessionLog {
+    #[validate(length(max = 20000, message = "must be at most 20000 characters"))]
+    pub summary: Option<String>,
+    /// Encounters and combats that ran, by name.
+    #[validate(length(max = 100, message = "must have at most 100 entries"))]
+    pub encounters: Vec<String>,
+    /// Written by XP awards. Editing the log keeps these as they are.
+    #[validate(nested, length(max = 100, message = "must have at most 100 entries"))]
+    pub xp: Vec<XpEntry>,
+    #[validate(nested, length(max = 500, message = "must have at most 500 entries"))]
+    pub loot: Vec<LootEntry>,
+}
+
+impl SessionLog {
+    /// Every character the log names.
+    pub fn character_ids(&self) -> impl Iterator<Item = Uuid> + '_ {
+        self.xp
+            .iter()
+            .map(|entry| entry.character_id)
+            .chain(self.loot.iter().filter_map(|entry| entry.character_id))
+    }
+
+    /// Every compendium item the loot links to.
+    pub fn item_ids(&self) -> impl Iterator<Item = Uuid> + '_ {
+        self.loot.iter().filter_map(|entry| entry.item_id)
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Default, FromRow,
+         ToSchema)]
+pub struct Session {
+    pub id: Uuid,
+    pub campaign_id: Uuid,
+    pub title: String,
+    /// The start as an instant in UTC.
+    pub scheduled_at: DateTime<Utc>,
+    /// Seconds east of UTC of the time zone the session was scheduled in,
+    /// so it can be shown in the DM's local time.
+    pub utc_offset: i32,
+    pub duration_minutes: Option<i32>,
+    pub location: Option<String>,
+    pub notes: Option<String>,
+    #[sqlx(json)]
+    pub log: SessionLog,
+    pub created_by: Uuid,
+    pub updated_at: DateTime<Utc>,
+}
+
+/// One member's answer to a session, for the audit log.
+#[derive(Serialize, Debug, Clone)]
+pub struct RsvpRecord {
+    pub session_id: Uuid,
+    pub user_id: Uuid,
+    pub rsvp: Rsvp,
+}
+
+/// A campaign member and their answer, `None` until they reply.
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct Attendee {
+    pub user_id: Uuid,
+    pub user_name: String,
+    pub role: CampaignRole,
+    pub rsvp: Option<Rsvp>,
+    pub responded_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Serialize, Debug, Clone, Default, ToSchema)]
+pub struct SessionDetail {
+    #[serde(flatten)]
+    pub session: Session,
+    /// `scheduled_at` in the time zone it was scheduled in.
+    #[schema(value_type = String, format = DateTime)]
+    pub local_start: DateTime<FixedOffset>,
+    pub attendees: Vec<Attendee>,
+}
+
+#[derive(Deserialize, Debug, Clone,
+         ToSchema, Validate)]
+pub struct SessionRequest {
+    #[validate(length(min = 1, max = 128, message = "must be between 1 and 128 characters"))]
+    #[schema(min_length = 1, max_length = 128)]
+    pub title: String,
+    /// RFC 3339 with the offset of the group's time zone, like
+    /// `2024-03-09T19:00:00-05:00`.
+    #[schema(value_type = String, format = DateTime)]
+    pub scheduled_at: DateTime<FixedOffset>,
+    #[serde(default)]
+    #[validate(range(min = 1, max = 1440, message = "must be between 1 and 1440"))]
+    #[schema(minimum = 1, maximum = 1440)]
+    pub duration_minutes: Option<i32>,
+    #[serde(default)]
+    #[validate(length(max = 256, message = "must be at most 256 characters"))]
+    #[schema(max_length = 256)]
+    pub location: Option<String>,
+    #[serde(default)]
+    #[validate(length(max = 4000, message = "must be at most 4000 characters"))]
+    #[schema(max_length = 4000)]
+    pub notes: Option<String>,
+}
+
+#[derive(Deserialize, Debug, Clone,
+         ToSchema, Validate)]
+pub struct RsvpRequest {
+    pub rsvp: Rsvp,
+}
+
+#[derive(Deserialize, Debug, IntoParams)]
+pub struct CalendarQuery {
+    /// From `/auth/campaign/{id}/calendar`.
+    pub token: String,
+}
+
+impl SessionRequest {
+    pub fn into_session(self, id: Uuid, campaign_id: Uuid, created_by: Uuid, log: SessionLog) -> Session {
+        Session {
+            id,
+            campaign_id,
+            title: self.title,
+            scheduled_at: self.scheduled_at.with_timezone(&Utc),
+            utc_offset: self.scheduled_at.offset().local_minus_utc(),
+            duration_minutes: self.duration_minutes,
+            location: self.location,
+            notes: self.notes,
+            log,
+            created_by,
+            updated_at: Utc::now(),
+        }
+    }
+}
+
+impl Session {
+    pub fn local_start(&self) -> DateTime<FixedOffset> {
+        let offset = FixedOffset::east_opt(self.utc_offset)
+            .unwrap_or_else(|| FixedOffset::east_opt(0).expect("zero is a valid offset"));
+        self.scheduled_at.with_timezone(&offset)
+    }
+
+    fn event(&self) -> ics::Event<'_> {
+        ics::Event {
+            uid: format!("{}@yaddak", self.id),
+            start: self.scheduled_at,
+            end: self.duration_minutes.map(|minutes| self.scheduled_at + Duration::minutes(minutes.into())),
+            summary: &self.title,
+            description: self.notes.as_deref(),
+            location: self.location.as_deref(),
+            last_modified: self.updated_at,
+        }
+    }
+
+    /// The campaign's sessions as an iCalendar feed.
+    pub fn calendar(campaign: &Campaign, sessions: &[Session]) -> String {
+        let events: Vec<ics::Event> = sessions.iter().map(Session::event).collect();
+        ics::calendar(&campaign.name, &events)
+    }
+
+    /// The session, if it belongs to `campaign_id`.
+    pub async fn get_in(con_str: String, campaign_id: Uuid, id: Uuid) -> SResult<Session> {
+        let session = Session::get(con_str, id).await?;
+        if session.campaign_id != campaign_id {
+            return Err(YaddakError::not_found(format!("No session with id {id} in this campaign")));
+        }
+        Ok(session)
+    }
+
+    /// Every member of the campaign with their RSVP.
+    pub async fn detail(con_str: String, session: Session) -> SResult<SessionDetail> {
+        let members = Campaign::members(con_str.clone(), session.campaign_id).await?;
+        let mut replies = Session::rsvps(con_str, session.id).await?;
+        let attendees = members
+            .into_iter()
+            .map(|member| {
+                let reply = replies.remove(&member.user_id);
+                Attendee {
+                    user_id: member.user_id,
+                    user_name: member.user_name,
+                    role: member.role,
+                    rsvp: reply.map(|(rsvp, _)| rsvp),
+                    responded_at: reply.map(|(_, at)| at),
+                }
+            })
+            .collect();
+        Ok(SessionDetail { local_start: session.local_start(), session, attendees })
+    }
+
+    #[instrument(name = "session.for_campaign", skip_all, fields(db.system = "postgresql"))]
+    pub async fn for_campaign(con_str: String, campaign_id: Uuid) -> SResult<Vec<Session>> {
+        let _timer = QueryTimer::new("session", "for_campaign");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .columns(SessionModel::cols())
+            .from(SessionModel::Table)
+            .and_where(Expr::col(SessionModel::CampaignId).eq(campaign_id))
+            .and_where(Expr::col(SessionModel::DeletedAt).is_null())
+            .order_by(SessionModel::ScheduledAt, sea_query::Order::Asc)
+            .build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<Session> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        Ok(rows)
+    }
+
+    #[instrument(name = "session.set_log", skip_all, fields(db.system = "postgresql"))]
+    pub async fn set_log(con_str: String, id: Uuid, log: &SessionLog) -> SResult<()> {
+        let _timer = QueryTimer::new("session", "set_log");
+        let mut client = connect(con_str).await?;
+        Session::write_log(&mut client, id, log).await
+    }
+
+    /// `set_log` on a connection the caller holds, so the log can change in
+    /// the same transaction as what it records.
+    pub(super) async fn write_log(tx: &mut PgConnection, id: Uuid, log: &SessionLog) -> SResult<()> {
+        let (sql, values) = Query::update()
+            .table(SessionModel::Table)
+            .values([
+                (SessionModel::Log, sea_query::Value::from(serde_json::to_value(log)?).into()),
+                (SessionModel::UpdatedAt, Utc::now().into()),
+            ])
+            .and_where(Expr::col(SessionModel::Id).eq(id))
+            .and_where(Expr::col(SessionModel::DeletedAt).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let _ = query_with(sql.as_str(), values)
+            .execute(&mut *tx)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(name = "session.rsvps", skip_all, fields(db.system = "postgresql"))]
+    pub async fn rsvps(con_str: String, id: Uuid) -> SResult<HashMap<Uuid, (Rsvp, DateTime<Utc>)>> {
+        let _timer = QueryTimer::new("session", "rsvps");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .columns([
+                SessionAttendeeModel::UserId,
+                SessionAttendeeModel::Rsvp,
+                SessionAttendeeModel::RespondedAt,
+            ])
+            .from(SessionAttendeeModel::Table)
+            .and_where(Expr::col(SessionAttendeeModel::SessionId).eq(id))
+            .build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<(Uuid, String, DateTime<Utc>)> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        rows.into_iter()
+            .map(|(user_id, rsvp, at)| Ok((user_id, (Rsvp::try_from(rsvp)?, at))))
+            .collect()
+    }
+
+    /// Records or replaces `user_id`'s answer.
+    #[instrument(name = "session.set_rsvp", skip_all, fields(db.system = "postgresql"))]
+    pub async fn set_rsvp(con_str: String, id: Uuid, user_id: Uuid, rsvp: Rsvp) -> SResult<()> {
+        let _timer = QueryTimer::new("session", "set_rsvp");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::insert()
+            .into_table(SessionAttendeeModel::Table)
+            .columns([
+                SessionAttendeeModel::SessionId,
+                SessionAttendeeModel::UserId,
+                SessionAttendeeModel::Rsvp,
+                SessionAttendeeModel::RespondedAt,
+            ])
+            .values_panic([
+                id.into(),
+                user_id.into(),
+                rsvp.as_str().into(),
+                Utc::now().into(),
+            ])
+            .on_conflict(
+                OnConflict::columns([SessionAttendeeModel::SessionId, SessionAttendeeModel::UserId])
+                    .update_columns([SessionAttendeeModel::Rsvp, SessionAttendeeModel::RespondedAt])
+                    .to_owned()
+            )
+            .build_sqlx(PostgresQueryBuilder);
+
+        let _ = query_with(&sql, values)
+            .execute(&mut *client)
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+pub(crate) enum SessionModel {
+    Table,
+    Id,
+    CampaignId,
+    Title,
+    ScheduledAt,
+    UtcOffset,
+    DurationMinutes,
+    Location,
+    Notes,
+    Log,
+    CreatedBy,
+    UpdatedAt,
+    DeletedAt,
+}
+
+impl SessionModel {
+    pub fn cols() -> Vec<Self> {
+        vec![
+            Self::Id,
+            Self::CampaignId,
+            Self::Title,
+            Self::ScheduledAt,
+            Self::UtcOffset,
+            Self::DurationMinutes,
+            Self::Location,
+            Self::Notes,
+            Self::Log,
+            Self::CreatedBy,
+            Self::UpdatedAt,
+        ]
+    }
+}
+
+#[derive(Iden)]
+enum SessionAttendeeModel {
+    Table,
+    SessionId,
+    UserId,
+    Rsvp,
+    RespondedAt,
+}
+
+impl Repo<'_, Session> for Session {
+    async fn migrate(con_str: String) -> SResult<()> {
+        let mut client = connect(con_str).await?;
+        let sessions = Table::create()
+            .table(SessionModel::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(SessionModel::Id)
+                    .uuid()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(SessionModel::CampaignId).uuid().not_null())
+            .col(ColumnDef::new(SessionModel::Title).string().not_null())
+            .col(ColumnDef::new(SessionModel::ScheduledAt).timestamp_with_time_zone().not_null())
+            .col(ColumnDef::new(SessionModel::UtcOffset).integer().not_null())
+            .col(ColumnDef::new(SessionModel::DurationMinutes).integer().null())
+            .col(ColumnDef::new(SessionModel::Location).string().null())
+            .col(ColumnDef::new(SessionModel::Notes).text().null())
+            .col(ColumnDef::new(SessionModel::Log).json_binary().not_null())
+            .col(ColumnDef::new(SessionModel::CreatedBy).uuid().not_null())
+            .col(ColumnDef::new(SessionModel::UpdatedAt).timestamp_with_time_zone().not_null())
+            .col(ColumnDef::new(SessionModel::DeletedAt).timestamp_with_time_zone().null())
+            .foreign_key(ForeignKey::create()
+                           .name("FK_Session_Campaign")
+                           .from(SessionModel::Table, SessionModel::CampaignId)
+                           .to(CampaignModel::Table, CampaignModel::Id)
+                           .on_delete(ForeignKeyAction::Cascade)
+                        )
+            .build(PostgresQueryBuilder);
+
+        let attendees = Table::create()
+            .table(SessionAttendeeModel::Table)
+            .if_not_exists()
+            .col(ColumnDef::new(SessionAttendeeModel::SessionId).uuid().not_null())
+            .col(ColumnDef::new(SessionAttendeeModel::UserId).uuid().not_null())
+            .col(ColumnDef::new(SessionAttendeeModel::Rsvp).string().not_null())
+            .col(ColumnDef::new(SessionAttendeeModel::RespondedAt).timestamp_with_time_zone().not_null())
+            .primary_key(Index::create()
+                           .col(SessionAttendeeModel::SessionId)
+                           .col(SessionAttendeeModel::UserId))
+            .foreign_key(ForeignKey::create()
+                           .name("FK_SessionAttendee_Session")
+                           .from(SessionAttendeeModel::Table, SessionAttendeeModel::SessionId)
+                           .to(SessionModel::Table, SessionModel::Id)
+                           .on_delete(ForeignKeyAction::Cascade)
+                        )
+            .foreign_key(ForeignKey::create()
+                           .name("FK_SessionAttendee_User")
+                           .from(SessionAttendeeModel::Table, SessionAttendeeModel::UserId)
+                           .to(UserModel::Table, UserModel::Id)
+                           .on_delete(ForeignKeyAction::Cascade)
+                        )
+            .build(PostgresQueryBuilder);
+
+        for sql in [sessions, attendees] {
+            let _ = query(sql.as_str())
+                .execute(&mut *client)
+                .await?;
+        }
+        Ok(())
+    }
+
+    #[instrument(name = "session.get", skip_all, fields(db.system = "postgresql"))]
+    async fn get(con_str: String, id: Uuid) -> SResult<Session> {
+        let _timer = QueryTimer::new("session", "get");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .columns(SessionModel::cols())
+            .from(SessionModel::Table)
+            .limit(1)
+            .and_where(Expr::col(SessionModel::Id).eq(id))
+            .and_where(Expr::col(SessionModel::DeletedAt).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let row: Session = query_as_with(&sql, values)
+            .fetch_one(&mut *client)
+            .await?;
+        Ok(row)
+    }
+
+    #[instrument(name = "session.get_all", skip_all, fields(db.system = "postgresql"))]
+    async fn get_all(con_str: String) -> SResult<Vec<Session>> {
+        let _timer = QueryTimer::new("session", "get_all");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .columns(SessionModel::cols())
+            .from(SessionModel::Table)
+            .and_where(Expr::col(SessionModel::DeletedAt).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<Session> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        Ok(rows)
+    }
+
+    #[instrument(name = "session.post", skip_all, fields(db.system = "postgresql"))]
+    async fn post(con_str: String, model: &Session) -> SResult<()> {
+        let _timer = QueryTimer::new("session", "post");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::insert()
+            .into_table(SessionModel::Table)
+            .columns(SessionModel::cols())
+            .values_panic([
+                model.id.into(),
+                model.campaign_id.into(),
+                model.title.clone().into(),
+                model.scheduled_at.into(),
+                model.utc_offset.into(),
+                model.duration_minutes.into(),
+                model.location.clone().into(),
+                model.notes.clone().into(),
+                sea_query::Value::from(serde_json::to_value(&model.log)?).into(),
+                model.created_by.into(),
+                model.updated_at.into(),
+            ])
+            .build_sqlx(PostgresQueryBuilder);
+
+        let _ = query_with(&sql, values)
+            .execute(&mut *client)
+            .await?;
+        Ok(())
+    }
+
+    /// Reschedules; the log has `set_log`.
+    #[instrument(name = "session.put", skip_all, fields(db.system = "postgresql"))]
+    async fn put(con_str: String, id: Uuid, model: &Session) -> SResult<()> {
+        let _timer = QueryTimer::new("session", "put");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::update()
+            .table(SessionModel::Table)
+            .values([
+                (SessionModel::Title, model.title.clone().into()),
+                (SessionModel::ScheduledAt, model.scheduled_at.into()),
+                (SessionModel::UtcOffset, model.utc_offset.into()),
+                (SessionModel::DurationMinutes, model.duration_minutes.into()),
+                (SessionModel::Location, model.location.clone().into()),
+                (SessionModel::Notes, model.notes.clone().into()),
+                (SessionModel::UpdatedAt, model.updated_at.into()),
+            ])
+            .and_where(Expr::col(SessionModel::Id).eq(id))
+            .and_where(Expr::col(SessionModel::DeletedAt).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let _ = query_with(sql.as_str(), values)
+            .execute(&mut *client)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(name = "session.delete", skip_all, fields(db.system = "postgresql"))]
+    async fn delete(con_str: String, id: Uuid) -> SResult<()> {
+        let _timer = QueryTimer::new("session", "delete");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::update()
+            .table(SessionModel::Table)
+            .value(SessionModel::DeletedAt, Utc::now())
+            .and_where(Expr::col(SessionModel::Id).eq(id))
+            .and_where(Expr::col(SessionModel::DeletedAt).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let result = query_with(sql.as_str(), values)
+            .execute(&mut *client)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(YaddakError::not_found(format!("No session with id {id} to delete")));
+        }
+        Ok(())
+    }
+
+    #[instrument(name = "session.restore", skip_all, fields(db.system = "postgresql"))]
+    async fn restore(con_str: String, id: Uuid) -> SResult<()> {
+        let _timer = QueryTimer::new("session", "restore");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::update()
+            .table(SessionModel::Table)
+            .value(SessionModel::DeletedAt, Option::<DateTime<Utc>>::None)
+            .and_where(Expr::col(SessionModel::Id).eq(id))
+            .and_where(Expr::col(SessionModel::DeletedAt).is_not_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let result = query_with(sql.as_str(), values)
+            .execute(&mut *client)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(YaddakError::not_found(format!("No restorable session with id {id}")));
+        }
+        Ok(())
+    }
+
+    #[instrument(name = "session.purge", skip_all, fields(db.system = "postgresql"))]
+    async fn purge(con_str: String, older_than: DateTime<Utc>) -> SResult<Vec<Uuid>> {
+        let _timer = QueryTimer::new("session", "purge");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::delete()
+            .from_table(SessionModel::Table)
+            .and_where(Expr::col(SessionModel::DeletedAt).lt(older_than))
+            .returning_col(SessionModel::Id)
+            .build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<(Uuid,)> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        Ok(rows.into_iter().map(|(id,)| id).collect())
+    }
+}
+
+impl Auditable for Session {
+    const ENTITY_TYPE: &'static str = "session";
+
+    fn entity_id(&self) -> Uuid {
+        self.id
+    }
+}
+
+/// Answers are logged against the session id.
+impl Auditable for RsvpRecord {
+    const ENTITY_TYPE: &'static str = "session_rsvp";
+
+    fn entity_id(&self) -> Uuid {
+        self.session_id
+    }
+}
@@ -0,0 +1,405 @@
+use std::path::{Path, PathBuf};
+
+use sea_query::{Iden, Table, ColumnDef, PostgresQueryBuilder, Query, Expr, Index, OnConflict, Order, Cond, extension::postgres::PgExpr};
+use sea_query_binder::SqlxBinder;
+use serde::{Serialize, Deserialize};
+use sqlx::{FromRow, query, query_as_with, query_with};
+use tracing::instrument;
+use utoipa::{IntoParams, ToSchema};
+use uuid::Uuid;
+
+use crate::{traits::repo::connect, utilities::metrics::QueryTimer};
+
+use super::{
+    character::CharacterClass,
+    errors::{SResult, YaddakError},
+    monsters::SRD_SOURCE,
+};
+
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Copy, Default, PartialEq, Eq,
+         ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum SpellSchool {
+    #[default]
+    Abjuration,
+    Conjuration,
+    Divination,
+    Enchantment,
+    Evocation,
+    Illusion,
+    Necromancy,
+    Transmutation,
+}
+
+impl SpellSchool {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            SpellSchool::Abjuration => "abjuration",
+            SpellSchool::Conjuration => "conjuration",
+            SpellSchool::Divination => "divination",
+            SpellSchool::Enchantment => "enchantment",
+            SpellSchool::Evocation => "evocation",
+            SpellSchool::Illusion => "illusion",
+            SpellSchool::Necromancy => "necromancy",
+            SpellSchool::Transmutation => "transmutation",
+        }
+    }
+}
+
+impl TryFrom<String> for SpellSchool {
+    type Error = YaddakError;
+
+    fn try_from(value: String) -> SResult<Self> {
+        serde_json::from_value(serde_json::Value::String(value.clone()))
+            .map_err(|_| YaddakError::bad_request(format!("{value:?} is not a school of magic")))
+    }
+}
+
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Default, FromRow,
+         ToSchema)]
+pub struct Spell {
+    pub id: Uuid,
+    pub name: String,
+    /// 0 for a cantrip.
+    pub level: i16,
+    #[sqlx(try_from = "String")]
+    pub school: SpellSchool,
+    pub casting_time: String,
+    pub range: String,
+    /// `V`, `S` and `M` as the spell needs them, e.g. `V, S, M`.
+    pub components: String,
+    /// What the `M` component stands for.
+    pub material: Option<String>,
+    pub duration: String,
+    pub concentration: bool,
+    pub ritual: bool,
+    /// Classes with the spell on their list.
+    #[sqlx(json)]
+    pub classes: Vec<CharacterClass>,
+    pub description: String,
+    /// What a higher level slot adds, if anything.
+    pub higher_level: Option<String>,
+    pub source: String,
+}
+
+/// A spell in the bundled `data/spells.json` shape. Paragraphs are arrays,
+/// the material is part of `components` and concentration is read from
+/// the duration, as stat blocks print them.
+#[derive(Deserialize, Debug, Clone)]
+pub struct SpellFromJson {
+    pub name: String,
+    pub level: i16,
+    pub school: SpellSchool,
+    pub casting_time: String,
+    pub range: String,
+    pub components: String,
+    pub duration: String,
+    #[serde(default)]
+    pub ritual: bool,
+    pub classes: Vec<CharacterClass>,
+    pub desc: Vec<String>,
+    #[serde(default)]
+    pub higher_level: Vec<String>,
+}
+
+impl SpellFromJson {
+    pub fn into_spell(self, id: Uuid, source: &str) -> Spell {
+        let (components, material) = match self.components.split_once('(') {
+            Some((components, material)) => (
+                components.trim().to_string(),
+                Some(material.trim_end().trim_end_matches(')').to_string()),
+            ),
+            None => (self.components.trim().to_string(), None),
+        };
+        Spell {
+            id,
+            name: self.name,
+            level: self.level,
+            school: self.school,
+            casting_time: self.casting_time,
+            range: self.range,
+            components,
+            material,
+            concentration: self.duration.starts_with("Concentration"),
+            duration: self.duration,
+            ritual: self.ritual,
+            classes: self.classes,
+            description: self.desc.join("\n"),
+            higher_level: (!self.higher_level.is_empty()).then(|| self.higher_level.join("\n")),
+            source: source.to_string(),
+        }
+    }
+}
+
+/// Search filters, every field is optional and they combine with AND.
+#[derive(Deserialize, Debug, Clone, Default,
+         IntoParams)]
+#[into_params(parameter_in = Query)]
+pub struct SpellQuery {
+    /// Only spells on this class's list.
+    pub class: Option<CharacterClass>,
+    /// 0 for cantrips.
+    pub level: Option<i16>,
+    pub school: Option<SpellSchool>,
+    pub concentration: Option<bool>,
+    pub ritual: Option<bool>,
+    /// Case insensitive text to find in the name or description.
+    pub q: Option<String>,
+}
+
+/// A spell a monster's traits name, enough to fetch the rest.
+#[derive(Serialize, Debug, Clone, Default, ToSchema)]
+pub struct SpellLink {
+    pub id: Uuid,
+    pub name: String,
+    pub level: i16,
+}
+
+impl Spell {
+    #[instrument(name = "spell.get", skip_all, fields(db.system = "postgresql"))]
+    pub async fn get(con_str: String, id: Uuid) -> SResult<Spell> {
+        let _timer = QueryTimer::new("spell", "get");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .columns(SpellModel::cols())
+            .from(SpellModel::Table)
+            .and_where(Expr::col(SpellModel::Id).eq(id))
+            .limit(1)
+            .build_sqlx(PostgresQueryBuilder);
+
+        let row: Spell = query_as_with(&sql, values)
+            .fetch_one(&mut *client)
+            .await?;
+        Ok(row)
+    }
+
+    /// Spells matching `filter`, by level and then name.
+    #[instrument(name = "spell.search", skip_all, fields(db.system = "postgresql"))]
+    pub async fn search(con_str: String, filter: SpellQuery) -> SResult<Vec<Spell>> {
+        let _timer = QueryTimer::new("spell", "search");
+        let mut client = connect(con_str).await?;
+        let mut select = Query::select();
+        select
+            .columns(SpellModel::cols())
+            .from(SpellModel::Table)
+            .order_by(SpellModel::Level, Order::Asc)
+            .order_by(SpellModel::Name, Order::Asc);
+        if let Some(class) = filter.class {
+            select.and_where(Expr::col(SpellModel::Classes).contains(serde_json::json!([class])));
+        }
+        if let Some(level) = filter.level {
+            select.and_where(Expr::col(SpellModel::Level).eq(level));
+        }
+        if let Some(school) = filter.school {
+            select.and_where(Expr::col(SpellModel::School).eq(school.as_str()));
+        }
+        if let Some(concentration) = filter.concentration {
+            select.and_where(Expr::col(SpellModel::Concentration).eq(concentration));
+        }
+        if let Some(ritual) = filter.ritual {
+            select.and_where(Expr::col(SpellModel::Ritual).eq(ritual));
+        }
+        if let Some(text) = filter.q.filter(|text| !text.trim().is_empty()) {
+            let pattern = format!("%{}%", escape_like(text.trim()));
+            select.cond_where(
+                Cond::any()
+                    .add(Expr::col(SpellModel::Name).ilike(pattern.clone()))
+                    .add(Expr::col(SpellModel::Description).ilike(pattern))
+            );
+        }
+        let (sql, values) = select.build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<Spell> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        Ok(rows)
+    }
+
+    /// The spells named in a monster's spellcasting traits, by level.
+    #[instrument(name = "spell.for_traits", skip_all, fields(db.system = "postgresql"))]
+    pub async fn for_traits(con_str: String, traits: &str) -> SResult<Vec<SpellLink>> {
+        let lists = spell_lists(traits);
+        if lists.is_empty() {
+            return Ok(Vec::new());
+        }
+        let _timer = QueryTimer::new("spell", "for_traits");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .columns([SpellModel::Id, SpellModel::Name, SpellModel::Level])
+            .from(SpellModel::Table)
+            .order_by(SpellModel::Level, Order::Asc)
+            .order_by(SpellModel::Name, Order::Asc)
+            .build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<(Uuid, String, i16)> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        Ok(rows
+            .into_iter()
+            .filter(|(_, name, _)| lists.iter().any(|list| names_spell(list, &name.to_lowercase())))
+            .map(|(id, name, level)| SpellLink { id, name, level })
+            .collect())
+    }
+
+    /// Loads spells from a file in the bundled `data/spells.json` shape.
+    /// Spells already loaded from the SRD are skipped, so seeding again
+    /// only adds what is new. Returns how many were added.
+    pub async fn seed(con_str: String, file_path: &Path) -> SResult<u64> {
+        let content = tokio::fs::read_to_string(file_path).await?;
+        let spells: Vec<SpellFromJson> = serde_json::from_str(&content)?;
+        let mut client = connect(con_str).await?;
+        let mut added = 0;
+        for spell in spells {
+            let spell = spell.into_spell(Uuid::new_v4(), SRD_SOURCE);
+            let (sql, values) = Query::insert()
+                .into_table(SpellModel::Table)
+                .columns(SpellModel::cols())
+                .values_panic([
+                    spell.id.into(),
+                    spell.name.into(),
+                    spell.level.into(),
+                    spell.school.as_str().into(),
+                    spell.casting_time.into(),
+                    spell.range.into(),
+                    spell.components.into(),
+                    spell.material.into(),
+                    spell.duration.into(),
+                    spell.concentration.into(),
+                    spell.ritual.into(),
+                    sea_query::Value::from(serde_json::to_value(&spell.classes)?).into(),
+                    spell.description.into(),
+                    spell.higher_level.into(),
+                    spell.source.into(),
+                ])
+                .on_conflict(
+                    OnConflict::columns([SpellModel::Name, SpellModel::Source])
+                        .do_nothing()
+                        .to_owned()
+                )
+                .build_sqlx(PostgresQueryBuilder);
+
+            added += query_with(&sql, values)
+                .execute(&mut *client)
+                .await?
+                .rows_affected();
+        }
+        Ok(added)
+    }
+
+    pub fn bundled_seed() -> PathBuf {
+        crate::config::get().storage.data_dir.join("spells.json")
+    }
+
+    pub async fn migrate(con_str: String) -> SResult<()> {
+        let mut client = connect(con_str).await?;
+        let sql = Table::create()
+            .table(SpellModel::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(SpellModel::Id)
+                    .uuid()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(SpellModel::Name).string().not_null())
+            .col(ColumnDef::new(SpellModel::Level).small_integer().not_null())
+            .col(ColumnDef::new(SpellModel::School).string().not_null())
+            .col(ColumnDef::new(SpellModel::CastingTime).string().not_null())
+            .col(ColumnDef::new(SpellModel::Range).string().not_null())
+            .col(ColumnDef::new(SpellModel::Components).string().not_null())
+            .col(ColumnDef::new(SpellModel::Material).text().null())
+            .col(ColumnDef::new(SpellModel::Duration).string().not_null())
+            .col(ColumnDef::new(SpellModel::Concentration).boolean().not_null())
+            .col(ColumnDef::new(SpellModel::Ritual).boolean().not_null())
+            .col(ColumnDef::new(SpellModel::Classes).json_binary().not_null())
+            .col(ColumnDef::new(SpellModel::Description).text().not_null())
+            .col(ColumnDef::new(SpellModel::HigherLevel).text().null())
+            .col(ColumnDef::new(SpellModel::Source).string().not_null())
+            .index(Index::create()
+                     .name("UQ_Spell_Name_Source")
+                     .unique()
+                     .col(SpellModel::Name)
+                     .col(SpellModel::Source))
+            .build(PostgresQueryBuilder);
+
+        let _ = query(sql.as_str())
+            .execute(&mut *client)
+            .await?;
+        Ok(())
+    }
+}
+
+/// `%` and `_` in search text match themselves.
+pub(super) fn escape_like(text: &str) -> String {
+    text.replace('\\', "\\\\").replace('%', "\\%").replace('_', "\\_")
+}
+
+/// The spell lists in a monster's traits. Stored traits have their markup
+/// stripped, so a list runs from the colon after a mention of spellcasting
+/// up to the next full stop, which spell names never contain.
+fn spell_lists(traits: &str) -> Vec<String> {
+    // matches both `Spellcasting` and `spellcasting`
+    traits
+        .match_indices("pellcasting")
+        .filter_map(|(at, _)| {
+            let list = &traits[at..];
+            let list = &list[list.find(':')? + 1..];
+            Some(list[..list.find('.').unwrap_or(list.len())].to_string())
+        })
+        .collect()
+}
+
+/// Whether `name` appears in `list` as whole words. Names are matched
+/// against the list as written, lowercase, so a capital straight after a
+/// name is the next trait's title run into it rather than more of the name.
+fn names_spell(list: &str, name: &str) -> bool {
+    list.match_indices(name).any(|(at, _)| {
+        let before = list[..at].chars().next_back();
+        let after = list[at + name.len()..].chars().next();
+        !before.is_some_and(char::is_alphanumeric) && !after.is_some_and(char::is_lowercase)
+    })
+}
+
+#[derive(Iden)]
+enum SpellModel {
+    Table,
+    Id,
+    Name,
+    Level,
+    School,
+    CastingTime,
+    Range,
+    Components,
+    Material,
+    Duration,
+    Concentration,
+    Ritual,
+    Classes,
+    Description,
+    HigherLevel,
+    Source,
+}
+
+impl SpellModel {
+    pub fn cols() -> Vec<Self> {
+        vec![
+            Self::Id,
+            Self::Name,
+            Self::Level,
+            Self::School,
+            Self::CastingTime,
+            Self::Range,
+            Self::Components,
+            Self::Material,
+            Self::Duration,
+            Self::Concentration,
+            Self::Ritual,
+            Self::Classes,
+            Self::Description,
+            Self::HigherLevel,
+            Self::Source,
+        ]
+    }
+}
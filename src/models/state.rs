@@ -1 +1,3 @@
-pub struct YaddakState { pub db: String, }
+use crate::utilities::storage::LocalStore;
+
+pub struct YaddakState { pub db: String, pub store: LocalStore, }
@@ -0,0 +1,622 @@
+use std::collections::{BTreeMap, HashMap};
+
+use serde::{Serialize, Deserialize};
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
+
+use crate::utilities::dice::Dice;
+
+use super::{
+    errors::SResult,
+    item::{Item, ItemCategory, ItemQuery, Rarity},
+    session::LootEntry,
+    xp::Defeated,
+};
+
+/// Individual treasure is what a few monsters carry, a hoard is what a
+/// lair or a boss guards.
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Copy, Default, PartialEq, Eq,
+         ToSchema)]
+#[serde(rename_all = "lowercase")]
+pub enum TreasureKind {
+    #[default]
+    Individual,
+    Hoard,
+}
+
+impl TreasureKind {
+    pub fn as_str(self) -> &'static str {
+        match self {
+            TreasureKind::Individual => "individual",
+            TreasureKind::Hoard => "hoard",
+        }
+    }
+}
+
+/// Rolls treasure for either a challenge rating or the monsters of an
+/// encounter. Individual treasure is rolled for each monster at its own
+/// rating, a hoard once at the highest rating among them.
+#[derive(Deserialize, Debug, Clone,
+         ToSchema, Validate)]
+#[validate(schema(function = "validate_treasure_request"))]
+pub struct TreasureRequest {
+    #[serde(default)]
+    pub kind: TreasureKind,
+    #[serde(default)]
+    #[validate(range(min = 0.0, max = 30.0, message = "must be between 0 and 30"))]
+    #[schema(minimum = 0, maximum = 30)]
+    pub challenge: Option<f32>,
+    #[serde(default)]
+    #[validate(nested, length(max = 100, message = "must have at most 100 entries"))]
+    pub defeated: Vec<Defeated>,
+    /// Same seed, same treasure. Picked at random when left out.
+    #[serde(default)]
+    pub seed: Option<u32>,
+    /// The encounter the treasure came from, added to the session log's
+    /// encounters when attached to a session.
+    #[serde(default)]
+    #[validate(length(min = 1, max = 128, message = "must be between 1 and 128 characters"))]
+    pub encounter: Option<String>,
+}
+
+fn validate_treasure_request(request: &TreasureRequest) -> Result<(), ValidationError> {
+    if request.challenge.is_some() != request.defeated.is_empty() {
+        let mut err = ValidationError::new("challenge");
+        err.message = Some("give one of challenge or defeated".into());
+        return Err(err);
+    }
+    Ok(())
+}
+
+#[derive(Serialize, Debug, Clone, Copy, Default, ToSchema)]
+pub struct Coins {
+    pub cp: u32,
+    pub sp: u32,
+    pub ep: u32,
+    pub gp: u32,
+    pub pp: u32,
+}
+
+impl Coins {
+    fn add(&mut self, coin: Coin, amount: u32) {
+        match coin {
+            Coin::Cp => self.cp += amount,
+            Coin::Sp => self.sp += amount,
+            Coin::Ep => self.ep += amount,
+            Coin::Gp => self.gp += amount,
+            Coin::Pp => self.pp += amount,
+        }
+    }
+
+    fn value_cp(&self) -> u64 {
+        u64::from(self.cp)
+            + 10 * u64::from(self.sp)
+            + 50 * u64::from(self.ep)
+            + 100 * u64::from(self.gp)
+            + 1000 * u64::from(self.pp)
+    }
+}
+
+/// A gem or art object, `quantity` of them worth `value_gp` each.
+#[derive(Serialize, Debug, Clone, Default, ToSchema)]
+pub struct Valuable {
+    pub name: String,
+    pub value_gp: u32,
+    pub quantity: u32,
+}
+
+#[derive(Serialize, Debug, Clone, ToSchema)]
+pub struct TreasureItem {
+    pub item_id: Uuid,
+    pub name: String,
+    pub rarity: Rarity,
+}
+
+#[derive(Serialize, Debug, Clone, Default, ToSchema)]
+pub struct Treasure {
+    /// Send it back to get the same treasure again.
+    pub seed: u32,
+    pub kind: TreasureKind,
+    /// The rating the treasure was rolled at, the highest for a hoard.
+    pub challenge: f32,
+    pub coins: Coins,
+    pub gems: Vec<Valuable>,
+    pub art: Vec<Valuable>,
+    pub magic_items: Vec<TreasureItem>,
+    /// Coins, gems and art in gold pieces. Magic items have no set price.
+    pub value_gp: f64,
+}
+
+#[derive(Clone, Copy)]
+enum Coin {
+    Cp,
+    Sp,
+    Ep,
+    Gp,
+    Pp,
+}
+
+/// `count`d`sides` times `times`, how the tables write amounts.
+#[derive(Clone, Copy)]
+struct Amount {
+    count: u32,
+    sides: u32,
+    times: u32,
+}
+
+const fn amount(count: u32, sides: u32, times: u32) -> Amount {
+    Amount { count, sides, times }
+}
+
+impl Amount {
+    fn roll(self, dice: &mut Dice) -> u32 {
+        dice.roll(self.count, self.sides) * self.times
+    }
+}
+
+/// Magic item tables A to I. Each stands in for the rarity most of its
+/// entries have, and an item of that rarity is drawn from the compendium,
+/// so homebrew items turn up in treasure too.
+#[derive(Clone, Copy)]
+enum MagicTable {
+    A, B, C, D, E, F, G, H, I,
+}
+
+impl MagicTable {
+    fn rarity(self) -> Rarity {
+        match self {
+            MagicTable::A => Rarity::Common,
+            MagicTable::B | MagicTable::F => Rarity::Uncommon,
+            MagicTable::C | MagicTable::G => Rarity::Rare,
+            MagicTable::D | MagicTable::H => Rarity::VeryRare,
+            MagicTable::E | MagicTable::I => Rarity::Legendary,
+        }
+    }
+}
+
+#[derive(Clone, Copy)]
+enum Valuables {
+    /// Gems worth this many gold pieces each.
+    Gems(u32),
+    /// Art objects worth this many gold pieces each.
+    Art(u32),
+}
+
+/// A d100 row, taken when the roll is at most `upto`.
+struct IndividualRow {
+    upto: u32,
+    coins: &'static [(Coin, Amount)],
+}
+
+struct HoardRow {
+    upto: u32,
+    valuables: Option<(Valuables, Amount)>,
+    magic: &'static [(MagicTable, Amount)],
+}
+
+const fn row(upto: u32, coins: &'static [(Coin, Amount)]) -> IndividualRow {
+    IndividualRow { upto, coins }
+}
+
+const fn hoard(upto: u32, valuables: Option<(Valuables, Amount)>, magic: &'static [(MagicTable, Amount)]) -> HoardRow {
+    HoardRow { upto, valuables, magic }
+}
+
+const fn gems(value: u32, count: u32, sides: u32) -> Option<(Valuables, Amount)> {
+    Some((Valuables::Gems(value), amount(count, sides, 1)))
+}
+
+const fn art(value: u32, count: u32, sides: u32) -> Option<(Valuables, Amount)> {
+    Some((Valuables::Art(value), amount(count, sides, 1)))
+}
+
+const ONE: Amount = amount(1, 1, 1);
+const D4: Amount = amount(1, 4, 1);
+const D6: Amount = amount(1, 6, 1);
+const D8: Amount = amount(1, 8, 1);
+
+/// Individual treasure by challenge band: 0-4, 5-10, 11-16 and 17+.
+const INDIVIDUAL: [&[IndividualRow]; 4] = [
+    &[
+        row(30, &[(Coin::Cp, amount(5, 6, 1))]),
+        row(60, &[(Coin::Sp, amount(4, 6, 1))]),
+        row(70, &[(Coin::Ep, amount(3, 6, 1))]),
+        row(95, &[(Coin::Gp, amount(3, 6, 1))]),
+        row(100, &[(Coin::Pp, amount(1, 6, 1))]),
+    ],
+    &[
+        row(30, &[(Coin::Cp, amount(4, 6, 100)), (Coin::Ep, amount(1, 6, 10))]),
+        row(60, &[(Coin::Sp, amount(6, 6, 10)), (Coin::Gp, amount(2, 6, 10))]),
+        row(70, &[(Coin::Ep, amount(3, 6, 10)), (Coin::Gp, amount(2, 6, 10))]),
+        row(95, &[(Coin::Gp, amount(4, 6, 10))]),
+        row(100, &[(Coin::Gp, amount(2, 6, 10)), (Coin::Pp, amount(3, 6, 1))]),
+    ],
+    &[
+        row(20, &[(Coin::Sp, amount(4, 6, 100)), (Coin::Gp, amount(1, 6, 100))]),
+        row(35, &[(Coin::Ep, amount(1, 6, 100)), (Coin::Gp, amount(1, 6, 100))]),
+        row(75, &[(Coin::Gp, amount(2, 6, 100)), (Coin::Pp, amount(1, 6, 10))]),
+        row(100, &[(Coin::Gp, amount(2, 6, 100)), (Coin::Pp, amount(2, 6, 10))]),
+    ],
+    &[
+        row(15, &[(Coin::Ep, amount(2, 6, 1000)), (Coin::Gp, amount(8, 6, 100))]),
+        row(55, &[(Coin::Gp, amount(1, 6, 1000)), (Coin::Pp, amount(1, 6, 100))]),
+        row(100, &[(Coin::Gp, amount(1, 6, 1000)), (Coin::Pp, amount(2, 6, 100))]),
+    ],
+];
+
+/// Coins every hoard of a band has, before its d100 row.
+const HOARD_COINS: [&[(Coin, Amount)]; 4] = [
+    &[(Coin::Cp, amount(6, 6, 100)), (Coin::Sp, amount(3, 6, 100)), (Coin::Gp, amount(2, 6, 10))],
+    &[(Coin::Cp, amount(2, 6, 100)), (Coin::Sp, amount(2, 6, 1000)), (Coin::Gp, amount(6, 6, 100)), (Coin::Pp, amount(3, 6, 10))],
+    &[(Coin::Gp, amount(4, 6, 1000)), (Coin::Pp, amount(5, 6, 100))],
+    &[(Coin::Gp, amount(12, 6, 1000)), (Coin::Pp, amount(8, 6, 1000))],
+];
+
+const HOARD: [&[HoardRow]; 4] = [
+    &[
+        hoard(6, None, &[]),
+        hoard(16, gems(10, 2, 6), &[]),
+        hoard(26, art(25, 2, 4), &[]),
+        hoard(36, gems(50, 2, 6), &[]),
+        hoard(44, gems(10, 2, 6), &[(MagicTable::A, D6)]),
+        hoard(52, art(25, 2, 4), &[(MagicTable::A, D6)]),
+        hoard(60, gems(50, 2, 6), &[(MagicTable::A, D6)]),
+        hoard(65, gems(10, 2, 6), &[(MagicTable::B, D4)]),
+        hoard(70, art(25, 2, 4), &[(MagicTable::B, D4)]),
+        hoard(75, gems(50, 2, 6), &[(MagicTable::B, D4)]),
+        hoard(78, gems(10, 2, 6), &[(MagicTable::C, D4)]),
+        hoard(80, art(25, 2, 4), &[(MagicTable::C, D4)]),
+        hoard(85, gems(50, 2, 6), &[(MagicTable::C, D4)]),
+        hoard(92, art(25, 2, 4), &[(MagicTable::F, D4)]),
+        hoard(97, gems(50, 2, 6), &[(MagicTable::F, D4)]),
+        hoard(99, art(25, 2, 4), &[(MagicTable::G, ONE)]),
+        hoard(100, gems(50, 2, 6), &[(MagicTable::G, ONE)]),
+    ],
+    &[
+        hoard(4, None, &[]),
+        hoard(10, art(25, 2, 4), &[]),
+        hoard(16, gems(50, 3, 6), &[]),
+        hoard(22, gems(100, 3, 6), &[]),
+        hoard(28, art(250, 2, 4), &[]),
+        hoard(32, art(25, 2, 4), &[(MagicTable::A, D6)]),
+        hoard(36, gems(50, 3, 6), &[(MagicTable::A, D6)]),
+        hoard(40, gems(100, 3, 6), &[(MagicTable::A, D6)]),
+        hoard(44, art(250, 2, 4), &[(MagicTable::A, D6)]),
+        hoard(49, art(25, 2, 4), &[(MagicTable::B, D4)]),
+        hoard(54, gems(50, 3, 6), &[(MagicTable::B, D4)]),
+        hoard(59, gems(100, 3, 6), &[(MagicTable::B, D4)]),
+        hoard(63, art(250, 2, 4), &[(MagicTable::B, D4)]),
+        hoard(66, art(25, 2, 4), &[(MagicTable::C, D4)]),
+        hoard(69, gems(50, 3, 6), &[(MagicTable::C, D4)]),
+        hoard(72, gems(100, 3, 6), &[(MagicTable::C, D4)]),
+        hoard(74, art(250, 2, 4), &[(MagicTable::C, D4)]),
+        hoard(76, art(25, 2, 4), &[(MagicTable::D, ONE)]),
+        hoard(78, gems(50, 3, 6), &[(MagicTable::D, ONE)]),
+        hoard(79, gems(100, 3, 6), &[(MagicTable::D, ONE)]),
+        hoard(80, art(250, 2, 4), &[(MagicTable::D, ONE)]),
+        hoard(84, art(25, 2, 4), &[(MagicTable::F, D4)]),
+        hoard(88, gems(50, 3, 6), &[(MagicTable::F, D4)]),
+        hoard(91, gems(100, 3, 6), &[(MagicTable::F, D4)]),
+        hoard(94, art(250, 2, 4), &[(MagicTable::F, D4)]),
+        hoard(96, gems(100, 3, 6), &[(MagicTable::G, D4)]),
+        hoard(98, art(250, 2, 4), &[(MagicTable::G, D4)]),
+        hoard(99, gems(100, 3, 6), &[(MagicTable::H, ONE)]),
+        hoard(100, art(250, 2, 4), &[(MagicTable::H, ONE)]),
+    ],
+    &[
+        hoard(3, None, &[]),
+        hoard(6, art(250, 2, 4), &[]),
+        hoard(9, art(750, 2, 4), &[]),
+        hoard(12, gems(500, 3, 6), &[]),
+        hoard(15, gems(1000, 3, 6), &[]),
+        hoard(19, art(250, 2, 4), &[(MagicTable::A, D4), (MagicTable::B, D6)]),
+        hoard(23, art(750, 2, 4), &[(MagicTable::A, D4), (MagicTable::B, D6)]),
+        hoard(26, gems(500, 3, 6), &[(MagicTable::A, D4), (MagicTable::B, D6)]),
+        hoard(29, gems(1000, 3, 6), &[(MagicTable::A, D4), (MagicTable::B, D6)]),
+        hoard(35, art(250, 2, 4), &[(MagicTable::C, D6)]),
+        hoard(40, art(750, 2, 4), &[(MagicTable::C, D6)]),
+        hoard(45, gems(500, 3, 6), &[(MagicTable::C, D6)]),
+        hoard(50, gems(1000, 3, 6), &[(MagicTable::C, D6)]),
+        hoard(54, art(250, 2, 4), &[(MagicTable::D, D4)]),
+        hoard(58, art(750, 2, 4), &[(MagicTable::D, D4)]),
+        hoard(62, gems(500, 3, 6), &[(MagicTable::D, D4)]),
+        hoard(66, gems(1000, 3, 6), &[(MagicTable::D, D4)]),
+        hoard(68, art(250, 2, 4), &[(MagicTable::E, ONE)]),
+        hoard(70, art(750, 2, 4), &[(MagicTable::E, ONE)]),
+        hoard(72, gems(500, 3, 6), &[(MagicTable::E, ONE)]),
+        hoard(74, gems(1000, 3, 6), &[(MagicTable::E, ONE)]),
+        hoard(76, art(250, 2, 4), &[(MagicTable::F, ONE), (MagicTable::G, D4)]),
+        hoard(78, art(750, 2, 4), &[(MagicTable::F, ONE), (MagicTable::G, D4)]),
+        hoard(80, gems(500, 3, 6), &[(MagicTable::F, ONE), (MagicTable::G, D4)]),
+        hoard(82, gems(1000, 3, 6), &[(MagicTable::F, ONE), (MagicTable::G, D4)]),
+        hoard(85, art(250, 2, 4), &[(MagicTable::H, D4)]),
+        hoard(88, art(750, 2, 4), &[(MagicTable::H, D4)]),
+        hoard(90, gems(500, 3, 6), &[(MagicTable::H, D4)]),
+        hoard(92, gems(1000, 3, 6), &[(MagicTable::H, D4)]),
+        hoard(94, art(250, 2, 4), &[(MagicTable::I, ONE)]),
+        hoard(96, art(750, 2, 4), &[(MagicTable::I, ONE)]),
+        hoard(98, gems(500, 3, 6), &[(MagicTable::I, ONE)]),
+        hoard(100, gems(1000, 3, 6), &[(MagicTable::I, ONE)]),
+    ],
+    &[
+        hoard(2, None, &[]),
+        hoard(5, gems(1000, 3, 6), &[(MagicTable::C, D8)]),
+        hoard(8, art(2500, 1, 10), &[(MagicTable::C, D8)]),
+        hoard(11, art(7500, 1, 4), &[(MagicTable::C, D8)]),
+        hoard(14, gems(5000, 1, 8), &[(MagicTable::C, D8)]),
+        hoard(22, gems(1000, 3, 6), &[(MagicTable::D, D6)]),
+        hoard(30, art(2500, 1, 10), &[(MagicTable::D, D6)]),
+        hoard(38, art(7500, 1, 4), &[(MagicTable::D, D6)]),
+        hoard(46, gems(5000, 1, 8), &[(MagicTable::D, D6)]),
+        hoard(52, gems(1000, 3, 6), &[(MagicTable::E, D6)]),
+        hoard(58, art(2500, 1, 10), &[(MagicTable::E, D6)]),
+        hoard(63, art(7500, 1, 4), &[(MagicTable::E, D6)]),
+        hoard(68, gems(5000, 1, 8), &[(MagicTable::E, D6)]),
+        hoard(69, gems(1000, 3, 6), &[(MagicTable::G, D4)]),
+        hoard(70, art(2500, 1, 10), &[(MagicTable::G, D4)]),
+        hoard(71, art(7500, 1, 4), &[(MagicTable::G, D4)]),
+        hoard(72, gems(5000, 1, 8), &[(MagicTable::G, D4)]),
+        hoard(74, gems(1000, 3, 6), &[(MagicTable::H, D4)]),
+        hoard(76, art(2500, 1, 10), &[(MagicTable::H, D4)]),
+        hoard(78, art(7500, 1, 4), &[(MagicTable::H, D4)]),
+        hoard(80, gems(5000, 1, 8), &[(MagicTable::H, D4)]),
+        hoard(85, gems(1000, 3, 6), &[(MagicTable::I, D4)]),
+        hoard(90, art(2500, 1, 10), &[(MagicTable::I, D4)]),
+        hoard(95, art(7500, 1, 4), &[(MagicTable::I, D4)]),
+        hoard(100, gems(5000, 1, 8), &[(MagicTable::I, D4)]),
+    ],
+];
+
+const GEMS: [(u32, &[&str]); 6] = [
+    (10, &["Azurite", "Banded agate", "Blue quartz", "Eye agate", "Hematite", "Lapis lazuli",
+           "Malachite", "Moss agate", "Obsidian", "Rhodochrosite", "Tiger eye", "Turquoise"]),
+    (50, &["Bloodstone", "Carnelian", "Chalcedony", "Chrysoprase", "Citrine", "Jasper",
+           "Moonstone", "Onyx", "Quartz", "Sardonyx", "Star rose quartz", "Zircon"]),
+    (100, &["Amber", "Amethyst", "Chrysoberyl", "Coral", "Garnet", "Jade", "Jet", "Pearl",
+            "Spinel", "Tourmaline"]),
+    (500, &["Alexandrite", "Aquamarine", "Black pearl", "Blue spinel", "Peridot", "Topaz"]),
+    (1000, &["Black opal", "Blue sapphire", "Emerald", "Fire opal", "Opal", "Star ruby",
+             "Star sapphire", "Yellow sapphire"]),
+    (5000, &["Black sapphire", "Diamond", "Jacinth", "Ruby"]),
+];
+
+const ART: [(u32, &[&str]); 5] = [
+    (25, &["Silver ewer", "Carved bone statuette", "Small gold bracelet", "Cloth-of-gold vestments",
+           "Black velvet mask stitched with silver thread", "Copper chalice with silver filigree",
+           "Pair of engraved bone dice", "Small mirror set in a painted wooden frame",
+           "Embroidered silk handkerchief", "Gold locket with a painted portrait inside"]),
+    (250, &["Gold ring set with bloodstones", "Carved ivory statuette", "Large gold bracelet",
+            "Silver necklace with a gemstone pendant", "Bronze crown", "Silk robe with gold embroidery",
+            "Large well-made tapestry", "Brass mug with jade inlay", "Box of turquoise animal figurines",
+            "Gold bird cage with electrum filigree"]),
+    (750, &["Silver chalice set with moonstones", "Silver-plated steel longsword with jet set in hilt",
+            "Carved harp of exotic wood with ivory inlay and zircon gems", "Small gold idol",
+            "Gold dragon comb set with red garnets as eyes",
+            "Bottle stopper cork embossed with gold leaf and set with amethysts",
+            "Ceremonial electrum dagger with a black pearl in the pommel", "Silver and gold brooch",
+            "Obsidian statuette with gold fittings and inlay", "Painted gold war mask"]),
+    (2500, &["Fine gold chain set with a fire opal", "Old masterpiece painting",
+             "Embroidered silk and velvet mantle set with numerous moonstones",
+             "Platinum bracelet set with a sapphire", "Embroidered glove set with jewel chips",
+             "Jeweled anklet", "Gold music box", "Gold circlet set with four aquamarines",
+             "Eye patch with a mock eye set in blue sapphire and moonstone",
+             "A necklace string of small pink pearls"]),
+    (7500, &["Jeweled gold crown", "Jeweled platinum ring", "Small gold statuette set with rubies",
+             "Gold cup set with emeralds", "Gold jewelry box with platinum filigree",
+             "Painted gold child's sarcophagus", "Jade game board with solid gold playing pieces",
+             "Bejeweled ivory drinking horn with gold filigree"]),
+];
+
+/// Which of the four treasure bands a rating falls in.
+fn band(challenge: f32) -> usize {
+    match challenge {
+        c if c <= 4.0 => 0,
+        c if c <= 10.0 => 1,
+        c if c <= 16.0 => 2,
+        _ => 3,
+    }
+}
+
+fn percentile<T>(dice: &mut Dice, rows: &'static [T], upto: impl Fn(&T) -> u32) -> &'static T {
+    let roll = dice.d(100);
+    rows.iter().find(|row| roll <= upto(row)).unwrap_or(&rows[rows.len() - 1])
+}
+
+/// Rolls `count` valuables from one of `table`'s value lists and adds
+/// them to `found` by name.
+fn valuables(
+    dice: &mut Dice,
+    table: &[(u32, &[&'static str])],
+    value: u32,
+    count: u32,
+    found: &mut BTreeMap<(u32, &'static str), u32>,
+) {
+    let Some((_, names)) = table.iter().find(|(worth, _)| *worth == value) else {
+        return;
+    };
+    for _ in 0..count {
+        if let Some(name) = dice.pick(names) {
+            *found.entry((value, *name)).or_default() += 1;
+        }
+    }
+}
+
+fn listed(found: BTreeMap<(u32, &'static str), u32>) -> Vec<Valuable> {
+    found
+        .into_iter()
+        .map(|((value_gp, name), quantity)| Valuable { name: name.to_string(), value_gp, quantity })
+        .collect()
+}
+
+impl Treasure {
+    /// Rolls treasure at each of `challenges`, individual treasure once
+    /// per entry and a hoard once at the highest. Magic items are drawn
+    /// from the compendium.
+    pub async fn roll(con_str: String, kind: TreasureKind, challenges: &[f32], seed: Option<u32>) -> SResult<Treasure> {
+        // only hoards hold magic items
+        let items = match kind {
+            TreasureKind::Individual => Vec::new(),
+            TreasureKind::Hoard => {
+                let filter = ItemQuery { category: Some(ItemCategory::MagicItem), ..Default::default() };
+                Item::search(con_str, filter).await?
+            }
+        };
+        Ok(Treasure::roll_with(kind, challenges, seed.unwrap_or_else(Dice::random_seed), &items))
+    }
+
+    /// `roll` with the magic items to draw from already loaded.
+    fn roll_with(kind: TreasureKind, challenges: &[f32], seed: u32, items: &[Item]) -> Treasure {
+        let mut dice = Dice::new(seed);
+        let mut coins = Coins::default();
+        let mut gems = BTreeMap::new();
+        let mut art = BTreeMap::new();
+        let mut magic: Vec<Rarity> = Vec::new();
+
+        let highest = challenges.iter().copied().fold(0.0, f32::max);
+        match kind {
+            TreasureKind::Individual => {
+                for challenge in challenges {
+                    let row = percentile(&mut dice, INDIVIDUAL[band(*challenge)], |row| row.upto);
+                    for (coin, amount) in row.coins {
+                        coins.add(*coin, amount.roll(&mut dice));
+                    }
+                }
+            }
+            TreasureKind::Hoard => {
+                let band = band(highest);
+                for (coin, amount) in HOARD_COINS[band] {
+                    coins.add(*coin, amount.roll(&mut dice));
+                }
+                let row = percentile(&mut dice, HOARD[band], |row| row.upto);
+                match row.valuables {
+                    Some((Valuables::Gems(value), amount)) => {
+                        let count = amount.roll(&mut dice);
+                        valuables(&mut dice, &GEMS, value, count, &mut gems);
+                    }
+                    Some((Valuables::Art(value), amount)) => {
+                        let count = amount.roll(&mut dice);
+                        valuables(&mut dice, &ART, value, count, &mut art);
+                    }
+                    None => {}
+                }
+                for (table, amount) in row.magic {
+                    let count = amount.roll(&mut dice);
+                    magic.extend((0..count).map(|_| table.rarity()));
+                }
+            }
+        }
+
+        let mut by_rarity: HashMap<Rarity, Vec<&Item>> = HashMap::new();
+        for item in items {
+            if let Some(rarity) = item.magic.as_ref().map(|magic| magic.rarity) {
+                by_rarity.entry(rarity).or_default().push(item);
+            }
+        }
+        let magic_items = magic
+            .into_iter()
+            .filter_map(|rarity| {
+                let item = dice.pick(by_rarity.get(&rarity).map(Vec::as_slice).unwrap_or_default())?;
+                Some(TreasureItem { item_id: item.id, name: item.name.clone(), rarity })
+            })
+            .collect();
+
+        let gems = listed(gems);
+        let art = listed(art);
+        let valuables_gp: u64 = gems
+            .iter()
+            .chain(art.iter())
+            .map(|valuable| u64::from(valuable.value_gp) * u64::from(valuable.quantity))
+            .sum();
+        Treasure {
+            seed,
+            kind,
+            challenge: highest,
+            value_gp: coins.value_cp() as f64 / 100.0 + valuables_gp as f64,
+            coins,
+            gems,
+            art,
+            magic_items,
+        }
+    }
+
+    /// The treasure as session log loot, nobody carrying it yet. `note`
+    /// goes on every entry.
+    pub fn loot(&self, note: &str) -> Vec<LootEntry> {
+        let coins = [
+            ("Copper pieces", self.coins.cp),
+            ("Silver pieces", self.coins.sp),
+            ("Electrum pieces", self.coins.ep),
+            ("Gold pieces", self.coins.gp),
+            ("Platinum pieces", self.coins.pp),
+        ];
+        let entry = |name: String, item_id: Option<Uuid>, quantity: u32| LootEntry {
+            name,
+            item_id,
+            quantity: quantity as i32,
+            character_id: None,
+            notes: Some(note.to_string()),
+        };
+        coins
+            .into_iter()
+            .filter(|(_, amount)| *amount > 0)
+            .map(|(name, amount)| entry(name.to_string(), None, amount))
+            .chain(self.gems.iter().map(|gem| entry(format!("{} ({} gp gem)", gem.name, gem.value_gp), None, gem.quantity)))
+            .chain(self.art.iter().map(|art| entry(format!("{} ({} gp art object)", art.name, art.value_gp), None, art.quantity)))
+            .chain(self.magic_items.iter().map(|item| entry(item.name.clone(), Some(item.item_id), 1)))
+            .collect()
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+    use crate::models::item::MagicStats;
+
+    fn magic_item(name: &str, rarity: Rarity) -> Item {
+        Item {
+            id: Uuid::new_v4(),
+            name: name.to_string(),
+            category: ItemCategory::MagicItem,
+            magic: Some(MagicStats { item_type: "Wondrous item".to_string(), rarity, attunement: false, attunement_by: None }),
+            ..Default::default()
+        }
+    }
+
+    #[test]
+    fn same_seed_gives_same_treasure() {
+        let items: Vec<Item> = [Rarity::Common, Rarity::Uncommon, Rarity::Rare, Rarity::VeryRare, Rarity::Legendary]
+            .into_iter()
+            .flat_map(|rarity| (0..3).map(move |n| magic_item(&format!("{rarity:?} {n}"), rarity)))
+            .collect();
+        for seed in 0..200 {
+            for (kind, challenges) in [
+                (TreasureKind::Individual, vec![0.25, 3.0, 9.0, 14.0, 22.0]),
+                (TreasureKind::Hoard, vec![2.0]),
+                (TreasureKind::Hoard, vec![8.0]),
+                (TreasureKind::Hoard, vec![13.0]),
+                (TreasureKind::Hoard, vec![20.0]),
+            ] {
+                let first = Treasure::roll_with(kind, &challenges, seed, &items);
+                let again = Treasure::roll_with(kind, &challenges, seed, &items);
+                assert_eq!(serde_json::to_value(&first).unwrap(), serde_json::to_value(&again).unwrap());
+                for item in &first.magic_items {
+                    assert!(items.iter().any(|known| known.id == item.item_id));
+                }
+            }
+        }
+    }
+
+    #[test]
+    fn every_band_ends_at_100() {
+        for band in INDIVIDUAL {
+            assert!(band.windows(2).all(|rows| rows[0].upto < rows[1].upto));
+            assert_eq!(band.last().map(|row| row.upto), Some(100));
+        }
+        for band in HOARD {
+            assert!(band.windows(2).all(|rows| rows[0].upto < rows[1].upto));
+            assert_eq!(band.last().map(|row| row.upto), Some(100));
+        }
+    }
+}
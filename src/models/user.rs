@@ -1,8 +1,8 @@
-use std::env;
-
+use chrono::{DateTime, Utc};
 use sqlx::{
     query,
-    query_as, FromRow, query_as_with, query_with
+    FromRow, query_as_with, query_with,
+    Connection
 };
 use sea_query::{
     Iden,
@@ -10,54 +10,141 @@ use sea_query::{
     PostgresQueryBuilder,
     Expr,
     Table,
-    ColumnDef
+    ColumnDef,
+    OnConflict
 };
 use sea_query_binder::SqlxBinder;
 use serde::{Deserialize, Serialize};
-use tracing::debug;
+use tracing::{debug, instrument};
 use utoipa::ToSchema;
-use uuid::{Uuid, fmt::Hyphenated};
+use uuid::Uuid;
+use validator::{Validate, ValidationError};
 use argon2::{self, Config, Variant, Version};
 
-use crate::traits::repo::{Repo, connect};
+use crate::{traits::{repo::{Repo, connect}, audit::Auditable}, utilities::metrics::{time_argon2, QueryTimer}};
 
 use super::errors::{YaddakError, SResult};
+use super::monsters::MonsterModel;
+use super::character::CharacterModel;
+use super::campaign::hand_over_campaigns;
 
-#[derive(Serialize, Deserialize, Debug,
+#[derive(Serialize, Deserialize,
          Clone, Default, FromRow,
-         ToSchema)]
+         ToSchema, Validate)]
 pub struct User {
     pub id: Uuid,
+    #[validate(length(min = 3, max = 32, message = "must be between 3 and 32 characters"))]
+    #[schema(min_length = 3, max_length = 32)]
     pub user_name: String,
+    #[validate(email(message = "must be a valid email address"))]
+    #[schema(format = "email")]
     pub user_email: String,
     pub user_auth: String,
 }
 
-#[derive(Deserialize, Debug, Clone,
+/// What other users see of an account. `user_auth` is the bearer token,
+/// so it only goes back to its owner at sign in.
+#[derive(Serialize, Debug,
+         Clone, Default,
          ToSchema)]
+pub struct UserProfile {
+    pub id: Uuid,
+    pub user_name: String,
+    pub user_email: String,
+}
+
+impl From<User> for UserProfile {
+    fn from(user: User) -> Self {
+        UserProfile { id: user.id, user_name: user.user_name, user_email: user.user_email }
+    }
+}
+
+/// Body for changing a user's name or email. The password and token are
+/// not changed here.
+#[derive(Deserialize, Debug, Clone,
+         ToSchema, Validate)]
+pub struct UserRequest {
+    #[validate(length(min = 3, max = 32, message = "must be between 3 and 32 characters"))]
+    #[schema(min_length = 3, max_length = 32)]
+    pub user_name: String,
+    #[validate(email(message = "must be a valid email address"))]
+    #[schema(format = "email")]
+    pub user_email: String,
+}
+
+#[derive(Deserialize, Clone,
+         ToSchema, Validate)]
 pub struct CreateUserRequest {
+    #[validate(length(min = 3, max = 32, message = "must be between 3 and 32 characters"))]
+    #[schema(min_length = 3, max_length = 32)]
     pub user_name: String,
+    #[validate(email(message = "must be a valid email address"))]
+    #[schema(format = "email")]
     pub user_email: String,
+    #[validate(
+        length(min = 8, max = 128, message = "must be between 8 and 128 characters"),
+        custom(function = "validate_password_strength")
+    )]
+    #[schema(format = Password, min_length = 8, max_length = 128)]
     pub user_pass: String,
 }
 
-#[derive(Deserialize, Debug, Clone,
-         ToSchema)]
+#[derive(Deserialize, Clone,
+         ToSchema, Validate)]
 pub struct LoginUserRequest {
+    #[validate(length(min = 1, message = "is required"))]
+    #[schema(min_length = 1)]
     pub user_name: String,
+    #[validate(length(min = 1, message = "is required"))]
+    #[schema(format = Password, min_length = 1)]
     pub user_pass: String,
 }
 
-impl User {
-    pub fn new() -> Self {
-        Self {
-            id: Uuid::nil(),
-            user_name: String::new(),
-            user_email: String::new(),
-            user_auth: String::new(),
-        }
+// Debug is written by hand so credentials never reach the logs.
+impl std::fmt::Debug for User {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("User")
+            .field("id", &self.id)
+            .field("user_name", &self.user_name)
+            .field("user_email", &self.user_email)
+            .field("user_auth", &"[redacted]")
+            .finish()
     }
+}
 
+impl std::fmt::Debug for CreateUserRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("CreateUserRequest")
+            .field("user_name", &self.user_name)
+            .field("user_email", &self.user_email)
+            .field("user_pass", &"[redacted]")
+            .finish()
+    }
+}
+
+impl std::fmt::Debug for LoginUserRequest {
+    fn fmt(&self, f: &mut std::fmt::Formatter<'_>) -> std::fmt::Result {
+        f.debug_struct("LoginUserRequest")
+            .field("user_name", &self.user_name)
+            .field("user_pass", &"[redacted]")
+            .finish()
+    }
+}
+
+/// Passwords need at least one letter and one digit on top of the length bound.
+fn validate_password_strength(pass: &str) -> Result<(), ValidationError> {
+    let has_letter = pass.chars().any(char::is_alphabetic);
+    let has_digit = pass.chars().any(|c| c.is_ascii_digit());
+    if has_letter && has_digit {
+        Ok(())
+    } else {
+        let mut err = ValidationError::new("password_strength");
+        err.message = Some("must contain at least one letter and one digit".into());
+        Err(err)
+    }
+}
+
+impl User {
     pub async fn create(
         con_str: String,
         user_name: String,
@@ -82,19 +169,18 @@ impl User {
     }
 
     fn hash_password(&mut self, user_pass: String) -> SResult<()> {
-        // Retrieve salt from environment variabl
-        let salt: String = env::var("YADDAK_SECRET")?; 
+        let salt = &crate::config::get().auth.secret;
 
-        let config = new_config(&salt.as_bytes());
+        let config = new_config(salt.as_bytes());
 
         // Concatenate data for hashing
         let concat_data = format!("{}:{}", self.user_name, user_pass);
 
         // Hash the password
-        self.user_auth = argon2::hash_encoded(
+        self.user_auth = time_argon2(|| argon2::hash_encoded(
             concat_data.as_bytes(),
             self.id.as_bytes(),
-            &config)?
+            &config))?
             .rsplit('$')
             .next()
             .unwrap()
@@ -117,13 +203,13 @@ impl User {
             user_pass,
         );
 
-        let salt: String = env::var("YADDAK_SECRET")?; 
+        let salt = &crate::config::get().auth.secret;
         let config = new_config(salt.as_bytes());
        
-        let generated_auth = argon2::hash_encoded(
+        let generated_auth = time_argon2(|| argon2::hash_encoded(
             concat_data.as_bytes(),
             self.id.as_bytes(),
-            &config)?
+            &config))?
             .rsplit('$')
             .next()
             .unwrap()
@@ -136,22 +222,103 @@ impl User {
         Ok(())
     }
 
+    /// Replaces the password of an active user, for operators locked out
+    /// of the API.
+    pub async fn reset_password(con_str: String, user_name: String, user_pass: String) -> SResult<User> {
+        let mut user = User::get_user_by_name(con_str.clone(), user_name).await?;
+        user.hash_password(user_pass)?;
+        User::put(con_str, user.id, &user).await?;
+        Ok(user)
+    }
+
+    pub async fn set_admin(con_str: String, id: Uuid, is_admin: bool) -> SResult<()> {
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::update()
+            .table(UserModel::Table)
+            .value(UserModel::IsAdmin, is_admin)
+            .and_where(Expr::col(UserModel::Id).eq(id))
+            .build_sqlx(PostgresQueryBuilder);
+
+        let result = query_with(&sql, values)
+            .execute(&mut *client)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(YaddakError::not_found(format!("No user with id {id}")));
+        }
+        Ok(())
+    }
+
+    /// Ids of every active admin.
+    pub async fn admin_ids(con_str: String) -> SResult<Vec<Uuid>> {
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .from(UserModel::Table)
+            .column(UserModel::Id)
+            .and_where(Expr::col(UserModel::IsAdmin).eq(true))
+            .and_where(Expr::col(UserModel::DeletedAt).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<(Uuid,)> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        Ok(rows.into_iter().map(|(id,)| id).collect())
+    }
+
+    /// Whether a user with `id` exists, soft deleted or not.
+    pub async fn exists(con_str: String, id: Uuid) -> SResult<bool> {
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .from(UserModel::Table)
+            .column(UserModel::Id)
+            .and_where(Expr::col(UserModel::Id).eq(id))
+            .build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<(Uuid,)> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        Ok(!rows.is_empty())
+    }
+
+    /// Creates the account that owns bundled content. Its `user_auth` is
+    /// empty, which `auth_handler` never accepts, so it cannot sign in.
+    pub async fn ensure_system_user(con_str: String, id: Uuid, user_name: &str) -> SResult<()> {
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::insert()
+            .into_table(UserModel::Table)
+            .columns([UserModel::Id, UserModel::UserName, UserModel::UserEmail, UserModel::UserAuth])
+            .values_panic([
+                id.into(),
+                user_name.into(),
+                format!("{user_name}@yaddak.invalid").into(),
+                String::new().into(),
+            ])
+            .on_conflict(OnConflict::column(UserModel::Id).do_nothing().to_owned())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let _ = query_with(&sql, values)
+            .execute(&mut *client)
+            .await?;
+        Ok(())
+    }
+
     pub async fn user_email_not_used(con_str: String, user_email: String) -> SResult<()> {
         let mut client = connect(con_str).await?;
 
+        // soft deleted users keep their email reserved so they can be restored
         let (sql, values) = Query::select()
             .from(UserModel::Table)
+            .column(UserModel::Id)
             .limit(1)
             .and_where(Expr::col(UserModel::UserEmail).eq(user_email))
             .build_sqlx(PostgresQueryBuilder);
 
 
-        let rows: Vec<User> = query_as_with(&sql, values)
+        let rows: Vec<_> = query_with(&sql, values)
             .fetch_all(&mut *client)
             .await?;
 
         if !rows.is_empty() {
-            return Err(YaddakError::authorize_error("That email is already used".to_string()));
+            return Err(YaddakError::conflict("That email is already used".to_string()));
         }
 
         Ok(())
@@ -160,10 +327,11 @@ impl User {
     pub async fn user_name_not_used(con_str: String, user_name: String) -> SResult<()> {
         let rows = get_users_by_username(
             con_str,
-            user_name
+            user_name,
+            true
         ).await?;
         if !rows.is_empty() {
-            return Err(YaddakError::authorize_error("Username is already used".to_string()));
+            return Err(YaddakError::conflict("Username is already used".to_string()));
         }
 
         Ok(())
@@ -175,47 +343,78 @@ impl User {
     ) -> SResult<User> {
         let rows = get_users_by_username(
             con_str,
-            user_name.clone()
+            user_name.clone(),
+            false
         ).await?;
-        debug!("{:?}",rows);
-        debug!("{:?}",user_name);
+        debug!(user_name, matches = rows.len(), "looked up user by name");
         if rows.len() != 1 {
-            return Err(YaddakError::authorize_error("Not Found".to_string()));
+            Err(YaddakError::not_found(format!("No user named {user_name}")))
         } else {
-            Ok(User::from(rows[0].clone()))
+            Ok(rows[0].clone())
         }
     }
 
+    /// Resolves an auth header to the id of the user it belongs to.
     pub async fn check_auth(
         con_str: String,
         auth_header:String
-    ) -> SResult<()> {
+    ) -> SResult<Uuid> {
         let mut client = connect(con_str).await?;
         let (sql, values) = Query::select()
             .from(UserModel::Table)
             .columns([
-                UserModel::UserAuth,
+                UserModel::Id,
             ]) 
             .and_where(Expr::col(UserModel::UserAuth).eq(auth_header))
+            .and_where(Expr::col(UserModel::DeletedAt).is_null())
             .build_sqlx(PostgresQueryBuilder);
 
-        let rows: Vec<_> = query_with(&sql, values.clone())
+        let rows: Vec<(Uuid,)> = query_as_with(&sql, values.clone())
             .fetch_all(&mut *client)
             .await?;
 
-        if rows.is_empty() {
-            return Err(YaddakError::authorize_error("Could not authenticate".to_string()));
+        match rows.first() {
+            Some((id,)) => Ok(*id),
+            None => Err(YaddakError::authorize_error("Could not authenticate".to_string())),
+        }
+    }
+
+    /// Same as `check_auth` but only for users flagged `is_admin`.
+    pub async fn check_admin(
+        con_str: String,
+        auth_header: String
+    ) -> SResult<Uuid> {
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .from(UserModel::Table)
+            .columns([
+                UserModel::Id,
+                UserModel::IsAdmin,
+            ])
+            .and_where(Expr::col(UserModel::UserAuth).eq(auth_header))
+            .and_where(Expr::col(UserModel::DeletedAt).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<(Uuid, bool)> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+
+        match rows.first() {
+            Some((id, true)) => Ok(*id),
+            Some((_, false)) => Err(YaddakError::forbidden("Admin access required".to_string())),
+            None => Err(YaddakError::authorize_error("Could not authenticate".to_string())),
         }
-        Ok(())
     }
 }
 
 async fn get_users_by_username(
     con_str: String,
-    user_name: String
+    user_name: String,
+    include_deleted: bool
 ) -> SResult<Vec<User>> {
     let mut client = connect(con_str).await?;
-    let (sql, values) = Query::select()
+    let mut select = Query::select();
+    select
         .from(UserModel::Table)
         .columns([
             UserModel::Id,
@@ -223,8 +422,11 @@ async fn get_users_by_username(
             UserModel::UserName,
             UserModel::UserAuth,
         ]) 
-        .and_where(Expr::col(UserModel::UserName).eq(user_name))
-        .build_sqlx(PostgresQueryBuilder);
+        .and_where(Expr::col(UserModel::UserName).eq(user_name));
+    if !include_deleted {
+        select.and_where(Expr::col(UserModel::DeletedAt).is_null());
+    }
+    let (sql, values) = select.build_sqlx(PostgresQueryBuilder);
 
     let rows: Vec<User> = query_as_with(&sql, values.clone())
         .fetch_all(&mut *client)
@@ -233,15 +435,32 @@ async fn get_users_by_username(
     Ok(rows)
 }
 
-fn new_config<'a>(salt: &[u8])
--> Config {
+impl Auditable for User {
+    const ENTITY_TYPE: &'static str = "user";
+
+    fn entity_id(&self) -> Uuid {
+        self.id
+    }
+
+    fn snapshot(&self) -> serde_json::Value {
+        let mut value = serde_json::to_value(self).unwrap_or_default();
+        if let Some(fields) = value.as_object_mut() {
+            fields.remove("user_auth");
+        }
+        value
+    }
+}
+
+fn new_config(salt: &[u8])
+-> Config<'_> {
+    let auth = &crate::config::get().auth;
     Config {
         hash_length: 32,
         ad: &[],
-        lanes: 4,
-        mem_cost: 65536,
+        lanes: auth.argon2_lanes,
+        mem_cost: auth.argon2_mem_cost,
         secret: salt,
-        time_cost: 10,
+        time_cost: auth.argon2_time_cost,
         variant: Variant::Argon2i,
         version: Version::Version13
     }
@@ -254,11 +473,15 @@ pub(crate) enum UserModel {
     Id,
     UserName,
     UserAuth,
-    UserEmail
+    UserEmail,
+    IsAdmin,
+    DeletedAt,
 }
 
 impl Repo<'_, User> for User {
+    #[instrument(name = "user.get", skip_all, fields(db.system = "postgresql"))]
     async fn get(con_str: String, id: Uuid) -> SResult<User> {
+        let _timer = QueryTimer::new("user", "get");
         let mut client = connect(con_str).await?;
 
         let (sql, values) = Query::select()
@@ -271,19 +494,21 @@ impl Repo<'_, User> for User {
             .from(UserModel::Table)
             .limit(1)
             .and_where(Expr::col(UserModel::Id).eq(id))
+            .and_where(Expr::col(UserModel::DeletedAt).is_null())
             .build_sqlx(PostgresQueryBuilder);
 
-        debug!("query:\t{}", sql.clone());
-        debug!("values:\t{:?}", values.clone());
+        debug!("query:\t{}", sql);
         let rows: User = query_as_with(&sql, values.clone())
             .fetch_one(&mut *client)
             .await?;
 
-        Ok(rows.into())
+        Ok(rows)
             
     }
 
+    #[instrument(name = "user.get_all", skip_all, fields(db.system = "postgresql"))]
     async fn get_all(con_str: String ) -> SResult<Vec<User>> {
+        let _timer = QueryTimer::new("user", "get_all");
         let mut client = connect(con_str).await?;
         let (sql, values) = Query::select()
             .columns([
@@ -293,6 +518,7 @@ impl Repo<'_, User> for User {
                 UserModel::UserAuth,
             ]) 
             .from(UserModel::Table)
+            .and_where(Expr::col(UserModel::DeletedAt).is_null())
             .build_sqlx(PostgresQueryBuilder);
 
         let users: Vec<User> = query_as_with(&sql, values)
@@ -302,7 +528,9 @@ impl Repo<'_, User> for User {
         Ok(users)
     }
 
+    #[instrument(name = "user.post", skip_all, fields(db.system = "postgresql"))]
     async fn post(con_str: String, model: &User) ->  SResult<()> {
+        let _timer = QueryTimer::new("user", "post");
         let mut client = connect(con_str).await?;
         let (sql, values) = Query::insert()
             .into_table(UserModel::Table)
@@ -326,7 +554,9 @@ impl Repo<'_, User> for User {
         Ok(())
     }
 
+    #[instrument(name = "user.put", skip_all, fields(db.system = "postgresql"))]
     async fn put(con_str: String, id: Uuid, model: &User) -> SResult<()> {
+        let _timer = QueryTimer::new("user", "put");
         let mut client = connect(con_str).await?;
         let (sql, values) = Query::update()
             .table(UserModel::Table)
@@ -337,6 +567,7 @@ impl Repo<'_, User> for User {
 
             ])
             .and_where(Expr::col(UserModel::Id).eq(id))
+            .and_where(Expr::col(UserModel::DeletedAt).is_null())
             .build_sqlx(PostgresQueryBuilder);
         
         let _ = query_with(sql.as_str(), values)
@@ -347,19 +578,145 @@ impl Repo<'_, User> for User {
         
     }
 
+    /// Soft deletes the user and every monster and character they own.
+    /// Those are stamped with the same `deleted_at` so `restore` can bring
+    /// back exactly the rows that were removed alongside the user.
+    #[instrument(name = "user.delete", skip_all, fields(db.system = "postgresql"))]
     async fn delete(con_str: String, id: Uuid) -> SResult<()> {
+        let _timer = QueryTimer::new("user", "delete");
         let mut client = connect(con_str).await?;
+        let mut tx = client.begin().await?;
+        let now = Utc::now();
+
         let (sql, values) = Query::update()
             .table(UserModel::Table)
+            .value(UserModel::DeletedAt, now)
             .and_where(Expr::col(UserModel::Id).eq(id))
+            .and_where(Expr::col(UserModel::DeletedAt).is_null())
             .build_sqlx(PostgresQueryBuilder);
         
+        let result = query_with(sql.as_str(), values)
+            .execute(&mut *tx)
+            .await?;
+        if result.rows_affected() == 0 {
+            return Err(YaddakError::not_found(format!("No user with id {id} to delete")));
+        }
+
+        let (sql, values) = Query::update()
+            .table(MonsterModel::Table)
+            .value(MonsterModel::DeletedAt, now)
+            .and_where(Expr::col(MonsterModel::UserId).eq(id))
+            .and_where(Expr::col(MonsterModel::DeletedAt).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
         let _ = query_with(sql.as_str(), values)
-            .execute(&mut *client)
+            .execute(&mut *tx)
+            .await?;
+
+        let (sql, values) = Query::update()
+            .table(CharacterModel::Table)
+            .value(CharacterModel::DeletedAt, now)
+            .and_where(Expr::col(CharacterModel::UserId).eq(id))
+            .and_where(Expr::col(CharacterModel::DeletedAt).is_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let _ = query_with(sql.as_str(), values)
+            .execute(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(())
+    }
+
+    #[instrument(name = "user.restore", skip_all, fields(db.system = "postgresql"))]
+    async fn restore(con_str: String, id: Uuid) -> SResult<()> {
+        let _timer = QueryTimer::new("user", "restore");
+        let mut client = connect(con_str).await?;
+        let mut tx = client.begin().await?;
+
+        let (sql, values) = Query::select()
+            .column(UserModel::DeletedAt)
+            .from(UserModel::Table)
+            .and_where(Expr::col(UserModel::Id).eq(id))
+            .and_where(Expr::col(UserModel::DeletedAt).is_not_null())
+            .build_sqlx(PostgresQueryBuilder);
+
+        let deleted_at: Option<(DateTime<Utc>,)> = query_as_with(&sql, values)
+            .fetch_optional(&mut *tx)
+            .await?;
+        let Some((deleted_at,)) = deleted_at else {
+            return Err(YaddakError::not_found(format!("No deleted user with id {id} to restore")));
+        };
+
+        let (sql, values) = Query::update()
+            .table(UserModel::Table)
+            .value(UserModel::DeletedAt, Option::<DateTime<Utc>>::None)
+            .and_where(Expr::col(UserModel::Id).eq(id))
+            .build_sqlx(PostgresQueryBuilder);
+
+        let _ = query_with(sql.as_str(), values)
+            .execute(&mut *tx)
+            .await?;
+
+        let (sql, values) = Query::update()
+            .table(MonsterModel::Table)
+            .value(MonsterModel::DeletedAt, Option::<DateTime<Utc>>::None)
+            .and_where(Expr::col(MonsterModel::UserId).eq(id))
+            .and_where(Expr::col(MonsterModel::DeletedAt).eq(deleted_at))
+            .build_sqlx(PostgresQueryBuilder);
+
+        let _ = query_with(sql.as_str(), values)
+            .execute(&mut *tx)
+            .await?;
+
+        let (sql, values) = Query::update()
+            .table(CharacterModel::Table)
+            .value(CharacterModel::DeletedAt, Option::<DateTime<Utc>>::None)
+            .and_where(Expr::col(CharacterModel::UserId).eq(id))
+            .and_where(Expr::col(CharacterModel::DeletedAt).eq(deleted_at))
+            .build_sqlx(PostgresQueryBuilder);
+
+        let _ = query_with(sql.as_str(), values)
+            .execute(&mut *tx)
             .await?;
+
+        tx.commit().await?;
         Ok(())
     }
 
+    /// Hard deletes users soft deleted before `older_than`. Their monsters
+    /// and characters go with them through the `FK_User` and
+    /// `FK_Character_User` cascades. Campaigns they created stay, handed
+    /// to another DM first.
+    #[instrument(name = "user.purge", skip_all, fields(db.system = "postgresql"))]
+    async fn purge(con_str: String, older_than: DateTime<Utc>) -> SResult<Vec<Uuid>> {
+        let _timer = QueryTimer::new("user", "purge");
+        let mut client = connect(con_str).await?;
+        let purged = Query::select()
+            .column(UserModel::Id)
+            .from(UserModel::Table)
+            .and_where(Expr::col(UserModel::DeletedAt).lt(older_than))
+            .to_owned();
+        let mut tx = client.begin().await?;
+
+        let (sql, values) = hand_over_campaigns(purged);
+        let _ = query_with(sql.as_str(), values)
+            .execute(&mut *tx)
+            .await?;
+
+        let (sql, values) = Query::delete()
+            .from_table(UserModel::Table)
+            .and_where(Expr::col(UserModel::DeletedAt).lt(older_than))
+            .returning_col(UserModel::Id)
+            .build_sqlx(PostgresQueryBuilder);
+        let rows: Vec<(Uuid,)> = query_as_with(&sql, values)
+            .fetch_all(&mut *tx)
+            .await?;
+
+        tx.commit().await?;
+        Ok(rows.into_iter().map(|(id,)| id).collect())
+    }
+
     async fn migrate(con_str: String ) -> SResult<()> {
         let mut client = connect(con_str).await?;
         let sql = Table::create()
@@ -374,6 +731,19 @@ impl Repo<'_, User> for User {
             .col(ColumnDef::new(UserModel::UserAuth).string().not_null())
             .col(ColumnDef::new(UserModel::UserName).string().not_null())
             .col(ColumnDef::new(UserModel::UserEmail).string().not_null())
+            .col(ColumnDef::new(UserModel::IsAdmin).boolean().not_null().default(false))
+            .col(ColumnDef::new(UserModel::DeletedAt).timestamp_with_time_zone().null())
+            .build(PostgresQueryBuilder);
+
+        let _ = query(sql.as_str())
+            .execute(&mut *client)
+            .await?;
+
+        // tables created before soft delete existed need the new columns
+        let sql = Table::alter()
+            .table(UserModel::Table)
+            .add_column_if_not_exists(ColumnDef::new(UserModel::IsAdmin).boolean().not_null().default(false))
+            .add_column_if_not_exists(ColumnDef::new(UserModel::DeletedAt).timestamp_with_time_zone().null())
             .build(PostgresQueryBuilder);
 
         let _ = query(sql.as_str())
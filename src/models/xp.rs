@@ -0,0 +1,275 @@
+use chrono::{DateTime, Utc};
+use sea_query::{Iden, Table, ColumnDef, PostgresQueryBuilder, Query, Expr, Func, ForeignKey, ForeignKeyAction, Index, Order};
+use sea_query_binder::SqlxBinder;
+use serde::{Serialize, Deserialize};
+use sqlx::{Connection, FromRow, PgConnection, query, query_as_with, query_with};
+use tracing::instrument;
+use utoipa::ToSchema;
+use uuid::Uuid;
+use validator::Validate;
+
+use crate::{traits::repo::connect, utilities::metrics::QueryTimer};
+
+use super::{
+    campaign::CampaignModel,
+    character::{Character, CharacterModel},
+    errors::SResult,
+    session::{Session, SessionLog, SessionModel},
+};
+
+/// XP given to one character, kept so the total can be explained.
+/// Awards are never edited, a correction is another award.
+#[derive(Serialize, Deserialize, Debug,
+         Clone, Default, FromRow,
+         ToSchema)]
+pub struct XpAward {
+    pub id: Uuid,
+    pub character_id: Uuid,
+    pub campaign_id: Uuid,
+    pub session_id: Option<Uuid>,
+    pub xp: i32,
+    /// Given to reach the next level in a milestone campaign.
+    pub milestone: bool,
+    pub reason: Option<String>,
+    pub awarded_by: Uuid,
+    pub created_at: DateTime<Utc>,
+}
+
+/// Monsters the party defeated, `count` of each.
+#[derive(Serialize, Deserialize, Debug, Clone,
+         ToSchema, Validate)]
+pub struct Defeated {
+    pub monster_id: Uuid,
+    #[serde(default = "one")]
+    #[validate(range(min = 1, max = 100, message = "must be between 1 and 100"))]
+    #[schema(minimum = 1, maximum = 100, default = 1)]
+    pub count: i32,
+}
+
+fn one() -> i32 {
+    1
+}
+
+/// Ends a combat session: the XP of `defeated` plus `bonus_xp` is split
+/// evenly between `characters`.
+#[derive(Deserialize, Debug, Clone,
+         ToSchema, Validate)]
+pub struct AwardRequest {
+    #[serde(default)]
+    #[validate(nested, length(max = 100, message = "must have at most 100 entries"))]
+    pub defeated: Vec<Defeated>,
+    /// Characters who took part, each gets an equal share.
+    #[validate(length(min = 1, max = 100, message = "must have between 1 and 100 entries"))]
+    pub characters: Vec<Uuid>,
+    /// XP for anything that was not a monster, such as a trap or a parley.
+    #[serde(default)]
+    #[validate(range(min = 0, max = 1000000, message = "must be between 0 and 1000000"))]
+    #[schema(minimum = 0, maximum = 1000000)]
+    pub bonus_xp: i32,
+    #[serde(default)]
+    #[validate(length(max = 1000, message = "must be at most 1000 characters"))]
+    pub reason: Option<String>,
+}
+
+/// Raises `characters` to their next level in a milestone campaign.
+#[derive(Deserialize, Debug, Clone,
+         ToSchema, Validate)]
+pub struct MilestoneRequest {
+    #[validate(length(min = 1, max = 100, message = "must have between 1 and 100 entries"))]
+    pub characters: Vec<Uuid>,
+    #[serde(default)]
+    pub session_id: Option<Uuid>,
+    #[serde(default)]
+    #[validate(length(max = 1000, message = "must be at most 1000 characters"))]
+    pub reason: Option<String>,
+}
+
+/// Where one character stands after an award.
+#[derive(Serialize, Debug, Clone, Default, ToSchema)]
+pub struct AwardOutcome {
+    pub character_id: Uuid,
+    pub name: String,
+    pub xp_awarded: i32,
+    pub total_xp: i32,
+    /// Sum of the character's class levels.
+    pub level: i16,
+    /// The level `total_xp` reaches.
+    pub xp_level: i16,
+    /// The player has a level to take.
+    pub level_up: bool,
+}
+
+#[derive(Serialize, Debug, Clone, Default, ToSchema)]
+pub struct AwardReport {
+    /// XP before splitting.
+    pub total_xp: i32,
+    /// Each character's share, any remainder is dropped.
+    pub per_character: i32,
+    pub characters: Vec<AwardOutcome>,
+}
+
+impl XpAward {
+    /// Adds each award's XP to its character and records the award, and
+    /// with `log` also replaces that session's log, all in one transaction.
+    /// Returns each character's new total in the order of `awards`.
+    #[instrument(name = "xp_award.grant", skip_all, fields(db.system = "postgresql"))]
+    pub async fn grant(con_str: String, awards: &[XpAward], log: Option<(Uuid, &SessionLog)>) -> SResult<Vec<i32>> {
+        let _timer = QueryTimer::new("xp_award", "grant");
+        let mut client = connect(con_str).await?;
+        let mut tx = client.begin().await?;
+        let mut totals = Vec::with_capacity(awards.len());
+        for award in awards {
+            totals.push(Character::add_xp(&mut tx, award.character_id, award.xp).await?);
+            XpAward::record(&mut tx, award).await?;
+        }
+        if let Some((session_id, log)) = log {
+            Session::write_log(&mut tx, session_id, log).await?;
+        }
+        tx.commit().await?;
+        Ok(totals)
+    }
+
+    async fn record(tx: &mut PgConnection, award: &XpAward) -> SResult<()> {
+        let (sql, values) = Query::insert()
+            .into_table(XpAwardModel::Table)
+            .columns(XpAwardModel::cols())
+            .values_panic([
+                award.id.into(),
+                award.character_id.into(),
+                award.campaign_id.into(),
+                award.session_id.into(),
+                award.xp.into(),
+                award.milestone.into(),
+                award.reason.clone().into(),
+                award.awarded_by.into(),
+                award.created_at.into(),
+            ])
+            .build_sqlx(PostgresQueryBuilder);
+
+        let _ = query_with(&sql, values)
+            .execute(&mut *tx)
+            .await?;
+        Ok(())
+    }
+
+    #[instrument(name = "xp_award.count", skip_all, fields(db.system = "postgresql"))]
+    pub async fn count(con_str: String) -> SResult<i64> {
+        let _timer = QueryTimer::new("xp_award", "count");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .expr(Func::count(Expr::col(XpAwardModel::Id)))
+            .from(XpAwardModel::Table)
+            .build_sqlx(PostgresQueryBuilder);
+
+        let (count,): (i64,) = query_as_with(&sql, values)
+            .fetch_one(&mut *client)
+            .await?;
+        Ok(count)
+    }
+
+    /// Every award the character has had, newest first.
+    #[instrument(name = "xp_award.for_character", skip_all, fields(db.system = "postgresql"))]
+    pub async fn for_character(con_str: String, character_id: Uuid) -> SResult<Vec<XpAward>> {
+        let _timer = QueryTimer::new("xp_award", "for_character");
+        let mut client = connect(con_str).await?;
+        let (sql, values) = Query::select()
+            .columns(XpAwardModel::cols())
+            .from(XpAwardModel::Table)
+            .and_where(Expr::col(XpAwardModel::CharacterId).eq(character_id))
+            .order_by(XpAwardModel::CreatedAt, Order::Desc)
+            .build_sqlx(PostgresQueryBuilder);
+
+        let rows: Vec<XpAward> = query_as_with(&sql, values)
+            .fetch_all(&mut *client)
+            .await?;
+        Ok(rows)
+    }
+
+    /// Awards go with their character or campaign, and outlive the
+    /// session they came from.
+    pub async fn migrate(con_str: String) -> SResult<()> {
+        let mut client = connect(con_str).await?;
+        let sql = Table::create()
+            .table(XpAwardModel::Table)
+            .if_not_exists()
+            .col(
+                ColumnDef::new(XpAwardModel::Id)
+                    .uuid()
+                    .not_null()
+                    .primary_key(),
+            )
+            .col(ColumnDef::new(XpAwardModel::CharacterId).uuid().not_null())
+            .col(ColumnDef::new(XpAwardModel::CampaignId).uuid().not_null())
+            .col(ColumnDef::new(XpAwardModel::SessionId).uuid().null())
+            .col(ColumnDef::new(XpAwardModel::Xp).integer().not_null())
+            .col(ColumnDef::new(XpAwardModel::Milestone).boolean().not_null())
+            .col(ColumnDef::new(XpAwardModel::Reason).text().null())
+            .col(ColumnDef::new(XpAwardModel::AwardedBy).uuid().not_null())
+            .col(ColumnDef::new(XpAwardModel::CreatedAt).timestamp_with_time_zone().not_null())
+            .foreign_key(ForeignKey::create()
+                           .name("FK_XpAward_Character")
+                           .from(XpAwardModel::Table, XpAwardModel::CharacterId)
+                           .to(CharacterModel::Table, CharacterModel::Id)
+                           .on_delete(ForeignKeyAction::Cascade)
+                        )
+            .foreign_key(ForeignKey::create()
+                           .name("FK_XpAward_Campaign")
+                           .from(XpAwardModel::Table, XpAwardModel::CampaignId)
+                           .to(CampaignModel::Table, CampaignModel::Id)
+                           .on_delete(ForeignKeyAction::Cascade)
+                        )
+            .foreign_key(ForeignKey::create()
+                           .name("FK_XpAward_Session")
+                           .from(XpAwardModel::Table, XpAwardModel::SessionId)
+                           .to(SessionModel::Table, SessionModel::Id)
+                           .on_delete(ForeignKeyAction::SetNull)
+                        )
+            .build(PostgresQueryBuilder);
+
+        let _ = query(sql.as_str())
+            .execute(&mut *client)
+            .await?;
+
+        let sql = Index::create()
+            .if_not_exists()
+            .name("IDX_XpAward_Character")
+            .table(XpAwardModel::Table)
+            .col(XpAwardModel::CharacterId)
+            .build(PostgresQueryBuilder);
+
+        let _ = query(sql.as_str())
+            .execute(&mut *client)
+            .await?;
+        Ok(())
+    }
+}
+
+#[derive(Iden)]
+enum XpAwardModel {
+    Table,
+    Id,
+    CharacterId,
+    CampaignId,
+    SessionId,
+    Xp,
+    Milestone,
+    Reason,
+    AwardedBy,
+    CreatedAt,
+}
+
+impl XpAwardModel {
+    pub fn cols() -> Vec<Self> {
+        vec![
+            Self::Id,
+            Self::CharacterId,
+            Self::CampaignId,
+            Self::SessionId,
+            Self::Xp,
+            Self::Milestone,
+            Self::Reason,
+            Self::AwardedBy,
+            Self::CreatedAt,
+        ]
+    }
+}
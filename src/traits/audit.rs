@@ -0,0 +1,65 @@
+use serde::Serialize;
+use serde_json::Value;
+use tracing::warn;
+use uuid::Uuid;
+
+use crate::models::audit::{AuditLog, AuditAction, diff};
+
+/// Models whose changes are written to the audit log. New models only need
+/// to name themselves and hand back their id; `snapshot` can be overridden
+/// to keep secrets out of the log.
+pub trait Auditable: Serialize {
+    const ENTITY_TYPE: &'static str;
+
+    fn entity_id(&self) -> Uuid;
+
+    fn snapshot(&self) -> Value {
+        serde_json::to_value(self).unwrap_or(Value::Null)
+    }
+}
+
+/// Records `action` on the entity described by `before`/`after`. The change
+/// has already been committed by the time this runs, so a failure to write
+/// the audit row is logged rather than handed back to the caller.
+pub async fn audit<T: Auditable>(
+    con_str: String,
+    actor_id: Option<Uuid>,
+    action: AuditAction,
+    before: Option<&T>,
+    after: Option<&T>,
+) {
+    let Some(entity_id) = after.or(before).map(Auditable::entity_id) else {
+        return;
+    };
+    let changes = diff(
+        before.map(Auditable::snapshot).as_ref(),
+        after.map(Auditable::snapshot).as_ref(),
+    );
+    if let Err(err) = AuditLog::record(
+        con_str,
+        actor_id,
+        action,
+        T::ENTITY_TYPE,
+        entity_id,
+        changes,
+    ).await {
+        warn!("failed to write audit log for {} {entity_id}: {err}", T::ENTITY_TYPE);
+    }
+}
+
+/// Records that the purge job hard deleted the `T`s with `ids`. Nothing is
+/// left to snapshot, so the rows carry no changes.
+pub async fn audit_purge<T: Auditable>(con_str: String, ids: &[Uuid]) {
+    for &entity_id in ids {
+        if let Err(err) = AuditLog::record(
+            con_str.clone(),
+            None,
+            AuditAction::Purge,
+            T::ENTITY_TYPE,
+            entity_id,
+            diff(None, None),
+        ).await {
+            warn!("failed to write audit log for {} {entity_id}: {err}", T::ENTITY_TYPE);
+        }
+    }
+}
@@ -1,14 +1,121 @@
 pub mod repo;
+pub mod audit;
+pub mod object_store;
 
-use sqlx::PgPool;
+use std::{path::Path, time::Duration};
 
-use crate::models::{user::User, errors::SResult, monsters::Monster};
+use chrono::Utc;
+use sea_query::{Alias, PostgresQueryBuilder, Table};
+use tracing::{info, warn};
 
-use self::repo::Repo;
+use crate::models::{user::User, errors::SResult, monsters::Monster, audit::AuditLog, schema::{SchemaVersion, SCHEMA_VERSION}, monster_import::ImportReport, asset::Asset, character::Character, campaign::Campaign, session::Session, xp::XpAward, spell::Spell, item::Item};
 
+use self::{audit::audit_purge, object_store::ObjectStore, repo::{Repo, connect}};
+
+/// Brings the schema up to `SCHEMA_VERSION`. Safe to run on every start.
 pub async fn migrate(client: String) -> SResult<()> {
     User::migrate(client.clone()).await?;
     Monster::migrate(client.clone()).await?;
-    Monster::migrate_json(client.clone()).await?;
+    Spell::migrate(client.clone()).await?;
+    Item::migrate(client.clone()).await?;
+    Character::migrate(client.clone()).await?;
+    Campaign::migrate(client.clone()).await?;
+    Session::migrate(client.clone()).await?;
+    XpAward::migrate(client.clone()).await?;
+    AuditLog::migrate(client.clone()).await?;
+    Asset::migrate(client.clone()).await?;
+    SchemaVersion::migrate(client.clone()).await?;
+    SchemaVersion::record(client.clone(), SCHEMA_VERSION).await?;
+    Ok(())
+}
+
+/// Drops every table `migrate` creates, data included.
+pub async fn rollback(client: String) -> SResult<()> {
+    let mut con = connect(client).await?;
+    let sql = Table::drop()
+        .table(Alias::new("audit_log"))
+        .table(Alias::new("xp_award_model"))
+        .table(Alias::new("session_attendee_model"))
+        .table(Alias::new("session_model"))
+        .table(Alias::new("campaign_member_model"))
+        .table(Alias::new("campaign_character_model"))
+        .table(Alias::new("campaign_monster_model"))
+        .table(Alias::new("campaign_model"))
+        .table(Alias::new("asset_model"))
+        .table(Alias::new("monster_model"))
+        .table(Alias::new("spell_model"))
+        .table(Alias::new("item_model"))
+        .table(Alias::new("character_model"))
+        .table(Alias::new("user_model"))
+        .table(Alias::new("schema_version"))
+        .if_exists()
+        .build(PostgresQueryBuilder);
+    let _ = sqlx::query(&sql)
+        .execute(&mut *con)
+        .await?;
     Ok(())
 }
+
+/// Loads SRD monsters from `file`, or the bundled `monsters.json` in `storage.data_dir`.
+pub async fn seed(client: String, file: Option<&Path>) -> SResult<ImportReport> {
+    let bundled = Monster::bundled_seed();
+    Monster::seed(client, file.unwrap_or(&bundled)).await
+}
+
+/// Loads SRD spells from `file`, or the bundled `spells.json` in `storage.data_dir`.
+/// Returns how many were new.
+pub async fn seed_spells(client: String, file: Option<&Path>) -> SResult<u64> {
+    let bundled = Spell::bundled_seed();
+    Spell::seed(client, file.unwrap_or(&bundled)).await
+}
+
+/// Loads SRD items from `file`, or the bundled `items.json` in `storage.data_dir`.
+/// Returns how many were new.
+pub async fn seed_items(client: String, file: Option<&Path>) -> SResult<u64> {
+    let bundled = Item::bundled_seed();
+    Item::seed(client, file.unwrap_or(&bundled)).await
+}
+
+/// Hard deletes soft deleted rows once they are older than `retention`.
+/// Users go first so what they own is taken by the cascade, then the
+/// files of their assets are removed from `store`. Every row that goes is
+/// written to the audit log.
+pub async fn purge<S: ObjectStore>(client: String, store: &S, retention: chrono::Duration) -> SResult<u64> {
+    let cutoff = Utc::now() - retention;
+    let assets = Asset::purgeable(client.clone(), cutoff).await?;
+    let users = User::purge(client.clone(), cutoff).await?;
+    for asset in &assets {
+        for key in [asset.key(), asset.thumbnail_key()] {
+            // the row is gone either way, a file left behind is only space
+            if let Err(err) = store.delete(&key).await {
+                warn!("could not delete purged asset {key}: {err}");
+            }
+        }
+    }
+    let monsters = Monster::purge(client.clone(), cutoff).await?;
+    let characters = Character::purge(client.clone(), cutoff).await?;
+    let campaigns = Campaign::purge(client.clone(), cutoff).await?;
+    let sessions = Session::purge(client.clone(), cutoff).await?;
+
+    let asset_ids: Vec<_> = assets.iter().map(|asset| asset.id).collect();
+    audit_purge::<Asset>(client.clone(), &asset_ids).await;
+    audit_purge::<User>(client.clone(), &users).await;
+    audit_purge::<Monster>(client.clone(), &monsters).await;
+    audit_purge::<Character>(client.clone(), &characters).await;
+    audit_purge::<Campaign>(client.clone(), &campaigns).await;
+    audit_purge::<Session>(client, &sessions).await;
+    Ok((users.len() + monsters.len() + characters.len() + campaigns.len() + sessions.len()) as u64)
+}
+
+/// Runs `purge` every `every` for the lifetime of the server.
+pub async fn purge_job<S: ObjectStore>(client: String, store: S, retention: chrono::Duration, every: Duration) {
+    let mut interval = tokio::time::interval(every);
+    loop {
+        interval.tick().await;
+        match purge(client.clone(), &store, retention).await {
+            Ok(0) => {},
+            Ok(purged) => info!("purged {purged} soft deleted rows"),
+            Err(err) => warn!("purge failed: {err}"),
+        }
+    }
+}
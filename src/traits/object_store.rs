@@ -0,0 +1,15 @@
+use std::future::Future;
+
+use crate::models::errors::SResult;
+
+/// Where image bytes live. Keys are flat names chosen by the caller, such
+/// as an asset id; the store decides how they map onto its own layout.
+/// `LocalStore` keeps them on disk, another backend only has to provide
+/// these three operations.
+pub trait ObjectStore: Send + Sync {
+    fn put(&self, key: &str, bytes: &[u8]) -> impl Future<Output = SResult<()>> + Send;
+    /// `None` when nothing is stored under `key`.
+    fn get(&self, key: &str) -> impl Future<Output = SResult<Option<Vec<u8>>>> + Send;
+    /// Removing a key that is not there is not an error.
+    fn delete(&self, key: &str) -> impl Future<Output = SResult<()>> + Send;
+}
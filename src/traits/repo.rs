@@ -1,6 +1,10 @@
-use sqlx::{PgPool, Postgres};
+use chrono::{DateTime, Utc};
+use sqlx::{PgPool, Postgres, postgres::PgPoolOptions};
+use tokio::sync::OnceCell;
 use uuid::Uuid;
-use crate::models::errors::SResult;
+use crate::{config::DatabaseConfig, models::errors::SResult};
+
+static POOL: OnceCell<PgPool> = OnceCell::const_new();
 
 pub trait Repo<'a, T>
 where T: serde::Serialize + serde::Deserialize<'a> {
@@ -9,15 +13,36 @@ where T: serde::Serialize + serde::Deserialize<'a> {
     async fn get_all(con_str: String ) -> SResult<Vec<T>>;
     async fn post(con_str: String, model: &T) ->  SResult<()>;
     async fn put(con_str: String, id: Uuid, model: &T) -> SResult<()>;
+    /// Soft delete: stamps `deleted_at` so the row drops out of every read.
     async fn delete(con_str: String, id: Uuid) -> SResult<()>;
+    /// Clears `deleted_at` on a soft deleted row.
+    async fn restore(con_str: String, id: Uuid) -> SResult<()>;
+    /// Hard deletes rows soft deleted before `older_than`, returning the ids
+    /// of the rows that went.
+    async fn purge(con_str: String, older_than: DateTime<Utc>) -> SResult<Vec<Uuid>>;
+}
+
+/// Opens the shared pool with the configured sizes. Must run before the
+/// first `connect`, otherwise the pool is opened with sqlx defaults.
+pub async fn init_pool(database: &DatabaseConfig) -> SResult<&'static PgPool> {
+    let pool = POOL.get_or_try_init(|| async {
+        PgPoolOptions::new()
+            .max_connections(database.max_connections)
+            .min_connections(database.min_connections)
+            .connect(&database.url)
+            .await
+    }).await?;
+    Ok(pool)
+}
+
+/// The shared pool, once `init_pool` or the first `connect` has opened it.
+pub fn pool() -> Option<&'static PgPool> {
+    POOL.get()
 }
 
 pub async fn connect(con_str: String) -> SResult<sqlx::pool::PoolConnection<Postgres>> {
-    let connection = PgPool::connect(&con_str)
-        .await
-        .unwrap();
-    let conn = connection.acquire().await?;
+    let pool = POOL.get_or_try_init(|| PgPool::connect(&con_str)).await?;
+    let conn = pool.acquire().await?;
 
     Ok(conn)
 }
-
@@ -0,0 +1,83 @@
+use uuid::Uuid;
+
+/// A dice roller that gives the same rolls for the same seed, so a DM can
+/// reroll a result they liked or show the table how it came out. The
+/// generator is SplitMix64, small and good enough for dice, not for
+/// anything secret.
+pub struct Dice {
+    state: u64,
+}
+
+impl Dice {
+    pub fn new(seed: u32) -> Self {
+        Dice { state: u64::from(seed) }
+    }
+
+    /// A seed for when the caller did not pick one.
+    pub fn random_seed() -> u32 {
+        Uuid::new_v4().as_u128() as u32
+    }
+
+    fn next(&mut self) -> u64 {
+        self.state = self.state.wrapping_add(0x9E37_79B9_7F4A_7C15);
+        let mut z = self.state;
+        z = (z ^ (z >> 30)).wrapping_mul(0xBF58_476D_1CE4_E5B9);
+        z = (z ^ (z >> 27)).wrapping_mul(0x94D0_49BB_1331_11EB);
+        z ^ (z >> 31)
+    }
+
+    /// One die with `sides` faces, 1 to `sides`.
+    pub fn d(&mut self, sides: u32) -> u32 {
+        // widening multiply maps the full range onto 0..sides without
+        // the bias of a plain modulo
+        ((u128::from(self.next()) * u128::from(sides)) >> 64) as u32 + 1
+    }
+
+    /// The sum of `count` dice with `sides` faces each, `count`d`sides`.
+    pub fn roll(&mut self, count: u32, sides: u32) -> u32 {
+        (0..count).map(|_| self.d(sides)).sum()
+    }
+
+    /// One of `options`, each as likely as the others. `None` if there
+    /// are none.
+    pub fn pick<'a, T>(&mut self, options: &'a [T]) -> Option<&'a T> {
+        if options.is_empty() {
+            return None;
+        }
+        options.get(self.d(options.len() as u32) as usize - 1)
+    }
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn rolls_stay_on_the_die() {
+        let mut dice = Dice::new(7);
+        for sides in [1, 2, 4, 6, 8, 10, 12, 20, 100] {
+            let mut seen = vec![false; sides as usize];
+            for _ in 0..10_000 {
+                let roll = dice.d(sides);
+                assert!((1..=sides).contains(&roll), "d{sides} rolled {roll}");
+                seen[roll as usize - 1] = true;
+            }
+            assert!(seen.iter().all(|face| *face), "d{sides} never rolled some faces");
+        }
+    }
+
+    #[test]
+    fn same_seed_same_rolls() {
+        let mut first = Dice::new(42);
+        let mut again = Dice::new(42);
+        let rolls: Vec<u32> = (0..100).map(|_| first.roll(3, 6)).collect();
+        assert_eq!(rolls, (0..100).map(|_| again.roll(3, 6)).collect::<Vec<_>>());
+        assert_ne!(rolls, (0..100).map(|_| Dice::new(43).roll(3, 6)).collect::<Vec<_>>());
+    }
+
+    #[test]
+    fn pick_from_nothing_is_none() {
+        assert_eq!(Dice::new(1).pick::<u8>(&[]), None);
+        assert_eq!(Dice::new(1).pick(&["only"]), Some(&"only"));
+    }
+}
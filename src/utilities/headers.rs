@@ -1,18 +1,39 @@
+
 use axum::http;
-use hyper::StatusCode;
+use uuid::Uuid;
 
-use crate::models::errors::{YaddakError, SResult};
+use crate::models::{errors::{YaddakError, SResult}, user::User};
 
 pub fn auth_handler(headers: http::HeaderMap) -> SResult<String> {
-    let authorization: String;
     if let Some(header_value) = headers.get("Authorization") {
-        authorization = header_value.to_str().unwrap_or("").to_string();
+        let authorization = header_value.to_str().unwrap_or("").to_string();
         match authorization.as_str().rsplit(' ').next() {
-            Some(header) => return Ok(header.to_string()),
-            None => return Err(YaddakError::authorize_error("Authorization Header is empty".to_string()))
+            Some(header) if !header.is_empty() => Ok(header.to_string()),
+            _ => Err(YaddakError::authorize_error("Authorization Header is empty".to_string()))
         }
 
     } else {
         Err(YaddakError::authorize_error("No Authorization Header".to_string()))
     }
 }
+
+/// Resolves the Authorization header to the id of the signed in user.
+pub async fn authenticate(con_str: String, headers: http::HeaderMap) -> SResult<Uuid> {
+    let auth = auth_handler(headers)?;
+    User::check_auth(con_str, auth).await
+}
+
+/// Like `authenticate` but rejects users who are not admins.
+pub async fn authenticate_admin(con_str: String, headers: http::HeaderMap) -> SResult<Uuid> {
+    let auth = auth_handler(headers)?;
+    User::check_admin(con_str, auth).await
+}
+
+/// Like `authenticate` but only lets through `owner` or an admin.
+pub async fn authenticate_self_or_admin(con_str: String, headers: http::HeaderMap, owner: Uuid) -> SResult<Uuid> {
+    let actor = authenticate(con_str.clone(), headers).await?;
+    if actor != owner && !User::admin_ids(con_str).await?.contains(&actor) {
+        return Err(YaddakError::forbidden("Only the account's owner or an admin can do this".to_string()));
+    }
+    Ok(actor)
+}
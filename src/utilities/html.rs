@@ -0,0 +1,113 @@
+/// Tags that end a line of text. Stat block entries are wrapped in `<p>`,
+/// so keeping these as line breaks keeps one trait or action per line.
+const BLOCK_TAGS: [&str; 10] = ["p", "br", "div", "li", "ul", "ol", "tr", "h1", "h2", "h3"];
+
+/// Converts an HTML fragment to plain text: tags are dropped, block tags
+/// become line breaks, entities are decoded and runs of whitespace inside
+/// a line collapse to one space. Empty lines are removed.
+pub fn to_text(html: &str) -> String {
+    let mut text = String::with_capacity(html.len());
+    let mut rest = html;
+    while let Some(start) = rest.find(['<', '&']) {
+        text.push_str(&rest[..start]);
+        rest = &rest[start..];
+        if rest.starts_with('<') {
+            let Some(end) = rest.find('>') else {
+                // a stray `<` is text, not a tag
+                text.push('<');
+                rest = &rest[1..];
+                continue;
+            };
+            if BLOCK_TAGS.contains(&tag_name(&rest[1..end]).as_str()) {
+                text.push('\n');
+            }
+            rest = &rest[end + 1..];
+        } else {
+            let (decoded, consumed) = entity(rest);
+            text.push_str(&decoded);
+            rest = &rest[consumed..];
+        }
+    }
+    text.push_str(rest);
+
+    text.lines()
+        .map(|line| line.split_whitespace().collect::<Vec<_>>().join(" "))
+        .filter(|line| !line.is_empty())
+        .collect::<Vec<_>>()
+        .join("\n")
+}
+
+/// `/p`, `br /` and `P class="x"` all name their tag as `p` or `br`.
+fn tag_name(tag: &str) -> String {
+    tag.trim_start_matches('/')
+        .split(|c: char| c.is_whitespace() || c == '/')
+        .next()
+        .unwrap_or_default()
+        .to_ascii_lowercase()
+}
+
+/// Decodes the entity at the start of `input`, returning the text and how
+/// many bytes it used. Anything unrecognised is kept as a literal `&`.
+fn entity(input: &str) -> (String, usize) {
+    // bounded by chars, a byte bound can land inside a multibyte char
+    let Some((end, _)) = input.char_indices().take(12).find(|(_, c)| *c == ';') else {
+        return ("&".to_string(), 1);
+    };
+    let name = &input[1..end];
+    let decoded = match name {
+        "amp" => Some('&'),
+        "lt" => Some('<'),
+        "gt" => Some('>'),
+        "quot" => Some('"'),
+        "apos" => Some('\''),
+        "nbsp" => Some(' '),
+        "ndash" => Some('\u{2013}'),
+        "mdash" => Some('\u{2014}'),
+        "rsquo" => Some('\u{2019}'),
+        "lsquo" => Some('\u{2018}'),
+        "times" => Some('\u{d7}'),
+        _ => name.strip_prefix('#').and_then(|code| {
+            match code.strip_prefix(['x', 'X']) {
+                Some(hex) => u32::from_str_radix(hex, 16).ok(),
+                None => code.parse().ok(),
+            }
+            .and_then(char::from_u32)
+        }),
+    };
+    match decoded {
+        Some(c) => (c.to_string(), end + 1),
+        None => ("&".to_string(), 1),
+    }
+}
+
+/// Escapes text for use inside an element or a quoted attribute.
+pub fn escape(text: &str) -> String {
+    let mut escaped = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '&' => escaped.push_str("&amp;"),
+            '<' => escaped.push_str("&lt;"),
+            '>' => escaped.push_str("&gt;"),
+            '"' => escaped.push_str("&quot;"),
+            '\'' => escaped.push_str("&#39;"),
+            c => escaped.push(c),
+        }
+    }
+    escaped
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    #[test]
+    fn decodes_entities() {
+        assert_eq!(to_text("<p>Fire &amp; ice&nbsp;&#8212; &#x2019;</p>"), "Fire & ice \u{2014} \u{2019}");
+    }
+
+    #[test]
+    fn bare_ampersand_before_non_ascii_is_kept() {
+        assert_eq!(to_text("Fire &\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}\u{e9} ok"), "Fire &\u{e9}\u{e9}\u{e9}\u{e9}\u{e9}\u{e9} ok");
+        assert_eq!(to_text("&\u{1f525}\u{1f525}\u{1f525};"), "&\u{1f525}\u{1f525}\u{1f525};");
+    }
+}
@@ -0,0 +1,127 @@
+use chrono::{DateTime, Utc};
+
+/// Lines longer than this many octets are folded, per RFC 5545 3.1.
+const FOLD_AT: usize = 75;
+
+/// One `VEVENT`. Times are written in UTC so no `VTIMEZONE` is needed.
+pub struct Event<'a> {
+    pub uid: String,
+    pub start: DateTime<Utc>,
+    pub end: Option<DateTime<Utc>>,
+    pub summary: &'a str,
+    pub description: Option<&'a str>,
+    pub location: Option<&'a str>,
+    pub last_modified: DateTime<Utc>,
+}
+
+/// Renders a `VCALENDAR` named `name` holding `events`, with CRLF line
+/// endings and long lines folded.
+pub fn calendar(name: &str, events: &[Event]) -> String {
+    let mut lines = vec![
+        "BEGIN:VCALENDAR".to_string(),
+        "VERSION:2.0".to_string(),
+        format!("PRODID:-//Yaddak//Encounter Server {}//EN", env!("CARGO_PKG_VERSION")),
+        "CALSCALE:GREGORIAN".to_string(),
+        "METHOD:PUBLISH".to_string(),
+        format!("X-WR-CALNAME:{}", escape(name)),
+    ];
+    for event in events {
+        lines.push("BEGIN:VEVENT".to_string());
+        lines.push(format!("UID:{}", event.uid));
+        lines.push(format!("DTSTAMP:{}", timestamp(event.last_modified)));
+        lines.push(format!("DTSTART:{}", timestamp(event.start)));
+        if let Some(end) = event.end {
+            lines.push(format!("DTEND:{}", timestamp(end)));
+        }
+        lines.push(format!("SUMMARY:{}", escape(event.summary)));
+        if let Some(description) = event.description {
+            lines.push(format!("DESCRIPTION:{}", escape(description)));
+        }
+        if let Some(location) = event.location {
+            lines.push(format!("LOCATION:{}", escape(location)));
+        }
+        lines.push("END:VEVENT".to_string());
+    }
+    lines.push("END:VCALENDAR".to_string());
+
+    let mut out = String::new();
+    for line in lines {
+        fold(&line, &mut out);
+    }
+    out
+}
+
+fn timestamp(at: DateTime<Utc>) -> String {
+    at.format("%Y%m%dT%H%M%SZ").to_string()
+}
+
+/// Escapes a TEXT value: backslash, semicolon, comma and newlines.
+pub fn escape(text: &str) -> String {
+    let mut out = String::with_capacity(text.len());
+    for c in text.chars() {
+        match c {
+            '\\' => out.push_str("\\\\"),
+            ';' => out.push_str("\\;"),
+            ',' => out.push_str("\\,"),
+            '\n' => out.push_str("\\n"),
+            '\r' => {},
+            c => out.push(c),
+        }
+    }
+    out
+}
+
+/// Writes `line` to `out`, continuing on a new line starting with a space
+/// whenever it would pass `FOLD_AT` octets. Never splits a character.
+fn fold(line: &str, out: &mut String) {
+    let mut width = 0;
+    for c in line.chars() {
+        if width + c.len_utf8() > FOLD_AT {
+            out.push_str("\r\n ");
+            width = 1;
+        }
+        out.push(c);
+        width += c.len_utf8();
+    }
+    out.push_str("\r\n");
+}
+
+#[cfg(test)]
+mod tests {
+    use super::*;
+
+    fn folded(line: &str) -> String {
+        let mut out = String::new();
+        fold(line, &mut out);
+        out
+    }
+
+    #[test]
+    fn folds_at_75_octets() {
+        let out = folded(&"a".repeat(200));
+        let lines: Vec<&str> = out.trim_end_matches("\r\n").split("\r\n").collect();
+        assert!(lines.iter().all(|line| line.len() <= FOLD_AT));
+        assert!(lines[1..].iter().all(|line| line.starts_with(' ')));
+        assert_eq!(lines.concat().replace(' ', ""), "a".repeat(200));
+    }
+
+    #[test]
+    fn never_splits_a_character() {
+        for text in ["\u{e9}".repeat(100), "\u{1f409}".repeat(60), format!("x{}", "\u{20ac}".repeat(80))] {
+            let out = folded(&text);
+            let lines: Vec<&str> = out.trim_end_matches("\r\n").split("\r\n").collect();
+            assert!(lines.iter().all(|line| line.len() <= FOLD_AT));
+            let unfolded: String = lines
+                .iter()
+                .enumerate()
+                .map(|(n, line)| if n == 0 { *line } else { &line[1..] })
+                .collect();
+            assert_eq!(unfolded, text);
+        }
+    }
+
+    #[test]
+    fn escapes_text_values() {
+        assert_eq!(escape("Roll, then; rest\\\r\nagain"), r"Roll\, then\; rest\\\nagain");
+    }
+}
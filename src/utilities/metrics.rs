@@ -0,0 +1,98 @@
+use std::{sync::OnceLock, time::Instant};
+
+use axum::{
+    extract::{MatchedPath, Request},
+    middleware::Next,
+    response::Response,
+};
+use metrics::{counter, gauge, histogram};
+use metrics_exporter_prometheus::{Matcher, PrometheusBuilder, PrometheusHandle};
+
+use crate::{models::errors::{SResult, YaddakError, YaddakErrorKind}, traits::repo::pool};
+
+static HANDLE: OnceLock<PrometheusHandle> = OnceLock::new();
+
+const REQUEST_BUCKETS: &[f64] = &[0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5, 5.0, 10.0, 15.0];
+const QUERY_BUCKETS: &[f64] = &[0.001, 0.0025, 0.005, 0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5];
+const ARGON2_BUCKETS: &[f64] = &[0.01, 0.025, 0.05, 0.1, 0.25, 0.5, 1.0, 2.5];
+
+/// Installs the global Prometheus recorder. Metrics recorded before this
+/// runs are dropped.
+pub fn install() -> SResult<()> {
+    let handle = PrometheusBuilder::new()
+        .set_buckets_for_metric(Matcher::Full("http_request_duration_seconds".to_string()), REQUEST_BUCKETS)
+        .and_then(|builder| builder.set_buckets_for_metric(Matcher::Full("db_query_duration_seconds".to_string()), QUERY_BUCKETS))
+        .and_then(|builder| builder.set_buckets_for_metric(Matcher::Full("argon2_hash_duration_seconds".to_string()), ARGON2_BUCKETS))
+        .and_then(|builder| builder.install_recorder())
+        .map_err(|err| YaddakError::new(YaddakErrorKind::InternalError, err.to_string()))?;
+    let _ = HANDLE.set(handle);
+    Ok(())
+}
+
+/// Renders every metric in the Prometheus text format, sampling the pool
+/// gauges first since sqlx only exposes them on demand.
+pub fn render() -> String {
+    if let Some(pool) = pool() {
+        let size = pool.size();
+        let idle = pool.num_idle() as u32;
+        gauge!("db_pool_connections").set(size);
+        gauge!("db_pool_idle_connections").set(idle);
+        gauge!("db_pool_in_use_connections").set(size.saturating_sub(idle));
+        gauge!("db_pool_max_connections").set(pool.options().get_max_connections());
+    }
+    HANDLE.get().map(PrometheusHandle::render).unwrap_or_default()
+}
+
+/// Counts requests and observes their latency by matched route, method and
+/// status. Install with `route_layer` so the route template is known and raw
+/// paths never end up as label values.
+pub async fn track_requests(request: Request, next: Next) -> Response {
+    let route = request
+        .extensions()
+        .get::<MatchedPath>()
+        .map(|path| path.as_str().to_string())
+        .unwrap_or_else(|| "unmatched".to_string());
+    let method = request.method().to_string();
+    let start = Instant::now();
+
+    let response = next.run(request).await;
+
+    let labels = [
+        ("method", method),
+        ("route", route),
+        ("status", response.status().as_u16().to_string()),
+    ];
+    counter!("http_requests_total", &labels).increment(1);
+    histogram!("http_request_duration_seconds", &labels).record(start.elapsed().as_secs_f64());
+    response
+}
+
+/// Observes `db_query_duration_seconds` for one `Repo` call when dropped,
+/// so early returns through `?` are timed as well.
+pub struct QueryTimer {
+    entity: &'static str,
+    operation: &'static str,
+    start: Instant,
+}
+
+impl QueryTimer {
+    pub fn new(entity: &'static str, operation: &'static str) -> Self {
+        Self { entity, operation, start: Instant::now() }
+    }
+}
+
+impl Drop for QueryTimer {
+    fn drop(&mut self) {
+        histogram!("db_query_duration_seconds", "entity" => self.entity, "operation" => self.operation)
+            .record(self.start.elapsed().as_secs_f64());
+    }
+}
+
+/// Runs an argon2 hash in its own span and observes how long it took.
+pub fn time_argon2<T>(hash: impl FnOnce() -> T) -> T {
+    let _span = tracing::info_span!("argon2.hash").entered();
+    let start = Instant::now();
+    let hashed = hash();
+    histogram!("argon2_hash_duration_seconds").record(start.elapsed().as_secs_f64());
+    hashed
+}
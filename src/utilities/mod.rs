@@ -1 +1,11 @@
 pub mod headers;
+pub mod validated_json;
+pub mod tls;
+pub mod metrics;
+pub mod request_id;
+pub mod telemetry;
+pub mod security;
+pub mod html;
+pub mod storage;
+pub mod ics;
+pub mod dice;
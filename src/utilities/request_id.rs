@@ -0,0 +1,62 @@
+use axum::{
+    extract::Request,
+    http::{HeaderName, HeaderValue},
+    middleware::Next,
+    response::Response,
+};
+use tracing::Span;
+use uuid::Uuid;
+
+use super::telemetry;
+
+pub static REQUEST_ID_HEADER: HeaderName = HeaderName::from_static("x-request-id");
+
+tokio::task_local! {
+    static REQUEST_ID: String;
+}
+
+/// The id of the request being handled, `None` outside of one.
+pub fn current() -> Option<String> {
+    REQUEST_ID.try_with(Clone::clone).ok()
+}
+
+/// Keeps a caller supplied `X-Request-Id` when it is short printable ASCII,
+/// otherwise generates one. The id is echoed on the response and readable
+/// through `current` while the request is handled.
+pub async fn propagate(mut request: Request, next: Next) -> Response {
+    let id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .filter(|id| !id.is_empty() && id.len() <= 128 && id.bytes().all(|b| b.is_ascii_graphic()))
+        .map(str::to_string)
+        .unwrap_or_else(|| Uuid::new_v4().to_string());
+    // validated above, or a uuid
+    let value = HeaderValue::from_str(&id).unwrap();
+    request.headers_mut().insert(REQUEST_ID_HEADER.clone(), value.clone());
+
+    let mut response = REQUEST_ID.scope(id, next.run(request)).await;
+    response.headers_mut().insert(REQUEST_ID_HEADER.clone(), value);
+    response
+}
+
+/// `TraceLayer` span carrying the request id, so every event logged while
+/// handling the request can be tied back to it. With the `otel` feature it
+/// also joins the caller's trace. Only the path is recorded, the query can
+/// carry credentials such as the calendar feed token.
+pub fn make_span<B>(request: &axum::http::Request<B>) -> Span {
+    let request_id = request
+        .headers()
+        .get(&REQUEST_ID_HEADER)
+        .and_then(|value| value.to_str().ok())
+        .unwrap_or_default();
+    let span = tracing::debug_span!(
+        "request",
+        method = %request.method(),
+        path = %request.uri().path(),
+        version = ?request.version(),
+        request_id,
+    );
+    telemetry::set_parent(&span, request.headers());
+    span
+}
@@ -0,0 +1,65 @@
+use axum::{
+    extract::Request,
+    http::{header, HeaderName, HeaderValue, Method},
+    middleware::Next,
+    response::Response,
+};
+use tower_http::cors::{AllowOrigin, CorsLayer};
+
+use crate::config::{self, HttpConfig};
+
+use super::request_id::REQUEST_ID_HEADER;
+
+/// Nothing on the API is meant to render, so lock it down entirely.
+const API_CSP: &str = "default-src 'none'; frame-ancestors 'none'";
+/// The Swagger UI loads its bundled scripts and styles and injects some inline.
+const SWAGGER_CSP: &str = "default-src 'self'; img-src 'self' data:; style-src 'self' 'unsafe-inline'; \
+    script-src 'self' 'unsafe-inline'; frame-ancestors 'none'";
+const HSTS: &str = "max-age=31536000; includeSubDomains";
+
+/// CORS for `http.cors_origins`. With no origins configured no CORS headers
+/// are sent, so browsers only allow same origin calls.
+pub fn cors(http: &HttpConfig) -> CorsLayer {
+    let origins = if http.cors_origins.iter().any(|origin| origin == "*") {
+        AllowOrigin::any()
+    } else {
+        // validated when the config was loaded
+        AllowOrigin::list(http.cors_origins.iter().filter_map(|origin| origin.parse().ok()))
+    };
+    CorsLayer::new()
+        .allow_origin(origins)
+        .allow_methods([Method::GET, Method::POST, Method::PUT, Method::DELETE])
+        .allow_headers([header::AUTHORIZATION, header::CONTENT_TYPE, REQUEST_ID_HEADER.clone()])
+        .expose_headers([REQUEST_ID_HEADER.clone()])
+        .allow_credentials(http.cors_credentials)
+}
+
+/// Adds the standard hardening headers to every response, leaving any a
+/// handler already set alone.
+pub async fn security_headers(request: Request, next: Next) -> Response {
+    let swagger = request.uri().path().starts_with("/swagger-ui");
+    let mut response = next.run(request).await;
+
+    let config = config::get();
+    let csp = if swagger { SWAGGER_CSP } else { API_CSP };
+    let mut headers = vec![
+        (header::X_CONTENT_TYPE_OPTIONS, "nosniff"),
+        (header::X_FRAME_OPTIONS, "DENY"),
+        (header::REFERRER_POLICY, "no-referrer"),
+        (header::CONTENT_SECURITY_POLICY, csp),
+    ];
+    if config.server.tls && config.http.hsts {
+        headers.push((header::STRICT_TRANSPORT_SECURITY, HSTS));
+    }
+    for (name, value) in headers {
+        set_if_missing(&mut response, name, value);
+    }
+    response
+}
+
+fn set_if_missing(response: &mut Response, name: HeaderName, value: &'static str) {
+    response
+        .headers_mut()
+        .entry(name)
+        .or_insert(HeaderValue::from_static(value));
+}
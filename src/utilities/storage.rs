@@ -0,0 +1,65 @@
+use std::{io::ErrorKind, path::PathBuf};
+
+use uuid::Uuid;
+
+use crate::{
+    config::StorageConfig,
+    models::errors::{SResult, YaddakError},
+    traits::object_store::ObjectStore,
+};
+
+/// Stores objects as files under `root`, fanned out by the first two
+/// characters of the key so no directory grows too large.
+#[derive(Debug, Clone)]
+pub struct LocalStore {
+    root: PathBuf,
+}
+
+impl LocalStore {
+    pub fn new(config: &StorageConfig) -> Self {
+        Self { root: config.path.clone() }
+    }
+
+    fn path(&self, key: &str) -> SResult<PathBuf> {
+        // keys come from our own ids, anything else is a bug or an attack
+        let safe = !key.is_empty()
+            && key.chars().all(|c| c.is_ascii_alphanumeric() || c == '-' || c == '.')
+            && !key.starts_with('.');
+        if !safe {
+            return Err(YaddakError::bad_request(format!("{key:?} is not a valid storage key")));
+        }
+        Ok(self.root.join(&key[..key.len().min(2)]).join(key))
+    }
+}
+
+impl ObjectStore for LocalStore {
+    async fn put(&self, key: &str, bytes: &[u8]) -> SResult<()> {
+        let path = self.path(key)?;
+        if let Some(parent) = path.parent() {
+            tokio::fs::create_dir_all(parent).await?;
+        }
+        // write then rename so readers never see half a file
+        let partial = path.with_extension(format!("{}.partial", Uuid::new_v4().simple()));
+        tokio::fs::write(&partial, bytes).await?;
+        if let Err(err) = tokio::fs::rename(&partial, &path).await {
+            let _ = tokio::fs::remove_file(&partial).await;
+            return Err(err.into());
+        }
+        Ok(())
+    }
+
+    async fn get(&self, key: &str) -> SResult<Option<Vec<u8>>> {
+        match tokio::fs::read(self.path(key)?).await {
+            Ok(bytes) => Ok(Some(bytes)),
+            Err(err) if err.kind() == ErrorKind::NotFound => Ok(None),
+            Err(err) => Err(err.into()),
+        }
+    }
+
+    async fn delete(&self, key: &str) -> SResult<()> {
+        match tokio::fs::remove_file(self.path(key)?).await {
+            Err(err) if err.kind() != ErrorKind::NotFound => Err(err.into()),
+            _ => Ok(()),
+        }
+    }
+}
@@ -0,0 +1,84 @@
+use axum::http::HeaderMap;
+use tracing::{Span, Subscriber};
+use tracing_subscriber::{registry::LookupSpan, Layer};
+
+use crate::{config::LogConfig, models::errors::SResult};
+#[cfg(not(feature = "otel"))]
+use crate::models::errors::{YaddakError, YaddakErrorKind};
+
+/// The OTLP export layer when `log.otlp_endpoint` is set. Builds without
+/// the `otel` feature refuse the setting rather than silently ignoring it.
+#[cfg(feature = "otel")]
+pub fn layer<S>(log: &LogConfig) -> SResult<Option<Box<dyn Layer<S> + Send + Sync>>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a> + Send + Sync,
+{
+    use opentelemetry::KeyValue;
+    use opentelemetry_otlp::WithExportConfig;
+    use opentelemetry_sdk::{propagation::TraceContextPropagator, runtime, trace, Resource};
+
+    use crate::models::errors::{YaddakError, YaddakErrorKind};
+
+    let Some(endpoint) = &log.otlp_endpoint else {
+        return Ok(None);
+    };
+    let tracer = opentelemetry_otlp::new_pipeline()
+        .tracing()
+        .with_exporter(opentelemetry_otlp::new_exporter().tonic().with_endpoint(endpoint))
+        .with_trace_config(trace::config().with_resource(Resource::new([
+            KeyValue::new("service.name", env!("CARGO_PKG_NAME")),
+            KeyValue::new("service.version", env!("CARGO_PKG_VERSION")),
+        ])))
+        .install_batch(runtime::Tokio)
+        .map_err(|err| YaddakError::new(YaddakErrorKind::InternalError, format!("could not start OTLP export: {err}")))?;
+    opentelemetry::global::set_text_map_propagator(TraceContextPropagator::new());
+
+    Ok(Some(tracing_opentelemetry::layer().with_tracer(tracer).boxed()))
+}
+
+#[cfg(not(feature = "otel"))]
+pub fn layer<S>(log: &LogConfig) -> SResult<Option<Box<dyn Layer<S> + Send + Sync>>>
+where
+    S: Subscriber + for<'a> LookupSpan<'a> + Send + Sync,
+{
+    match log.otlp_endpoint {
+        None => Ok(None),
+        Some(_) => Err(YaddakError::new(
+            YaddakErrorKind::EnvError,
+            "log.otlp_endpoint is set but this build has no `otel` feature".to_string(),
+        )),
+    }
+}
+
+/// Continues the trace named by an incoming W3C `traceparent` header.
+#[cfg(feature = "otel")]
+pub fn set_parent(span: &Span, headers: &HeaderMap) {
+    use opentelemetry::propagation::Extractor;
+    use tracing_opentelemetry::OpenTelemetrySpanExt;
+
+    struct HeaderExtractor<'a>(&'a HeaderMap);
+
+    impl Extractor for HeaderExtractor<'_> {
+        fn get(&self, key: &str) -> Option<&str> {
+            self.0.get(key).and_then(|value| value.to_str().ok())
+        }
+
+        fn keys(&self) -> Vec<&str> {
+            self.0.keys().map(|key| key.as_str()).collect()
+        }
+    }
+
+    let parent = opentelemetry::global::get_text_map_propagator(|propagator| {
+        propagator.extract(&HeaderExtractor(headers))
+    });
+    span.set_parent(parent);
+}
+
+#[cfg(not(feature = "otel"))]
+pub fn set_parent(_span: &Span, _headers: &HeaderMap) {}
+
+/// Flushes spans still waiting in the batch exporter.
+pub fn shutdown() {
+    #[cfg(feature = "otel")]
+    opentelemetry::global::shutdown_tracer_provider();
+}
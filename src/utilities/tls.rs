@@ -0,0 +1,206 @@
+use std::{io, path::Path, sync::Arc, time::Duration};
+
+use axum::{
+    extract::Request,
+    middleware::{AddExtension, Next},
+    response::Response,
+    Extension,
+};
+use axum_server::{
+    accept::Accept,
+    tls_rustls::{RustlsAcceptor, RustlsConfig},
+};
+use futures_util::future::BoxFuture;
+use rustls::{server::AllowAnyAnonymousOrAuthenticatedClient, Certificate, PrivateKey, RootCertStore, ServerConfig};
+use tokio::io::{AsyncRead, AsyncWrite};
+use tokio_rustls::server::TlsStream;
+use tower::Layer;
+use tracing::{info, warn};
+
+use crate::{config::{self, TlsConfig}, models::errors::{SResult, YaddakError, YaddakErrorKind}};
+
+/// Whether the connection a request arrived on presented a client
+/// certificate that chained to `tls.client_ca_path`.
+#[derive(Debug, Clone, Copy)]
+pub struct ClientCert(pub bool);
+
+/// Loads the server certificate. With a client CA configured the config is
+/// built here so client certificates are requested but still optional.
+pub async fn load(tls: &TlsConfig) -> io::Result<RustlsConfig> {
+    match &tls.client_ca_path {
+        None => RustlsConfig::from_pem_file(&tls.cert_path, &tls.key_path).await,
+        Some(ca) => Ok(RustlsConfig::from_config(Arc::new(
+            mtls_server_config(&tls.cert_path, &tls.key_path, ca).await?
+        ))),
+    }
+}
+
+/// Swaps in freshly read certificates. Connections already open keep
+/// the config they were accepted with.
+pub async fn reload(rustls: &RustlsConfig, tls: &TlsConfig) -> io::Result<()> {
+    match &tls.client_ca_path {
+        None => rustls.reload_from_pem_file(&tls.cert_path, &tls.key_path).await,
+        Some(ca) => {
+            let server_config = mtls_server_config(&tls.cert_path, &tls.key_path, ca).await?;
+            rustls.reload_from_config(Arc::new(server_config));
+            Ok(())
+        }
+    }
+}
+
+/// Reloads on SIGHUP and, when `tls.reload_interval_secs` is set, on that
+/// interval. A failed reload keeps serving the previous certificate.
+pub async fn reload_job(rustls: RustlsConfig, tls: TlsConfig) {
+    #[cfg(unix)]
+    let mut hangup = match tokio::signal::unix::signal(tokio::signal::unix::SignalKind::hangup()) {
+        Ok(hangup) => Some(hangup),
+        Err(err) => {
+            warn!("could not listen for SIGHUP, certificates only reload on the interval: {err}");
+            None
+        }
+    };
+    let mut interval = (tls.reload_interval_secs > 0)
+        .then(|| tokio::time::interval(Duration::from_secs(tls.reload_interval_secs)));
+    if let Some(interval) = interval.as_mut() {
+        // the first tick fires straight away and the certificate was just loaded
+        interval.tick().await;
+    }
+
+    loop {
+        let tick = async {
+            match interval.as_mut() {
+                Some(interval) => { interval.tick().await; },
+                None => std::future::pending::<()>().await,
+            }
+        };
+        #[cfg(unix)]
+        let hup = async {
+            match hangup.as_mut() {
+                Some(hangup) => { hangup.recv().await; },
+                None => std::future::pending::<()>().await,
+            }
+        };
+        #[cfg(not(unix))]
+        let hup = std::future::pending::<()>();
+
+        tokio::select! {
+            _ = tick => {},
+            _ = hup => info!("received SIGHUP"),
+        }
+
+        match reload(&rustls, &tls).await {
+            Ok(()) => info!("reloaded TLS certificate from {}", tls.cert_path.display()),
+            Err(err) => warn!("TLS reload failed, keeping the current certificate: {err}"),
+        }
+    }
+}
+
+async fn mtls_server_config(cert: &Path, key: &Path, client_ca: &Path) -> io::Result<ServerConfig> {
+    let certs = read_certs(cert).await?;
+    let key_pem = tokio::fs::read(key).await?;
+    let key = rustls_pemfile::private_key(&mut key_pem.as_slice())?
+        .ok_or_else(|| io::Error::new(io::ErrorKind::InvalidData, format!("no private key in {}", key.display())))?;
+    let key = PrivateKey(key.secret_der().to_vec());
+
+    let mut roots = RootCertStore::empty();
+    for ca in read_certs(client_ca).await? {
+        roots.add(&ca).map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    }
+
+    let mut config = ServerConfig::builder()
+        .with_safe_defaults()
+        .with_client_cert_verifier(AllowAnyAnonymousOrAuthenticatedClient::new(roots).boxed())
+        .with_single_cert(certs, key)
+        .map_err(|err| io::Error::new(io::ErrorKind::InvalidData, err))?;
+    config.alpn_protocols = vec![b"h2".to_vec(), b"http/1.1".to_vec()];
+
+    Ok(config)
+}
+
+async fn read_certs(path: &Path) -> io::Result<Vec<Certificate>> {
+    let pem = tokio::fs::read(path).await?;
+    rustls_pemfile::certs(&mut pem.as_slice())
+        .map(|cert| cert.map(|cert| Certificate(cert.to_vec())))
+        .collect()
+}
+
+/// Rustls acceptor that tags every request with a `ClientCert` extension.
+#[derive(Clone)]
+pub struct ClientCertAcceptor {
+    inner: RustlsAcceptor,
+}
+
+impl ClientCertAcceptor {
+    pub fn new(config: RustlsConfig) -> Self {
+        Self { inner: RustlsAcceptor::new(config) }
+    }
+}
+
+impl<I, S> Accept<I, S> for ClientCertAcceptor
+where
+    I: AsyncRead + AsyncWrite + Unpin + Send + 'static,
+    S: Send + 'static,
+{
+    type Stream = TlsStream<I>;
+    type Service = AddExtension<S, ClientCert>;
+    type Future = BoxFuture<'static, io::Result<(Self::Stream, Self::Service)>>;
+
+    fn accept(&self, stream: I, service: S) -> Self::Future {
+        let acceptor = self.inner.clone();
+
+        Box::pin(async move {
+            let (stream, service) = acceptor.accept(stream, service).await?;
+            // rustls only exposes peer certificates that passed verification
+            let presented = stream.get_ref().1
+                .peer_certificates()
+                .is_some_and(|certs| !certs.is_empty());
+            let service = Extension(ClientCert(presented)).layer(service);
+
+            Ok((stream, service))
+        })
+    }
+}
+
+/// Middleware for admin routes: once a client CA is configured, requests
+/// must come over a connection that presented a verified client certificate.
+pub async fn require_client_cert(request: Request, next: Next) -> Result<Response, YaddakError> {
+    if config::get().tls.client_ca_path.is_some() {
+        let presented = request
+            .extensions()
+            .get::<ClientCert>()
+            .is_some_and(|cert| cert.0);
+        if !presented {
+            return Err(YaddakError::forbidden("A client certificate is required for admin routes".to_string()));
+        }
+    }
+    Ok(next.run(request).await)
+}
+
+/// Writes a self signed certificate and key for local development.
+pub fn generate_dev_cert(cert_path: &Path, key_path: &Path, hosts: Vec<String>) -> SResult<()> {
+    let certified = rcgen::generate_simple_self_signed(hosts)
+        .map_err(|err| YaddakError::new(YaddakErrorKind::InternalError, err.to_string()))?;
+    std::fs::write(cert_path, certified.cert.pem())?;
+    write_private(key_path, certified.key_pair.serialize_pem().as_bytes())?;
+    Ok(())
+}
+
+/// Writes `contents` to a file that is only ever readable by the owner.
+/// Any old file is removed first, since the mode only applies to a file
+/// being created and `--force` may be replacing a world readable one.
+fn write_private(path: &Path, contents: &[u8]) -> io::Result<()> {
+    use std::io::Write;
+
+    match std::fs::remove_file(path) {
+        Err(err) if err.kind() != io::ErrorKind::NotFound => return Err(err),
+        _ => {},
+    }
+    let mut options = std::fs::OpenOptions::new();
+    options.write(true).create_new(true);
+    #[cfg(unix)]
+    {
+        use std::os::unix::fs::OpenOptionsExt;
+        options.mode(0o600);
+    }
+    options.open(path)?.write_all(contents)
+}
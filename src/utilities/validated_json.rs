@@ -0,0 +1,68 @@
+use axum::{
+    async_trait,
+    extract::{FromRequest, Request},
+    Json,
+};
+use serde::de::DeserializeOwned;
+use validator::{Validate, ValidationErrors, ValidationErrorsKind};
+
+use crate::models::errors::{FieldError, YaddakError};
+
+/// `Json` that also runs the body's `Validate` rules, rejecting with a
+/// `Validation` error listing every failing field.
+pub struct ValidatedJson<T>(pub T);
+
+#[async_trait]
+impl<T, S> FromRequest<S> for ValidatedJson<T>
+where
+    T: DeserializeOwned + Validate,
+    S: Send + Sync,
+{
+    type Rejection = YaddakError;
+
+    async fn from_request(req: Request, state: &S) -> Result<Self, Self::Rejection> {
+        let Json(value) = Json::<T>::from_request(req, state).await?;
+        value.validate()?;
+        Ok(ValidatedJson(value))
+    }
+}
+
+impl From<ValidationErrors> for YaddakError {
+    fn from(value: ValidationErrors) -> Self {
+        let mut details = Vec::new();
+        flatten(String::new(), &value, &mut details);
+        YaddakError::validation(details)
+    }
+}
+
+/// Walks nested structs and lists so each error names its full path,
+/// e.g. `abilities.str` or `items[2].name`.
+fn flatten(prefix: String, errors: &ValidationErrors, details: &mut Vec<FieldError>) {
+    for (field, kind) in errors.errors() {
+        let path = if prefix.is_empty() {
+            field.to_string()
+        } else {
+            format!("{prefix}.{field}")
+        };
+        match kind {
+            ValidationErrorsKind::Field(errs) => {
+                for err in errs {
+                    details.push(FieldError {
+                        field: path.clone(),
+                        message: err
+                            .message
+                            .as_ref()
+                            .map(|m| m.to_string())
+                            .unwrap_or_else(|| err.code.to_string()),
+                    });
+                }
+            },
+            ValidationErrorsKind::Struct(inner) => flatten(path, inner, details),
+            ValidationErrorsKind::List(items) => {
+                for (index, inner) in items {
+                    flatten(format!("{path}[{index}]"), inner, details);
+                }
+            },
+        }
+    }
+}